@@ -1,7 +1,7 @@
 //! Example demonstrating middleware usage
 
 use jrow_server::{
-    from_typed_fn, JrowServer, LoggingMiddleware, MetricsMiddleware, Middleware, MiddlewareAction,
+    from_typed_fn, JrowServer, LoggingMiddleware, MetricsMiddleware, MiddlewareAction,
     MiddlewareContext, SyncMiddleware,
 };
 use serde::{Deserialize, Serialize};
@@ -0,0 +1,64 @@
+//! OpenRPC discovery example
+//!
+//! Registers typed handlers with schemas and serves the generated OpenRPC
+//! document via `rpc.discover`. Call `rpc.discover` from any client (or
+//! paste the printed document into the OpenRPC playground) to inspect the API.
+
+use jrow_server::{from_typed_fn_with_schema, JrowServer, OpenRpcInfo};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, JsonSchema)]
+struct AddParams {
+    a: i32,
+    b: i32,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct AddResult {
+    sum: i32,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct GreetParams {
+    /// Name of the person to greet
+    name: String,
+    /// Optional greeting, defaults to "Hello"
+    greeting: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:8080")?
+        .handler(
+            "add",
+            from_typed_fn_with_schema(|params: AddParams| async move {
+                Ok(AddResult {
+                    sum: params.a + params.b,
+                })
+            }),
+        )
+        .handler(
+            "greet",
+            from_typed_fn_with_schema(|params: GreetParams| async move {
+                let greeting = params.greeting.unwrap_or_else(|| "Hello".to_string());
+                Ok(format!("{}, {}!", greeting, params.name))
+            }),
+        )
+        .with_discovery(
+            OpenRpcInfo::new("Discovery Example", "1.0.0")
+                .with_description("Typed methods described via OpenRPC"),
+        )
+        .build()
+        .await?;
+
+    if let Some(document) = server.openrpc_document() {
+        println!("{}", serde_json::to_string_pretty(&document)?);
+    }
+
+    println!("\nServer listening on ws://127.0.0.1:8080 (try rpc.discover)");
+    server.run().await?;
+
+    Ok(())
+}
@@ -169,7 +169,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         tokio::time::sleep(Duration::from_secs(2)).await;
 
         // orders.cancelled
-        if counter % 3 == 0 {
+        if counter.is_multiple_of(3) {
             let seq = server.publish_persistent(
                 "orders.cancelled",
                 serde_json::json!({
@@ -15,7 +15,7 @@
 
 use async_trait::async_trait;
 use jrow_core::Result as JrowResult;
-use jrow_server::{from_fn, from_typed_fn, JrowServer, Middleware, MiddlewareAction, MiddlewareContext, RetentionPolicy};
+use jrow_server::{from_fn, from_typed_fn, JrowServer, Middleware, MiddlewareAction, MiddlewareContext, RetentionPolicy, RouterBuilder};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::SocketAddr;
@@ -572,6 +572,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create response logging middleware (uses the same channel as notification handlers)
     let response_logger = ResponseLoggingMiddleware::new(log_tx.clone());
 
+    // User management methods live in their own router, mounted under `user`
+    let users_router = RouterBuilder::new()
+        .handler("create", create_user_handler)
+        .handler("get", get_user_handler)
+        .handler("list", list_users_handler)
+        .try_build()?;
+
     // Build JROW server
     let server = JrowServer::builder()
         .bind_str(ws_addr)?
@@ -607,10 +614,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .handler("echo", echo_handler)
         .handler("reverse", reverse_handler)
         .handler("toUpper", to_upper_handler)
-        // User management
-        .handler("user.create", create_user_handler)
-        .handler("user.get", get_user_handler)
-        .handler("user.list", list_users_handler)
+        // User management (served as user.create, user.get, user.list)
+        .nest("user", users_router)
         // Special operations
         .handler("slowOperation", slow_operation_handler)
         .handler("testError", error_test_handler)
@@ -769,7 +774,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Spawn task to publish demo events
     tokio::spawn(async move {
         let mut interval = interval(Duration::from_secs(15));
-        let events = vec![
+        let events = [
             "User logged in",
             "Data synchronized",
             "Cache cleared",
@@ -802,7 +807,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Spawn task to publish server logs
     tokio::spawn(async move {
         let mut interval = interval(Duration::from_secs(8));
-        let log_messages = vec![
+        let log_messages = [
             ("info", "Server health check passed"),
             ("success", "Background task completed successfully"),
             ("debug", "Processing scheduled maintenance"),
@@ -879,7 +884,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Spawn task to publish persistent notifications
     tokio::spawn(async move {
         let mut interval = interval(Duration::from_secs(20));
-        let notifications = vec![
+        let notifications = [
             "System backup completed",
             "Security scan finished",
             "Data synchronization successful",
@@ -0,0 +1,77 @@
+//! TypeScript bindings example
+//!
+//! Builds a router with typed handlers, generates its OpenRPC document and
+//! renders TypeScript bindings for browser clients.
+//!
+//! ```bash
+//! # Print to stdout
+//! cargo run --example typescript_bindings
+//!
+//! # Write next to the playground client
+//! cargo run --example typescript_bindings -- playground/api.ts
+//! ```
+
+use jrow_core::Topic;
+use jrow_server::{
+    from_typed_fn_with_schema, openrpc_document, OpenRpcInfo, Router, TypeScriptGenerator,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, JsonSchema)]
+struct CreateUserParams {
+    /// Display name of the new user
+    name: String,
+    /// Optional age in years
+    age: Option<u32>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct User {
+    id: u64,
+    name: String,
+    age: Option<u32>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct UserEvent {
+    user_id: u64,
+    kind: String,
+}
+
+impl Topic for UserEvent {
+    const TOPIC: &'static str = "users.*.events";
+}
+
+fn main() -> jrow_core::Result<()> {
+    let mut router = Router::new();
+    router.register(
+        "user.create",
+        from_typed_fn_with_schema(|params: CreateUserParams| async move {
+            Ok(User {
+                id: 1,
+                name: params.name,
+                age: params.age,
+            })
+        }),
+    );
+    router.register(
+        "user.list",
+        from_typed_fn_with_schema(|_: ()| async move { Ok(Vec::<User>::new()) }),
+    );
+
+    let document = openrpc_document(&OpenRpcInfo::new("Users API", "1.0.0"), &router, false);
+    let generator = TypeScriptGenerator::new(document)
+        .topic::<UserEvent>()
+        .error_code("UserNotFound", -32004);
+
+    match std::env::args().nth(1) {
+        Some(path) => {
+            generator.write_to(&path)?;
+            println!("Wrote TypeScript bindings to {}", path);
+        }
+        None => print!("{}", generator.generate()?),
+    }
+
+    Ok(())
+}
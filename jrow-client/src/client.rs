@@ -22,11 +22,19 @@
 //! The client is fully thread-safe and can be shared across tasks without
 //! additional synchronization.
 
-use crate::{connection_state::ConnectionManager, request::RequestManager, NotificationHandler};
+use crate::{
+    connection_state::ConnectionManager, request::RequestManager, NotificationHandler, SubscribeOptions,
+};
 use futures::{SinkExt, StreamExt};
-use jrow_core::{codec, Error, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, Result};
+use jrow_core::presence::{presence_topic, PRESENCE_METHOD};
+use jrow_core::request_reply::{TopicRequest, PUBLISH_METHOD, REQUEST_TOPIC_METHOD};
+use jrow_core::snapshot::Versioned;
+use jrow_core::{
+    codec, ContentFilter, Error, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, Result,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
 use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
@@ -63,8 +71,8 @@ pub struct JrowClient {
     pub(crate) request_manager: RequestManager,
     /// Notification handler for incoming notifications
     pub(crate) notification_handler: NotificationHandler,
-    /// Set of subscribed topics
-    pub(crate) subscribed_topics: Arc<Mutex<HashSet<String>>>,
+    /// Subscribed topics with the options they were subscribed with
+    pub(crate) subscribed_topics: Arc<Mutex<HashMap<String, SubscribeOptions>>>,
     /// Persistent subscriptions for auto-resume on reconnect
     pub(crate) persistent_subscriptions: Arc<Mutex<Vec<PersistentSubscriptionInfo>>>,
     /// Connection manager for reconnection
@@ -74,6 +82,8 @@ pub struct JrowClient {
     pub(crate) pending_requests: Arc<RwLock<Vec<PendingRequest>>>,
     /// Metrics for observability
     pub(crate) metrics: Option<Arc<crate::ClientMetrics>>,
+    /// Set by `disconnect` so the receive loop does not reconnect
+    pub(crate) closed: Arc<AtomicBool>,
 }
 
 impl JrowClient {
@@ -91,9 +101,10 @@ impl JrowClient {
 
         let request_manager = RequestManager::new();
         let notification_handler = NotificationHandler::new();
-        let subscribed_topics = Arc::new(Mutex::new(HashSet::new()));
+        let subscribed_topics = Arc::new(Mutex::new(HashMap::new()));
 
         let persistent_subscriptions = Arc::new(Mutex::new(Vec::new()));
+        let closed = Arc::new(AtomicBool::new(false));
 
         let client = Self {
             sender: sender.clone(),
@@ -104,6 +115,7 @@ impl JrowClient {
             connection_manager: None,
             pending_requests: Arc::new(RwLock::new(Vec::new())),
             metrics: None,
+            closed: closed.clone(),
         };
 
         tracing::info!("Connected successfully");
@@ -119,6 +131,7 @@ impl JrowClient {
             persistent_subscriptions,
             url.to_string(),
             None,
+            closed,
         ));
 
         Ok(client)
@@ -146,6 +159,17 @@ impl JrowClient {
         }
     }
 
+    /// Close the connection
+    ///
+    /// Pending requests fail with `Error::ConnectionClosed` and no
+    /// reconnection is attempted, even if a reconnection strategy is set.
+    pub async fn disconnect(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        if let Err(e) = self.sender.lock().await.send(Message::Close(None)).await {
+            tracing::debug!(error = %e, "Connection already closed");
+        }
+    }
+
     /// Send a JSON-RPC request and wait for the response
     #[tracing::instrument(skip(self, params), fields(method = %method.as_ref()))]
     pub async fn request<P, R>(&self, method: impl Into<String> + AsRef<str>, params: P) -> Result<R>
@@ -169,7 +193,7 @@ impl JrowClient {
         self.sender
             .lock()
             .await
-            .send(Message::Text(request_text))
+            .send(Message::Text(request_text.into()))
             .await
             .map_err(|e| Error::WebSocket(e.to_string()))?;
 
@@ -223,7 +247,7 @@ impl JrowClient {
         self.sender
             .lock()
             .await
-            .send(Message::Text(notification_text))
+            .send(Message::Text(notification_text.into()))
             .await
             .map_err(|e| Error::WebSocket(e.to_string()))?;
 
@@ -239,6 +263,18 @@ impl JrowClient {
         self.notification_handler.register(method, handler).await;
     }
 
+    /// Register a handler for notifications no other handler is registered for
+    ///
+    /// Useful for relaying server-to-client notifications whose methods are
+    /// not known in advance.
+    pub async fn on_unhandled_notification<F, Fut>(&self, handler: F)
+    where
+        F: Fn(JsonRpcNotification) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.notification_handler.set_fallback(handler).await;
+    }
+
     /// Get the notification handler
     pub fn notification_handler(&self) -> &NotificationHandler {
         &self.notification_handler
@@ -246,6 +282,98 @@ impl JrowClient {
 
     /// Subscribe to a topic and register a handler for messages on that topic
     pub async fn subscribe<F, Fut>(&self, topic: impl Into<String>, handler: F) -> Result<()>
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.subscribe_with_options(topic, SubscribeOptions::new(), handler).await
+    }
+
+    /// Subscribe to a topic, receiving only messages whose data matches `filter`
+    ///
+    /// The server evaluates the filter before sending, so messages that
+    /// don't match never reach the client. Subscribing to the same topic
+    /// again replaces the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use jrow_client::JrowClient;
+    /// use jrow_core::ContentFilter;
+    ///
+    /// # async fn example(client: &JrowClient) -> jrow_core::Result<()> {
+    /// let large_eu_orders = ContentFilter::all(vec![
+    ///     ContentFilter::eq("/region", "eu"),
+    ///     ContentFilter::gte("/amount", 1000),
+    /// ]);
+    /// client
+    ///     .subscribe_filtered("orders.>", large_eu_orders, |order| async move {
+    ///         println!("{}", order);
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn subscribe_filtered<F, Fut>(
+        &self,
+        topic: impl Into<String>,
+        filter: ContentFilter,
+        handler: F,
+    ) -> Result<()>
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.subscribe_with_options(topic, SubscribeOptions::new().filter(filter), handler)
+            .await
+    }
+
+    /// Subscribe to a topic or pattern as a member of a queue group
+    ///
+    /// Subscribers in the same queue group share its messages: the server
+    /// sends each message to only one member, which lets several workers
+    /// split a stream of jobs. How the member is chosen is configured on
+    /// the server.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use jrow_client::JrowClient;
+    ///
+    /// # async fn example(client: &JrowClient) -> jrow_core::Result<()> {
+    /// client
+    ///     .subscribe_queue("jobs.>", "workers", |job| async move {
+    ///         println!("Processing {}", job);
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn subscribe_queue<F, Fut>(
+        &self,
+        topic: impl Into<String>,
+        queue_group: impl Into<String>,
+        handler: F,
+    ) -> Result<()>
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.subscribe_with_options(topic, SubscribeOptions::new().queue_group(queue_group), handler)
+            .await
+    }
+
+    /// Subscribe to a topic or pattern with a filter, queue group,
+    /// retained message preference or throttle
+    ///
+    /// See [`SubscribeOptions`]. Subscribing to the same topic again
+    /// replaces the options.
+    pub async fn subscribe_with_options<F, Fut>(
+        &self,
+        topic: impl Into<String>,
+        options: SubscribeOptions,
+        handler: F,
+    ) -> Result<()>
     where
         F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
         Fut: std::future::Future<Output = ()> + Send + 'static,
@@ -268,6 +396,8 @@ impl JrowClient {
         #[derive(Serialize)]
         struct SubscribeParams {
             topic: String,
+            #[serde(flatten)]
+            options: SubscribeOptions,
         }
 
         #[derive(Deserialize)]
@@ -282,13 +412,14 @@ impl JrowClient {
                 "rpc.subscribe",
                 SubscribeParams {
                     topic: topic.clone(),
+                    options: options.clone(),
                 },
             )
             .await?;
 
         if result.subscribed {
             // Track subscription locally
-            self.subscribed_topics.lock().await.insert(topic);
+            self.subscribed_topics.lock().await.insert(topic, options);
             Ok(())
         } else {
             Err(Error::Internal("Failed to subscribe".to_string()))
@@ -329,6 +460,371 @@ impl JrowClient {
         Ok(())
     }
 
+    /// Subscribe to a topic and receive its messages as a typed stream
+    ///
+    /// Each notification payload is deserialized into `T`. For pattern
+    /// subscriptions the `{"topic", "data"}` wrapper is removed, so `T`
+    /// describes the published data in both cases. The stream ends once
+    /// `unsubscribe` is called for the topic.
+    pub async fn subscribe_stream<T>(
+        &self,
+        topic: impl Into<String>,
+    ) -> Result<crate::SubscriptionStream<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let topic = topic.into();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+        self.subscribe(topic.clone(), move |value| {
+            // A closed receiver just means the stream was dropped
+            let _ = tx.send(value);
+            async {}
+        })
+        .await?;
+
+        Ok(crate::SubscriptionStream::new(topic, rx))
+    }
+
+    /// Subscribe to the topic bound to a payload type
+    ///
+    /// Uses `T::TOPIC` from the type's [`Topic`](jrow_core::Topic)
+    /// implementation, so the subscriber and the server's `publish_typed`
+    /// always agree on both topic and payload type.
+    pub async fn subscribe_typed<T>(&self) -> Result<crate::SubscriptionStream<T>>
+    where
+        T: jrow_core::Topic + serde::de::DeserializeOwned,
+    {
+        self.subscribe_stream(T::TOPIC).await
+    }
+
+    /// Unsubscribe from the topic bound to a payload type
+    pub async fn unsubscribe_typed<T>(&self) -> Result<()>
+    where
+        T: jrow_core::Topic,
+    {
+        self.unsubscribe(T::TOPIC).await
+    }
+
+    /// Subscribe to a topic with a snapshot provider on the server
+    ///
+    /// The handler first receives the topic's state as a snapshot, then
+    /// each published message as a delta; see `jrow_core::snapshot`. Deltas
+    /// not newer than the last snapshot or delta applied are dropped, and
+    /// after a reconnect the new snapshot replaces the state. Snapshots are
+    /// sent per topic, so patterns are rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use jrow_client::JrowClient;
+    ///
+    /// # async fn example(client: &JrowClient) -> jrow_core::Result<()> {
+    /// client
+    ///     .subscribe_versioned("orders.stats", |message| async move {
+    ///         if message.is_snapshot() {
+    ///             println!("State at {}: {}", message.version, message.data);
+    ///         } else {
+    ///             println!("Change {}: {}", message.version, message.data);
+    ///         }
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn subscribe_versioned<F, Fut>(&self, topic: impl Into<String>, handler: F) -> Result<()>
+    where
+        F: Fn(Versioned) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let topic = topic.into();
+        if crate::stream::is_pattern(&topic) {
+            return Err(Error::InvalidParams(format!(
+                "Snapshots are sent per topic, not for pattern '{}'",
+                topic
+            )));
+        }
+
+        // Notifications are handled in order, so the last applied version
+        // only needs to be shared with the handler
+        let applied = AtomicU64::new(0);
+        let name = topic.clone();
+        self.subscribe(topic, move |value| {
+            let message = match serde_json::from_value::<Versioned>(value) {
+                Ok(message) if message.is_snapshot() => {
+                    applied.store(message.version, Ordering::SeqCst);
+                    Some(message)
+                }
+                Ok(message) => {
+                    (applied.fetch_max(message.version, Ordering::SeqCst) < message.version).then_some(message)
+                }
+                Err(e) => {
+                    tracing::warn!(topic = %name, error = %e, "Ignoring unversioned message");
+                    None
+                }
+            };
+            let handled = message.map(&handler);
+            async move {
+                if let Some(handled) = handled {
+                    handled.await;
+                }
+            }
+        })
+        .await
+    }
+
+    /// Send a request to the subscribers of a topic and wait for the first reply
+    ///
+    /// The server publishes the request with a reply inbox (see
+    /// `jrow_core::request_reply`) and answers with the first reply published
+    /// to it. Fails if no subscriber received the request, or if no reply
+    /// arrives within `timeout`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use jrow_client::JrowClient;
+    /// use std::time::Duration;
+    ///
+    /// # async fn example(client: &JrowClient) -> jrow_core::Result<()> {
+    /// let status = client
+    ///     .request_topic("orders.status", serde_json::json!({"order_id": 42}), Duration::from_secs(5))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn request_topic<P>(
+        &self,
+        topic: impl Into<String>,
+        data: P,
+        timeout: std::time::Duration,
+    ) -> Result<serde_json::Value>
+    where
+        P: Serialize,
+    {
+        #[derive(Serialize)]
+        struct RequestTopicParams<P> {
+            topic: String,
+            data: P,
+            timeout_ms: u64,
+        }
+
+        #[derive(Deserialize)]
+        struct RequestTopicResult {
+            #[serde(default)]
+            reply: serde_json::Value,
+        }
+
+        let result: RequestTopicResult = self
+            .request(
+                REQUEST_TOPIC_METHOD,
+                RequestTopicParams {
+                    topic: topic.into(),
+                    data,
+                    timeout_ms: u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX),
+                },
+            )
+            .await?;
+        Ok(result.reply)
+    }
+
+    /// Publish `data` to `topic`, like `JrowServer::publish` on the server
+    ///
+    /// The server only accepts topics its publish ACL allows. Returns the
+    /// number of notifications the server queued.
+    ///
+    /// **Warning**: Like other requests, don't await this directly in a
+    /// notification handler; spawn a task with a clone of the client.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use jrow_client::JrowClient;
+    ///
+    /// # async fn example(client: &JrowClient) -> jrow_core::Result<()> {
+    /// let delivered = client
+    ///     .publish("chat.lobby", serde_json::json!({"text": "hello"}))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn publish<P>(&self, topic: impl Into<String>, data: P) -> Result<usize>
+    where
+        P: Serialize,
+    {
+        #[derive(Serialize)]
+        struct PublishParams<P> {
+            topic: String,
+            data: P,
+        }
+
+        #[derive(Deserialize)]
+        struct PublishResult {
+            delivered: usize,
+        }
+
+        let result: PublishResult = self
+            .request(
+                PUBLISH_METHOD,
+                PublishParams {
+                    topic: topic.into(),
+                    data,
+                },
+            )
+            .await?;
+        Ok(result.delivered)
+    }
+
+    /// Publish `data` as the reply to a request received on `reply_to`
+    ///
+    /// Returns the number of recipients, 0 if the request was already
+    /// answered or timed out.
+    ///
+    /// **Warning**: Like other requests, don't await this directly in a
+    /// notification handler; `respond` replies from a background task.
+    pub async fn reply<P>(&self, reply_to: impl Into<String>, data: P) -> Result<usize>
+    where
+        P: Serialize,
+    {
+        self.publish(reply_to, data).await
+    }
+
+    /// Answer requests sent to a topic or pattern with `request_topic`
+    ///
+    /// The handler receives the request data and its result is published to
+    /// the request's inbox; errors are logged and left unanswered, so the
+    /// requester times out. Every responder subscribed to the topic gets
+    /// each request and the first reply wins; use `respond_with_options`
+    /// with a queue group to spread requests over responders instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use jrow_client::JrowClient;
+    ///
+    /// # async fn example(client: &JrowClient) -> jrow_core::Result<()> {
+    /// client
+    ///     .respond("orders.status", |request| async move {
+    ///         Ok(serde_json::json!({"order_id": request["order_id"], "status": "shipped"}))
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn respond<F, Fut>(&self, topic: impl Into<String>, handler: F) -> Result<()>
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<serde_json::Value>> + Send + 'static,
+    {
+        self.respond_with_options(topic, SubscribeOptions::new(), handler)
+            .await
+    }
+
+    /// Answer requests sent to a topic or pattern, subscribing with `options`
+    pub async fn respond_with_options<F, Fut>(
+        &self,
+        topic: impl Into<String>,
+        options: SubscribeOptions,
+        handler: F,
+    ) -> Result<()>
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<serde_json::Value>> + Send + 'static,
+    {
+        let topic = topic.into();
+        let pattern = crate::stream::is_pattern(&topic);
+        let client = self.clone();
+        let handler = Arc::new(handler);
+
+        self.subscribe_with_options(topic.clone(), options, move |mut message| {
+            // Pattern subscriptions wrap the request with its topic
+            if pattern {
+                message = message.get_mut("data").map(serde_json::Value::take).unwrap_or_default();
+            }
+            let client = client.clone();
+            let handler = Arc::clone(&handler);
+            let topic = topic.clone();
+
+            // Reply from a task, since the reply's response is read by the
+            // loop running this handler
+            tokio::spawn(async move {
+                let request: TopicRequest = match serde_json::from_value(message) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        tracing::warn!(topic = %topic, error = %e, "Ignoring message without a reply inbox");
+                        return;
+                    }
+                };
+                let replied = match handler(request.data).await {
+                    Ok(reply) => client.reply(request.reply_to, reply).await.map(drop),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = replied {
+                    tracing::warn!(topic = %topic, error = %e, "Failed to answer topic request");
+                }
+            });
+            async {}
+        })
+        .await
+    }
+
+    /// Keep a live list of the members of a topic
+    ///
+    /// Subscribes to the topic's companion `presence.<topic>` and fetches
+    /// the current members with `rpc.presence`. The server must track
+    /// presence for the topic (`ServerBuilder::track_presence`) for the list
+    /// to update. Presence is tracked per topic, so patterns are rejected.
+    pub async fn subscribe_presence(
+        &self,
+        topic: impl Into<String>,
+    ) -> Result<crate::PresenceList> {
+        let topic = topic.into();
+        if crate::stream::is_pattern(&topic) {
+            return Err(Error::InvalidParams(format!(
+                "Presence is tracked per topic, not for pattern '{}'",
+                topic
+            )));
+        }
+
+        // Subscribe before listing so no change falls between the two
+        let (tx, rx) = tokio::sync::watch::channel(crate::presence::Members::new());
+        let tx = Arc::new(tx);
+        let events = Arc::clone(&tx);
+        self.subscribe(presence_topic(&topic), move |data| {
+            crate::presence::apply(&events, data);
+            async {}
+        })
+        .await?;
+
+        #[derive(Serialize)]
+        struct PresenceParams<'a> {
+            topic: &'a str,
+        }
+
+        #[derive(Deserialize)]
+        struct PresenceResult {
+            members: Vec<jrow_core::presence::Member>,
+        }
+
+        let listed: PresenceResult = self
+            .request(PRESENCE_METHOD, PresenceParams { topic: &topic })
+            .await?;
+        tx.send_replace(
+            listed
+                .members
+                .into_iter()
+                .map(|member| (member.conn_id, member))
+                .collect(),
+        );
+
+        Ok(crate::PresenceList::new(topic, rx))
+    }
+
+    /// Stop updating the member list of a topic
+    pub async fn unsubscribe_presence(&self, topic: impl AsRef<str>) -> Result<()> {
+        self.unsubscribe(presence_topic(topic.as_ref())).await
+    }
+
     /// Subscribe to multiple topics at once using a batch request
     pub async fn subscribe_batch<F, Fut>(&self, topics: Vec<(String, F)>) -> Result<()>
     where
@@ -387,7 +883,7 @@ impl JrowClient {
         // Track all subscriptions locally
         let mut subscribed = self.subscribed_topics.lock().await;
         for topic in topic_names {
-            subscribed.insert(topic);
+            subscribed.insert(topic, SubscribeOptions::default());
         }
 
         Ok(())
@@ -436,7 +932,7 @@ impl JrowClient {
         self.subscribed_topics
             .lock()
             .await
-            .iter()
+            .keys()
             .cloned()
             .collect()
     }
@@ -459,8 +955,41 @@ impl JrowClient {
         F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
         Fut: std::future::Future<Output = ()> + Send + 'static,
     {
-        let subscription_id = subscription_id.into();
-        let topic = topic.into();
+        self.subscribe_persistent_with(subscription_id.into(), topic.into(), None, handler)
+            .await
+    }
+
+    /// Subscribe persistently, receiving only messages whose data matches `filter`
+    ///
+    /// The server stores the filter with the subscription and applies it to
+    /// replayed messages as well. Resuming with `subscribe_persistent` keeps
+    /// the stored filter; subscribing with another filter replaces it.
+    pub async fn subscribe_persistent_filtered<F, Fut>(
+        &self,
+        subscription_id: impl Into<String>,
+        topic: impl Into<String>,
+        filter: ContentFilter,
+        handler: F,
+    ) -> Result<u64>
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.subscribe_persistent_with(subscription_id.into(), topic.into(), Some(filter), handler)
+            .await
+    }
+
+    async fn subscribe_persistent_with<F, Fut>(
+        &self,
+        subscription_id: String,
+        topic: String,
+        filter: Option<ContentFilter>,
+        handler: F,
+    ) -> Result<u64>
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
 
         // Register the notification handler for this topic
         self.notification_handler
@@ -479,11 +1008,14 @@ impl JrowClient {
         struct SubscribePersistentParams {
             subscription_id: String,
             topic: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            filter: Option<ContentFilter>,
         }
 
         #[derive(Deserialize)]
         struct SubscribePersistentResult {
             subscribed: bool,
+            #[allow(dead_code)]
             subscription_id: String,
             #[allow(dead_code)]
             topic: String,
@@ -498,13 +1030,18 @@ impl JrowClient {
                 SubscribePersistentParams {
                     subscription_id: subscription_id.clone(),
                     topic: topic.clone(),
+                    filter: filter.clone(),
                 },
             )
             .await?;
 
         if result.subscribed {
             // Track subscription locally
-            self.subscribed_topics.lock().await.insert(topic.clone());
+            let options = SubscribeOptions {
+                filter,
+                ..SubscribeOptions::default()
+            };
+            self.subscribed_topics.lock().await.insert(topic.clone(), options);
             
             // Track persistent subscription for auto-resume on reconnect
             self.persistent_subscriptions.lock().await.push(PersistentSubscriptionInfo {
@@ -624,6 +1161,38 @@ impl JrowClient {
         Ok(())
     }
 
+    /// Store `data` on the server and send it to the persistent subscribers
+    /// of `topic`, like `JrowServer::publish_persistent`
+    ///
+    /// The server must have persistent storage, and its publish ACL must
+    /// allow the topic. Returns the message's sequence ID.
+    pub async fn publish_persistent<P>(&self, topic: impl Into<String>, data: P) -> Result<u64>
+    where
+        P: Serialize,
+    {
+        #[derive(Serialize)]
+        struct PublishPersistentParams<P> {
+            topic: String,
+            data: P,
+        }
+
+        #[derive(Deserialize)]
+        struct PublishPersistentResult {
+            sequence_id: u64,
+        }
+
+        let result: PublishPersistentResult = self
+            .request(
+                "rpc.publish_persistent",
+                PublishPersistentParams {
+                    topic: topic.into(),
+                    data,
+                },
+            )
+            .await?;
+        Ok(result.sequence_id)
+    }
+
     /// Subscribe to multiple persistent subscriptions at once using a batch request
     /// 
     /// This method provides the same guarantees as `subscribe_persistent` but for multiple
@@ -653,6 +1222,7 @@ impl JrowClient {
         #[derive(Deserialize)]
         struct SubscribeResult {
             subscription_id: String,
+            #[allow(dead_code)]
             topic: String,
             success: bool,
             resumed_from_seq: u64,
@@ -861,7 +1431,7 @@ impl JrowClient {
         self.sender
             .lock()
             .await
-            .send(Message::Text(batch_text))
+            .send(Message::Text(batch_text.into()))
             .await
             .map_err(|e| Error::WebSocket(e.to_string()))?;
 
@@ -887,6 +1457,7 @@ impl JrowClient {
     }
 
     /// Wrapper for receive loop that handles reconnection
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn receive_loop_with_reconnect(
         mut receiver: futures::stream::SplitStream<
             WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>,
@@ -902,10 +1473,11 @@ impl JrowClient {
             >,
         >,
         connection_manager: Option<Arc<ConnectionManager>>,
-        subscribed_topics: Arc<Mutex<HashSet<String>>>,
+        subscribed_topics: Arc<Mutex<HashMap<String, SubscribeOptions>>>,
         persistent_subscriptions: Arc<Mutex<Vec<PersistentSubscriptionInfo>>>,
         url: String,
         metrics: Option<Arc<crate::ClientMetrics>>,
+        closed: Arc<AtomicBool>,
     ) {
         loop {
             // Process messages until disconnection
@@ -937,6 +1509,19 @@ impl JrowClient {
                 }
             }
 
+            // Closed on purpose - don't reconnect
+            if closed.load(Ordering::SeqCst) {
+                tracing::info!("Connection closed by client");
+                if let Some(ref cm) = connection_manager {
+                    cm.disconnected().await;
+                }
+                if let Some(ref m) = metrics {
+                    m.update_connection_state(0); // Disconnected
+                }
+                request_manager.fail_all(Error::ConnectionClosed).await;
+                return;
+            }
+
             // Connection lost - attempt reconnection if enabled
             if let Some(ref cm) = connection_manager {
                 cm.disconnected().await;
@@ -987,13 +1572,19 @@ impl JrowClient {
                                     }
 
                                     // Resubscribe to all regular topics
-                                    let topics: Vec<String> =
-                                        subscribed_topics.lock().await.iter().cloned().collect();
-                                    for topic in topics {
+                                    let topics: Vec<(String, SubscribeOptions)> = subscribed_topics
+                                        .lock()
+                                        .await
+                                        .iter()
+                                        .map(|(topic, options)| (topic.clone(), options.clone()))
+                                        .collect();
+                                    for (topic, options) in topics {
                                         tracing::info!(topic = %topic, "Resubscribing to regular topic");
                                         #[derive(Serialize)]
                                         struct SubscribeParams {
                                             topic: String,
+                                            #[serde(flatten)]
+                                            options: SubscribeOptions,
                                         }
 
                                         let id = request_manager.next_id().await;
@@ -1002,6 +1593,7 @@ impl JrowClient {
                                             Some(
                                                 serde_json::to_value(SubscribeParams {
                                                     topic: topic.clone(),
+                                                    options,
                                                 })
                                                 .unwrap(),
                                             ),
@@ -1012,7 +1604,7 @@ impl JrowClient {
                                             let _ = sender
                                                 .lock()
                                                 .await
-                                                .send(Message::Text(request_text))
+                                                .send(Message::Text(request_text.into()))
                                                 .await;
                                         }
                                     }
@@ -1050,7 +1642,7 @@ impl JrowClient {
                                             let _ = sender
                                                 .lock()
                                                 .await
-                                                .send(Message::Text(request_text))
+                                                .send(Message::Text(request_text.into()))
                                                 .await;
                                         }
                                     }
@@ -36,7 +36,8 @@ use crate::{
 use crate::{reconnect::ExponentialBackoff, request::RequestManager};
 use futures::StreamExt;
 use jrow_core::{Error, Result};
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
 use tokio_tungstenite::connect_async;
@@ -105,8 +106,9 @@ impl ClientBuilder {
     pub async fn connect(self) -> Result<JrowClient> {
         let request_manager = RequestManager::new();
         let notification_handler = NotificationHandler::new();
-        let subscribed_topics = Arc::new(Mutex::new(HashSet::new()));
+        let subscribed_topics = Arc::new(Mutex::new(HashMap::new()));
         let persistent_subscriptions = Arc::new(Mutex::new(Vec::new()));
+        let closed = Arc::new(AtomicBool::new(false));
 
         // Initialize observability if configured
         let metrics = if let Some(mut config) = self.observability_config {
@@ -165,6 +167,7 @@ impl ClientBuilder {
             connection_manager: connection_manager.clone(),
             pending_requests: Arc::new(RwLock::new(Vec::new())),
             metrics: metrics.clone(),
+            closed: closed.clone(),
         };
 
         tracing::info!("Connected successfully");
@@ -180,6 +183,7 @@ impl ClientBuilder {
             persistent_subscriptions.clone(),
             self.url.clone(),
             metrics,
+            closed,
         ));
 
         Ok(client)
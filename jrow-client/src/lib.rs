@@ -9,6 +9,12 @@
 //! - **WebSocket Transport**: Async WebSocket communication
 //! - **Request-Response**: Send requests and await responses with type safety
 //! - **Pub/Sub**: Subscribe to topics and receive notifications
+//! - **Subscribe Options**: Content filters, queue groups, retained message opt-out and throttling
+//! - **Snapshots**: Receive a topic's state, then versioned deltas in order
+//! - **Publishing**: Publish to the topics the server's publish ACL allows
+//! - **Request-Reply**: Send requests to a topic's subscribers and answer them as a responder
+//! - **Typed Streams**: Consume topic messages as a `Stream` of deserialized payloads
+//! - **Presence**: Keep a live list of the members of a topic
 //! - **Batch Requests**: Send multiple requests efficiently in one message
 //! - **Auto-Reconnection**: Configurable reconnection with exponential backoff
 //! - **Persistent Subscriptions**: Durable subscriptions with automatic resume
@@ -70,8 +76,11 @@ mod client_builder;
 mod connection_state;
 mod metrics;
 mod notification;
+mod presence;
 mod reconnect;
 mod request;
+mod stream;
+mod subscribe_options;
 
 pub use batch::{BatchRequest, BatchResponse};
 pub use client::JrowClient;
@@ -79,4 +88,13 @@ pub use client_builder::ClientBuilder;
 pub use connection_state::{ConnectionManager, ConnectionState};
 pub use metrics::ClientMetrics;
 pub use notification::NotificationHandler;
+pub use presence::PresenceList;
 pub use reconnect::{ExponentialBackoff, FixedDelay, NoReconnect, ReconnectionStrategy};
+pub use stream::SubscriptionStream;
+pub use subscribe_options::SubscribeOptions;
+
+/// Items used by code generated with `jrow-macros`; not part of the public API
+#[doc(hidden)]
+pub mod __private {
+    pub use jrow_core;
+}
@@ -50,6 +50,8 @@ pub type NotificationFn =
 #[derive(Clone)]
 pub struct NotificationHandler {
     handlers: Arc<Mutex<HashMap<String, NotificationFn>>>,
+    /// Handler for methods without a registered handler
+    fallback: Arc<Mutex<Option<NotificationFn>>>,
 }
 
 impl NotificationHandler {
@@ -57,6 +59,7 @@ impl NotificationHandler {
     pub fn new() -> Self {
         Self {
             handlers: Arc::new(Mutex::new(HashMap::new())),
+            fallback: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -70,6 +73,16 @@ impl NotificationHandler {
         self.handlers.lock().await.insert(method.into(), handler);
     }
 
+    /// Set the handler for notifications no other handler is registered for
+    pub async fn set_fallback<F, Fut>(&self, handler: F)
+    where
+        F: Fn(JsonRpcNotification) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handler: NotificationFn = Arc::new(move |notif| Box::pin(handler(notif)));
+        *self.fallback.lock().await = Some(handler);
+    }
+
     /// Handle an incoming notification
     pub async fn handle(&self, notification: JsonRpcNotification) {
         let method = notification.method.clone();
@@ -80,6 +93,9 @@ impl NotificationHandler {
             drop(handlers); // Release the lock before calling the handler
 
             handler(notification).await;
+        } else if let Some(fallback) = self.fallback.lock().await.clone() {
+            drop(handlers);
+            fallback(notification).await;
         } else {
             // Debug level: notifications are often sent for persistent subscriptions
             // but aren't needed since data comes through a different mechanism
@@ -0,0 +1,167 @@
+//! Live topic member lists
+//!
+//! Servers that track presence for a topic publish `presence.join` and
+//! `presence.leave` events on its companion topic (`presence.<topic>`) and
+//! list the current members through `rpc.presence`. A [`PresenceList`]
+//! combines both: it starts from the listed members and applies the events
+//! as they arrive, so it always reflects who is subscribed to the topic.
+//!
+//! Events published while the initial listing is in flight may be applied
+//! before it and then overwritten, so the list can briefly lag behind the
+//! server; the next event for the same member corrects it.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use jrow_client::JrowClient;
+//!
+//! # async fn example(client: &JrowClient) -> jrow_core::Result<()> {
+//! let mut lobby = client.subscribe_presence("rooms.lobby").await?;
+//! loop {
+//!     let online: Vec<String> = lobby
+//!         .members()
+//!         .into_iter()
+//!         .filter_map(|member| member.identity)
+//!         .collect();
+//!     println!("online: {:?}", online);
+//!     if lobby.changed().await.is_err() {
+//!         break;
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use jrow_core::presence::{Member, PresenceChange, PresenceEvent};
+use jrow_core::{Error, Result};
+use std::collections::BTreeMap;
+use tokio::sync::watch;
+
+/// Members of a topic by connection ID
+pub(crate) type Members = BTreeMap<u64, Member>;
+
+/// Live list of the members of a topic
+///
+/// Created by `JrowClient::subscribe_presence`. The list stops updating once
+/// `JrowClient::unsubscribe_presence` is called for the topic.
+pub struct PresenceList {
+    /// Topic whose members are listed
+    topic: String,
+    /// Latest member list, updated by the companion topic subscription
+    rx: watch::Receiver<Members>,
+}
+
+impl PresenceList {
+    pub(crate) fn new(topic: String, mut rx: watch::Receiver<Members>) -> Self {
+        // The list starts out as seen, so `changed` waits for the next update
+        rx.borrow_and_update();
+        Self { topic, rx }
+    }
+
+    /// Get the topic whose members are listed
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Current members, ordered by connection ID
+    pub fn members(&mut self) -> Vec<Member> {
+        self.rx.borrow_and_update().values().cloned().collect()
+    }
+
+    /// Number of current members
+    pub fn len(&self) -> usize {
+        self.rx.borrow().len()
+    }
+
+    /// Check if the topic has no members
+    pub fn is_empty(&self) -> bool {
+        self.rx.borrow().is_empty()
+    }
+
+    /// Check if any member is bound to `identity`
+    pub fn contains_identity(&self, identity: &str) -> bool {
+        self.rx
+            .borrow()
+            .values()
+            .any(|member| member.identity.as_deref() == Some(identity))
+    }
+
+    /// Wait until the member list changes
+    ///
+    /// Changes made since the last call to `members` count, so no update is
+    /// missed between the two. Returns an error once the list stops
+    /// updating.
+    pub async fn changed(&mut self) -> Result<()> {
+        self.rx
+            .changed()
+            .await
+            .map_err(|_| Error::Internal(format!("Presence of '{}' is no longer tracked", self.topic)))
+    }
+}
+
+impl std::fmt::Debug for PresenceList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PresenceList")
+            .field("topic", &self.topic)
+            .field("members", &*self.rx.borrow())
+            .finish()
+    }
+}
+
+/// Apply a presence event to the member list, ignoring malformed events
+pub(crate) fn apply(tx: &watch::Sender<Members>, data: serde_json::Value) {
+    let event: PresenceEvent = match serde_json::from_value(data) {
+        Ok(event) => event,
+        Err(e) => {
+            tracing::warn!(error = %e, "Ignoring malformed presence event");
+            return;
+        }
+    };
+
+    tx.send_modify(|members| match event.event {
+        PresenceChange::Join => {
+            members.insert(event.member.conn_id, event.member);
+        }
+        PresenceChange::Leave => {
+            members.remove(&event.member.conn_id);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event: &str, conn_id: u64, identity: Option<&str>) -> serde_json::Value {
+        serde_json::json!({
+            "event": event,
+            "topic": "rooms.1",
+            "conn_id": conn_id,
+            "identity": identity
+        })
+    }
+
+    #[tokio::test]
+    async fn test_events_update_list() {
+        let (tx, rx) = watch::channel(Members::new());
+        let mut list = PresenceList::new("rooms.1".to_string(), rx);
+
+        apply(&tx, event("presence.join", 2, Some("alice")));
+        apply(&tx, event("presence.join", 1, None));
+        list.changed().await.unwrap();
+        assert_eq!(
+            list.members().iter().map(|m| m.conn_id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(list.contains_identity("alice"));
+
+        apply(&tx, event("presence.leave", 2, Some("alice")));
+        apply(&tx, serde_json::json!({"event": "unknown"}));
+        assert_eq!(list.len(), 1);
+        assert!(!list.contains_identity("alice"));
+
+        drop(tx);
+        list.members();
+        assert!(list.changed().await.is_err());
+    }
+}
@@ -0,0 +1,137 @@
+//! Typed subscription streams
+//!
+//! `JrowClient::subscribe` delivers topic messages to a callback as raw JSON.
+//! A [`SubscriptionStream`] instead exposes the messages of a topic as a
+//! `futures::Stream` of deserialized payloads, which composes with the usual
+//! stream combinators and `while let Some(..) = stream.next().await` loops.
+//!
+//! # Pattern Topics
+//!
+//! For NATS-style patterns (`*`, `>`), the server wraps every message as
+//! `{"topic": ..., "data": ...}`. The stream unwraps `data` so the item type is
+//! the same whether the subscription is exact or pattern-based.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use futures::StreamExt;
+//! use jrow_client::JrowClient;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Tick { value: u64 }
+//!
+//! # async fn example(client: &JrowClient) -> jrow_core::Result<()> {
+//! let mut ticks = client.subscribe_stream::<Tick>("ticks").await?;
+//! while let Some(tick) = ticks.next().await {
+//!     println!("tick {}", tick?.value);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use futures::Stream;
+use jrow_core::{Error, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+/// Stream of typed messages received on a subscribed topic
+///
+/// Created by `JrowClient::subscribe_stream`. Items that cannot be
+/// deserialized into `T` are yielded as `Err(Error::Serialization)` rather
+/// than being dropped silently. The stream ends when the client unsubscribes
+/// from the topic or the connection is dropped.
+pub struct SubscriptionStream<T> {
+    /// Topic or pattern this stream is subscribed to
+    topic: String,
+    /// Whether payloads arrive wrapped as `{"topic", "data"}`
+    pattern: bool,
+    /// Raw notification params forwarded by the subscription callback
+    rx: mpsc::UnboundedReceiver<Value>,
+    /// The stream yields `T` but never stores one
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> SubscriptionStream<T> {
+    /// Create a stream fed by the given receiver
+    pub(crate) fn new(topic: String, rx: mpsc::UnboundedReceiver<Value>) -> Self {
+        let pattern = is_pattern(&topic);
+        Self {
+            topic,
+            pattern,
+            rx,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the topic or pattern this stream is subscribed to
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+}
+
+impl<T: DeserializeOwned> Stream for SubscriptionStream<T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.rx.poll_recv(cx) {
+            Poll::Ready(Some(mut value)) => {
+                if self.pattern {
+                    value = value.get_mut("data").map(Value::take).unwrap_or_default();
+                }
+                let item =
+                    serde_json::from_value(value).map_err(|e| Error::Serialization(e.to_string()));
+                Poll::Ready(Some(item))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Check whether a topic uses NATS wildcard tokens
+pub(crate) fn is_pattern(topic: &str) -> bool {
+    topic.split('.').any(|token| token == "*" || token == ">")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Tick {
+        value: u64,
+    }
+
+    #[tokio::test]
+    async fn test_exact_topic_stream() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut stream = SubscriptionStream::<Tick>::new("ticks".to_string(), rx);
+
+        tx.send(serde_json::json!({"value": 1})).unwrap();
+        tx.send(serde_json::json!({"wrong": true})).unwrap();
+        drop(tx);
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), Tick { value: 1 });
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pattern_topic_stream_unwraps_data() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut stream = SubscriptionStream::<Tick>::new("ticks.*".to_string(), rx);
+
+        tx.send(serde_json::json!({"topic": "ticks.fast", "data": {"value": 7}}))
+            .unwrap();
+
+        assert_eq!(stream.topic(), "ticks.*");
+        assert_eq!(stream.next().await.unwrap().unwrap(), Tick { value: 7 });
+    }
+}
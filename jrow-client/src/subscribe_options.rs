@@ -0,0 +1,89 @@
+//! Options for `rpc.subscribe`
+//!
+//! `JrowClient::subscribe` covers the common case. [`SubscribeOptions`]
+//! combines the optional parts of a subscription for
+//! `JrowClient::subscribe_with_options`: a content filter evaluated by the
+//! server, membership in a queue group, whether to receive the topic's
+//! retained messages, and throttling with conflation by key. The options are
+//! kept with the subscription and sent again when the client reconnects.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use jrow_client::{JrowClient, SubscribeOptions};
+//! use jrow_core::ContentFilter;
+//!
+//! # async fn example(client: &JrowClient) -> jrow_core::Result<()> {
+//! let options = SubscribeOptions::new()
+//!     .filter(ContentFilter::eq("/region", "eu"))
+//!     .without_retained()
+//!     .max_rate(4.0)
+//!     .conflate("/order_id");
+//! client
+//!     .subscribe_with_options("orders.>", options, |order| async move {
+//!         println!("{}", order);
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use jrow_core::ContentFilter;
+use serde::Serialize;
+
+/// Optional parts of a topic subscription
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SubscribeOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) filter: Option<ContentFilter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) queue_group: Option<String>,
+    /// Only sent when opting out, so older servers accept the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) retained: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) max_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) conflate: Option<String>,
+}
+
+impl SubscribeOptions {
+    /// Options for a plain subscription
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only receive messages whose data matches `filter`
+    pub fn filter(mut self, filter: ContentFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Join a queue group, sharing its messages with the other members
+    pub fn queue_group(mut self, queue_group: impl Into<String>) -> Self {
+        self.queue_group = Some(queue_group.into());
+        self
+    }
+
+    /// Don't receive the topic's retained messages when subscribing
+    pub fn without_retained(mut self) -> Self {
+        self.retained = Some(false);
+        self
+    }
+
+    /// Receive at most `per_second` deliveries per second
+    ///
+    /// Messages published in between are held on the server, and only the
+    /// latest one (per `conflate` key, if set) is delivered.
+    pub fn max_rate(mut self, per_second: f64) -> Self {
+        self.max_rate = Some(per_second);
+        self
+    }
+
+    /// Keep only the latest message per value at the JSON pointer `key`
+    /// while the client is behind, e.g. `"/symbol"` for a ticker
+    pub fn conflate(mut self, key: impl Into<String>) -> Self {
+        self.conflate = Some(key.into());
+        self
+    }
+}
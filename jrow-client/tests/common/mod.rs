@@ -68,7 +68,7 @@ impl MockWsServer {
                                                 
                                                 // Call handler and send response if any
                                                 if let Some(response) = handler(text).await {
-                                                    let _ = write.send(Message::Text(response)).await;
+                                                    let _ = write.send(Message::Text(response.into())).await;
                                                 }
                                             }
                                         } else {
@@ -0,0 +1,430 @@
+//! Content filters for subscriptions
+//!
+//! A `ContentFilter` is a predicate over the payload of published messages.
+//! Clients pass one as the `filter` parameter of `rpc.subscribe` or
+//! `rpc.subscribe_persistent`, and the server only delivers messages whose
+//! `data` matches it, so uninteresting messages never cross the wire.
+//!
+//! # Wire Format
+//!
+//! A field condition selects a value with a JSON pointer (`path`, `""` for
+//! the whole payload) and tests it with one or more operators, all of which
+//! must hold:
+//!
+//! ```json
+//! {"path": "/status", "eq": "open"}
+//! {"path": "/amount", "gte": 100, "lt": 1000}
+//! {"path": "/region", "in": ["eu", "us"]}
+//! {"path": "/customer/vip", "exists": true}
+//! ```
+//!
+//! Conditions combine with `all`, `any` and `not`:
+//!
+//! ```json
+//! {"all": [{"path": "/status", "eq": "open"}, {"not": {"path": "/test", "eq": true}}]}
+//! ```
+//!
+//! # Semantics
+//!
+//! - `eq`, `ne` and `in` compare JSON values; numbers compare by value, so
+//!   `1` equals `1.0`
+//! - `gt`, `gte`, `lt` and `lte` take a number or a string and only hold for
+//!   a value of the same kind
+//! - A missing value fails every operator except `ne` and `exists: false`
+//! - `all` of nothing matches everything, `any` of nothing matches nothing
+//!
+//! # Examples
+//!
+//! ```rust
+//! use jrow_core::ContentFilter;
+//! use serde_json::json;
+//!
+//! let filter = ContentFilter::all(vec![
+//!     ContentFilter::eq("/status", "open"),
+//!     ContentFilter::gte("/amount", 100),
+//! ]);
+//! assert!(filter.matches(&json!({"status": "open", "amount": 250})));
+//! assert!(!filter.matches(&json!({"status": "open", "amount": 50})));
+//!
+//! let parsed = ContentFilter::from_value(json!({"path": "/amount", "gte": 100})).unwrap();
+//! assert_eq!(parsed, ContentFilter::gte("/amount", 100));
+//! ```
+
+use crate::{Error, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+
+/// Predicate over the payload of a published message
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ContentFilter {
+    /// Every filter matches
+    All {
+        /// Filters that must all match
+        all: Vec<ContentFilter>,
+    },
+    /// At least one filter matches
+    Any {
+        /// Filters of which one must match
+        any: Vec<ContentFilter>,
+    },
+    /// The filter doesn't match
+    Not {
+        /// Filter that must not match
+        not: Box<ContentFilter>,
+    },
+    /// Conditions on one value of the payload
+    Field(Box<FieldFilter>),
+}
+
+/// Conditions on the value at a JSON pointer
+///
+/// Every operator that is set must hold.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FieldFilter {
+    /// JSON pointer to the value, `""` for the whole payload
+    pub path: String,
+    /// Value equals
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eq: Option<Value>,
+    /// Value differs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ne: Option<Value>,
+    /// Value is greater than
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gt: Option<Value>,
+    /// Value is greater than or equal to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gte: Option<Value>,
+    /// Value is less than
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lt: Option<Value>,
+    /// Value is less than or equal to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lte: Option<Value>,
+    /// Value equals one of
+    #[serde(rename = "in", skip_serializing_if = "Option::is_none")]
+    pub one_of: Option<Vec<Value>>,
+    /// Value is present (`true`) or absent (`false`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exists: Option<bool>,
+}
+
+impl ContentFilter {
+    /// Parse and validate a filter from its wire format
+    pub fn from_value(value: Value) -> Result<Self> {
+        parse(value).map_err(|e| Error::InvalidParams(format!("Invalid filter: {}", e)))
+    }
+
+    /// Match when every filter matches
+    pub fn all(filters: Vec<ContentFilter>) -> Self {
+        ContentFilter::All { all: filters }
+    }
+
+    /// Match when any filter matches
+    pub fn any(filters: Vec<ContentFilter>) -> Self {
+        ContentFilter::Any { any: filters }
+    }
+
+    /// Match when `filter` doesn't
+    pub fn negate(filter: ContentFilter) -> Self {
+        ContentFilter::Not {
+            not: Box::new(filter),
+        }
+    }
+
+    /// Value at `path` equals `value`
+    pub fn eq(path: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::field(path, |f| f.eq = Some(value.into()))
+    }
+
+    /// Value at `path` differs from `value`
+    pub fn ne(path: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::field(path, |f| f.ne = Some(value.into()))
+    }
+
+    /// Value at `path` is greater than `value`
+    pub fn gt(path: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::field(path, |f| f.gt = Some(value.into()))
+    }
+
+    /// Value at `path` is greater than or equal to `value`
+    pub fn gte(path: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::field(path, |f| f.gte = Some(value.into()))
+    }
+
+    /// Value at `path` is less than `value`
+    pub fn lt(path: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::field(path, |f| f.lt = Some(value.into()))
+    }
+
+    /// Value at `path` is less than or equal to `value`
+    pub fn lte(path: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::field(path, |f| f.lte = Some(value.into()))
+    }
+
+    /// Value at `path` equals one of `values`
+    pub fn one_of<V: Into<Value>>(path: impl Into<String>, values: impl IntoIterator<Item = V>) -> Self {
+        let values = values.into_iter().map(Into::into).collect();
+        Self::field(path, |f| f.one_of = Some(values))
+    }
+
+    /// A value is present at `path`
+    pub fn exists(path: impl Into<String>) -> Self {
+        Self::field(path, |f| f.exists = Some(true))
+    }
+
+    fn field(path: impl Into<String>, set: impl FnOnce(&mut FieldFilter)) -> Self {
+        let mut field = FieldFilter {
+            path: path.into(),
+            ..FieldFilter::default()
+        };
+        set(&mut field);
+        ContentFilter::Field(Box::new(field))
+    }
+
+    /// Check if a message payload matches this filter
+    pub fn matches(&self, payload: &Value) -> bool {
+        match self {
+            ContentFilter::All { all } => all.iter().all(|filter| filter.matches(payload)),
+            ContentFilter::Any { any } => any.iter().any(|filter| filter.matches(payload)),
+            ContentFilter::Not { not } => !not.matches(payload),
+            ContentFilter::Field(field) => field.matches(payload),
+        }
+    }
+}
+
+impl FieldFilter {
+    /// Check if the value at `path` of a payload meets every condition
+    pub fn matches(&self, payload: &Value) -> bool {
+        let value = payload.pointer(&self.path);
+
+        if let Some(exists) = self.exists {
+            if value.is_some() != exists {
+                return false;
+            }
+        }
+        if let Some(ne) = &self.ne {
+            if value.is_some_and(|value| equal(value, ne)) {
+                return false;
+            }
+        }
+
+        let has_value_conditions = self.eq.is_some()
+            || self.one_of.is_some()
+            || [&self.gt, &self.gte, &self.lt, &self.lte]
+                .iter()
+                .any(|bound| bound.is_some());
+        let Some(value) = value else {
+            return !has_value_conditions;
+        };
+
+        let ordered = |bound: &Option<Value>, accept: fn(Ordering) -> bool| {
+            bound
+                .as_ref()
+                .is_none_or(|bound| compare(value, bound).is_some_and(accept))
+        };
+
+        self.eq.as_ref().is_none_or(|eq| equal(value, eq))
+            && self
+                .one_of
+                .as_ref()
+                .is_none_or(|values| values.iter().any(|candidate| equal(value, candidate)))
+            && ordered(&self.gt, Ordering::is_gt)
+            && ordered(&self.gte, Ordering::is_ge)
+            && ordered(&self.lt, Ordering::is_lt)
+            && ordered(&self.lte, Ordering::is_le)
+    }
+}
+
+impl<'de> Deserialize<'de> for ContentFilter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        parse(value).map_err(|e| serde::de::Error::custom(format!("invalid filter: {}", e)))
+    }
+}
+
+/// JSON equality, comparing numbers by value
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| equal(x, y))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len()
+                && x.iter().all(|(key, x)| y.get(key).is_some_and(|y| equal(x, y)))
+        }
+        _ => a == b,
+    }
+}
+
+/// Order two numbers or two strings
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64()?.partial_cmp(&y.as_f64()?),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
+fn parse(value: Value) -> std::result::Result<ContentFilter, String> {
+    let Value::Object(mut map) = value else {
+        return Err("expected an object".to_string());
+    };
+
+    if map.len() == 1 {
+        if let Some(all) = map.remove("all") {
+            return parse_list(all, "all").map(ContentFilter::all);
+        }
+        if let Some(any) = map.remove("any") {
+            return parse_list(any, "any").map(ContentFilter::any);
+        }
+        if let Some(not) = map.remove("not") {
+            return parse(not).map(ContentFilter::negate);
+        }
+    }
+
+    parse_field(map).map(|field| ContentFilter::Field(Box::new(field)))
+}
+
+fn parse_list(value: Value, name: &str) -> std::result::Result<Vec<ContentFilter>, String> {
+    match value {
+        Value::Array(filters) => filters.into_iter().map(parse).collect(),
+        _ => Err(format!("'{}' expects an array of filters", name)),
+    }
+}
+
+fn parse_field(map: Map<String, Value>) -> std::result::Result<FieldFilter, String> {
+    let mut field = FieldFilter::default();
+    let mut path = None;
+    let mut has_operator = false;
+
+    for (key, value) in map {
+        match key.as_str() {
+            "path" => match value {
+                Value::String(p) if p.is_empty() || p.starts_with('/') => path = Some(p),
+                Value::String(p) => {
+                    return Err(format!("path '{}' is not a JSON pointer", p));
+                }
+                _ => return Err("'path' expects a string".to_string()),
+            },
+            "eq" => field.eq = Some(value),
+            "ne" => field.ne = Some(value),
+            "gt" | "gte" | "lt" | "lte" => {
+                if !(value.is_number() || value.is_string()) {
+                    return Err(format!("'{}' expects a number or a string", key));
+                }
+                let bound = match key.as_str() {
+                    "gt" => &mut field.gt,
+                    "gte" => &mut field.gte,
+                    "lt" => &mut field.lt,
+                    _ => &mut field.lte,
+                };
+                *bound = Some(value);
+            }
+            "in" => match value {
+                Value::Array(values) => field.one_of = Some(values),
+                _ => return Err("'in' expects an array".to_string()),
+            },
+            "exists" => match value {
+                Value::Bool(exists) => field.exists = Some(exists),
+                _ => return Err("'exists' expects a boolean".to_string()),
+            },
+            other => return Err(format!("unknown operator '{}'", other)),
+        }
+        has_operator |= key != "path";
+    }
+
+    field.path = path.ok_or("missing 'path'")?;
+    if !has_operator {
+        return Err(format!("no operator for path '{}'", field.path));
+    }
+    Ok(field)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parsed(value: Value) -> ContentFilter {
+        ContentFilter::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_field_operators() {
+        let order = json!({"status": "open", "amount": 250, "region": "eu", "tags": ["a"]});
+
+        assert!(ContentFilter::eq("/amount", 250.0).matches(&order));
+        assert!(ContentFilter::ne("/status", "closed").matches(&order));
+        assert!(ContentFilter::one_of("/region", ["eu", "us"]).matches(&order));
+        assert!(ContentFilter::eq("/tags", json!(["a"])).matches(&order));
+        assert!(parsed(json!({"path": "/amount", "gt": 100, "lte": 250})).matches(&order));
+        assert!(!parsed(json!({"path": "/amount", "gt": 100, "lt": 250})).matches(&order));
+        assert!(ContentFilter::gte("/status", "open").matches(&order));
+        // Numbers and strings don't order against each other
+        assert!(!ContentFilter::lt("/status", 1).matches(&order));
+    }
+
+    #[test]
+    fn test_missing_values() {
+        let payload = json!({"a": 1});
+
+        assert!(!ContentFilter::eq("/b", 1).matches(&payload));
+        assert!(!ContentFilter::exists("/b").matches(&payload));
+        assert!(ContentFilter::ne("/b", 1).matches(&payload));
+        assert!(parsed(json!({"path": "/b", "exists": false})).matches(&payload));
+        assert!(ContentFilter::eq("", json!({"a": 1})).matches(&payload));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let filter = parsed(json!({
+            "all": [
+                {"any": [{"path": "/kind", "eq": "buy"}, {"path": "/kind", "eq": "sell"}]},
+                {"not": {"path": "/test", "eq": true}}
+            ]
+        }));
+
+        assert!(filter.matches(&json!({"kind": "buy"})));
+        assert!(!filter.matches(&json!({"kind": "buy", "test": true})));
+        assert!(!filter.matches(&json!({"kind": "hold"})));
+        assert!(ContentFilter::all(vec![]).matches(&json!(null)));
+        assert!(!ContentFilter::any(vec![]).matches(&json!(null)));
+    }
+
+    #[test]
+    fn test_wire_round_trip() {
+        let filter = ContentFilter::all(vec![
+            ContentFilter::one_of("/region", ["eu"]),
+            ContentFilter::negate(ContentFilter::eq("/status", json!(null))),
+        ]);
+        let value = serde_json::to_value(&filter).unwrap();
+        assert_eq!(
+            value,
+            json!({"all": [
+                {"path": "/region", "in": ["eu"]},
+                {"not": {"path": "/status", "eq": null}}
+            ]})
+        );
+        assert_eq!(serde_json::from_value::<ContentFilter>(value).unwrap(), filter);
+    }
+
+    #[test]
+    fn test_invalid_filters() {
+        for (value, message) in [
+            (json!("open"), "expected an object"),
+            (json!({"eq": 1}), "missing 'path'"),
+            (json!({"path": "status", "eq": 1}), "not a JSON pointer"),
+            (json!({"path": "/a"}), "no operator"),
+            (json!({"path": "/a", "gt": [1]}), "number or a string"),
+            (json!({"path": "/a", "like": "x"}), "unknown operator 'like'"),
+            (json!({"all": {"path": "/a", "eq": 1}}), "expects an array"),
+        ] {
+            let error = ContentFilter::from_value(value).unwrap_err().to_string();
+            assert!(error.contains(message), "{} should mention {}", error, message);
+        }
+    }
+}
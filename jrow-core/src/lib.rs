@@ -6,6 +6,11 @@
 //! - **Types**: Core JSON-RPC 2.0 data structures (requests, responses, notifications)
 //! - **Codec**: Serialization and deserialization utilities for JSON-RPC messages
 //! - **Error handling**: Comprehensive error types for JSON-RPC operations
+//! - **Topics**: The `Topic` trait binding pub/sub topics to payload types
+//! - **Content filters**: Server-side predicates over published payloads
+//! - **Presence**: Wire format of topic membership listings and join/leave events
+//! - **Request-Reply**: Requests published to topics with a reply inbox
+//! - **Snapshots**: Versioned snapshot and delta messages of topics with snapshot providers
 //! - **Observability**: OpenTelemetry integration for distributed tracing, metrics, and logs
 //!
 //! # Overview
@@ -39,13 +44,20 @@
 
 pub mod codec;
 pub mod error;
+pub mod filter;
 pub mod observability;
+pub mod presence;
+pub mod request_reply;
+pub mod snapshot;
+pub mod topic;
 pub mod types;
 
 // Re-export the most commonly used types for convenience
 // This allows users to use `jrow_core::Error` instead of `jrow_core::error::Error`
 pub use error::{Error, JsonRpcErrorData, Result};
+pub use filter::ContentFilter;
 pub use observability::{init_observability, shutdown_observability, ObservabilityConfig};
+pub use topic::Topic;
 pub use types::{
     Id, JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
 };
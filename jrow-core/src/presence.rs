@@ -0,0 +1,93 @@
+//! Topic presence wire format
+//!
+//! Presence tells who is subscribed to a topic (a chat room, a shared
+//! document). Servers answer `rpc.presence` with the current members of a
+//! topic and can announce membership changes on the topic's companion
+//! topic, `presence.<topic>`:
+//!
+//! ```json
+//! {"event": "presence.join", "topic": "rooms.1", "conn_id": 7, "identity": "alice"}
+//! ```
+//!
+//! Both exact-topic subscribers and pattern subscribers whose pattern
+//! matches the topic count as members.
+
+use serde::{Deserialize, Serialize};
+
+/// Built-in method listing the members of a topic
+pub const PRESENCE_METHOD: &str = "rpc.presence";
+
+/// Prefix of the companion topics presence events are published on
+pub const PRESENCE_PREFIX: &str = "presence";
+
+/// Companion topic carrying the presence events of `topic`
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(jrow_core::presence::presence_topic("rooms.1"), "presence.rooms.1");
+/// ```
+pub fn presence_topic(topic: &str) -> String {
+    format!("{}.{}", PRESENCE_PREFIX, topic)
+}
+
+/// A connection subscribed to a topic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Member {
+    /// Server-assigned connection ID
+    pub conn_id: u64,
+    /// Identity the connection is bound to, if authenticated
+    #[serde(default)]
+    pub identity: Option<String>,
+}
+
+/// Kind of membership change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresenceChange {
+    /// A connection subscribed to the topic
+    #[serde(rename = "presence.join")]
+    Join,
+    /// A connection unsubscribed from the topic or disconnected
+    #[serde(rename = "presence.leave")]
+    Leave,
+}
+
+/// Membership change published on a companion topic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresenceEvent {
+    /// What happened
+    pub event: PresenceChange,
+    /// Topic whose membership changed
+    pub topic: String,
+    /// The member that joined or left
+    #[serde(flatten)]
+    pub member: Member,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_wire_format() {
+        let event = PresenceEvent {
+            event: PresenceChange::Join,
+            topic: "rooms.1".to_string(),
+            member: Member {
+                conn_id: 7,
+                identity: Some("alice".to_string()),
+            },
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "event": "presence.join",
+                "topic": "rooms.1",
+                "conn_id": 7,
+                "identity": "alice"
+            })
+        );
+        assert_eq!(serde_json::from_value::<PresenceEvent>(value).unwrap(), event);
+    }
+}
@@ -0,0 +1,75 @@
+//! Request-reply over pub/sub topics
+//!
+//! Like NATS request/reply, a request is published to a topic together
+//! with a reply inbox, a unique topic that only the requester waits on.
+//! Any subscriber of the topic can act as a responder; the first reply
+//! published to the inbox answers the request and later ones are dropped.
+//!
+//! Subscribers of the topic receive the request wrapped with its inbox:
+//!
+//! ```json
+//! {"reply_to": "_INBOX.6c1f0e2b9d4a7f3e81b2c5d4e6f70a19", "data": {"order_id": 42}}
+//! ```
+//!
+//! and reply by publishing to the inbox with `rpc.publish`:
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "method": "rpc.publish", "params": {"topic": "_INBOX.6c1f0e2b9d4a7f3e81b2c5d4e6f70a19", "data": {"status": "shipped"}}, "id": 7}
+//! ```
+//!
+//! Clients send requests with `rpc.request_topic`; the server publishes
+//! them with an inbox of its own and answers with the reply.
+
+use serde::{Deserialize, Serialize};
+
+/// Built-in method publishing a message to a topic
+pub const PUBLISH_METHOD: &str = "rpc.publish";
+
+/// Built-in method publishing a request to a topic and returning the reply
+pub const REQUEST_TOPIC_METHOD: &str = "rpc.request_topic";
+
+/// First token of reply inbox topics
+pub const INBOX_PREFIX: &str = "_INBOX";
+
+/// Whether `topic` is a reply inbox
+///
+/// # Examples
+///
+/// ```rust
+/// use jrow_core::request_reply::is_inbox;
+///
+/// assert!(is_inbox("_INBOX.6c1f0e2b"));
+/// assert!(!is_inbox("_INBOXES"));
+/// ```
+pub fn is_inbox(topic: &str) -> bool {
+    topic
+        .strip_prefix(INBOX_PREFIX)
+        .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Request delivered to the subscribers of a topic
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopicRequest<T = serde_json::Value> {
+    /// Inbox to publish the reply to
+    pub reply_to: String,
+    /// The request data
+    pub data: T,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_wire_format() {
+        let request = TopicRequest {
+            reply_to: "_INBOX.1".to_string(),
+            data: json!({"order_id": 42}),
+        };
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(value, json!({"reply_to": "_INBOX.1", "data": {"order_id": 42}}));
+        assert_eq!(serde_json::from_value::<TopicRequest>(value).unwrap(), request);
+        assert!(!is_inbox("orders._INBOX.1"));
+    }
+}
@@ -0,0 +1,84 @@
+//! Snapshot-then-delta wire format
+//!
+//! Servers can register a snapshot provider for a topic pattern. Messages
+//! on matching topics are then versioned: a new exact subscriber first
+//! receives a snapshot of the current state, then every later message as a
+//! delta, with nothing missing or repeated in between:
+//!
+//! ```json
+//! {"kind": "snapshot", "version": 3, "data": {"open_orders": 12}}
+//! {"kind": "delta", "version": 4, "data": {"opened": 1}}
+//! ```
+//!
+//! Versions count the messages published to a topic, and a snapshot has the
+//! version of the last delta it includes. A client that receives deltas
+//! from an older subscription, for instance around a reconnect, discards
+//! those whose version isn't greater than the last one it applied. Versions
+//! start over when the server restarts, so a snapshot always replaces the
+//! client's state whatever its version.
+
+use serde::{Deserialize, Serialize};
+
+/// Whether a versioned message is a full snapshot or a change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionedKind {
+    /// The state of the topic, sent once after subscribing
+    Snapshot,
+    /// A message published to the topic
+    Delta,
+}
+
+/// Message on a topic with a snapshot provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Versioned<T = serde_json::Value> {
+    /// Snapshot or delta
+    pub kind: VersionedKind,
+    /// Version of the topic after this message
+    pub version: u64,
+    /// The snapshot or published data
+    pub data: T,
+}
+
+impl<T> Versioned<T> {
+    /// Snapshot of a topic at `version`
+    pub fn snapshot(version: u64, data: T) -> Self {
+        Self {
+            kind: VersionedKind::Snapshot,
+            version,
+            data,
+        }
+    }
+
+    /// Message published to a topic as `version`
+    pub fn delta(version: u64, data: T) -> Self {
+        Self {
+            kind: VersionedKind::Delta,
+            version,
+            data,
+        }
+    }
+
+    /// Whether this is a snapshot
+    pub fn is_snapshot(&self) -> bool {
+        self.kind == VersionedKind::Snapshot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_wire_format() {
+        let snapshot = Versioned::snapshot(3, json!({"open_orders": 12}));
+        let value = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(value, json!({"kind": "snapshot", "version": 3, "data": {"open_orders": 12}}));
+        assert_eq!(serde_json::from_value::<Versioned>(value).unwrap(), snapshot);
+
+        let delta: Versioned = serde_json::from_value(json!({"kind": "delta", "version": 4, "data": 1})).unwrap();
+        assert_eq!(delta, Versioned::delta(4, json!(1)));
+        assert!(!delta.is_snapshot());
+    }
+}
@@ -0,0 +1,82 @@
+//! Typed pub/sub topics
+//!
+//! Topics are plain strings on the wire, which makes it easy for a publisher
+//! and a subscriber to disagree about the payload sent on a topic. The
+//! [`Topic`] trait binds a topic name (or NATS-style pattern) to a payload
+//! type, so both sides refer to the type instead of the string:
+//!
+//! - Server: `JrowServer::publish_typed(&message)`
+//! - Client: `JrowClient::subscribe_typed::<Message>()`
+//!
+//! Because the topic is derived from the payload type, publishing and
+//! subscribing with mismatched types is caught at compile time.
+//!
+//! # Deriving
+//!
+//! Use `#[derive(Topic)]` from `jrow-macros`. Fields can be interpolated into
+//! the published topic with `{field}` placeholders; each placeholder becomes a
+//! `*` wildcard in the subscription pattern. A field's value must be a single
+//! literal token (see [`is_literal_token`]); `JrowServer::publish_typed`
+//! rejects messages whose topic doesn't match the pattern:
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize, Topic)]
+//! #[topic("orders.created")]
+//! struct OrderCreated { order_id: u64 }
+//!
+//! #[derive(Serialize, Deserialize, Topic)]
+//! #[topic("users.{user_id}.events")]
+//! struct UserEvent { user_id: String, kind: String }
+//!
+//! assert_eq!(OrderCreated::TOPIC, "orders.created");
+//! assert_eq!(UserEvent::TOPIC, "users.*.events");
+//! ```
+//!
+//! # Manual Implementation
+//!
+//! ```rust
+//! use jrow_core::Topic;
+//!
+//! struct Heartbeat;
+//!
+//! impl Topic for Heartbeat {
+//!     const TOPIC: &'static str = "system.heartbeat";
+//! }
+//!
+//! assert_eq!(Heartbeat.topic(), "system.heartbeat");
+//! ```
+
+/// A payload type bound to a pub/sub topic
+pub trait Topic {
+    /// Topic name or NATS pattern that subscribers of this type use
+    const TOPIC: &'static str;
+
+    /// Concrete topic this message is published to
+    ///
+    /// Defaults to [`Topic::TOPIC`]. Types bound to a pattern override this
+    /// to fill the wildcards from the message contents, each with a value
+    /// for which [`is_literal_token`] holds.
+    fn topic(&self) -> String {
+        Self::TOPIC.to_string()
+    }
+}
+
+/// Whether `value` can fill a wildcard of [`Topic::TOPIC`]
+///
+/// It must be a single, non-empty token without `.`, `*` or `>`; otherwise
+/// the published topic wouldn't match the pattern subscribers use, or would
+/// read as a pattern itself.
+///
+/// # Examples
+///
+/// ```rust
+/// use jrow_core::topic::is_literal_token;
+///
+/// assert!(is_literal_token("alice"));
+/// assert!(!is_literal_token("alice.admin"));
+/// assert!(!is_literal_token(">"));
+/// assert!(!is_literal_token(""));
+/// ```
+pub fn is_literal_token(value: &str) -> bool {
+    !value.is_empty() && !value.contains(['.', '*', '>'])
+}
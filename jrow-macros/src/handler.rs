@@ -11,9 +11,30 @@
 //! 1. **Parse**: Parse the input function using `syn::ItemFn`
 //! 2. **Extract**: Extract function name, visibility, parameters, return type
 //! 3. **Transform**: Create an inner async function with the original body
-//! 4. **Wrap**: Generate a factory function that uses `from_typed_fn`
+//! 4. **Wrap**: Generate a factory function that uses `from_typed_fn_with_schema`,
+//!    or `from_args_fn` for handlers with named arguments
 //! 5. **Quote**: Convert the transformed AST back to Rust code
 //!
+//! # Whole Params vs Named Arguments
+//!
+//! A handler with zero or one argument receives the whole `params` value,
+//! deserialized into the argument's type. A handler with two or more
+//! arguments, an `Option<T>` or `#[default]` argument, or marked
+//! `#[handler(args)]` takes named arguments instead: the macro generates a hidden `<Name>Args` struct with
+//! one field per argument, and the request may pass them by name (object) or
+//! by position (array).
+//!
+//! Arguments of type `Option<T>` may be omitted. Arguments marked
+//! `#[default]` fall back to `Default::default()`, and `#[default(expr)]`
+//! falls back to `expr`.
+//!
+//! # Validation
+//!
+//! `#[handler(validate)]` wraps the handler with `jrow_server::validated`, so
+//! params are checked against the derived JSON Schema before deserialization
+//! and violations are reported with JSON pointers in the error `data`. The
+//! factory panics if the derived schema does not compile, naming the handler.
+//!
 //! # Why This Design?
 //!
 //! We generate a factory function (returns `Box<dyn Handler>`) rather than
@@ -22,6 +43,7 @@
 //! - **Easier registration**: Can call `router.route("method", handler())`
 //! - **Type inference**: Rust can infer parameter and return types automatically
 //! - **Closure compatibility**: Works with the existing `from_typed_fn` infrastructure
+//! - **Discovery**: The handler carries JSON Schemas for the OpenRPC document
 //!
 //! # Code Generation Example
 //!
@@ -36,19 +58,52 @@
 //! Generated output:
 //! ```ignore
 //! fn add() -> Box<dyn jrow_server::Handler> {
-//!     use jrow_server::from_typed_fn;
+//!     use jrow_server::from_typed_fn_with_schema;
 //!
 //!     async fn inner_handler(params: AddParams) -> Result<i32> {
 //!         Ok(params.a + params.b)
 //!     }
 //!
-//!     from_typed_fn(inner_handler)
+//!     from_typed_fn_with_schema(inner_handler)
+//! }
+//! ```
+//!
+//! With named arguments:
+//! ```ignore
+//! #[handler]
+//! async fn scale(value: i32, #[default(1)] factor: i32) -> Result<i32> {
+//!     Ok(value * factor)
+//! }
+//! ```
+//!
+//! Generated output:
+//! ```ignore
+//! fn scale() -> Box<dyn jrow_server::Handler> {
+//!     #[derive(::serde::Deserialize, jrow_server::schemars::JsonSchema)]
+//!     #[schemars(crate = "jrow_server::schemars")]
+//!     struct ScaleArgs {
+//!         value: i32,
+//!         #[serde(default = "__default_factor")]
+//!         factor: i32,
+//!     }
+//!
+//!     fn __default_factor() -> i32 { 1 }
+//!
+//!     async fn inner_handler(value: i32, factor: i32) -> Result<i32> {
+//!         Ok(value * factor)
+//!     }
+//!
+//!     jrow_server::from_args_fn(&["value", "factor"], |args: ScaleArgs| {
+//!         inner_handler(args.value, args.factor)
+//!     })
 //! }
 //! ```
 
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, FnArg, ItemFn, ReturnType};
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::{parse_macro_input, Attribute, Expr, FnArg, Ident, ItemFn, Pat, ReturnType, Type};
 
 /// Implementation of the handler attribute macro
 ///
@@ -58,6 +113,8 @@ use syn::{parse_macro_input, FnArg, ItemFn, ReturnType};
 ///
 /// # Arguments
 ///
+/// * `attr` - The attribute options: `args` forces named arguments, `validate`
+///   checks params against the derived JSON Schema
 /// * `input` - The token stream representing the attributed async function
 ///
 /// # Returns
@@ -69,32 +126,46 @@ use syn::{parse_macro_input, FnArg, ItemFn, ReturnType};
 /// We preserve all function attributes (doc comments, cfg, etc.) so that the
 /// generated function has the same metadata as the original. This ensures
 /// documentation and conditional compilation still work correctly.
-pub fn handler_impl(input: TokenStream) -> TokenStream {
+pub fn handler_impl(attr: TokenStream, input: TokenStream) -> TokenStream {
     // Parse the input tokens as a function item
     // This gives us structured access to all parts of the function
     let input_fn = parse_macro_input!(input as ItemFn);
 
-    // Extract key components we need to preserve or transform
-    let fn_name = &input_fn.sig.ident;      // Function name (e.g., "add")
-    let fn_vis = &input_fn.vis;              // Visibility (e.g., pub, pub(crate))
-    let fn_block = &input_fn.block;          // Function body (the actual implementation)
-    let fn_attrs = &input_fn.attrs;          // Attributes like #[doc], #[cfg], etc.
+    match expand(attr.into(), input_fn) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
 
-    // Extract the parameter type from the function signature
-    // We support either one typed parameter or no parameters
-    let param_type = match input_fn.sig.inputs.first() {
-        Some(FnArg::Typed(pat_type)) => {
-            // Found a typed parameter like `params: AddParams`
-            // Extract just the type part (AddParams)
-            let ty = &pat_type.ty;
-            quote! { #ty }
+/// Expand the attribute, returning a syn error on invalid input
+fn expand(attr: TokenStream2, mut input_fn: ItemFn) -> syn::Result<TokenStream2> {
+    // `#[handler(args)]` forces named arguments even for a single argument,
+    // `#[handler(validate)]` checks params against the derived JSON Schema
+    let options = syn::parse::Parser::parse2(
+        syn::punctuated::Punctuated::<Ident, syn::Token![,]>::parse_terminated,
+        attr,
+    )?;
+    let mut force_args = false;
+    let mut validate = false;
+    for option in options {
+        if option == "args" {
+            force_args = true;
+        } else if option == "validate" {
+            validate = true;
+        } else {
+            return Err(syn::Error::new_spanned(
+                option,
+                "unknown handler option, expected `args` or `validate`",
+            ));
         }
-        _ => {
-            // No parameters, or self parameter (which we don't support)
-            // Default to unit type () which deserializes from null or omitted params
-            quote! { () }
-        }
-    };
+    }
+
+    if let Some(FnArg::Receiver(receiver)) = input_fn.sig.inputs.first() {
+        return Err(syn::Error::new_spanned(
+            receiver,
+            "#[handler] functions cannot take `self`",
+        ));
+    }
 
     // Extract the return type from the function signature
     // This determines what type the async function returns
@@ -109,20 +180,155 @@ pub fn handler_impl(input: TokenStream) -> TokenStream {
         }
     };
 
+    // Named arguments need one field per argument, so collect them first
+    // (this also strips `#[default]` attributes from the inputs)
+    let args = take_args(&mut input_fn)?;
+
+    // A lone argument that may be omitted only keeps its fallback as a
+    // named argument; as whole params, omitting it would mean `null`
+    let omittable = args
+        .iter()
+        .any(|arg| !matches!(arg.default, ArgDefault::None) || is_option(&arg.ty));
+    if force_args || args.len() > 1 || omittable {
+        Ok(expand_args(&input_fn, &return_type, &args, validate))
+    } else {
+        Ok(expand_whole_params(&input_fn, &return_type, validate))
+    }
+}
+
+/// A named handler argument
+struct HandlerArg {
+    /// Argument binding, used as the field and wire name
+    ident: Ident,
+    /// Argument type
+    ty: Type,
+    /// Fallback when the argument is omitted
+    default: ArgDefault,
+}
+
+/// How an omitted argument is filled in
+enum ArgDefault {
+    /// Required, unless the type is `Option<T>`
+    None,
+    /// `#[default]`: use `Default::default()`
+    Default,
+    /// `#[default(expr)]`: evaluate `expr`
+    Expr(Expr),
+}
+
+/// Collect the function's arguments, removing `#[default]` attributes
+fn take_args(input_fn: &mut ItemFn) -> syn::Result<Vec<HandlerArg>> {
+    let mut args = Vec::new();
+
+    for input in input_fn.sig.inputs.iter_mut() {
+        let FnArg::Typed(pat_type) = input else {
+            continue;
+        };
+
+        let default = take_default(&mut pat_type.attrs)?;
+        let ident = match &*pat_type.pat {
+            Pat::Ident(pat) => Some(pat.ident.clone()),
+            _ => None,
+        };
+
+        match ident {
+            Some(ident) => args.push(HandlerArg {
+                ident,
+                ty: (*pat_type.ty).clone(),
+                default,
+            }),
+            None if !matches!(default, ArgDefault::None) => {
+                return Err(syn::Error::new_spanned(
+                    &pat_type.pat,
+                    "#[default] arguments must be plain identifiers",
+                ));
+            }
+            // Destructuring patterns are fine for the whole-params form;
+            // `expand_args` reports them if named arguments are needed
+            None => args.push(HandlerArg {
+                ident: Ident::new("_", Span::call_site()),
+                ty: (*pat_type.ty).clone(),
+                default,
+            }),
+        }
+    }
+
+    Ok(args)
+}
+
+/// Remove and parse a `#[default]` or `#[default(expr)]` attribute
+fn take_default(attrs: &mut Vec<Attribute>) -> syn::Result<ArgDefault> {
+    let Some(index) = attrs.iter().position(|a| a.path().is_ident("default")) else {
+        return Ok(ArgDefault::None);
+    };
+
+    let attr = attrs.remove(index);
+    match &attr.meta {
+        syn::Meta::Path(_) => Ok(ArgDefault::Default),
+        syn::Meta::List(_) => Ok(ArgDefault::Expr(attr.parse_args()?)),
+        syn::Meta::NameValue(_) => Err(syn::Error::new_spanned(
+            attr,
+            "expected `#[default]` or `#[default(expr)]`",
+        )),
+    }
+}
+
+/// Wrap a typed handler expression with `jrow_server::validated`
+///
+/// Derived schemas always compile, so a failure is a bug worth a loud panic
+/// naming the handler rather than a silently unvalidated method.
+fn validated(fn_name: &syn::Ident, handler: TokenStream2) -> TokenStream2 {
+    let message = format!("params schema of handler `{}` should compile: {{}}", fn_name);
+    quote! {
+        jrow_server::validated(#handler).unwrap_or_else(|e| panic!(#message, e))
+    }
+}
+
+/// Generate a handler that receives the whole params value
+fn expand_whole_params(input_fn: &ItemFn, return_type: &TokenStream2, validate: bool) -> TokenStream2 {
+    let fn_name = &input_fn.sig.ident;      // Function name (e.g., "add")
+    let fn_vis = &input_fn.vis;              // Visibility (e.g., pub, pub(crate))
+    let fn_block = &input_fn.block;          // Function body (the actual implementation)
+    let fn_attrs = &input_fn.attrs;          // Attributes like #[doc], #[cfg], etc.
+
+    // Extract the parameter from the function signature
+    // We support either one typed parameter or no parameters
+    let param = match input_fn.sig.inputs.first() {
+        Some(FnArg::Typed(pat_type)) => {
+            // Found a typed parameter like `params: AddParams`
+            // Keep the user's binding so the body can refer to it
+            let pat = &pat_type.pat;
+            let ty = &pat_type.ty;
+            quote! { #pat: #ty }
+        }
+        _ => {
+            // No parameters: use the unit type, which deserializes
+            // from null or omitted params
+            quote! { _: () }
+        }
+    };
+
+    // Optionally check params against the schema before deserializing
+    let handler = if validate {
+        validated(fn_name, quote! { from_typed_fn_with_schema(inner_handler) })
+    } else {
+        quote! { from_typed_fn_with_schema(inner_handler) }
+    };
+
     // Generate the replacement code
     // This is the factory function that will be called to create handlers
-    let expanded = quote! {
+    quote! {
         // Preserve all original attributes (doc comments, cfg, etc.)
         #(#fn_attrs)*
         // Keep the same visibility as the original function
         #fn_vis fn #fn_name() -> Box<dyn jrow_server::Handler> {
             // Import the typed handler factory function
-            use jrow_server::from_typed_fn;
+            use jrow_server::from_typed_fn_with_schema;
 
             // Create an inner async function with the original body
             // This is necessary because we need to extract the parameter type
             // separately from the handler creation logic
-            async fn inner_handler(params: #param_type) -> #return_type {
+            async fn inner_handler(#param) -> #return_type {
                 // Insert the original function body here
                 // This is the user's actual implementation
                 #fn_block
@@ -130,13 +336,137 @@ pub fn handler_impl(input: TokenStream) -> TokenStream {
 
             // Use the jrow_server helper to convert the typed async function
             // into a Box<dyn Handler> that handles JSON-RPC protocol details
-            from_typed_fn(inner_handler)
+            // and reports param/result schemas for OpenRPC discovery
+            #handler
+        }
+    }
+}
+
+/// Generate a handler that receives named arguments
+fn expand_args(
+    input_fn: &ItemFn,
+    return_type: &TokenStream2,
+    args: &[HandlerArg],
+    validate: bool,
+) -> TokenStream2 {
+    let fn_name = &input_fn.sig.ident;
+    let fn_vis = &input_fn.vis;
+    let fn_block = &input_fn.block;
+    let fn_attrs = &input_fn.attrs;
+    let inputs = &input_fn.sig.inputs;
+
+    // Every argument becomes a struct field, so it needs a name
+    if let Some(FnArg::Typed(pat_type)) = inputs
+        .iter()
+        .zip(args)
+        .find(|(_, arg)| arg.ident == "_")
+        .map(|(input, _)| input)
+    {
+        return syn::Error::new_spanned(
+            &pat_type.pat,
+            "named handler arguments must be plain identifiers",
+        )
+        .to_compile_error();
+    }
+
+    // The struct name shows up as the params title in discovery documents
+    let args_ident = format_ident!("{}Args", camel_case(&fn_name.unraw().to_string()));
+
+    let mut fields = Vec::new();
+    let mut default_fns = Vec::new();
+    for arg in args {
+        let ident = &arg.ident;
+        let ty = &arg.ty;
+        let attr = match &arg.default {
+            ArgDefault::None if is_option(ty) => quote! { #[serde(default)] },
+            ArgDefault::None => quote! {},
+            ArgDefault::Default => quote! { #[serde(default)] },
+            ArgDefault::Expr(expr) => {
+                // serde takes default functions by path, so emit one per argument
+                let default_fn = format_ident!("__default_{}", ident.unraw());
+                let path = default_fn.to_string();
+                default_fns.push(quote! {
+                    fn #default_fn() -> #ty {
+                        #expr
+                    }
+                });
+                quote! { #[serde(default = #path)] }
+            }
+        };
+        fields.push(quote! { #attr #ident: #ty });
+    }
+
+    let idents: Vec<_> = args.iter().map(|arg| &arg.ident).collect();
+    let names: Vec<_> = idents.iter().map(|ident| ident.unraw().to_string()).collect();
+
+    // Validation runs after positional params are mapped to names, so
+    // violations are reported by argument name
+    let handler = if validate {
+        let validated_handler = validated(
+            fn_name,
+            quote! {
+                jrow_server::from_typed_fn_with_schema(|args: #args_ident| {
+                    inner_handler(#(args.#idents),*)
+                })
+            },
+        );
+        quote! {
+            jrow_server::positional_args(
+                &[#(#names),*],
+                #validated_handler,
+            )
+        }
+    } else {
+        quote! {
+            jrow_server::from_args_fn(&[#(#names),*], |args: #args_ident| {
+                inner_handler(#(args.#idents),*)
+            })
         }
     };
 
-    // Convert the generated code back to a TokenStream
-    // This is what the compiler will use to replace the original function
-    TokenStream::from(expanded)
+    quote! {
+        #(#fn_attrs)*
+        #fn_vis fn #fn_name() -> Box<dyn jrow_server::Handler> {
+            // One field per argument, accepted by name or by position
+            #[derive(::serde::Deserialize, jrow_server::schemars::JsonSchema)]
+            #[schemars(crate = "jrow_server::schemars")]
+            struct #args_ident {
+                #(#fields,)*
+            }
+
+            #(#default_fns)*
+
+            async fn inner_handler(#inputs) -> #return_type {
+                #fn_block
+            }
+
+            #handler
+        }
+    }
 }
 
+/// Whether a type is spelled `Option<T>` (possibly path-qualified)
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
 
+/// Convert a snake_case function name to CamelCase
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
@@ -12,6 +12,19 @@
 //! - Parameter deserialization from JSON-RPC params
 //! - Return value serialization to JSON-RPC result
 //! - Error mapping to JSON-RPC errors
+//! - JSON Schemas for the OpenRPC discovery document (`rpc.discover`)
+//!
+//! ## `#[rpc]` - Shared Server/Client API Definition
+//!
+//! Turns a trait into the single definition of an RPC service, generating:
+//! - A `register_into(RouterBuilder)` helper that registers every method
+//! - A typed `<Trait>Client` wrapping `JrowClient`, one async method per RPC
+//! - Typed subscription streams and publish helpers for declared topics
+//!
+//! ## `#[derive(Topic)]` - Typed Pub/Sub Topics
+//!
+//! Binds a payload type to a topic name or pattern by implementing
+//! `jrow_core::Topic`, for use with `publish_typed` and `subscribe_typed`.
 //!
 //! # How It Works
 //!
@@ -20,7 +33,7 @@
 //! 1. Parses the function signature to extract parameter and return types
 //! 2. Wraps the function body in an inner async function
 //! 3. Generates a factory function that returns a `Box<dyn Handler>`
-//! 4. Uses `from_typed_fn` to create the handler with type conversion logic
+//! 4. Uses `from_typed_fn_with_schema` to create the handler with type conversion logic
 //!
 //! This means you write normal Rust functions with type-safe parameters, and
 //! the macro generates all the JSON-RPC protocol handling automatically.
@@ -75,6 +88,8 @@
 //! ```
 
 mod handler;
+mod rpc;
+mod topic;
 
 use proc_macro::TokenStream;
 
@@ -102,22 +117,47 @@ use proc_macro::TokenStream;
 ///     async fn inner_handler(params: MyParams) -> Result<MyResult> {
 ///         // ... implementation ...
 ///     }
-///     from_typed_fn(inner_handler)
+///     from_typed_fn_with_schema(inner_handler)
 /// }
 /// ```
 ///
 /// # Parameter Types
 ///
-/// The parameter type must implement `serde::Deserialize`. Common patterns:
+/// The parameter type must implement `serde::Deserialize` and
+/// `schemars::JsonSchema` (re-exported as `jrow_server::schemars`). Common patterns:
 ///
 /// - **Struct params**: `params: MyParams` for object parameters
 /// - **Unit params**: `params: ()` for methods with no parameters
 /// - **No params**: Omit the parameter entirely
+/// - **Named arguments**: Two or more arguments, or one with `#[handler(args)]`,
+///   `#[default]` or an `Option<T>` type
+///
+/// # Named Arguments
+///
+/// With several arguments, each one is a named parameter that clients may
+/// pass by name (`{"a": 1, "b": 2}`) or by position (`[1, 2]`). A single
+/// argument that may be omitted is named too, so its fallback applies:
+///
+/// - `Option<T>` arguments may be omitted and become `None`
+/// - `#[default]` arguments fall back to `Default::default()`
+/// - `#[default(expr)]` arguments fall back to `expr`
+///
+/// Invalid params errors name the argument that failed, e.g.
+/// ``invalid params at `b`: invalid type: string "x", expected i32``.
+/// The generated code derives `serde::Deserialize`, so the calling crate
+/// must depend on `serde`.
+///
+/// # Validation
+///
+/// `#[handler(validate)]` (or `#[handler(args, validate)]`) validates params
+/// against the derived JSON Schema before the handler runs. Violations are
+/// rejected with `-32602` and a `data` array of `{pointer, keyword, message}`
+/// entries instead of a serde error string.
 ///
 /// # Return Types
 ///
 /// The return type must:
-/// - Be a `Result<T, E>` where `T: Serialize` and `E` converts to `jrow_core::Error`
+/// - Be a `Result<T, E>` where `T: Serialize + JsonSchema` and `E` converts to `jrow_core::Error`
 /// - Or implement `Serialize` directly (though Result is recommended)
 ///
 /// # Attributes and Visibility
@@ -131,7 +171,7 @@ use proc_macro::TokenStream;
 /// ## Simple handler with struct params
 ///
 /// ```ignore
-/// #[derive(Deserialize)]
+/// #[derive(Deserialize, JsonSchema)]
 /// struct AddParams {
 ///     a: i32,
 ///     b: i32,
@@ -155,7 +195,7 @@ use proc_macro::TokenStream;
 /// ## Handler with complex return type
 ///
 /// ```ignore
-/// #[derive(Serialize)]
+/// #[derive(Serialize, JsonSchema)]
 /// struct Status {
 ///     version: String,
 ///     uptime: u64,
@@ -170,6 +210,16 @@ use proc_macro::TokenStream;
 /// }
 /// ```
 ///
+/// ## Handler with named arguments
+///
+/// ```ignore
+/// #[handler]
+/// async fn search(query: String, limit: Option<u32>, #[default(0)] offset: u32) -> Result<Vec<Hit>> {
+///     // Callable as {"query": "rust", "limit": 10} or ["rust", 10, 20]
+///     run_search(&query, limit.unwrap_or(25), offset).await
+/// }
+/// ```
+///
 /// # Error Handling
 ///
 /// Errors are automatically converted to JSON-RPC errors:
@@ -180,11 +230,141 @@ use proc_macro::TokenStream;
 /// # Limitations
 ///
 /// - The macro only works with async functions
-/// - Functions must have at most one parameter
+/// - Named arguments must be plain identifiers (no destructuring patterns)
 /// - Cannot use `self` (this is for free functions, not methods)
 #[proc_macro_attribute]
-pub fn handler(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    handler::handler_impl(item)
+pub fn handler(attr: TokenStream, item: TokenStream) -> TokenStream {
+    handler::handler_impl(attr, item)
 }
 
+/// Attribute macro for defining an RPC service as a trait
+///
+/// The trait is the single source of truth for method names, params and
+/// result types, so the server and client can no longer drift apart.
+///
+/// # Arguments
+///
+/// - `server`: generate the server side only (`register_into`, `<Trait>Topics`)
+/// - `client`: generate the client side only (`<Trait>Client`)
+/// - `namespace = "calc"`: prefix every method name as `calc.<method>`
+///
+/// With neither `server` nor `client`, both sides are generated. The
+/// generated code refers to `jrow_server`, `jrow_client`, `jrow_core` and
+/// `serde_json`, so the crate using the macro must depend on the ones
+/// needed by the sides it generates.
+///
+/// # Methods
+///
+/// Each RPC method is an `async fn` taking `&self` and at most one params
+/// argument, returning `jrow_core::Result<T>`. The wire name defaults to the
+/// method name and can be overridden with `#[method(name = "...")]`. Params
+/// and result types must implement `Serialize`, `Deserialize` and
+/// `JsonSchema` (they are registered with `from_typed_fn_with_schema`).
+///
+/// # Subscriptions
+///
+/// Topics are declared as body-less methods returning the payload type:
+///
+/// ```ignore
+/// #[subscription(topic = "calc.results")]
+/// fn results(&self) -> CalcResult;
+/// ```
+///
+/// The client gets `async fn results(&self) -> Result<SubscriptionStream<CalcResult>>`,
+/// and `<Trait>Topics` gets a `RESULTS` constant plus `publish_results(&server, &payload)`.
+/// For pattern topics (`calc.*`), the publish helper also takes the concrete topic and
+/// returns `InvalidParams` if it does not match the pattern.
+///
+/// # Examples
+///
+/// ```ignore
+/// use jrow_core::Result;
+/// use jrow_macros::rpc;
+///
+/// #[rpc(namespace = "calc")]
+/// pub trait Calculator {
+///     /// Add two numbers
+///     async fn add(&self, params: AddParams) -> Result<i32>;
+///
+///     #[method(name = "reset_all")]
+///     async fn reset(&self) -> Result<()>;
+///
+///     #[subscription(topic = "calc.results")]
+///     fn results(&self) -> CalcResult;
+/// }
+///
+/// struct CalculatorImpl;
+///
+/// impl Calculator for CalculatorImpl {
+///     async fn add(&self, params: AddParams) -> Result<i32> {
+///         Ok(params.a + params.b)
+///     }
+///
+///     async fn reset(&self) -> Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// // Server
+/// let router = CalculatorImpl.register_into(RouterBuilder::new()).try_build()?;
+///
+/// // Client
+/// let calc = CalculatorClient::new(JrowClient::connect("ws://localhost:8080").await?);
+/// let sum = calc.add(AddParams { a: 1, b: 2 }).await?;
+/// let mut results = calc.results().await?;
+/// ```
+///
+/// # Limitations
+///
+/// - The trait cannot be generic
+/// - Methods take at most one params argument after `&self`
+/// - Implementations must produce `Send` futures
+#[proc_macro_attribute]
+pub fn rpc(attr: TokenStream, item: TokenStream) -> TokenStream {
+    rpc::rpc_impl(attr, item)
+}
 
+/// Derive macro binding a payload type to a pub/sub topic
+///
+/// Implements `jrow_core::Topic` from a `#[topic("...")]` template. Use the
+/// type with `JrowServer::publish_typed(&message)` and
+/// `JrowClient::subscribe_typed::<Type>()`; since both sides name the type
+/// rather than the topic string, their payload types always match.
+///
+/// # Placeholders
+///
+/// A `{field}` token is filled from the message when publishing and becomes
+/// a `*` wildcard when subscribing. Placeholder fields must implement
+/// `Display`, and their values must be single tokens without `.`, `*` or
+/// `>` so the published topic matches the pattern; `JrowServer::publish_typed`
+/// returns an error for messages that break this.
+///
+/// # Examples
+///
+/// ```ignore
+/// use jrow_macros::Topic;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, Topic)]
+/// #[topic("orders.created")]
+/// struct OrderCreated {
+///     order_id: u64,
+/// }
+///
+/// #[derive(Serialize, Deserialize, Topic)]
+/// #[topic("users.{user_id}.events")]
+/// struct UserEvent {
+///     user_id: String,
+///     kind: String,
+/// }
+///
+/// // Server: publishes to "users.alice.events"
+/// server.publish_typed(&UserEvent { user_id: "alice".into(), kind: "login".into() }).await?;
+///
+/// // Client: subscribes to "users.*.events"
+/// let mut events = client.subscribe_typed::<UserEvent>().await?;
+/// ```
+#[proc_macro_derive(Topic, attributes(topic))]
+pub fn derive_topic(item: TokenStream) -> TokenStream {
+    topic::topic_impl(item)
+}
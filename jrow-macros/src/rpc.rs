@@ -0,0 +1,603 @@
+//! RPC trait procedural macro implementation
+//!
+//! This module implements the `#[rpc]` attribute macro. A single trait
+//! definition becomes the source of truth for both sides of an API:
+//!
+//! - **Server**: the trait itself (with `async fn` methods rewritten to return
+//!   `Send` futures) plus a provided `register_into(RouterBuilder)` method
+//!   that registers one handler per RPC method
+//! - **Client**: a `<Trait>Client` struct wrapping `JrowClient` with one
+//!   typed async method per RPC method and one typed stream per subscription
+//! - **Topics**: a `<Trait>Topics` struct with the topic name constants and
+//!   typed publish helpers for each declared subscription
+//!
+//! # Macro Expansion Process
+//!
+//! 1. **Parse**: Parse the trait and the `server`/`client`/`namespace` arguments
+//! 2. **Classify**: Split trait items into RPC methods and `#[subscription]` declarations
+//! 3. **Rewrite**: Turn `async fn` into `fn -> impl Future + Send` so handlers can be spawned
+//! 4. **Generate**: Emit the registration helper, client struct and topics struct
+//!
+//! # Code Generation Example
+//!
+//! Input:
+//! ```ignore
+//! #[rpc(namespace = "calc")]
+//! pub trait Calculator {
+//!     async fn add(&self, params: AddParams) -> Result<i32>;
+//!
+//!     #[subscription(topic = "calc.results")]
+//!     fn results(&self) -> CalcResult;
+//! }
+//! ```
+//!
+//! Generated output (abridged):
+//! ```ignore
+//! pub trait Calculator: Send + Sync + 'static {
+//!     fn add(&self, params: AddParams) -> impl Future<Output = Result<i32>> + Send;
+//!
+//!     fn register_into(self, builder: RouterBuilder) -> RouterBuilder where Self: Sized {
+//!         let service = Arc::new(self);
+//!         builder.handler("calc.add", /* from_typed_fn_with_schema(...) */)
+//!     }
+//! }
+//!
+//! pub struct CalculatorClient { client: JrowClient }
+//!
+//! impl CalculatorClient {
+//!     pub async fn add(&self, params: AddParams) -> jrow_core::Result<i32> { ... }
+//!     pub async fn results(&self) -> jrow_core::Result<SubscriptionStream<CalcResult>> { ... }
+//! }
+//!
+//! pub struct CalculatorTopics;
+//!
+//! impl CalculatorTopics {
+//!     pub const RESULTS: &'static str = "calc.results";
+//!     pub async fn publish_results(server: &JrowServer, payload: &CalcResult) -> jrow_core::Result<usize> { ... }
+//! }
+//! ```
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_quote, Attribute, Expr, ExprLit, FnArg, GenericArgument, Ident, ItemTrait, Lit, Meta,
+    Pat, PathArguments, ReturnType, Token, TraitItem, TraitItemFn, Type,
+};
+
+/// Options given as arguments to `#[rpc(...)]`
+#[derive(Default)]
+struct RpcOptions {
+    /// Generate the server side (`register_into`, topics struct)
+    server: bool,
+    /// Generate the client struct
+    client: bool,
+    /// Prefix added to every method name as `<namespace>.<method>`
+    namespace: Option<String>,
+}
+
+/// An RPC method declared in the trait
+struct RpcMethod {
+    /// Rust method name
+    ident: Ident,
+    /// Wire method name (namespace and `#[method(name)]` applied)
+    name: String,
+    /// Binding and type of the single params argument, if any
+    param: Option<(Ident, Type)>,
+    /// Success type `T` of the declared `Result<T>`
+    output: Type,
+    /// Doc comments copied onto the client method
+    docs: Vec<Attribute>,
+}
+
+/// A subscription declared with `#[subscription(topic = "...")]`
+struct RpcSubscription {
+    /// Rust method name
+    ident: Ident,
+    /// Topic name or NATS pattern
+    topic: String,
+    /// Payload type of each message
+    item: Type,
+    /// Doc comments copied onto the generated items
+    docs: Vec<Attribute>,
+}
+
+/// Implementation of the rpc attribute macro
+///
+/// Errors are reported as `compile_error!` pointing at the offending item,
+/// so a malformed trait produces a readable diagnostic instead of a panic.
+pub fn rpc_impl(attr: TokenStream, item: TokenStream) -> TokenStream {
+    match expand(attr.into(), item.into()) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Expand the macro, returning a syn error on invalid input
+fn expand(attr: TokenStream2, item: TokenStream2) -> syn::Result<TokenStream2> {
+    let options = parse_options(attr)?;
+    let mut item_trait: ItemTrait = syn::parse2(item)?;
+
+    if !item_trait.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item_trait.generics,
+            "#[rpc] traits cannot be generic",
+        ));
+    }
+
+    // Split trait items into RPC methods and subscription declarations
+    let mut methods = Vec::new();
+    let mut subscriptions = Vec::new();
+    let mut items = Vec::new();
+
+    for trait_item in std::mem::take(&mut item_trait.items) {
+        match trait_item {
+            TraitItem::Fn(mut method) => {
+                if let Some(topic) = take_subscription_topic(&mut method.attrs)? {
+                    subscriptions.push(parse_subscription(&method, topic)?);
+                    continue;
+                }
+
+                let name = take_method_name(&mut method.attrs)?
+                    .unwrap_or_else(|| method.sig.ident.to_string());
+                let name = match &options.namespace {
+                    Some(namespace) => format!("{}.{}", namespace, name),
+                    None => name,
+                };
+
+                methods.push(parse_method(&method, name)?);
+                items.push(TraitItem::Fn(rewrite_async(method)));
+            }
+            other => items.push(other),
+        }
+    }
+
+    item_trait.items = items;
+
+    // Handlers run on spawned tasks, so the service must be shareable
+    item_trait.supertraits.push(parse_quote!(::std::marker::Send));
+    item_trait.supertraits.push(parse_quote!(::std::marker::Sync));
+    item_trait.supertraits.push(parse_quote!('static));
+
+    let vis = item_trait.vis.clone();
+    let trait_ident = item_trait.ident.clone();
+
+    if options.server {
+        item_trait
+            .items
+            .push(TraitItem::Fn(register_into(&methods)));
+    }
+
+    let client = if options.client {
+        client_struct(&vis, &trait_ident, &methods, &subscriptions)
+    } else {
+        TokenStream2::new()
+    };
+
+    let topics = if options.server && !subscriptions.is_empty() {
+        topics_struct(&vis, &trait_ident, &subscriptions)
+    } else {
+        TokenStream2::new()
+    };
+
+    Ok(quote! {
+        #item_trait
+        #client
+        #topics
+    })
+}
+
+/// Parse `server`, `client` and `namespace = "..."` macro arguments
+///
+/// When neither `server` nor `client` is given, both sides are generated.
+fn parse_options(attr: TokenStream2) -> syn::Result<RpcOptions> {
+    let mut options = RpcOptions::default();
+    let metas = Punctuated::<Meta, Token![,]>::parse_terminated.parse2(attr)?;
+
+    for meta in metas {
+        match &meta {
+            Meta::Path(path) if path.is_ident("server") => options.server = true,
+            Meta::Path(path) if path.is_ident("client") => options.client = true,
+            Meta::NameValue(nv) if nv.path.is_ident("namespace") => {
+                options.namespace = Some(lit_str(&nv.value)?);
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "expected `server`, `client` or `namespace = \"...\"`",
+                ))
+            }
+        }
+    }
+
+    if !options.server && !options.client {
+        options.server = true;
+        options.client = true;
+    }
+
+    Ok(options)
+}
+
+/// Remove a `#[method(name = "...")]` attribute and return the name
+fn take_method_name(attrs: &mut Vec<Attribute>) -> syn::Result<Option<String>> {
+    take_name_value(attrs, "method", "name")
+}
+
+/// Remove a `#[subscription(topic = "...")]` attribute and return the topic
+fn take_subscription_topic(attrs: &mut Vec<Attribute>) -> syn::Result<Option<String>> {
+    take_name_value(attrs, "subscription", "topic")
+}
+
+/// Remove the attribute `#[attr_name(key = "...")]` and return its value
+fn take_name_value(
+    attrs: &mut Vec<Attribute>,
+    attr_name: &str,
+    key: &str,
+) -> syn::Result<Option<String>> {
+    let Some(index) = attrs.iter().position(|a| a.path().is_ident(attr_name)) else {
+        return Ok(None);
+    };
+    let attr = attrs.remove(index);
+
+    let mut value = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident(key) {
+            let lit: syn::LitStr = meta.value()?.parse()?;
+            value = Some(lit.value());
+            Ok(())
+        } else {
+            Err(meta.error(format!("expected `{} = \"...\"`", key)))
+        }
+    })?;
+
+    value
+        .map(Some)
+        .ok_or_else(|| syn::Error::new_spanned(attr, format!("missing `{} = \"...\"`", key)))
+}
+
+/// Extract a string literal from an attribute value
+fn lit_str(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(s), ..
+        }) => Ok(s.value()),
+        _ => Err(syn::Error::new_spanned(expr, "expected a string literal")),
+    }
+}
+
+/// Collect `#[doc]` attributes so they can be copied to generated items
+fn doc_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .cloned()
+        .collect()
+}
+
+/// Validate an RPC method signature and extract its parts
+///
+/// RPC methods must be `async`, take `&self` and at most one params
+/// argument, and return `Result<T>`.
+fn parse_method(method: &TraitItemFn, name: String) -> syn::Result<RpcMethod> {
+    let sig = &method.sig;
+
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            sig,
+            "rpc methods must be `async fn` (use #[subscription] for topics)",
+        ));
+    }
+
+    let param = match rpc_inputs(sig)?.as_slice() {
+        [] => None,
+        [(ident, ty)] => Some((ident.clone(), ty.clone())),
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &sig.inputs,
+                "rpc methods take at most one params argument after `&self`",
+            ))
+        }
+    };
+
+    Ok(RpcMethod {
+        ident: sig.ident.clone(),
+        name,
+        param,
+        output: result_ok_type(&sig.output)?,
+        docs: doc_attrs(&method.attrs),
+    })
+}
+
+/// Validate a subscription declaration and extract its parts
+fn parse_subscription(method: &TraitItemFn, topic: String) -> syn::Result<RpcSubscription> {
+    let sig = &method.sig;
+
+    if sig.asyncness.is_some() || method.default.is_some() {
+        return Err(syn::Error::new_spanned(
+            sig,
+            "subscriptions are declared as `fn name(&self) -> Item;` without a body",
+        ));
+    }
+    if !rpc_inputs(sig)?.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.inputs,
+            "subscriptions take no arguments besides `&self`",
+        ));
+    }
+
+    let item = match &sig.output {
+        ReturnType::Type(_, ty) => (**ty).clone(),
+        ReturnType::Default => {
+            return Err(syn::Error::new_spanned(
+                sig,
+                "subscriptions must declare their payload type as the return type",
+            ))
+        }
+    };
+
+    Ok(RpcSubscription {
+        ident: sig.ident.clone(),
+        topic,
+        item,
+        docs: doc_attrs(&method.attrs),
+    })
+}
+
+/// Check for a `&self` receiver and return the remaining typed arguments
+fn rpc_inputs(sig: &syn::Signature) -> syn::Result<Vec<(Ident, Type)>> {
+    let mut inputs = sig.inputs.iter();
+
+    match inputs.next() {
+        Some(FnArg::Receiver(receiver))
+            if receiver.reference.is_some() && receiver.mutability.is_none() => {}
+        _ => {
+            return Err(syn::Error::new_spanned(
+                sig,
+                "rpc trait methods must take `&self`",
+            ))
+        }
+    }
+
+    inputs
+        .enumerate()
+        .map(|(i, arg)| match arg {
+            FnArg::Typed(pat_type) => {
+                let ident = match &*pat_type.pat {
+                    Pat::Ident(pat) => pat.ident.clone(),
+                    _ => format_ident!("arg{}", i),
+                };
+                Ok((ident, (*pat_type.ty).clone()))
+            }
+            FnArg::Receiver(r) => Err(syn::Error::new_spanned(r, "unexpected receiver")),
+        })
+        .collect()
+}
+
+/// Extract `T` from a `Result<T>` or `Result<T, E>` return type
+fn result_ok_type(output: &ReturnType) -> syn::Result<Type> {
+    if let ReturnType::Type(_, ty) = output {
+        if let Type::Path(path) = &**ty {
+            if let Some(segment) = path.path.segments.last() {
+                if segment.ident == "Result" {
+                    if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                        if let Some(GenericArgument::Type(ok)) = args.args.first() {
+                            return Ok(ok.clone());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Err(syn::Error::new_spanned(
+        output,
+        "rpc methods must return `jrow_core::Result<T>`",
+    ))
+}
+
+/// Rewrite `async fn f(&self, ..) -> R` into `fn f(&self, ..) -> impl Future<Output = R> + Send`
+///
+/// Implementors can still write `async fn` in their impl blocks; the
+/// explicit `Send` bound is what lets the registered handlers be spawned.
+fn rewrite_async(mut method: TraitItemFn) -> TraitItemFn {
+    method.sig.asyncness = None;
+
+    let output = match &method.sig.output {
+        ReturnType::Type(_, ty) => quote! { #ty },
+        ReturnType::Default => quote! { () },
+    };
+    method.sig.output = parse_quote! {
+        -> impl ::std::future::Future<Output = #output> + ::std::marker::Send
+    };
+
+    if let Some(block) = method.default.take() {
+        method.default = Some(parse_quote! {{ async move #block }});
+    }
+
+    method
+}
+
+/// Generate the provided `register_into` trait method
+fn register_into(methods: &[RpcMethod]) -> TraitItemFn {
+    let registrations = methods.iter().map(|method| {
+        let ident = &method.ident;
+        let name = &method.name;
+
+        let (param, call) = match &method.param {
+            Some((_, ty)) => (quote! { params: #ty }, quote! { service.#ident(params).await }),
+            None => (quote! { _: () }, quote! { service.#ident().await }),
+        };
+
+        quote! {
+            let builder = builder.handler(#name, {
+                let service = ::std::sync::Arc::clone(&service);
+                ::jrow_server::from_typed_fn_with_schema(move |#param| {
+                    let service = ::std::sync::Arc::clone(&service);
+                    async move { #call }
+                })
+            });
+        }
+    });
+
+    parse_quote! {
+        /// Register every RPC method of this service with a router builder
+        ///
+        /// Generated by `#[rpc]`. The service is shared between handlers
+        /// through an `Arc`.
+        fn register_into(self, builder: ::jrow_server::RouterBuilder) -> ::jrow_server::RouterBuilder
+        where
+            Self: Sized,
+        {
+            let service = ::std::sync::Arc::new(self);
+            #(#registrations)*
+            builder
+        }
+    }
+}
+
+/// Generate the typed client struct
+fn client_struct(
+    vis: &syn::Visibility,
+    trait_ident: &Ident,
+    methods: &[RpcMethod],
+    subscriptions: &[RpcSubscription],
+) -> TokenStream2 {
+    let client_ident = format_ident!("{}Client", trait_ident);
+    let struct_doc = format!(
+        "Typed client for the [`{}`] RPC service (generated by `#[rpc]`)",
+        trait_ident
+    );
+
+    let method_fns = methods.iter().map(|method| {
+        let ident = &method.ident;
+        let name = &method.name;
+        let output = &method.output;
+        let docs = &method.docs;
+
+        match &method.param {
+            Some((param, ty)) => quote! {
+                #(#docs)*
+                pub async fn #ident(&self, #param: #ty) -> ::jrow_client::__private::jrow_core::Result<#output> {
+                    self.client.request(#name, #param).await
+                }
+            },
+            None => quote! {
+                #(#docs)*
+                pub async fn #ident(&self) -> ::jrow_client::__private::jrow_core::Result<#output> {
+                    self.client.request(#name, ()).await
+                }
+            },
+        }
+    });
+
+    let subscription_fns = subscriptions.iter().map(|sub| {
+        let ident = &sub.ident;
+        let topic = &sub.topic;
+        let item = &sub.item;
+        let docs = &sub.docs;
+
+        quote! {
+            #(#docs)*
+            pub async fn #ident(&self) -> ::jrow_client::__private::jrow_core::Result<::jrow_client::SubscriptionStream<#item>> {
+                self.client.subscribe_stream(#topic).await
+            }
+        }
+    });
+
+    quote! {
+        #[doc = #struct_doc]
+        #[derive(Clone)]
+        #vis struct #client_ident {
+            client: ::jrow_client::JrowClient,
+        }
+
+        impl #client_ident {
+            /// Wrap a connected client
+            pub fn new(client: ::jrow_client::JrowClient) -> Self {
+                Self { client }
+            }
+
+            /// Get the underlying untyped client
+            pub fn inner(&self) -> &::jrow_client::JrowClient {
+                &self.client
+            }
+
+            #(#method_fns)*
+            #(#subscription_fns)*
+        }
+    }
+}
+
+/// Generate topic constants and typed publish helpers
+fn topics_struct(
+    vis: &syn::Visibility,
+    trait_ident: &Ident,
+    subscriptions: &[RpcSubscription],
+) -> TokenStream2 {
+    let topics_ident = format_ident!("{}Topics", trait_ident);
+    let struct_doc = format!(
+        "Topics declared by the [`{}`] RPC service (generated by `#[rpc]`)",
+        trait_ident
+    );
+
+    let items = subscriptions.iter().map(|sub| {
+        let const_ident = Ident::new(&sub.ident.to_string().to_uppercase(), Span::call_site());
+        let publish_ident = format_ident!("publish_{}", sub.ident);
+        let topic = &sub.topic;
+        let item = &sub.item;
+        let docs = &sub.docs;
+
+        let is_pattern = topic.split('.').any(|t| t == "*" || t == ">");
+
+        // Pattern subscriptions need a concrete topic to publish to
+        let publish = if is_pattern {
+            quote! {
+                /// Publish a message on a concrete topic matching the declared pattern
+                ///
+                /// Returns `InvalidParams` if `topic` does not match the pattern.
+                pub async fn #publish_ident(
+                    server: &::jrow_server::JrowServer,
+                    topic: &str,
+                    payload: &#item,
+                ) -> ::jrow_server::__private::jrow_core::Result<usize> {
+                    ::jrow_server::__private::check_topic(topic, #topic)?;
+                    let data = ::jrow_server::__private::serde_json::to_value(payload).map_err(|e| {
+                        ::jrow_server::__private::jrow_core::Error::Serialization(e.to_string())
+                    })?;
+                    server.publish(topic, data).await
+                }
+            }
+        } else {
+            quote! {
+                /// Publish a message to all subscribers of this topic
+                pub async fn #publish_ident(
+                    server: &::jrow_server::JrowServer,
+                    payload: &#item,
+                ) -> ::jrow_server::__private::jrow_core::Result<usize> {
+                    let data = ::jrow_server::__private::serde_json::to_value(payload).map_err(|e| {
+                        ::jrow_server::__private::jrow_core::Error::Serialization(e.to_string())
+                    })?;
+                    server.publish(#topic, data).await
+                }
+            }
+        };
+
+        quote! {
+            #(#docs)*
+            pub const #const_ident: &'static str = #topic;
+
+            #publish
+        }
+    });
+
+    quote! {
+        #[doc = #struct_doc]
+        #vis struct #topics_ident;
+
+        impl #topics_ident {
+            #(#items)*
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! Topic derive macro implementation
+//!
+//! This module implements `#[derive(Topic)]`, which binds a payload type to a
+//! pub/sub topic by implementing `jrow_core::Topic`.
+//!
+//! # Topic Templates
+//!
+//! The `#[topic("...")]` attribute takes a dot-separated template. Tokens of
+//! the form `{field}` are placeholders:
+//!
+//! - In `Topic::TOPIC` (used by subscribers) each placeholder becomes `*`
+//! - In `Topic::topic()` (used by publishers) each placeholder is replaced
+//!   with the `Display` value of the named field, which must be a single
+//!   literal token (`jrow_core::topic::is_literal_token`). `topic()` can't
+//!   fail, so `JrowServer::publish_typed` checks the result against `TOPIC`
+//!   and returns an error instead of publishing.
+//!
+//! Placeholders must span a whole token, and raw `*`/`>` wildcards are not
+//! allowed because a publisher could not produce a concrete topic from them.
+//!
+//! # Code Generation Example
+//!
+//! Input:
+//! ```ignore
+//! #[derive(Topic)]
+//! #[topic("users.{user_id}.events")]
+//! struct UserEvent { user_id: String }
+//! ```
+//!
+//! Generated output:
+//! ```ignore
+//! impl jrow_core::Topic for UserEvent {
+//!     const TOPIC: &'static str = "users.*.events";
+//!
+//!     fn topic(&self) -> String {
+//!         format!("users.{}.events", self.user_id)
+//!     }
+//! }
+//! ```
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, LitStr};
+
+/// Implementation of the Topic derive macro
+pub fn topic_impl(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Expand the derive, returning a syn error on invalid input
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let template = topic_template(&input)?;
+    let template_value = template.value();
+
+    let mut pattern_tokens = Vec::new();
+    let mut format_tokens = Vec::new();
+    let mut fields = Vec::new();
+
+    for token in template_value.split('.') {
+        if token.is_empty() {
+            return Err(syn::Error::new_spanned(&template, "topic tokens cannot be empty"));
+        }
+
+        if let Some(field) = token.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+            if field.is_empty() || field.contains(['{', '}']) {
+                return Err(syn::Error::new_spanned(
+                    &template,
+                    format!("invalid placeholder `{}`", token),
+                ));
+            }
+            pattern_tokens.push("*");
+            format_tokens.push("{}");
+            fields.push(format_ident!("{}", field));
+        } else if token.contains(['{', '}']) {
+            return Err(syn::Error::new_spanned(
+                &template,
+                format!("placeholder must span a whole token, found `{}`", token),
+            ));
+        } else if token == "*" || token == ">" {
+            return Err(syn::Error::new_spanned(
+                &template,
+                "use `{field}` placeholders instead of raw wildcards",
+            ));
+        } else {
+            pattern_tokens.push(token);
+            format_tokens.push(token);
+        }
+    }
+
+    if !fields.is_empty() {
+        let has_named_fields = matches!(
+            &input.data,
+            Data::Struct(data) if matches!(data.fields, Fields::Named(_))
+        );
+        if !has_named_fields {
+            return Err(syn::Error::new_spanned(
+                &template,
+                "topic placeholders require a struct with named fields",
+            ));
+        }
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let pattern = pattern_tokens.join(".");
+
+    // Exact topics use the provided `topic()` implementation
+    let topic_fn = if fields.is_empty() {
+        quote! {}
+    } else {
+        let format = format_tokens.join(".");
+        quote! {
+            fn topic(&self) -> ::std::string::String {
+                ::std::format!(#format, #(self.#fields),*)
+            }
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics jrow_core::Topic for #ident #ty_generics #where_clause {
+            const TOPIC: &'static str = #pattern;
+
+            #topic_fn
+        }
+    })
+}
+
+/// Find the `#[topic("...")]` attribute
+fn topic_template(input: &DeriveInput) -> syn::Result<LitStr> {
+    let attr = input
+        .attrs
+        .iter()
+        .find(|a| a.path().is_ident("topic"))
+        .ok_or_else(|| {
+            syn::Error::new_spanned(
+                &input.ident,
+                "missing #[topic(\"...\")] attribute on #[derive(Topic)] type",
+            )
+        })?;
+
+    attr.parse_args::<LitStr>()
+}
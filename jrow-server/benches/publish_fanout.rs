@@ -0,0 +1,84 @@
+//! Publish fan-out throughput with 10k subscribers
+//!
+//! Run with `cargo bench -p jrow-server --features bench --bench publish_fanout`.
+//! Throughput is reported in notifications per second. The
+//! `encode_per_subscriber` case reproduces the previous fan-out, which
+//! cloned the payload and encoded the notification once per subscriber, as
+//! a baseline. The `to_frames` case also turns every queued notification
+//! into the frame its connection's send task writes.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use jrow_core::{codec, JsonRpcNotification};
+use jrow_server::bench::FanoutFixture;
+use std::time::{Duration, Instant};
+
+const SUBSCRIBERS: usize = 10_000;
+
+fn payload() -> serde_json::Value {
+    serde_json::json!({
+        "id": 42,
+        "symbol": "JROW",
+        "price": 123.45,
+        "tags": ["fast", "shared", "encoded-once"],
+        "book": { "bid": [123.4, 123.3, 123.2], "ask": [123.5, 123.6, 123.7] },
+    })
+}
+
+/// Time `iters` publishes, excluding the time spent draining the queues
+fn run(runtime: &tokio::runtime::Runtime, fixture: &mut FanoutFixture, topic: &str, iters: u64) -> Duration {
+    let data = payload();
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..iters {
+        let start = Instant::now();
+        let sent = runtime.block_on(fixture.publish(topic, &data)).unwrap();
+        elapsed += start.elapsed();
+        assert_eq!(sent, SUBSCRIBERS);
+        fixture.drain();
+    }
+    elapsed
+}
+
+fn publish_fanout(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("publish_fanout");
+    group.throughput(Throughput::Elements(SUBSCRIBERS as u64));
+
+    let mut exact = FanoutFixture::new();
+    runtime.block_on(exact.subscribe(SUBSCRIBERS, "prices.jrow"));
+    group.bench_function("exact_10k", |b| {
+        b.iter_custom(|iters| run(&runtime, &mut exact, "prices.jrow", iters))
+    });
+
+    let mut pattern = FanoutFixture::new();
+    runtime.block_on(pattern.subscribe(SUBSCRIBERS, "prices.*"));
+    group.bench_function("pattern_10k", |b| {
+        b.iter_custom(|iters| run(&runtime, &mut pattern, "prices.jrow", iters))
+    });
+
+    group.bench_function("exact_10k_to_frames", |b| {
+        b.iter_custom(|iters| {
+            let data = payload();
+            let start = Instant::now();
+            for _ in 0..iters {
+                runtime.block_on(exact.publish("prices.jrow", &data)).unwrap();
+                assert_eq!(exact.frames(), SUBSCRIBERS);
+            }
+            start.elapsed()
+        })
+    });
+
+    let data = payload();
+    group.bench_function("encode_per_subscriber_10k", |b| {
+        b.iter(|| {
+            for _ in 0..SUBSCRIBERS {
+                let notification = JsonRpcNotification::new("prices.jrow", Some(data.clone()));
+                criterion::black_box(codec::encode_notification(&notification).unwrap());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, publish_fanout);
+criterion_main!(benches);
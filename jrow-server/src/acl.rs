@@ -0,0 +1,134 @@
+//! Access control for publishing from clients
+//!
+//! Clients publish with the built-in `rpc.publish` and
+//! `rpc.publish_persistent` methods and their `_batch` variants. Each topic
+//! a client publishes to is checked by the publish ACL registered with
+//! `ServerBuilder::publish_acl`:
+//!
+//! - Without an ACL, clients can only publish replies to reply inboxes
+//!   (see `jrow_core::request_reply`) and send `rpc.request_topic`
+//!   requests. Everything else is denied.
+//! - With an ACL, it decides for every publish and topic request. Replies
+//!   to inboxes are always allowed, since only the requester waits on them.
+//!
+//! A denied publish fails with an invalid request error and isn't
+//! delivered. In a batch, only the denied items fail.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use jrow_server::{JrowServer, PublishKind};
+//!
+//! # async fn example() -> jrow_core::Result<()> {
+//! let server = JrowServer::builder()
+//!     .bind_str("127.0.0.1:8080")?
+//!     .publish_acl(|attempt| async move {
+//!         match attempt.identity {
+//!             Some(user) => {
+//!                 attempt.kind != PublishKind::Persistent
+//!                     && attempt.topic.starts_with(&format!("chat.{}.", user))
+//!             }
+//!             None => false,
+//!         }
+//!     })
+//!     .build()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::IdentityRegistry;
+use jrow_core::request_reply::is_inbox;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// How a client is publishing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublishKind {
+    /// `rpc.publish`, delivered to current subscribers
+    Message,
+    /// `rpc.publish_persistent`, stored and delivered to persistent subscribers
+    Persistent,
+    /// `rpc.request_topic`, delivered with a reply inbox
+    Request,
+}
+
+/// A client's attempt to publish, as seen by the publish ACL
+#[derive(Debug, Clone)]
+pub struct PublishAttempt {
+    /// Connection ID, as seen by middleware and `notify_connection`
+    pub conn_id: u64,
+    /// Identity the connection is bound to in the `IdentityRegistry`
+    pub identity: Option<String>,
+    /// Topic published to
+    pub topic: String,
+    /// How the client is publishing
+    pub kind: PublishKind,
+}
+
+/// Hook deciding whether a client may publish
+pub(crate) type PublishAclHook =
+    Arc<dyn Fn(PublishAttempt) -> Pin<Box<dyn Future<Output = bool> + Send>> + Send + Sync>;
+
+/// The publish ACL of a server
+#[derive(Clone, Default)]
+pub(crate) struct PublishAcl {
+    hook: Option<PublishAclHook>,
+    identities: IdentityRegistry,
+}
+
+impl PublishAcl {
+    /// Check attempts with `hook`, or apply the defaults without one
+    pub(crate) fn new(hook: Option<PublishAclHook>, identities: IdentityRegistry) -> Self {
+        Self { hook, identities }
+    }
+
+    /// Whether connection `conn_id` may publish to `topic`
+    pub(crate) async fn allows(&self, conn_id: u64, topic: &str, kind: PublishKind) -> bool {
+        if kind == PublishKind::Message && is_inbox(topic) {
+            return true;
+        }
+        match &self.hook {
+            Some(hook) => {
+                hook(PublishAttempt {
+                    conn_id,
+                    identity: self.identities.identity_of(conn_id),
+                    topic: topic.to_string(),
+                    kind,
+                })
+                .await
+            }
+            None => kind == PublishKind::Request,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_defaults_without_hook() {
+        let acl = PublishAcl::default();
+        assert!(acl.allows(1, "_INBOX.1", PublishKind::Message).await);
+        assert!(acl.allows(1, "orders", PublishKind::Request).await);
+        assert!(!acl.allows(1, "orders", PublishKind::Message).await);
+        assert!(!acl.allows(1, "_INBOX.1", PublishKind::Persistent).await);
+    }
+
+    #[tokio::test]
+    async fn test_hook_sees_identity() {
+        let identities = IdentityRegistry::new();
+        identities.bind(1, "alice");
+        let hook: PublishAclHook = Arc::new(|attempt| {
+            Box::pin(async move { attempt.identity.as_deref() == Some("alice") && attempt.topic.starts_with("alice.") })
+        });
+        let acl = PublishAcl::new(Some(hook), identities);
+
+        assert!(acl.allows(1, "alice.status", PublishKind::Persistent).await);
+        assert!(!acl.allows(1, "bob.status", PublishKind::Message).await);
+        assert!(!acl.allows(2, "alice.status", PublishKind::Request).await);
+        assert!(acl.allows(2, "_INBOX.1", PublishKind::Message).await);
+    }
+}
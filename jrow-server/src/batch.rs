@@ -25,8 +25,11 @@
 //! let sequential = BatchProcessor::new(BatchMode::Sequential);
 //! ```
 
+use crate::connection::Dispatcher;
+use crate::session;
 use crate::{Router, SubscriptionManager};
 use jrow_core::{codec, JsonRpcErrorData, JsonRpcMessage, JsonRpcResponse};
+use std::sync::Arc;
 
 /// Mode for processing batch requests
 ///
@@ -36,13 +39,14 @@ use jrow_core::{codec, JsonRpcErrorData, JsonRpcMessage, JsonRpcResponse};
 ///
 /// - **Parallel**: Faster overall, but requests may complete out of order
 /// - **Sequential**: Preserves order, necessary if later requests depend on earlier ones
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum BatchMode {
     /// Process all requests concurrently (unordered)
     ///
     /// This is the default and recommended mode for maximum throughput.
     /// Responses are collected and returned in the same order as requests,
     /// but execution happens concurrently.
+    #[default]
     Parallel,
     
     /// Process requests sequentially in order
@@ -52,12 +56,6 @@ pub enum BatchMode {
     Sequential,
 }
 
-impl Default for BatchMode {
-    fn default() -> Self {
-        BatchMode::Parallel
-    }
-}
-
 /// Processor for handling batch requests
 #[derive(Clone)]
 pub struct BatchProcessor {
@@ -77,13 +75,28 @@ impl BatchProcessor {
     }
 
     /// Process a batch of JSON-RPC messages
-    #[tracing::instrument(skip(self, batch_values, router, sub_manager), fields(batch_size = batch_values.len(), mode = ?self.mode, conn_id = conn_id))]
+    ///
+    /// Entries are dispatched like requests from a connection without
+    /// server-side state: pattern subscriptions only last for this call and
+    /// persistent subscription methods fail. Batches received by a running
+    /// server have the connection's full state available.
     pub async fn process_batch(
         &self,
         batch_values: Vec<serde_json::Value>,
         router: &Router,
         conn_id: u64,
         sub_manager: &SubscriptionManager,
+    ) -> Vec<JsonRpcResponse> {
+        let dispatcher = Dispatcher::detached(Arc::new(router.clone()), conn_id, sub_manager.clone());
+        self.dispatch(batch_values, &dispatcher).await
+    }
+
+    /// Process a batch through the dispatch path of single messages
+    #[tracing::instrument(skip(self, batch_values, dispatcher), fields(batch_size = batch_values.len(), mode = ?self.mode, conn_id = dispatcher.conn_id))]
+    pub(crate) async fn dispatch(
+        &self,
+        batch_values: Vec<serde_json::Value>,
+        dispatcher: &Dispatcher,
     ) -> Vec<JsonRpcResponse> {
         // Check batch size limit
         if let Some(max_size) = self.max_size {
@@ -105,14 +118,8 @@ impl BatchProcessor {
         let messages = codec::decode_batch_messages(batch_values);
 
         let responses = match self.mode {
-            BatchMode::Parallel => {
-                self.process_parallel(messages, router, conn_id, sub_manager)
-                    .await
-            }
-            BatchMode::Sequential => {
-                self.process_sequential(messages, router, conn_id, sub_manager)
-                    .await
-            }
+            BatchMode::Parallel => self.process_parallel(messages, dispatcher).await,
+            BatchMode::Sequential => self.process_sequential(messages, dispatcher).await,
         };
         
         tracing::debug!(response_count = responses.len(), "Batch processing completed");
@@ -123,19 +130,17 @@ impl BatchProcessor {
     async fn process_parallel(
         &self,
         messages: Vec<Result<JsonRpcMessage, jrow_core::Error>>,
-        router: &Router,
-        conn_id: u64,
-        sub_manager: &SubscriptionManager,
+        dispatcher: &Dispatcher,
     ) -> Vec<JsonRpcResponse> {
         let mut tasks = Vec::new();
 
         for msg_result in messages {
-            let router = router.clone();
-            let sub_manager = sub_manager.clone();
+            let dispatcher = dispatcher.clone();
 
-            tasks.push(tokio::spawn(async move {
-                process_single_message(msg_result, &router, conn_id, &sub_manager).await
-            }));
+            // Keep the connection's session visible inside the spawned task
+            tasks.push(tokio::spawn(session::inherit(async move {
+                process_single_message(msg_result, &dispatcher).await
+            })));
         }
 
         // Wait for all tasks and collect results
@@ -153,16 +158,12 @@ impl BatchProcessor {
     async fn process_sequential(
         &self,
         messages: Vec<Result<JsonRpcMessage, jrow_core::Error>>,
-        router: &Router,
-        conn_id: u64,
-        sub_manager: &SubscriptionManager,
+        dispatcher: &Dispatcher,
     ) -> Vec<JsonRpcResponse> {
         let mut responses = Vec::new();
 
         for msg_result in messages {
-            if let Some(response) =
-                process_single_message(msg_result, router, conn_id, sub_manager).await
-            {
+            if let Some(response) = process_single_message(msg_result, dispatcher).await {
                 responses.push(response);
             }
         }
@@ -174,25 +175,14 @@ impl BatchProcessor {
 /// Process a single message from a batch
 async fn process_single_message(
     msg_result: Result<JsonRpcMessage, jrow_core::Error>,
-    router: &Router,
-    conn_id: u64,
-    sub_manager: &SubscriptionManager,
+    dispatcher: &Dispatcher,
 ) -> Option<JsonRpcResponse> {
     match msg_result {
-        Ok(JsonRpcMessage::Request(request)) => {
-            // Process request - use the same logic as connection.rs
-            Some(
-                crate::connection::process_request_for_batch(request, router, conn_id, sub_manager)
-                    .await,
-            )
-        }
+        Ok(JsonRpcMessage::Request(request)) => Some(dispatcher.request(request).await),
         Ok(JsonRpcMessage::Notification(notification)) => {
             // Process notification but don't return a response
-            if let Err(e) = router
-                .route_with_conn_id(&notification.method, notification.params, conn_id)
-                .await
-            {
-                eprintln!("Error processing notification in batch: {}", e);
+            if let Err(e) = dispatcher.notification(notification).await {
+                tracing::error!(error = %e, "Error processing notification in batch");
             }
             None
         }
@@ -418,4 +408,37 @@ mod tests {
         assert_eq!(responses.len(), 1);
         assert!(responses[0].error.is_some());
     }
+
+    #[tokio::test]
+    async fn test_batch_builtin_methods() {
+        let router = Router::new();
+        let sub_manager = SubscriptionManager::new();
+        let processor = BatchProcessor::new(BatchMode::Sequential);
+
+        let pattern = JsonRpcRequest::new(
+            "rpc.subscribe",
+            Some(serde_json::json!({"topic": "events.*"})),
+            Id::Number(1),
+        );
+        let persistent = JsonRpcRequest::new(
+            "rpc.subscribe_persistent",
+            Some(serde_json::json!({"subscription_id": "s", "topic": "orders"})),
+            Id::Number(2),
+        );
+        let batch = vec![
+            serde_json::to_value(&pattern).unwrap(),
+            serde_json::to_value(&persistent).unwrap(),
+        ];
+
+        let responses = processor
+            .process_batch(batch, &router, 1, &sub_manager)
+            .await;
+
+        // Patterns aren't mistaken for exact topics
+        assert_eq!(responses[0].result.as_ref().unwrap()["pattern"], true);
+        assert!(sub_manager.get_subscribers("events.*").await.is_empty());
+        // Persistent methods are built-ins, not unknown methods
+        let error = responses[1].error.as_ref().unwrap();
+        assert!(error.message.contains("Persistent storage not configured"));
+    }
 }
@@ -0,0 +1,111 @@
+//! Fixtures for the benchmarks in `benches/`
+//!
+//! Benchmarks run outside the crate and can't reach the publish fan-out
+//! without real WebSocket connections, which would make 10k subscribers
+//! cost 20k file descriptors in one process. This module attaches
+//! in-memory connections to the same subscription managers and registry
+//! the server uses. It is only built with the `bench` feature and is not
+//! part of the public API.
+
+use crate::connection::{Backlog, Connection, Outbound, Publisher};
+use crate::TopicFilter;
+use jrow_core::Result;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Subscribers attached to in-memory connections
+pub struct FanoutFixture {
+    publisher: Publisher,
+    /// Each connection's queue, with its backlog to settle like a send task
+    receivers: Vec<(Arc<Backlog>, mpsc::UnboundedReceiver<Outbound>)>,
+}
+
+impl FanoutFixture {
+    /// Create a fixture without subscribers
+    pub fn new() -> Self {
+        Self {
+            publisher: Publisher::default(),
+            receivers: Vec::new(),
+        }
+    }
+
+    /// Attach `count` connections subscribed to `topic` or pattern
+    pub async fn subscribe(&mut self, count: usize, topic: &str) {
+        for _ in 0..count {
+            let conn_id = self.receivers.len() as u64;
+            let (tx, rx) = mpsc::unbounded_channel();
+            let conn = Connection::new(conn_id, tx);
+            self.receivers.push((Arc::clone(&conn.backlog), rx));
+            self.publisher.conn_registry.insert(conn_id, conn);
+
+            if topic.contains('*') || topic.contains('>') {
+                let filter = TopicFilter::new(topic).expect("valid pattern");
+                self.publisher.filtered_sub_manager.lock().await.subscribe(conn_id, filter);
+            } else {
+                self.publisher.sub_manager.subscribe(conn_id, topic).await;
+            }
+        }
+    }
+
+    /// Publish through the server's fan-out, returning the notifications sent
+    pub async fn publish(&self, topic: &str, data: &serde_json::Value) -> Result<usize> {
+        self.publisher.publish(topic, data).await
+    }
+
+    /// Discard queued notifications, returning how many there were
+    pub fn drain(&mut self) -> usize {
+        self.take(drop)
+    }
+
+    /// Turn queued notifications into frames as each send task would,
+    /// returning how many there were
+    pub fn frames(&mut self) -> usize {
+        self.take(|outbound| {
+            std::hint::black_box(outbound.into_message());
+        })
+    }
+
+    /// Hand every queued notification to `write`, settling the backlog
+    /// like the send task so pending counts don't grow across iterations
+    fn take(&mut self, mut write: impl FnMut(Outbound)) -> usize {
+        let mut taken = 0;
+        for (backlog, rx) in &mut self.receivers {
+            while let Ok(outbound) = rx.try_recv() {
+                let published = matches!(outbound, Outbound::Shared(_));
+                write(outbound);
+                if published {
+                    backlog.sent();
+                }
+                taken += 1;
+            }
+        }
+        taken
+    }
+}
+
+impl Default for FanoutFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_settles_backlog() {
+        let mut fixture = FanoutFixture::new();
+        fixture.subscribe(2, "news").await;
+
+        let data = serde_json::json!({"n": 1});
+        assert_eq!(fixture.publish("news", &data).await.unwrap(), 2);
+        assert_eq!(fixture.drain(), 2);
+        assert_eq!(fixture.publish("news", &data).await.unwrap(), 2);
+        assert_eq!(fixture.frames(), 2);
+
+        for conn in fixture.publisher.conn_registry.iter() {
+            assert_eq!(conn.pending(), 0);
+        }
+    }
+}
@@ -4,11 +4,17 @@
 //! a `JrowServer`. It allows you to:
 //! - Set the bind address
 //! - Register method handlers
+//! - Mount nested routers under method namespaces
 //! - Configure batch processing
-//! - Add middleware
+//! - Add middleware, globally or scoped to method patterns
 //! - Enable observability
 //! - Enable persistent storage
 //! - Configure retention policies
+//! - Enable OpenRPC discovery (`rpc.discover`)
+//! - Forward method prefixes to upstream servers (gateway mode, `gateway` feature)
+//! - React to connections opening and closing
+//! - Announce topic presence changes
+//! - Retain recent messages and provide topic snapshots
 //!
 //! # Examples
 //!
@@ -34,16 +40,25 @@
 //! # }
 //! ```
 
+use crate::acl::{PublishAcl, PublishAclHook};
+use crate::connection::Publisher;
+use crate::lifecycle::LifecycleHooks;
+use crate::presence::Presence;
+use crate::snapshot::SnapshotProvider;
 use crate::{
-    BatchMode, BatchProcessor, Handler, JrowServer, Middleware, MiddlewareChain, 
-    PersistentStorage, PersistentSubscriptionManager, RetentionPolicy, Router,
-    SubscriptionManager, SyncMiddleware,
+    from_fn, BatchMode, BatchProcessor, ConnectionInfo, DisconnectReason, Handler,
+    IdentityRegistry, JrowServer, MethodHandler, Middleware, MiddlewareChain, OpenRpcInfo,
+    PersistentStorage, PersistentSubscriptionManager, PublishAttempt, QueueStrategy,
+    RetentionPolicy, Router, RouterHandle, SubscriptionManager, SyncMiddleware, TopicFilter,
 };
+#[cfg(feature = "gateway")]
+use crate::{gateway::Gateway, Upstream};
 use jrow_core::{Error, Result};
 use std::collections::HashMap;
+use std::future::Future;
 use std::net::SocketAddr;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError};
 use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::sync::Mutex;
@@ -61,6 +76,22 @@ pub struct ServerBuilder {
     topic_retention_policies: HashMap<String, RetentionPolicy>,
     subscription_timeout: Option<Duration>,
     retention_interval: Duration,
+    discovery_info: Option<OpenRpcInfo>,
+    nested_routers: Vec<(String, Router)>,
+    /// Methods, patterns and fallbacks registered more than once
+    duplicates: Vec<String>,
+    /// First invalid nest prefix, reported by `build()`
+    invalid_prefix: Option<Error>,
+    #[cfg(feature = "gateway")]
+    upstreams: Vec<(String, Upstream)>,
+    identities: IdentityRegistry,
+    hooks: LifecycleHooks,
+    publish_acl: Option<PublishAclHook>,
+    presence_topics: Vec<TopicFilter>,
+    queue_strategy: QueueStrategy,
+    retention_rules: Vec<(TopicFilter, usize)>,
+    snapshot_providers: Vec<(TopicFilter, SnapshotProvider)>,
+    max_topic_request_timeout: Duration,
 }
 
 impl ServerBuilder {
@@ -78,6 +109,20 @@ impl ServerBuilder {
             topic_retention_policies: HashMap::new(),
             subscription_timeout: None,
             retention_interval: Duration::from_secs(60),
+            discovery_info: None,
+            nested_routers: Vec::new(),
+            duplicates: Vec::new(),
+            invalid_prefix: None,
+            #[cfg(feature = "gateway")]
+            upstreams: Vec::new(),
+            identities: IdentityRegistry::new(),
+            hooks: LifecycleHooks::default(),
+            publish_acl: None,
+            presence_topics: Vec::new(),
+            queue_strategy: QueueStrategy::default(),
+            retention_rules: Vec::new(),
+            snapshot_providers: Vec::new(),
+            max_topic_request_timeout: crate::inbox::DEFAULT_MAX_TIMEOUT,
         }
     }
 
@@ -97,14 +142,65 @@ impl ServerBuilder {
     }
 
     /// Register a handler for a method
+    ///
+    /// Registering a method twice makes `build()` fail.
     pub fn handler(mut self, method: impl Into<String>, handler: Box<dyn Handler>) -> Self {
+        let method = method.into();
+        if self.router.has_method(&method) {
+            self.duplicates.push(method.clone());
+        }
         self.router.register(method, handler);
         self
     }
 
+    /// Register a handler for every method matching a `NatsPattern`
+    ///
+    /// The handler receives the name of the method that was called. Methods
+    /// registered under their exact name take precedence. Registering a
+    /// pattern twice makes `build()` fail.
+    pub fn pattern_handler(mut self, pattern: &str, handler: Box<dyn MethodHandler>) -> Result<Self> {
+        if self.router.has_pattern(pattern) {
+            self.duplicates.push(pattern.to_string());
+        }
+        self.router.register_pattern(pattern, handler)?;
+        Ok(self)
+    }
+
+    /// Set the handler for methods matched by no registration or pattern
+    ///
+    /// Setting a second fallback makes `build()` fail.
+    pub fn fallback(mut self, handler: Box<dyn MethodHandler>) -> Self {
+        if self.router.has_fallback() {
+            self.duplicates.push("<fallback>".to_string());
+        }
+        self.router.set_fallback(handler);
+        self
+    }
+
     /// Set the router (replaces any previously registered handlers)
     pub fn router(mut self, router: Router) -> Self {
         self.router = router;
+        self.duplicates.clear();
+        self
+    }
+
+    /// Mount a router's methods under `prefix` (e.g. `user` for `user.create`)
+    ///
+    /// The nested router keeps its own middleware. Method names that collide
+    /// with other registered methods make `build()` fail, as does a prefix
+    /// that is empty or not made of literal tokens.
+    pub fn nest(mut self, prefix: impl Into<String>, router: Router) -> Self {
+        let prefix = prefix.into();
+        if let Err(e) = crate::router::validate_prefix(&prefix) {
+            self.invalid_prefix.get_or_insert(e);
+        }
+        self.nested_routers.push((prefix, router));
+        self
+    }
+
+    /// Mount a router's methods without a prefix
+    pub fn merge(mut self, router: Router) -> Self {
+        self.nested_routers.push((String::new(), router));
         self
     }
 
@@ -126,12 +222,82 @@ impl ServerBuilder {
         self
     }
 
+    /// Get the registry the server will use for connection identities
+    ///
+    /// Available before `build()` so authentication middleware can bind
+    /// connections to identities; see `JrowServer::notify_identity`.
+    pub fn identities(&self) -> IdentityRegistry {
+        self.identities.clone()
+    }
+
+    /// Run `hook` for each new connection, before it handles any message
+    ///
+    /// Returning an error rejects the connection, which is then closed with
+    /// a policy violation close frame carrying the error message. Hooks run
+    /// in registration order until one rejects.
+    pub fn on_connect<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ConnectionInfo) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks
+            .on_connect
+            .push(Arc::new(move |info| Box::pin(hook(info))));
+        self
+    }
+
+    /// Run `hook` after each accepted connection closes and is cleaned up
+    pub fn on_disconnect<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ConnectionInfo, DisconnectReason) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks
+            .on_disconnect
+            .push(Arc::new(move |info, reason| Box::pin(hook(info, reason))));
+        self
+    }
+
+    /// Decide with `hook` which topics clients may publish to
+    ///
+    /// The hook is asked for every `rpc.publish`, `rpc.publish_persistent`
+    /// and `rpc.request_topic`, and each item of their batches, except
+    /// replies to reply inboxes. Without one, clients can only reply and
+    /// send topic requests. Setting a hook again replaces it.
+    pub fn publish_acl<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(PublishAttempt) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.publish_acl = Some(Arc::new(move |attempt| Box::pin(hook(attempt))));
+        self
+    }
+
     /// Add sync middleware to the server
     pub fn use_sync_middleware<T: SyncMiddleware + 'static>(mut self, middleware: T) -> Self {
         self.middleware_chain.add_sync(middleware);
         self
     }
 
+    /// Add middleware that only runs for methods matching `pattern`
+    ///
+    /// `pattern` uses `NatsPattern` syntax, e.g. `admin.*` or `user.get`.
+    /// Returns an error if the pattern is invalid.
+    pub fn use_middleware_for(mut self, pattern: &str, middleware: Arc<dyn Middleware>) -> Result<Self> {
+        self.middleware_chain.add_scoped(pattern, middleware)?;
+        Ok(self)
+    }
+
+    /// Add sync middleware that only runs for methods matching `pattern`
+    pub fn use_sync_middleware_for<T: SyncMiddleware + 'static>(
+        mut self,
+        pattern: &str,
+        middleware: T,
+    ) -> Result<Self> {
+        self.middleware_chain.add_sync_scoped(pattern, middleware)?;
+        Ok(self)
+    }
+
     /// Enable OpenTelemetry observability with custom configuration
     pub fn with_observability(mut self, config: jrow_core::ObservabilityConfig) -> Self {
         self.observability_config = Some(config);
@@ -174,12 +340,135 @@ impl ServerBuilder {
         self
     }
 
+    /// Serve an OpenRPC document describing the server via `rpc.discover`
+    ///
+    /// The document is generated on each call from the handlers registered
+    /// at that moment, plus the built-in pub/sub methods.
+    pub fn with_discovery(mut self, info: OpenRpcInfo) -> Self {
+        self.discovery_info = Some(info);
+        self
+    }
+
+    /// Forward methods and topics under `prefix` to an upstream server
+    ///
+    /// `users` forwards `users.get` and subscriptions to `users.events`,
+    /// taking precedence over local handlers. Empty, malformed and duplicate
+    /// prefixes make `build()` fail. See [`Upstream`] for the options.
+    ///
+    /// Upstream connections aren't shared: each downstream connection opens
+    /// its own connection to every upstream it calls, so that upstream
+    /// notifications reach exactly the connection they belong to. With N
+    /// downstream clients and M upstreams, up to N×M upstream connections
+    /// are open; size the upstream servers' connection limits accordingly.
+    #[cfg(feature = "gateway")]
+    pub fn upstream(mut self, prefix: impl Into<String>, upstream: Upstream) -> Self {
+        self.upstreams.push((prefix.into(), upstream));
+        self
+    }
+
+    /// Publish join/leave events for topics matching `pattern`
+    ///
+    /// Events are published on the companion topic `presence.<topic>` when
+    /// a connection joins or leaves a matching topic, by subscribing to it
+    /// or to a pattern matching it; see
+    /// `jrow_core::presence` for the format. Returns an error if the pattern
+    /// is invalid.
+    pub fn track_presence(mut self, pattern: &str) -> Result<Self> {
+        let filter = TopicFilter::new(pattern)
+            .map_err(|e| Error::InvalidRequest(format!("Invalid presence pattern: {}", e)))?;
+        self.presence_topics.push(filter);
+        Ok(self)
+    }
+
+    /// Set how the member of a queue group that receives a message is chosen
+    ///
+    /// Defaults to `QueueStrategy::RoundRobin`.
+    pub fn queue_strategy(mut self, strategy: QueueStrategy) -> Self {
+        self.queue_strategy = strategy;
+        self
+    }
+
+    /// Keep the last `count` messages of each topic matching `pattern` in
+    /// memory and send them to new subscribers
+    ///
+    /// Use a count of 1 to retain the last value. When several patterns
+    /// match a topic, the first one registered applies. Returns an error if
+    /// the pattern is invalid or `count` is zero.
+    pub fn retain_messages(mut self, pattern: &str, count: usize) -> Result<Self> {
+        let filter = TopicFilter::new(pattern)
+            .map_err(|e| Error::InvalidRequest(format!("Invalid retention pattern: {}", e)))?;
+        if count == 0 {
+            return Err(Error::InvalidRequest(format!(
+                "Retention count for '{}' must be at least 1",
+                pattern
+            )));
+        }
+        self.retention_rules.push((filter, count));
+        Ok(self)
+    }
+
+    /// Send subscribers of topics matching `pattern` a snapshot from
+    /// `provider`, then each published message as a delta
+    ///
+    /// The provider is called with the topic when a connection subscribes to
+    /// it exactly, and its result is sent before any later message; an
+    /// error fails the subscription. Messages on matching topics are
+    /// versioned as described in `jrow_core::snapshot`. Change the state the
+    /// provider reads with `JrowServer::publish_update`, and don't publish
+    /// to the topic from the provider. When several patterns match a topic,
+    /// the first one registered applies. Returns an error if the pattern is
+    /// invalid.
+    pub fn snapshot_provider<F, Fut>(mut self, pattern: &str, provider: F) -> Result<Self>
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<serde_json::Value>> + Send + 'static,
+    {
+        let filter = TopicFilter::new(pattern)
+            .map_err(|e| Error::InvalidRequest(format!("Invalid snapshot pattern: {}", e)))?;
+        self.snapshot_providers
+            .push((filter, Arc::new(move |topic| Box::pin(provider(topic)))));
+        Ok(self)
+    }
+
+    /// Limit how long `rpc.request_topic` waits for a reply (default: 60 seconds)
+    ///
+    /// Clients asking for a longer `timeout_ms` get this limit instead, so a
+    /// client can't keep reply inboxes open indefinitely.
+    /// `JrowServer::request_topic` isn't limited.
+    pub fn max_topic_request_timeout(mut self, timeout: Duration) -> Self {
+        self.max_topic_request_timeout = timeout;
+        self
+    }
+
     /// Build and start the server
     pub async fn build(mut self) -> Result<JrowServer> {
         let addr = self
             .addr
             .ok_or_else(|| Error::InvalidRequest("No bind address specified".to_string()))?;
 
+        // Mount nested routers before binding so collisions fail fast,
+        // reporting them along with the methods registered twice
+        if let Some(e) = self.invalid_prefix {
+            return Err(e);
+        }
+        for (prefix, router) in std::mem::take(&mut self.nested_routers) {
+            let duplicates = self.router.mount(&prefix, router, false)?;
+            self.duplicates.extend(duplicates);
+        }
+        if !self.duplicates.is_empty() {
+            self.duplicates.sort();
+            self.duplicates.dedup();
+            return Err(crate::router::duplicate_methods_error(self.duplicates));
+        }
+        #[cfg(feature = "gateway")]
+        let gateway = if self.upstreams.is_empty() {
+            None
+        } else {
+            Some(Arc::new(Gateway::new(self.upstreams)?))
+        };
+        #[cfg(not(feature = "gateway"))]
+        let gateway = None;
+
         let listener = TcpListener::bind(addr)
             .await
             .map_err(|e| Error::Io(e.to_string()))?;
@@ -208,6 +497,29 @@ impl ServerBuilder {
             self.router.set_middleware(self.middleware_chain);
         }
 
+        let persistent = self.persistent_db_path.is_some();
+        let router = RouterHandle::new(self.router);
+
+        // Expose the discovery document as `rpc.discover`, generated from the
+        // live router so methods added at runtime are included
+        let discovery = self.discovery_info.map(|info| {
+            let served_info = info.clone();
+            let live_router = router.downgrade();
+            router.register(
+                crate::openrpc::DISCOVER_METHOD,
+                from_fn(move |_| {
+                    let document = live_router.upgrade().map(|current| {
+                        let current = current.read().unwrap_or_else(PoisonError::into_inner);
+                        crate::openrpc::openrpc_document(&served_info, &current, persistent)
+                    });
+                    async move {
+                        document.ok_or_else(|| Error::Internal("Server is shutting down".to_string()))
+                    }
+                }),
+            );
+            (info, persistent)
+        });
+
         // Initialize persistent storage if configured
         let (persistent_storage, persistent_sub_manager, retention_shutdown_tx) = if let Some(db_path) = self.persistent_db_path {
             tracing::info!(path = ?db_path, "Initializing persistent storage");
@@ -241,19 +553,42 @@ impl ServerBuilder {
             (None, None, None)
         };
 
+        let subscription_manager = SubscriptionManager::new();
+        let connection_registry: crate::Connections = Arc::new(dashmap::DashMap::new());
+        let publisher = Publisher {
+            sub_manager: subscription_manager.clone(),
+            filtered_sub_manager: Arc::new(Mutex::new(crate::FilteredSubscriptionManager::new())),
+            conn_registry: Arc::clone(&connection_registry),
+            queue_groups: Arc::new(crate::queue_group::QueueGroups::new(self.queue_strategy)),
+            retained: Arc::new(crate::retained::RetainedMessages::new(self.retention_rules)),
+            snapshots: Arc::new(crate::snapshot::Snapshots::new(self.snapshot_providers)),
+            throttles: Arc::default(),
+            inboxes: Arc::new(crate::inbox::Inboxes::new(self.max_topic_request_timeout)),
+            acl: PublishAcl::new(self.publish_acl, self.identities.clone()),
+            metrics: metrics.clone(),
+        };
+        let presence = Arc::new(Presence::new(
+            publisher.clone(),
+            self.identities.clone(),
+            self.presence_topics,
+        ));
+
         Ok(JrowServer {
             listener,
-            router: self.router,
-            subscription_manager: SubscriptionManager::new(),
-            filtered_subscription_manager: Arc::new(Mutex::new(
-                crate::FilteredSubscriptionManager::new(),
-            )),
-            connection_registry: Arc::new(Mutex::new(HashMap::new())),
+            router,
+            subscription_manager,
+            connection_registry,
             batch_processor: BatchProcessor::with_limit(self.batch_mode, self.max_batch_size),
             metrics,
             persistent_storage,
             persistent_sub_manager,
             retention_shutdown_tx,
+            discovery,
+            gateway,
+            identities: self.identities,
+            hooks: self.hooks,
+            presence,
+            publisher,
         })
     }
 }
@@ -282,6 +617,66 @@ mod tests {
         assert!(server.router.has_method("test"));
     }
 
+    #[tokio::test]
+    async fn test_builder_nested_routers() {
+        let users = crate::RouterBuilder::new()
+            .handler("create", from_fn(|_| async { Ok(serde_json::Value::Null) }))
+            .try_build()
+            .unwrap();
+
+        let server = ServerBuilder::new()
+            .bind_str("127.0.0.1:0")
+            .unwrap()
+            .handler("user.delete", from_fn(|_| async { Ok(serde_json::Value::Null) }))
+            .nest("user", users.clone())
+            .build()
+            .await
+            .unwrap();
+        assert!(server.router.has_method("user.create"));
+        assert!(server.router.has_method("user.delete"));
+
+        let result = ServerBuilder::new()
+            .bind_str("127.0.0.1:0")
+            .unwrap()
+            .handler("user.create", from_fn(|_| async { Ok(serde_json::Value::Null) }))
+            .handler("ping", from_fn(|_| async { Ok(serde_json::Value::Null) }))
+            .handler("ping", from_fn(|_| async { Ok(serde_json::Value::Null) }))
+            .nest("user", users)
+            .build()
+            .await;
+        let err = result.err().unwrap();
+        assert_eq!(err.to_string(), "Invalid request: Duplicate methods: ping, user.create");
+
+        let proxy = crate::RouterBuilder::new()
+            .fallback(crate::handler::from_method_fn(|_, _| async { Ok(serde_json::Value::Null) }))
+            .try_build()
+            .unwrap();
+        let result = ServerBuilder::new()
+            .bind_str("127.0.0.1:0")
+            .unwrap()
+            .nest("user.", proxy.clone())
+            .build()
+            .await;
+        assert!(result.is_err());
+
+        let result = ServerBuilder::new()
+            .bind_str("127.0.0.1:0")
+            .unwrap()
+            .nest("", proxy.clone())
+            .build()
+            .await;
+        assert!(result.is_err());
+
+        let server = ServerBuilder::new()
+            .bind_str("127.0.0.1:0")
+            .unwrap()
+            .merge(proxy)
+            .build()
+            .await
+            .unwrap();
+        assert!(server.router.snapshot().has_fallback());
+    }
+
     #[test]
     fn test_builder_no_address() {
         let builder = ServerBuilder::new();
@@ -386,6 +781,42 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_builder_retain_messages() {
+        assert!(ServerBuilder::new().retain_messages("prices.*", 1).is_ok());
+        assert!(ServerBuilder::new().retain_messages("prices.>.x", 1).is_err());
+        assert!(ServerBuilder::new().retain_messages("prices.*", 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_builder_discovery() {
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let server = ServerBuilder::new()
+            .bind(addr)
+            .handler("ping", from_fn(|_| async { Ok(serde_json::json!("pong")) }))
+            .with_discovery(OpenRpcInfo::new("Test API", "0.1.0"))
+            .build()
+            .await
+            .unwrap();
+
+        let document = server.router.route("rpc.discover", None).await.unwrap();
+        assert_eq!(document["info"]["title"], "Test API");
+        assert_eq!(document, server.openrpc_document().unwrap());
+
+        // Methods added at runtime show up in the document
+        server
+            .router_handle()
+            .register("late", from_fn(|_| async { Ok(serde_json::Value::Null) }));
+        let document = server.router.route("rpc.discover", None).await.unwrap();
+        let names: Vec<&str> = document["methods"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["name"].as_str().unwrap())
+            .collect();
+        assert!(names.contains(&"late"));
+    }
+
     #[test]
     fn test_builder_default() {
         let builder = ServerBuilder::default();
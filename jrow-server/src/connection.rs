@@ -23,8 +23,12 @@
 //! # Built-in Methods
 //!
 //! The connection handler implements several built-in JSON-RPC methods:
-//! - `rpc.subscribe` - Subscribe to a topic or pattern
+//! - `rpc.subscribe` - Subscribe to a topic or pattern, optionally filtered by content or
+//!   as a member of a queue group, and receive its retained messages or snapshot
 //! - `rpc.unsubscribe` - Unsubscribe from a topic
+//! - `rpc.presence` - List the connections subscribed to a topic
+//! - `rpc.publish` - Publish a reply to a request's inbox
+//! - `rpc.request_topic` - Publish a request to a topic and return the first reply
 //! - `rpc.subscribe_persistent` - Durable subscription with replay
 //! - `rpc.ack_persistent` - Acknowledge persistent message delivery
 //!
@@ -34,15 +38,34 @@
 //! connection to close. The connection is automatically removed from
 //! the registry and all subscriptions are cleaned up.
 
-use crate::router::Router;
+use crate::acl::{PublishAcl, PublishKind};
+use crate::gateway::{Gateway, GatewaySession};
+use crate::inbox::Inboxes;
+use crate::lifecycle::{ConnectionInfo, DisconnectReason, Handshake, LifecycleHooks};
+use crate::presence::Presence;
+use crate::queue_group::QueueGroups;
+use crate::retained::RetainedMessages;
+use crate::snapshot::{Sequence, Snapshots};
+use crate::throttle::{Delivery, Throttle, Throttles};
+use crate::session::Session;
+use crate::router::{Router, RouterHandle};
 use futures::{SinkExt, StreamExt};
+use jrow_core::presence::PRESENCE_METHOD;
+use jrow_core::request_reply::{is_inbox, TopicRequest, PUBLISH_METHOD, REQUEST_TOPIC_METHOD};
 use jrow_core::{
     codec, Error, JsonRpcErrorData, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest,
     JsonRpcResponse, Result,
 };
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpStream;
-use tokio::sync::mpsc;
-use tokio_tungstenite::{accept_async, tungstenite::Message};
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinSet;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::{accept_hdr_async, tungstenite::{Message, Utf8Bytes}};
 
 /// Handle for a WebSocket connection
 ///
@@ -60,13 +83,63 @@ pub struct Connection {
     pub id: u64,
     /// Channel sender for outgoing WebSocket messages
     /// Using unbounded channel prevents send tasks from blocking
-    tx: mpsc::UnboundedSender<Message>,
+    tx: mpsc::UnboundedSender<Outbound>,
+    /// Published messages queued but not yet written to the socket
+    pub(crate) backlog: Arc<Backlog>,
+}
+
+/// Count of the published messages queued for a connection
+#[derive(Debug, Default)]
+pub(crate) struct Backlog {
+    pending: AtomicUsize,
+    /// Woken when the last pending message is written
+    drained: Notify,
+}
+
+impl Backlog {
+    /// Record that a published message was written or dropped
+    pub(crate) fn sent(&self) {
+        if self.pending.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.drained.notify_waiters();
+        }
+    }
+}
+
+/// Frame queued for a connection's send task
+pub(crate) enum Outbound {
+    /// A frame owned by this connection
+    Frame(Message),
+    /// Text encoded once and shared by every recipient
+    ///
+    /// Published messages are queued this way so fan-out costs one
+    /// reference count per subscriber, and the same buffer is handed to the
+    /// socket without copying.
+    Shared(Utf8Bytes),
+}
+
+impl From<Message> for Outbound {
+    fn from(msg: Message) -> Self {
+        Outbound::Frame(msg)
+    }
+}
+
+impl Outbound {
+    pub(crate) fn into_message(self) -> Message {
+        match self {
+            Outbound::Frame(msg) => msg,
+            Outbound::Shared(text) => Message::Text(text),
+        }
+    }
 }
 
 impl Connection {
     /// Create a new connection handle
-    pub fn new(id: u64, tx: mpsc::UnboundedSender<Message>) -> Self {
-        Self { id, tx }
+    pub(crate) fn new(id: u64, tx: mpsc::UnboundedSender<Outbound>) -> Self {
+        Self {
+            id,
+            tx,
+            backlog: Arc::default(),
+        }
     }
 
     /// Send a notification to the client
@@ -77,92 +150,560 @@ impl Connection {
     ) -> Result<()> {
         let notification = JsonRpcNotification::new(method, params);
         let msg = codec::encode_notification(&notification)?;
-        self.tx
-            .send(Message::Text(msg))
-            .map_err(|_| Error::ConnectionClosed)?;
-        Ok(())
+        self.send_message(Message::Text(msg.into()))
     }
 
     /// Send a raw message to the client
-    #[allow(dead_code)]
     pub fn send_message(&self, msg: Message) -> Result<()> {
-        self.tx.send(msg).map_err(|_| Error::ConnectionClosed)?;
+        self.tx.send(msg.into()).map_err(|_| Error::ConnectionClosed)?;
         Ok(())
     }
+
+    /// Queue text encoded once for several connections
+    pub(crate) fn send_shared(&self, text: Utf8Bytes) -> Result<()> {
+        // Count first, so the send task never writes an uncounted message
+        self.backlog.pending.fetch_add(1, Ordering::Relaxed);
+        self.tx.send(Outbound::Shared(text)).map_err(|_| {
+            self.backlog.sent();
+            Error::ConnectionClosed
+        })
+    }
+
+    /// Number of published messages waiting to be written to the socket
+    ///
+    /// Used as the connection's load when choosing queue group members.
+    pub(crate) fn pending(&self) -> usize {
+        self.backlog.pending.load(Ordering::Relaxed)
+    }
+
+    /// Wait until every published message queued so far is written
+    ///
+    /// Returns `false` if the connection closes first.
+    pub(crate) async fn drained(&self) -> bool {
+        loop {
+            let notified = self.backlog.drained.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.pending() == 0 {
+                return true;
+            }
+            tokio::select! {
+                _ = notified => {}
+                _ = self.tx.closed() => return false,
+            }
+        }
+    }
+}
+
+/// Outcome of sending a notification to one or more connections
+///
+/// A connection counts as delivered once the notification is queued for
+/// it; the client may still disconnect before reading it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeliveryReport {
+    /// Connections the notification was queued for
+    pub delivered: Vec<u64>,
+    /// Connections that were closed or unknown
+    pub failed: Vec<u64>,
+}
+
+impl DeliveryReport {
+    /// Number of connections the notification was queued for
+    pub fn delivered_count(&self) -> usize {
+        self.delivered.len()
+    }
+
+    /// Whether the notification was queued for every targeted connection
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Capture the parts of the upgrade request the lifecycle hooks see
+fn handshake_info(request: &Request) -> Handshake {
+    let mut headers: std::collections::HashMap<String, String> = std::collections::HashMap::new();
+    for (name, value) in request.headers() {
+        let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+        headers
+            .entry(name.as_str().to_string())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert(value);
+    }
+
+    Handshake {
+        path: request
+            .uri()
+            .path_and_query()
+            .map(|path| path.as_str().to_string())
+            .unwrap_or_default(),
+        headers,
+    }
+}
+
+/// Encode a notification once, for sending to any number of connections
+///
+/// Produces the same JSON as `codec::encode_notification`, but borrows the
+/// params instead of requiring an owned `JsonRpcNotification`.
+pub(crate) fn encode_shared<P: serde::Serialize>(method: &str, params: Option<&P>) -> Result<Utf8Bytes> {
+    #[derive(serde::Serialize)]
+    struct Notification<'a, P> {
+        jsonrpc: &'static str,
+        method: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        params: Option<&'a P>,
+    }
+
+    let notification = Notification {
+        jsonrpc: "2.0",
+        method,
+        params,
+    };
+    serde_json::to_string(&notification)
+        .map(Utf8Bytes::from)
+        .map_err(|e| Error::Serialization(e.to_string()))
+}
+
+/// Queue shared text for one connection, returning whether it was queued
+pub(crate) fn send_shared_to(registry: &crate::Connections, conn_id: u64, text: &Utf8Bytes) -> bool {
+    registry
+        .get(&conn_id)
+        .is_some_and(|conn| conn.send_shared(text.clone()).is_ok())
+}
+
+/// Send a notification to the given connections, encoding it once
+pub(crate) fn deliver(
+    registry: &crate::Connections,
+    conn_ids: impl IntoIterator<Item = u64>,
+    method: &str,
+    params: Option<&serde_json::Value>,
+) -> Result<DeliveryReport> {
+    let text = encode_shared(method, params)?;
+
+    let mut report = DeliveryReport::default();
+    for conn_id in conn_ids {
+        if send_shared_to(registry, conn_id, &text) {
+            report.delivered.push(conn_id);
+        } else {
+            report.failed.push(conn_id);
+        }
+    }
+    Ok(report)
+}
+
+/// Store a persistent message and send it to the matching persistent
+/// subscribers, returning its sequence ID
+pub(crate) async fn publish_persistent(
+    storage: &crate::PersistentStorage,
+    sub_manager: &crate::PersistentSubscriptionManager,
+    registry: &crate::Connections,
+    topic: &str,
+    data: serde_json::Value,
+) -> Result<u64> {
+    // Store message and get sequence ID
+    let sequence_id = storage.store_message(topic, data.clone()).await?;
+
+    tracing::debug!(
+        topic = %topic,
+        sequence_id = sequence_id,
+        "Message stored persistently"
+    );
+
+    // Find active persistent subscribers whose patterns and filters match
+    let matching_subs = sub_manager.get_recipients(topic, &data).await;
+
+    // Every subscriber gets the same notification, so encode it once
+    let notification_data = serde_json::json!({
+        "sequence_id": sequence_id,
+        "data": data,
+    });
+    let text = encode_shared(topic, Some(&notification_data))?;
+
+    let mut delivered_count = 0;
+    for (subscription_id, conn_id) in matching_subs {
+        if send_shared_to(registry, conn_id, &text) {
+            delivered_count += 1;
+            tracing::trace!(
+                subscription_id = %subscription_id,
+                conn_id = conn_id,
+                sequence_id = sequence_id,
+                topic = %topic,
+                "Delivered persistent message"
+            );
+        }
+    }
+
+    tracing::debug!(
+        topic = %topic,
+        sequence_id = sequence_id,
+        delivered_count = delivered_count,
+        "Persistent message published"
+    );
+
+    Ok(sequence_id)
+}
+
+/// Payload of a message delivered to a pattern subscription
+#[derive(serde::Serialize)]
+struct PatternPayload<'a> {
+    topic: &'a str,
+    data: &'a serde_json::Value,
+}
+
+/// Sends published messages to the subscribers of their topic
+///
+/// Holds the subscription managers, the connection registry and the state
+/// publishing keeps per topic: queue group positions, retained messages,
+/// snapshot versions, throttled subscriptions and reply inboxes, along with
+/// the ACL clients publish through. Cloning is cheap, and the server,
+/// presence announcements and each connection share the same state.
+#[derive(Clone, Default)]
+pub(crate) struct Publisher {
+    pub(crate) sub_manager: crate::SubscriptionManager,
+    pub(crate) filtered_sub_manager: Arc<tokio::sync::Mutex<crate::FilteredSubscriptionManager>>,
+    pub(crate) conn_registry: crate::Connections,
+    pub(crate) queue_groups: Arc<QueueGroups>,
+    pub(crate) retained: Arc<RetainedMessages>,
+    pub(crate) snapshots: Arc<Snapshots>,
+    pub(crate) throttles: Arc<Throttles>,
+    pub(crate) inboxes: Arc<Inboxes>,
+    pub(crate) acl: PublishAcl,
+    pub(crate) metrics: Option<Arc<crate::ServerMetrics>>,
+}
+
+impl Publisher {
+    /// Send a published message to the exact and pattern subscribers of `topic`
+    ///
+    /// Pattern subscribers get the message under their pattern, wrapped as
+    /// `{"topic": ..., "data": ...}`. Each distinct notification (one for the
+    /// exact subscribers, one per matching pattern) is encoded once and shared
+    /// by its recipients; no lock is held while sending. Subscribers whose
+    /// content filter doesn't match `data` are skipped, and each queue group
+    /// gets the message once, through the member `queue_groups` chooses. The
+    /// message is kept in `retained` if the topic has a retention rule, or
+    /// sent as the next delta if it has a snapshot provider. Throttled
+    /// subscriptions may hold the message for later, replacing an older
+    /// one. A message to the inbox of a request waiting for a reply answers
+    /// the request instead. Returns the number of notifications queued or
+    /// held.
+    pub(crate) async fn publish(&self, topic: &str, data: &serde_json::Value) -> Result<usize> {
+        if is_inbox(topic) && self.inboxes.reply(topic, data) {
+            return Ok(1);
+        }
+        let sequence = self.snapshots.sequence(topic).await;
+        self.fan_out(topic, data, sequence).await
+    }
+
+    /// Compute a message with `update` and publish it
+    ///
+    /// For topics with a snapshot provider, `update` runs while no snapshot
+    /// of the topic can be taken, so state it changes is either in a
+    /// snapshot or in the delta, never both.
+    pub(crate) async fn publish_update(
+        &self,
+        topic: &str,
+        update: impl FnOnce() -> serde_json::Value,
+    ) -> Result<usize> {
+        let sequence = self.snapshots.sequence(topic).await;
+        let data = update();
+        self.fan_out(topic, &data, sequence).await
+    }
+
+    /// Publish `data` to `topic` with a reply inbox and wait for the first reply
+    ///
+    /// Fails if no subscriber received the request or no reply arrives
+    /// within `timeout`.
+    pub(crate) async fn request(&self, topic: &str, data: serde_json::Value, timeout: Duration) -> Result<serde_json::Value> {
+        let mut inbox = self.inboxes.open();
+        let request = TopicRequest {
+            reply_to: inbox.topic().to_string(),
+            data,
+        };
+        let request = serde_json::to_value(request).map_err(|e| Error::Serialization(e.to_string()))?;
+        if self.publish(topic, &request).await? == 0 {
+            return Err(Error::Internal(format!("No responders for topic '{}'", topic)));
+        }
+
+        match tokio::time::timeout(timeout, inbox.reply()).await {
+            Ok(Some(reply)) => Ok(reply),
+            Ok(None) | Err(_) => Err(Error::Timeout),
+        }
+    }
+
+    async fn fan_out(&self, topic: &str, data: &serde_json::Value, mut sequence: Option<Sequence>) -> Result<usize> {
+        // Versioned topics are sent as deltas, holding the version lock until
+        // they are queued, and aren't retained
+        let delta;
+        let payload = match sequence.as_mut() {
+            Some(sequence) => {
+                delta = serde_json::to_value(sequence.delta(data)).map_err(|e| Error::Serialization(e.to_string()))?;
+                &delta
+            }
+            None => {
+                self.retained.record(topic, data);
+                data
+            }
+        };
+
+        let mut exact_subscribers = self.sub_manager.get_recipients(topic, data).await;
+
+        // Queue group members, with the pattern they subscribed with if any
+        let mut queue_members: std::collections::HashMap<String, Vec<(u64, Option<String>)>> =
+            std::collections::HashMap::new();
+        for (group, conn_id) in self.sub_manager.get_queue_members(topic, data).await {
+            queue_members.entry(group).or_default().push((conn_id, None));
+        }
+
+        // Group pattern subscribers so each pattern's notification is encoded once
+        let mut pattern_subscribers: std::collections::HashMap<String, Vec<u64>> =
+            std::collections::HashMap::new();
+        {
+            let filtered_sub_manager = self.filtered_sub_manager.lock().await;
+            for (conn_id, pattern) in filtered_sub_manager.get_recipients_with_patterns(topic, data) {
+                pattern_subscribers.entry(pattern).or_default().push(conn_id);
+            }
+            for (group, conn_id, pattern) in filtered_sub_manager.get_queue_members(topic, data) {
+                queue_members.entry(group).or_default().push((conn_id, Some(pattern)));
+            }
+        }
+
+        // The chosen member of each group gets the message like a plain
+        // subscriber of its own subscription
+        for (group, mut members) in queue_members {
+            members.sort_unstable();
+            let load = |(conn_id, _): &(u64, Option<String>)| {
+                self.conn_registry.get(conn_id).map_or(usize::MAX, |conn| conn.pending())
+            };
+            match self.queue_groups.pick(&group, &members, load) {
+                Some((conn_id, None)) => exact_subscribers.push(*conn_id),
+                Some((conn_id, Some(pattern))) => {
+                    pattern_subscribers.entry(pattern.clone()).or_default().push(*conn_id);
+                }
+                None => {}
+            }
+        }
+
+        let mut sent_count = 0;
+        let mut conflated = 0;
+        let mut send = |conn_id: u64, subscription: &str, text: &Utf8Bytes| {
+            let Some(throttle) = self.throttles.get(conn_id, subscription) else {
+                return send_shared_to(&self.conn_registry, conn_id, text);
+            };
+            let Some(conn) = self.conn_registry.get(&conn_id) else {
+                return false;
+            };
+            match throttle.deliver(&conn, data, text) {
+                Delivery::Sent | Delivery::Held => true,
+                Delivery::Conflated => {
+                    conflated += 1;
+                    true
+                }
+                Delivery::Failed => false,
+            }
+        };
+
+        if !exact_subscribers.is_empty() {
+            let text = encode_shared(topic, Some(payload))?;
+            sent_count += exact_subscribers
+                .into_iter()
+                .filter(|&conn_id| send(conn_id, topic, &text))
+                .count();
+        }
+
+        // Pattern subscribers get the message under their pattern, with the
+        // actual topic included in the payload
+        for (pattern, conn_ids) in pattern_subscribers {
+            let text = encode_shared(&pattern, Some(&PatternPayload { topic, data: payload }))?;
+            sent_count += conn_ids
+                .into_iter()
+                .filter(|&conn_id| send(conn_id, &pattern, &text))
+                .count();
+        }
+
+        if let (true, Some(metrics)) = (conflated > 0, &self.metrics) {
+            metrics.record_conflated(topic, conflated);
+        }
+        Ok(sent_count)
+    }
+}
+
+/// Dispatches the requests and notifications of one connection
+///
+/// Single messages and batch entries both go through this, so built-in
+/// methods, gateway forwarding and presence announcements behave the same
+/// whether or not a request is part of a batch. Cloning is cheap, which
+/// lets parallel batches hand a copy to each spawned entry.
+#[derive(Clone)]
+pub(crate) struct Dispatcher {
+    pub(crate) router: Arc<Router>,
+    pub(crate) conn_id: u64,
+    publisher: Publisher,
+    persistent_storage: Option<std::sync::Arc<crate::PersistentStorage>>,
+    persistent_sub_manager: Option<std::sync::Arc<crate::PersistentSubscriptionManager>>,
+    tx: mpsc::UnboundedSender<Outbound>,
+    gateway: Option<Arc<GatewaySession>>,
+    presence: Option<Arc<Presence>>,
+}
+
+impl Dispatcher {
+    /// Dispatcher without a connection, for `BatchProcessor::process_batch`
+    ///
+    /// Pattern subscriptions are kept in a manager of their own, persistent
+    /// subscriptions are unavailable and nothing is sent.
+    pub(crate) fn detached(router: Arc<Router>, conn_id: u64, sub_manager: crate::SubscriptionManager) -> Self {
+        let (tx, _) = mpsc::unbounded_channel();
+        Self {
+            router,
+            conn_id,
+            publisher: Publisher {
+                sub_manager,
+                ..Default::default()
+            },
+            persistent_storage: None,
+            persistent_sub_manager: None,
+            tx,
+            gateway: None,
+            presence: None,
+        }
+    }
+
+    /// Process a request and return its response
+    pub(crate) async fn request(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+        process_request(
+            request,
+            &self.router,
+            self.conn_id,
+            &self.publisher,
+            &self.persistent_storage,
+            &self.persistent_sub_manager,
+            &self.tx,
+            self.gateway.as_deref(),
+            self.presence.as_deref(),
+        )
+        .await
+    }
+
+    /// Process a notification
+    pub(crate) async fn notification(&self, notification: JsonRpcNotification) -> Result<()> {
+        process_notification(notification, &self.router, self.conn_id, self.gateway.as_deref()).await
+    }
 }
 
 /// Handle a single WebSocket connection
-#[tracing::instrument(skip(stream, router, sub_manager, filtered_sub_manager, conn_registry, batch_processor, metrics, persistent_storage, persistent_sub_manager), fields(conn_id = conn_id))]
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(stream, router, publisher, batch_processor, metrics, persistent_storage, persistent_sub_manager, gateway, identities, hooks, presence), fields(conn_id = conn_id, peer_addr = %peer_addr))]
 pub async fn handle_connection(
     stream: TcpStream,
     conn_id: u64,
-    router: Router,
-    sub_manager: crate::SubscriptionManager,
-    filtered_sub_manager: std::sync::Arc<tokio::sync::Mutex<crate::FilteredSubscriptionManager>>,
-    conn_registry: crate::ConnectionRegistry,
+    peer_addr: std::net::SocketAddr,
+    router: RouterHandle,
+    publisher: Publisher,
     batch_processor: crate::BatchProcessor,
     metrics: Option<std::sync::Arc<crate::ServerMetrics>>,
     persistent_storage: Option<std::sync::Arc<crate::PersistentStorage>>,
     persistent_sub_manager: Option<std::sync::Arc<crate::PersistentSubscriptionManager>>,
+    gateway: Option<Arc<Gateway>>,
+    identities: crate::IdentityRegistry,
+    hooks: LifecycleHooks,
+    presence: Arc<Presence>,
 ) -> Result<()> {
     tracing::debug!("Upgrading connection to WebSocket");
-    // Upgrade to WebSocket
-    let ws_stream = accept_async(stream)
-        .await
-        .map_err(|e| Error::WebSocket(e.to_string()))?;
+    // Upgrade to WebSocket, keeping the upgrade request for the hooks
+    let mut handshake = Handshake::default();
+    // The error type is fixed by tungstenite's callback signature
+    #[allow(clippy::result_large_err)]
+    let mut ws_stream = accept_hdr_async(stream, |request: &Request, response: Response| {
+        handshake = handshake_info(request);
+        Ok(response)
+    })
+    .await
+    .map_err(|e| Error::WebSocket(e.to_string()))?;
+
+    let mut info = ConnectionInfo {
+        id: conn_id,
+        peer_addr,
+        identity: None,
+        handshake,
+        session: Session::new(),
+    };
+    if let Err(e) = hooks.connected(&info).await {
+        tracing::info!(error = %e, "Connection rejected");
+        let frame = CloseFrame {
+            code: CloseCode::Policy,
+            reason: e.to_string().into(),
+        };
+        ws_stream.close(Some(frame)).await.ok();
+        return Ok(());
+    }
 
     // Split the WebSocket stream
     let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
     // Create a channel for outgoing messages
-    let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Outbound>();
 
     // Create connection handle
     let conn = Connection::new(conn_id, tx.clone());
+    let backlog = Arc::clone(&conn.backlog);
+
+    // Upstream connections are per downstream connection, so upstream
+    // notifications are relayed to this connection only
+    let gateway_session = gateway.map(|gateway| Arc::new(GatewaySession::new(gateway, conn.clone())));
 
     // Register connection in the registry
-    {
-        let mut registry = conn_registry.lock().await;
-        registry.insert(conn_id, conn.clone());
-    }
+    let conn_registry = Arc::clone(&publisher.conn_registry);
+    conn_registry.insert(conn_id, conn.clone());
 
     // Spawn task to forward messages from channel to WebSocket
     let mut send_task = tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
-            if let Err(e) = ws_sender.send(msg).await {
+            let published = matches!(msg, Outbound::Shared(_));
+            if let Err(e) = ws_sender.send(msg.into_message()).await {
                 tracing::error!(error = %e, "Error sending message");
-                break;
+                return DisconnectReason::Error(e.to_string());
+            }
+            if published {
+                backlog.sent();
             }
         }
+        DisconnectReason::ConnectionLost
     });
 
     // Handle incoming messages
     let router_clone = router.clone();
-    let tx_clone = tx.clone();
-    let sub_manager_clone = sub_manager.clone();
-    let filtered_sub_manager_clone = filtered_sub_manager.clone();
+    let mut dispatcher = Dispatcher {
+        router: router.snapshot(),
+        conn_id,
+        publisher: publisher.clone(),
+        persistent_storage: persistent_storage.clone(),
+        persistent_sub_manager: persistent_sub_manager.clone(),
+        tx: tx.clone(),
+        gateway: gateway_session.clone(),
+        presence: Some(Arc::clone(&presence)),
+    };
     let batch_processor_clone = batch_processor.clone();
     let metrics_clone = metrics.clone();
-    let persistent_storage_clone = persistent_storage.clone();
-    let persistent_sub_manager_clone = persistent_sub_manager.clone();
+    let session = info.session.clone();
     let mut recv_task = tokio::spawn(async move {
+        // Topic requests waiting for a reply, aborted along with this task
+        // when the connection ends
+        let mut topic_requests = JoinSet::new();
         while let Some(message) = ws_receiver.next().await {
             match message {
                 Ok(Message::Text(text)) => {
-                    if let Err(e) = handle_message(
+                    // Dispatch through the router as it is now, so runtime
+                    // registrations apply from the next message on
+                    dispatcher.router = router_clone.snapshot();
+                    let handled = handle_message(
                         &text,
-                        &router_clone,
-                        &tx_clone,
-                        conn_id,
-                        &sub_manager_clone,
-                        &filtered_sub_manager_clone,
+                        &dispatcher,
                         &batch_processor_clone,
                         &metrics_clone,
-                        &persistent_storage_clone,
-                        &persistent_sub_manager_clone,
-                    )
-                    .await
-                    {
+                        &mut topic_requests,
+                    );
+                    if let Err(e) = session.clone().scope(handled).await {
                         tracing::error!(error = %e, "Error handling message");
                         if let Some(ref m) = metrics_clone {
                             m.record_error("message_handling");
@@ -171,7 +712,7 @@ pub async fn handle_connection(
                 }
                 Ok(Message::Close(_)) => {
                     tracing::info!("Connection closed by client");
-                    break;
+                    return DisconnectReason::ClientClosed;
                 }
                 Ok(_) => {} // Ignore other message types
                 Err(e) => {
@@ -179,92 +720,90 @@ pub async fn handle_connection(
                     if let Some(ref m) = metrics_clone {
                         m.record_error("websocket");
                     }
-                    break;
+                    return DisconnectReason::Error(e.to_string());
                 }
             }
         }
+        DisconnectReason::ConnectionLost
     });
 
     // Wait for either task to complete
-    tokio::select! {
-        _ = &mut send_task => {
+    let finished = tokio::select! {
+        finished = &mut send_task => {
             recv_task.abort();
+            finished
         }
-        _ = &mut recv_task => {
+        finished = &mut recv_task => {
             send_task.abort();
+            finished
         }
-    }
+    };
+    let reason = finished.unwrap_or_else(|e| DisconnectReason::Error(e.to_string()));
 
     // Cleanup: remove connection from registry and all subscriptions
-    {
-        let mut registry = conn_registry.lock().await;
-        registry.remove(&conn_id);
-    }
-    sub_manager.remove_connection(conn_id).await;
-    filtered_sub_manager.lock().await.remove_connection(conn_id);
+    conn_registry.remove(&conn_id);
+    info.identity = identities.unbind(conn_id);
+    let rooms = presence.rooms_of(conn_id).await;
+    publisher.sub_manager.remove_connection(conn_id).await;
+    publisher.filtered_sub_manager.lock().await.remove_connection(conn_id);
+    publisher.throttles.remove_connection(conn_id);
+    publisher.queue_groups.remove_connection(conn_id);
+    presence
+        .announce_changes(conn_id, &rooms, &Default::default(), info.identity.clone())
+        .await;
     
     // Clean up persistent subscriptions
     if let Some(ref psm) = persistent_sub_manager {
         psm.remove_connection(conn_id).await;
     }
+    if let Some(session) = gateway_session {
+        session.close().await;
+    }
     
     // Record disconnection metrics
     if let Some(ref m) = metrics {
-        let registry = conn_registry.lock().await;
-        let active = registry.len() as i64;
+        let active = conn_registry.len() as i64;
         m.record_disconnection(active);
     }
     
     tracing::info!("Connection cleaned up");
+    hooks.disconnected(&info, &reason).await;
 
     Ok(())
 }
 
 /// Handle a single JSON-RPC message
-#[tracing::instrument(skip(text, router, tx, sub_manager, filtered_sub_manager, batch_processor, metrics, persistent_storage, persistent_sub_manager), fields(conn_id = conn_id))]
+///
+/// Topic requests run in `topic_requests`, since they wait for a reply.
+#[tracing::instrument(skip(text, dispatcher, batch_processor, metrics, topic_requests), fields(conn_id = dispatcher.conn_id))]
 async fn handle_message(
     text: &str,
-    router: &Router,
-    tx: &mpsc::UnboundedSender<Message>,
-    conn_id: u64,
-    sub_manager: &crate::SubscriptionManager,
-    filtered_sub_manager: &std::sync::Arc<tokio::sync::Mutex<crate::FilteredSubscriptionManager>>,
+    dispatcher: &Dispatcher,
     batch_processor: &crate::BatchProcessor,
     metrics: &Option<std::sync::Arc<crate::ServerMetrics>>,
-    persistent_storage: &Option<std::sync::Arc<crate::PersistentStorage>>,
-    persistent_sub_manager: &Option<std::sync::Arc<crate::PersistentSubscriptionManager>>,
+    topic_requests: &mut JoinSet<()>,
 ) -> Result<()> {
     let start = std::time::Instant::now();
     let message = codec::decode(text)?;
+    let tx = &dispatcher.tx;
 
     match message {
-        JsonRpcMessage::Request(request) => {
-            let method = request.method.clone();
-            let response = process_request(
-                request,
-                router,
-                conn_id,
-                sub_manager,
-                filtered_sub_manager,
-                persistent_storage,
-                persistent_sub_manager,
-                tx,
-            ).await;
-            let response_text = codec::encode_response(&response)?;
-            // Send response back to client
-            tx.send(Message::Text(response_text))
-                .map_err(|_| Error::ConnectionClosed)?;
-            
-            // Record metrics
-            if let Some(ref m) = metrics {
-                let duration = start.elapsed().as_secs_f64();
-                let status = if response.error.is_none() { "success" } else { "error" };
-                m.record_request(&method, status, duration);
-            }
+        JsonRpcMessage::Request(request) if request.method == REQUEST_TOPIC_METHOD => {
+            // Waiting for the reply mustn't hold up this connection, which
+            // may be the responder itself
+            while topic_requests.try_join_next().is_some() {}
+            let dispatcher = dispatcher.clone();
+            let metrics = metrics.clone();
+            topic_requests.spawn(async move {
+                if let Err(e) = respond(request, &dispatcher, &metrics, start).await {
+                    tracing::debug!(error = %e, "Failed to send topic request reply");
+                }
+            });
         }
+        JsonRpcMessage::Request(request) => respond(request, dispatcher, metrics, start).await?,
         JsonRpcMessage::Notification(notification) => {
             // Process notification (no response needed)
-            if let Err(e) = process_notification(notification, router, conn_id).await {
+            if let Err(e) = dispatcher.notification(notification).await {
                 tracing::error!(error = %e, "Error processing notification");
             }
         }
@@ -278,12 +817,12 @@ async fn handle_message(
             tracing::debug!(batch_size = batch_size, "Processing batch request");
             
             let responses = batch_processor
-                .process_batch(batch_values, router, conn_id, sub_manager)
+                .dispatch(batch_values, dispatcher)
                 .await;
 
             if !responses.is_empty() {
                 let response_text = codec::encode_batch_responses(&responses)?;
-                tx.send(Message::Text(response_text))
+                tx.send(Message::Text(response_text.into()).into())
                     .map_err(|_| Error::ConnectionClosed)?;
             }
             
@@ -294,55 +833,322 @@ async fn handle_message(
         }
     }
 
-    Ok(())
-}
-
-/// Process a JSON-RPC request and return a response (public for batch processor)
-/// Note: Batch requests only support exact topic subscriptions, not patterns
-pub async fn process_request_for_batch(
-    request: JsonRpcRequest,
-    router: &Router,
-    conn_id: u64,
-    sub_manager: &crate::SubscriptionManager,
-) -> JsonRpcResponse {
-    let id = request.id.clone();
-    let method = request.method.as_str();
+    Ok(())
+}
+
+/// Process a single request and send its response
+async fn respond(
+    request: JsonRpcRequest,
+    dispatcher: &Dispatcher,
+    metrics: &Option<std::sync::Arc<crate::ServerMetrics>>,
+    start: std::time::Instant,
+) -> Result<()> {
+    let method = request.method.clone();
+    let response = dispatcher.request(request).await;
+    let response_text = codec::encode_response(&response)?;
+    // Send response back to client
+    dispatcher
+        .tx
+        .send(Message::Text(response_text.into()).into())
+        .map_err(|_| Error::ConnectionClosed)?;
+
+    // Record metrics
+    if let Some(ref m) = metrics {
+        let duration = start.elapsed().as_secs_f64();
+        let status = if response.error.is_none() { "success" } else { "error" };
+        m.record_request(&method, status, duration);
+    }
+    Ok(())
+}
+
+/// Turn a handler result into the response for request `id`
+fn into_response(result: Result<serde_json::Value>, id: jrow_core::Id) -> JsonRpcResponse {
+    match result {
+        Ok(result) => JsonRpcResponse::success(result, id),
+        Err(Error::MethodNotFound(method)) => {
+            JsonRpcResponse::error(JsonRpcErrorData::method_not_found(method), id)
+        }
+        Err(Error::InvalidParams(msg)) => {
+            JsonRpcResponse::error(JsonRpcErrorData::invalid_params(msg), id)
+        }
+        Err(Error::JsonRpc(error)) => JsonRpcResponse::error(error, id),
+        Err(e) => JsonRpcResponse::error(JsonRpcErrorData::internal_error(e.to_string()), id),
+    }
+}
+
+/// Process a JSON-RPC notification
+async fn process_notification(
+    notification: JsonRpcNotification,
+    router: &Router,
+    conn_id: u64,
+    gateway: Option<&GatewaySession>,
+) -> Result<()> {
+    if let Some(gateway) = gateway {
+        if let Some(result) = gateway.notify(&notification.method, notification.params.as_ref()).await {
+            return result;
+        }
+    }
+
+    // Notifications don't return responses, but we still route them
+    router
+        .route_with_conn_id(&notification.method, notification.params, conn_id)
+        .await?;
+    Ok(())
+}
+
+/// Process a JSON-RPC request and return a response (internal)
+#[allow(clippy::too_many_arguments)]
+async fn process_request(
+    request: JsonRpcRequest,
+    router: &Router,
+    conn_id: u64,
+    publisher: &Publisher,
+    persistent_storage: &Option<std::sync::Arc<crate::PersistentStorage>>,
+    persistent_sub_manager: &Option<std::sync::Arc<crate::PersistentSubscriptionManager>>,
+    tx: &mpsc::UnboundedSender<Outbound>,
+    gateway: Option<&GatewaySession>,
+    presence: Option<&Presence>,
+) -> JsonRpcResponse {
+    let id = request.id.clone();
+    let method = request.method.as_str();
+
+    // Forward methods and topics mapped to an upstream
+    if let Some(gateway) = gateway {
+        if let Some(result) = gateway.request(method, request.params.as_ref()).await {
+            return into_response(result, id);
+        }
+    }
+
+    // Handle built-in subscription methods
+    if method == "rpc.subscribe" {
+        return handle_subscribe(request, conn_id, publisher, presence, tx).await;
+    } else if method == "rpc.unsubscribe" {
+        return handle_unsubscribe(request, conn_id, publisher, presence).await;
+    } else if let (PRESENCE_METHOD, Some(presence)) = (method, presence) {
+        return handle_presence(request, presence).await;
+    } else if method == PUBLISH_METHOD {
+        return handle_publish(request, conn_id, PublishKind::Message, publisher, persistent_storage, persistent_sub_manager).await;
+    } else if method == "rpc.publish_batch" {
+        return handle_publish_batch(request, conn_id, PublishKind::Message, publisher, persistent_storage, persistent_sub_manager).await;
+    } else if method == REQUEST_TOPIC_METHOD {
+        return handle_request_topic(request, conn_id, publisher).await;
+    } else if method == "rpc.publish_persistent" {
+        return handle_publish(request, conn_id, PublishKind::Persistent, publisher, persistent_storage, persistent_sub_manager).await;
+    } else if method == "rpc.publish_persistent_batch" {
+        return handle_publish_batch(request, conn_id, PublishKind::Persistent, publisher, persistent_storage, persistent_sub_manager).await;
+    } else if method == "rpc.subscribe_persistent" {
+        return handle_subscribe_persistent(request, conn_id, persistent_storage, persistent_sub_manager, tx).await;
+    } else if method == "rpc.ack_persistent" {
+        return handle_ack_persistent(request, conn_id, persistent_sub_manager).await;
+    } else if method == "rpc.unsubscribe_persistent" {
+        return handle_unsubscribe_persistent(request, conn_id, persistent_sub_manager).await;
+    } else if method == "rpc.subscribe_persistent_batch" {
+        return handle_subscribe_persistent_batch(request, conn_id, persistent_storage, persistent_sub_manager, tx).await;
+    } else if method == "rpc.ack_persistent_batch" {
+        return handle_ack_persistent_batch(request, conn_id, persistent_sub_manager).await;
+    } else if method == "rpc.unsubscribe_persistent_batch" {
+        return handle_unsubscribe_persistent_batch(request, conn_id, persistent_sub_manager).await;
+    }
+
+    let result = router.route_with_conn_id(&request.method, request.params, conn_id).await;
+    into_response(result, id)
+}
+
+/// Handle subscribe request
+async fn handle_subscribe(
+    request: JsonRpcRequest,
+    conn_id: u64,
+    publisher: &Publisher,
+    presence: Option<&Presence>,
+    tx: &mpsc::UnboundedSender<Outbound>,
+) -> JsonRpcResponse {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct SubscribeParams {
+        topic: String,
+        #[serde(default)]
+        filter: Option<jrow_core::ContentFilter>,
+        #[serde(default)]
+        queue_group: Option<String>,
+        #[serde(default)]
+        retained: Option<bool>,
+        #[serde(default)]
+        max_rate: Option<f64>,
+        #[serde(default)]
+        conflate: Option<String>,
+    }
+
+    let id = request.id.clone();
+
+    // Parse parameters
+    let params: SubscribeParams = match request.params {
+        Some(p) => match serde_json::from_value(p) {
+            Ok(params) => params,
+            Err(e) => {
+                return JsonRpcResponse::error(JsonRpcErrorData::invalid_params(e.to_string()), id);
+            }
+        },
+        None => {
+            return JsonRpcResponse::error(
+                JsonRpcErrorData::invalid_params("Missing 'topic' parameter"),
+                id,
+            );
+        }
+    };
+
+    if params.queue_group.as_deref().is_some_and(str::is_empty) {
+        return JsonRpcResponse::error(
+            JsonRpcErrorData::invalid_params("'queue_group' must not be empty"),
+            id,
+        );
+    }
+    if let Some(pointer) = params.conflate.as_deref().filter(|p| !p.is_empty() && !p.starts_with('/')) {
+        return JsonRpcResponse::error(
+            JsonRpcErrorData::invalid_params(format!("'conflate' path '{}' is not a JSON pointer", pointer)),
+            id,
+        );
+    }
+    let throttle = match Throttle::new(params.max_rate, params.conflate) {
+        Ok(throttle) => throttle,
+        Err(e) => return into_response(Err(e), id),
+    };
+
+    // Topics with NATS wildcards (* or >) are patterns
+    let subscription = match crate::TopicFilter::new(&params.topic) {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            return JsonRpcResponse::error(
+                JsonRpcErrorData::invalid_params(format!("Invalid pattern: {}", e)),
+                id,
+            );
+        }
+    };
+    let is_pattern = matches!(subscription, crate::TopicFilter::Pattern { .. });
+
+    // Queue groups skip retained messages, as each member would get them
+    let replay = params.retained.unwrap_or(true) && params.queue_group.is_none();
+    let replay_filter = if replay { params.filter.clone() } else { None };
+
+    // A plain subscriber of a topic with a snapshot provider gets a snapshot,
+    // taken under the topic's version lock so no delta is missed or repeated
+    let sequence = if is_pattern || params.queue_group.is_some() {
+        None
+    } else {
+        publisher.snapshots.sequence(&params.topic).await
+    };
+    let snapshot = match &sequence {
+        Some(sequence) => match sequence.snapshot().await {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) => return into_response(Err(e), id),
+        },
+        None => None,
+    };
+
+    // Subscribing again replaces the throttle along with the other options
+    publisher
+        .throttles
+        .set(conn_id, &params.topic, throttle);
+    publisher
+        .queue_groups
+        .set(conn_id, &params.topic, params.queue_group.clone());
+
+    let rooms = match presence {
+        Some(presence) => presence.rooms_of(conn_id).await,
+        None => Default::default(),
+    };
+    if is_pattern {
+        // Use filtered subscription manager for patterns
+        publisher.filtered_sub_manager.lock().await.subscribe_queue(
+            conn_id,
+            subscription.clone(),
+            params.queue_group.clone(),
+            params.filter,
+        );
+    } else {
+        // Use regular subscription manager for exact topics
+        publisher
+            .sub_manager
+            .subscribe_queue(conn_id, &params.topic, params.queue_group.clone(), params.filter)
+            .await;
+    }
+    if let Some(presence) = presence {
+        let identity = presence.identity_of(conn_id);
+        presence
+            .announce_changes(conn_id, &rooms, &presence.rooms_of(conn_id).await, identity)
+            .await;
+    }
+
+    if let Some(snapshot) = snapshot {
+        match encode_shared(&params.topic, Some(&snapshot)) {
+            Ok(text) => {
+                let _ = tx.send(Message::Text(text).into());
+            }
+            Err(e) => tracing::error!(error = %e, topic = %params.topic, "Failed to encode snapshot"),
+        }
+    }
+    drop(sequence);
+
+    if replay {
+        send_retained(&subscription, replay_filter.as_ref(), &publisher.retained, tx);
+    }
 
-    // Handle built-in subscription methods (exact topics only in batch mode)
-    if method == "rpc.subscribe" {
-        return handle_subscribe_exact(request, conn_id, sub_manager).await;
-    } else if method == "rpc.unsubscribe" {
-        return handle_unsubscribe_exact(request, conn_id, sub_manager).await;
+    // Return success
+    let mut result = serde_json::json!({
+        "subscribed": true,
+        "topic": params.topic,
+        "pattern": is_pattern
+    });
+    if let Some(group) = params.queue_group {
+        result["queue_group"] = serde_json::json!(group);
     }
+    JsonRpcResponse::success(result, id)
+}
 
-    match router.route_with_conn_id(&request.method, request.params, conn_id).await {
-        Ok(result) => JsonRpcResponse::success(result, id),
-        Err(Error::MethodNotFound(method)) => {
-            JsonRpcResponse::error(JsonRpcErrorData::method_not_found(method), id)
+/// Queue the retained messages matching a new subscription and its filter
+fn send_retained(
+    subscription: &crate::TopicFilter,
+    filter: Option<&jrow_core::ContentFilter>,
+    retained: &RetainedMessages,
+    tx: &mpsc::UnboundedSender<Outbound>,
+) {
+    for (topic, data) in retained.replay(subscription) {
+        if filter.is_some_and(|f| !f.matches(&data)) {
+            continue;
         }
-        Err(Error::InvalidParams(msg)) => {
-            JsonRpcResponse::error(JsonRpcErrorData::invalid_params(msg), id)
+        let text = match subscription {
+            crate::TopicFilter::Exact(_) => encode_shared(&topic, Some(&data)),
+            crate::TopicFilter::Pattern { original, .. } => {
+                encode_shared(original, Some(&PatternPayload { topic: &topic, data: &data }))
+            }
+        };
+        match text {
+            Ok(text) => {
+                let _ = tx.send(Message::Text(text).into());
+            }
+            Err(e) => tracing::error!(error = %e, topic = %topic, "Failed to encode retained message"),
         }
-        Err(e) => JsonRpcResponse::error(JsonRpcErrorData::internal_error(e.to_string()), id),
     }
 }
 
-/// Handle subscribe request for exact topics only (used in batch mode)
-async fn handle_subscribe_exact(
+/// Handle unsubscribe request
+async fn handle_unsubscribe(
     request: JsonRpcRequest,
     conn_id: u64,
-    sub_manager: &crate::SubscriptionManager,
+    publisher: &Publisher,
+    presence: Option<&Presence>,
 ) -> JsonRpcResponse {
     use serde::Deserialize;
 
     #[derive(Deserialize)]
-    struct SubscribeParams {
+    struct UnsubscribeParams {
         topic: String,
     }
 
     let id = request.id.clone();
 
-    let params: SubscribeParams = match request.params {
+    // Parse parameters
+    let params: UnsubscribeParams = match request.params {
         Some(p) => match serde_json::from_value(p) {
             Ok(params) => params,
             Err(e) => {
@@ -357,34 +1163,51 @@ async fn handle_subscribe_exact(
         }
     };
 
-    sub_manager.subscribe(conn_id, &params.topic).await;
+    let rooms = match presence {
+        Some(presence) => presence.rooms_of(conn_id).await,
+        None => Default::default(),
+    };
+
+    // Try both managers
+    let was_subscribed_exact = publisher.sub_manager.unsubscribe(conn_id, &params.topic).await;
+    let was_subscribed_pattern = publisher
+        .filtered_sub_manager
+        .lock()
+        .await
+        .unsubscribe(conn_id, &params.topic);
+    if let Some(presence) = presence {
+        let identity = presence.identity_of(conn_id);
+        presence
+            .announce_changes(conn_id, &rooms, &presence.rooms_of(conn_id).await, identity)
+            .await;
+    }
+    
+    let was_subscribed = was_subscribed_exact || was_subscribed_pattern;
+    publisher.throttles.set(conn_id, &params.topic, None);
+    publisher.queue_groups.set(conn_id, &params.topic, None);
 
+    // Return success
     JsonRpcResponse::success(
         serde_json::json!({
-            "subscribed": true,
-            "topic": params.topic,
-            "pattern": false
+            "unsubscribed": was_subscribed,
+            "topic": params.topic
         }),
         id,
     )
 }
 
-/// Handle unsubscribe request for exact topics only (used in batch mode)
-async fn handle_unsubscribe_exact(
-    request: JsonRpcRequest,
-    conn_id: u64,
-    sub_manager: &crate::SubscriptionManager,
-) -> JsonRpcResponse {
+/// Handle presence request: list the members of a topic
+async fn handle_presence(request: JsonRpcRequest, presence: &Presence) -> JsonRpcResponse {
     use serde::Deserialize;
 
     #[derive(Deserialize)]
-    struct UnsubscribeParams {
+    struct PresenceParams {
         topic: String,
     }
 
     let id = request.id.clone();
 
-    let params: UnsubscribeParams = match request.params {
+    let params: PresenceParams = match request.params {
         Some(p) => match serde_json::from_value(p) {
             Ok(params) => params,
             Err(e) => {
@@ -399,89 +1222,82 @@ async fn handle_unsubscribe_exact(
         }
     };
 
-    let was_subscribed = sub_manager.unsubscribe(conn_id, &params.topic).await;
-
+    let members = presence.members(&params.topic).await;
     JsonRpcResponse::success(
         serde_json::json!({
-            "unsubscribed": was_subscribed,
-            "topic": params.topic
+            "topic": params.topic,
+            "members": members
         }),
         id,
     )
 }
 
-/// Process a JSON-RPC notification
-async fn process_notification(notification: JsonRpcNotification, router: &Router, conn_id: u64) -> Result<()> {
-    // Notifications don't return responses, but we still route them
-    router
-        .route_with_conn_id(&notification.method, notification.params, conn_id)
-        .await?;
-    Ok(())
+/// Message published by a client with `rpc.publish` or
+/// `rpc.publish_persistent`, or an item of their batches
+#[derive(serde::Deserialize)]
+struct PublishParams {
+    topic: String,
+    #[serde(default)]
+    data: serde_json::Value,
 }
 
-/// Process a JSON-RPC request and return a response (internal)
-async fn process_request(
-    request: JsonRpcRequest,
-    router: &Router,
+/// Publish a client's message if the publish ACL allows it
+///
+/// Returns `{"topic", "delivered"}` for a message and `{"topic",
+/// "sequence_id"}` for a persistent one.
+async fn client_publish(
+    params: PublishParams,
     conn_id: u64,
-    sub_manager: &crate::SubscriptionManager,
-    filtered_sub_manager: &std::sync::Arc<tokio::sync::Mutex<crate::FilteredSubscriptionManager>>,
+    kind: PublishKind,
+    publisher: &Publisher,
     persistent_storage: &Option<std::sync::Arc<crate::PersistentStorage>>,
     persistent_sub_manager: &Option<std::sync::Arc<crate::PersistentSubscriptionManager>>,
-    tx: &mpsc::UnboundedSender<Message>,
-) -> JsonRpcResponse {
-    let id = request.id.clone();
-    let method = request.method.as_str();
-
-    // Handle built-in subscription methods
-    if method == "rpc.subscribe" {
-        return handle_subscribe(request, conn_id, sub_manager, filtered_sub_manager).await;
-    } else if method == "rpc.unsubscribe" {
-        return handle_unsubscribe(request, conn_id, sub_manager, filtered_sub_manager).await;
-    } else if method == "rpc.subscribe_persistent" {
-        return handle_subscribe_persistent(request, conn_id, persistent_storage, persistent_sub_manager, tx).await;
-    } else if method == "rpc.ack_persistent" {
-        return handle_ack_persistent(request, conn_id, persistent_sub_manager).await;
-    } else if method == "rpc.unsubscribe_persistent" {
-        return handle_unsubscribe_persistent(request, conn_id, persistent_sub_manager).await;
-    } else if method == "rpc.subscribe_persistent_batch" {
-        return handle_subscribe_persistent_batch(request, conn_id, persistent_storage, persistent_sub_manager, tx).await;
-    } else if method == "rpc.ack_persistent_batch" {
-        return handle_ack_persistent_batch(request, conn_id, persistent_sub_manager).await;
-    } else if method == "rpc.unsubscribe_persistent_batch" {
-        return handle_unsubscribe_persistent_batch(request, conn_id, persistent_sub_manager).await;
+) -> std::result::Result<serde_json::Value, JsonRpcErrorData> {
+    if !publisher.acl.allows(conn_id, &params.topic, kind).await {
+        return Err(JsonRpcErrorData::invalid_request(format!(
+            "Not allowed to publish to '{}'",
+            params.topic
+        )));
     }
 
-    match router.route_with_conn_id(&request.method, request.params, conn_id).await {
-        Ok(result) => JsonRpcResponse::success(result, id),
-        Err(Error::MethodNotFound(method)) => {
-            JsonRpcResponse::error(JsonRpcErrorData::method_not_found(method), id)
-        }
-        Err(Error::InvalidParams(msg)) => {
-            JsonRpcResponse::error(JsonRpcErrorData::invalid_params(msg), id)
+    let result = if kind == PublishKind::Persistent {
+        let (storage, sub_manager) = match (persistent_storage, persistent_sub_manager) {
+            (Some(s), Some(m)) => (s, m),
+            _ => return Err(JsonRpcErrorData::internal_error("Persistent storage not configured")),
+        };
+        publish_persistent(storage, sub_manager, &publisher.conn_registry, &params.topic, params.data)
+            .await
+            .map(|sequence_id| serde_json::json!({"topic": params.topic, "sequence_id": sequence_id}))
+    } else {
+        publisher
+            .publish(&params.topic, &params.data)
+            .await
+            .map(|delivered| serde_json::json!({"topic": params.topic, "delivered": delivered}))
+    };
+
+    match result {
+        Ok(result) => {
+            if let Some(ref m) = publisher.metrics {
+                m.record_publish(&params.topic);
+            }
+            Ok(result)
         }
-        Err(e) => JsonRpcResponse::error(JsonRpcErrorData::internal_error(e.to_string()), id),
+        Err(e) => Err(JsonRpcErrorData::internal_error(e.to_string())),
     }
 }
 
-/// Handle subscribe request
-async fn handle_subscribe(
+/// Handle publish request, as a message or a persistent message
+async fn handle_publish(
     request: JsonRpcRequest,
     conn_id: u64,
-    sub_manager: &crate::SubscriptionManager,
-    filtered_sub_manager: &std::sync::Arc<tokio::sync::Mutex<crate::FilteredSubscriptionManager>>,
+    kind: PublishKind,
+    publisher: &Publisher,
+    persistent_storage: &Option<std::sync::Arc<crate::PersistentStorage>>,
+    persistent_sub_manager: &Option<std::sync::Arc<crate::PersistentSubscriptionManager>>,
 ) -> JsonRpcResponse {
-    use serde::Deserialize;
-
-    #[derive(Deserialize)]
-    struct SubscribeParams {
-        topic: String,
-    }
-
     let id = request.id.clone();
 
-    // Parse parameters
-    let params: SubscribeParams = match request.params {
+    let params: PublishParams = match request.params {
         Some(p) => match serde_json::from_value(p) {
             Ok(params) => params,
             Err(e) => {
@@ -496,56 +1312,78 @@ async fn handle_subscribe(
         }
     };
 
-    // Check if topic is a NATS pattern (contains * or >)
-    let is_pattern = params.topic.contains('*') || params.topic.contains('>');
+    match client_publish(params, conn_id, kind, publisher, persistent_storage, persistent_sub_manager).await {
+        Ok(result) => JsonRpcResponse::success(result, id),
+        Err(error) => JsonRpcResponse::error(error, id),
+    }
+}
+
+/// Handle batch publish request, publishing each message in order
+///
+/// Each result is the single publish's result with `"success": true`, or
+/// `{"topic", "success": false, "error"}` if that message failed.
+async fn handle_publish_batch(
+    request: JsonRpcRequest,
+    conn_id: u64,
+    kind: PublishKind,
+    publisher: &Publisher,
+    persistent_storage: &Option<std::sync::Arc<crate::PersistentStorage>>,
+    persistent_sub_manager: &Option<std::sync::Arc<crate::PersistentSubscriptionManager>>,
+) -> JsonRpcResponse {
+    let id = request.id.clone();
 
-    if is_pattern {
-        // Use filtered subscription manager for patterns
-        match crate::TopicFilter::new(&params.topic) {
-            Ok(filter) => {
-                filtered_sub_manager.lock().await.subscribe(conn_id, filter);
-            }
+    let items: Vec<PublishParams> = match request.params {
+        Some(p) => match serde_json::from_value(p) {
+            Ok(items) => items,
             Err(e) => {
                 return JsonRpcResponse::error(
-                    JsonRpcErrorData::invalid_params(format!("Invalid pattern: {}", e)),
+                    JsonRpcErrorData::invalid_params(format!("Expected array of messages: {}", e)),
                     id,
                 );
             }
+        },
+        None => {
+            return JsonRpcResponse::error(
+                JsonRpcErrorData::invalid_params("Missing messages array"),
+                id,
+            );
+        }
+    };
+
+    let mut results = Vec::with_capacity(items.len());
+    for item in items {
+        let topic = item.topic.clone();
+        match client_publish(item, conn_id, kind, publisher, persistent_storage, persistent_sub_manager).await {
+            Ok(mut result) => {
+                result["success"] = serde_json::Value::Bool(true);
+                results.push(result);
+            }
+            Err(error) => results.push(serde_json::json!({
+                "topic": topic,
+                "success": false,
+                "error": error.message,
+            })),
         }
-    } else {
-        // Use regular subscription manager for exact topics
-        sub_manager.subscribe(conn_id, &params.topic).await;
     }
 
-    // Return success
-    JsonRpcResponse::success(
-        serde_json::json!({
-            "subscribed": true,
-            "topic": params.topic,
-            "pattern": is_pattern
-        }),
-        id,
-    )
+    JsonRpcResponse::success(serde_json::Value::Array(results), id)
 }
 
-/// Handle unsubscribe request
-async fn handle_unsubscribe(
-    request: JsonRpcRequest,
-    conn_id: u64,
-    sub_manager: &crate::SubscriptionManager,
-    filtered_sub_manager: &std::sync::Arc<tokio::sync::Mutex<crate::FilteredSubscriptionManager>>,
-) -> JsonRpcResponse {
+/// Handle topic request: publish with a reply inbox and return the first reply
+async fn handle_request_topic(request: JsonRpcRequest, conn_id: u64, publisher: &Publisher) -> JsonRpcResponse {
     use serde::Deserialize;
 
     #[derive(Deserialize)]
-    struct UnsubscribeParams {
+    struct RequestTopicParams {
         topic: String,
+        #[serde(default)]
+        data: serde_json::Value,
+        timeout_ms: u64,
     }
 
     let id = request.id.clone();
 
-    // Parse parameters
-    let params: UnsubscribeParams = match request.params {
+    let params: RequestTopicParams = match request.params {
         Some(p) => match serde_json::from_value(p) {
             Ok(params) => params,
             Err(e) => {
@@ -560,23 +1398,41 @@ async fn handle_unsubscribe(
         }
     };
 
-    // Try both managers
-    let was_subscribed_exact = sub_manager.unsubscribe(conn_id, &params.topic).await;
-    let was_subscribed_pattern = filtered_sub_manager
-        .lock()
-        .await
-        .unsubscribe(conn_id, &params.topic);
-    
-    let was_subscribed = was_subscribed_exact || was_subscribed_pattern;
+    if !publisher.acl.allows(conn_id, &params.topic, PublishKind::Request).await {
+        return JsonRpcResponse::error(
+            JsonRpcErrorData::invalid_request(format!("Not allowed to send requests to '{}'", params.topic)),
+            id,
+        );
+    }
 
-    // Return success
-    JsonRpcResponse::success(
-        serde_json::json!({
-            "unsubscribed": was_subscribed,
-            "topic": params.topic
-        }),
-        id,
-    )
+    let timeout = Duration::from_millis(params.timeout_ms).min(publisher.inboxes.max_timeout());
+    let reply = publisher.request(&params.topic, params.data, timeout).await;
+    if let Some(ref m) = publisher.metrics {
+        m.record_publish(&params.topic);
+    }
+    match reply {
+        Ok(reply) => JsonRpcResponse::success(
+            serde_json::json!({
+                "topic": params.topic,
+                "reply": reply
+            }),
+            id,
+        ),
+        Err(e) => into_response(Err(e), id),
+    }
+}
+
+/// Deserialize a field that is present, so `null` is told apart from a
+/// missing field
+///
+/// Used with `#[serde(default)]`: a missing field is `None` and `null` is
+/// `Some(None)`.
+fn deserialize_present<'de, T, D>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
 }
 
 /// Handle persistent subscribe request
@@ -585,7 +1441,7 @@ async fn handle_subscribe_persistent(
     conn_id: u64,
     persistent_storage: &Option<std::sync::Arc<crate::PersistentStorage>>,
     persistent_sub_manager: &Option<std::sync::Arc<crate::PersistentSubscriptionManager>>,
-    tx: &mpsc::UnboundedSender<Message>,
+    tx: &mpsc::UnboundedSender<Outbound>,
 ) -> JsonRpcResponse {
     use serde::Deserialize;
 
@@ -593,6 +1449,8 @@ async fn handle_subscribe_persistent(
     struct SubscribePersistentParams {
         subscription_id: String,
         topic: String,
+        #[serde(default, deserialize_with = "deserialize_present")]
+        filter: Option<Option<jrow_core::ContentFilter>>,
     }
 
     let id = request.id.clone();
@@ -626,7 +1484,12 @@ async fn handle_subscribe_persistent(
 
     // Register subscription
     let state = match sub_manager
-        .register_subscription(params.subscription_id.clone(), params.topic.clone(), conn_id)
+        .register_filtered_subscription(
+            params.subscription_id.clone(),
+            params.topic.clone(),
+            conn_id,
+            params.filter,
+        )
         .await
     {
         Ok(s) => s,
@@ -649,7 +1512,7 @@ async fn handle_subscribe_persistent(
         }
     };
     
-    let messages = match storage.get_messages_matching_pattern(&pattern, state.last_ack_seq).await {
+    let messages = match backlog(storage, &pattern, &state).await {
         Ok(m) => m,
         Err(e) => {
             return JsonRpcResponse::error(
@@ -671,20 +1534,7 @@ async fn handle_subscribe_persistent(
     let undelivered_count = messages.len();
 
     // Deliver undelivered messages to the client
-    for message in messages {
-        // Parse data from JSON string
-        let data_value = match message.data_as_value() {
-            Ok(v) => v,
-            Err(e) => {
-                tracing::error!(
-                    error = %e,
-                    sequence_id = message.sequence_id,
-                    "Failed to parse message data"
-                );
-                continue;
-            }
-        };
-        
+    for (message, data_value) in messages {
         let notification_data = serde_json::json!({
             "sequence_id": message.sequence_id,
             "topic": message.topic,  // Include actual topic in data
@@ -695,7 +1545,7 @@ async fn handle_subscribe_persistent(
         let notification = JsonRpcNotification::new(&params.topic, Some(notification_data));
         if let Ok(notification_text) = codec::encode_notification(&notification) {
             // Send the notification (ignore errors, client will resume on reconnect)
-            let _ = tx.send(Message::Text(notification_text));
+            let _ = tx.send(Message::Text(notification_text.into()).into());
             
             tracing::trace!(
                 subscription_id = %params.subscription_id,
@@ -853,13 +1703,48 @@ async fn handle_unsubscribe_persistent(
     }
 }
 
+/// Messages a persistent subscription hasn't acknowledged, with their data
+///
+/// Messages rejected by the subscription's content filter and messages
+/// whose data can't be parsed are left out.
+async fn backlog(
+    storage: &crate::PersistentStorage,
+    pattern: &crate::NatsPattern,
+    state: &crate::SubscriptionState,
+) -> Result<Vec<(crate::PersistentMessage, serde_json::Value)>> {
+    let filter = state.content_filter()?;
+    let messages = storage.get_messages_matching_pattern(pattern, state.last_ack_seq).await?;
+
+    Ok(messages
+        .into_iter()
+        .filter_map(|message| {
+            // Parse data from JSON string
+            let data = match message.data_as_value() {
+                Ok(data) => data,
+                Err(e) => {
+                    tracing::error!(
+                        error = %e,
+                        sequence_id = message.sequence_id,
+                        "Failed to parse message data"
+                    );
+                    return None;
+                }
+            };
+            filter
+                .as_ref()
+                .is_none_or(|f| f.matches(&data))
+                .then_some((message, data))
+        })
+        .collect())
+}
+
 /// Handle batch persistent subscription requests
 async fn handle_subscribe_persistent_batch(
     request: JsonRpcRequest,
     conn_id: u64,
     persistent_storage: &Option<std::sync::Arc<crate::PersistentStorage>>,
     persistent_sub_manager: &Option<std::sync::Arc<crate::PersistentSubscriptionManager>>,
-    tx: &mpsc::UnboundedSender<Message>,
+    tx: &mpsc::UnboundedSender<Outbound>,
 ) -> JsonRpcResponse {
     use serde::{Deserialize, Serialize};
 
@@ -867,6 +1752,8 @@ async fn handle_subscribe_persistent_batch(
     struct SubscribePersistentItem {
         subscription_id: String,
         topic: String,
+        #[serde(default, deserialize_with = "deserialize_present")]
+        filter: Option<Option<jrow_core::ContentFilter>>,
     }
 
     #[derive(Serialize)]
@@ -918,7 +1805,12 @@ async fn handle_subscribe_persistent_batch(
     for item in items {
         // Register subscription
         let state = match sub_manager
-            .register_subscription(item.subscription_id.clone(), item.topic.clone(), conn_id)
+            .register_filtered_subscription(
+                item.subscription_id.clone(),
+                item.topic.clone(),
+                conn_id,
+                item.filter,
+            )
             .await
         {
             Ok(s) => s,
@@ -951,7 +1843,7 @@ async fn handle_subscribe_persistent_batch(
             }
         };
         
-        let messages = match storage.get_messages_matching_pattern(&pattern, state.last_ack_seq).await {
+        let messages = match backlog(storage, &pattern, &state).await {
             Ok(m) => m,
             Err(e) => {
                 results.push(SubscribeResult {
@@ -969,19 +1861,7 @@ async fn handle_subscribe_persistent_batch(
         let undelivered_count = messages.len();
 
         // Deliver undelivered messages to the client
-        for message in messages {
-            let data_value = match message.data_as_value() {
-                Ok(v) => v,
-                Err(e) => {
-                    tracing::error!(
-                        error = %e,
-                        sequence_id = message.sequence_id,
-                        "Failed to parse message data"
-                    );
-                    continue;
-                }
-            };
-            
+        for (message, data_value) in messages {
             let notification_data = serde_json::json!({
                 "sequence_id": message.sequence_id,
                 "topic": message.topic,  // Include actual topic in data
@@ -991,7 +1871,7 @@ async fn handle_subscribe_persistent_batch(
             // Send notification to the subscription's topic/pattern, not the message topic
             let notification = JsonRpcNotification::new(&item.topic, Some(notification_data));
             if let Ok(notification_text) = codec::encode_notification(&notification) {
-                let _ = tx.send(Message::Text(notification_text));
+                let _ = tx.send(Message::Text(notification_text.into()).into());
                 
                 tracing::trace!(
                     subscription_id = %item.subscription_id,
@@ -1206,19 +2086,31 @@ mod tests {
     use super::*;
     use crate::handler::from_fn;
 
+    #[test]
+    fn test_null_filter_is_present() {
+        #[derive(serde::Deserialize)]
+        struct Params {
+            #[serde(default, deserialize_with = "deserialize_present")]
+            filter: Option<Option<jrow_core::ContentFilter>>,
+        }
+
+        let parse = |params| serde_json::from_value::<Params>(params).unwrap().filter;
+        assert!(parse(serde_json::json!({})).is_none());
+        assert_eq!(parse(serde_json::json!({"filter": null})), Some(None));
+        let filter = parse(serde_json::json!({"filter": {"path": "/region", "eq": "eu"}}));
+        assert!(filter.is_some_and(|filter| filter.is_some()));
+    }
+
     #[tokio::test]
     async fn test_process_request() {
         let mut router = Router::new();
         let handler = from_fn(|_| async { Ok(serde_json::json!({"result": 42})) });
         router.register("test", handler);
-        let sub_manager = crate::SubscriptionManager::new();
-        let filtered_sub_manager = std::sync::Arc::new(tokio::sync::Mutex::new(
-            crate::FilteredSubscriptionManager::new(),
-        ));
+        let publisher = Publisher::default();
 
         let (tx, _rx) = mpsc::unbounded_channel();
         let request = JsonRpcRequest::new("test", None, jrow_core::Id::Number(1));
-        let response = process_request(request, &router, 1, &sub_manager, &filtered_sub_manager, &None, &None, &tx).await;
+        let response = process_request(request, &router, 1, &publisher, &None, &None, &tx, None, None).await;
 
         assert!(response.is_success());
         assert_eq!(response.result, Some(serde_json::json!({"result": 42})));
@@ -1227,13 +2119,10 @@ mod tests {
     #[tokio::test]
     async fn test_process_request_method_not_found() {
         let router = Router::new();
-        let sub_manager = crate::SubscriptionManager::new();
-        let filtered_sub_manager = std::sync::Arc::new(tokio::sync::Mutex::new(
-            crate::FilteredSubscriptionManager::new(),
-        ));
+        let publisher = Publisher::default();
         let (tx, _rx) = mpsc::unbounded_channel();
         let request = JsonRpcRequest::new("unknown", None, jrow_core::Id::Number(1));
-        let response = process_request(request, &router, 1, &sub_manager, &filtered_sub_manager, &None, &None, &tx).await;
+        let response = process_request(request, &router, 1, &publisher, &None, &None, &tx, None, None).await;
 
         assert!(response.is_error());
         assert_eq!(response.error.as_ref().unwrap().code, -32601);
@@ -1242,10 +2131,7 @@ mod tests {
     #[tokio::test]
     async fn test_subscribe_request() {
         let router = Router::new();
-        let sub_manager = crate::SubscriptionManager::new();
-        let filtered_sub_manager = std::sync::Arc::new(tokio::sync::Mutex::new(
-            crate::FilteredSubscriptionManager::new(),
-        ));
+        let publisher = Publisher::default();
 
         let request = JsonRpcRequest::new(
             "rpc.subscribe",
@@ -1254,23 +2140,20 @@ mod tests {
         );
 
         let (tx, _rx) = mpsc::unbounded_channel();
-        let response = process_request(request, &router, 1, &sub_manager, &filtered_sub_manager, &None, &None, &tx).await;
+        let response = process_request(request, &router, 1, &publisher, &None, &None, &tx, None, None).await;
 
         assert!(response.is_success());
         assert!(response.result.unwrap()["subscribed"].as_bool().unwrap());
 
         // Verify subscription was registered
-        let subscribers = sub_manager.get_subscribers("test.topic").await;
+        let subscribers = publisher.sub_manager.get_subscribers("test.topic").await;
         assert_eq!(subscribers, vec![1]);
     }
 
     #[tokio::test]
     async fn test_subscribe_pattern() {
         let router = Router::new();
-        let sub_manager = crate::SubscriptionManager::new();
-        let filtered_sub_manager = std::sync::Arc::new(tokio::sync::Mutex::new(
-            crate::FilteredSubscriptionManager::new(),
-        ));
+        let publisher = Publisher::default();
 
         // Subscribe to a pattern
         let request = JsonRpcRequest::new(
@@ -1280,7 +2163,7 @@ mod tests {
         );
 
         let (tx, _rx) = mpsc::unbounded_channel();
-        let response = process_request(request, &router, 1, &sub_manager, &filtered_sub_manager, &None, &None, &tx).await;
+        let response = process_request(request, &router, 1, &publisher, &None, &None, &tx, None, None).await;
 
         assert!(response.is_success());
         let result = response.result.unwrap();
@@ -1288,20 +2171,17 @@ mod tests {
         assert!(result["pattern"].as_bool().unwrap());
 
         // Verify pattern subscription was registered
-        let subscribers = filtered_sub_manager.lock().await.get_subscribers("events.login");
+        let subscribers = publisher.filtered_sub_manager.lock().await.get_subscribers("events.login");
         assert_eq!(subscribers, vec![1]);
     }
 
     #[tokio::test]
     async fn test_unsubscribe_request() {
         let router = Router::new();
-        let sub_manager = crate::SubscriptionManager::new();
-        let filtered_sub_manager = std::sync::Arc::new(tokio::sync::Mutex::new(
-            crate::FilteredSubscriptionManager::new(),
-        ));
+        let publisher = Publisher::default();
 
         // Subscribe first
-        sub_manager.subscribe(1, "test.topic").await;
+        publisher.sub_manager.subscribe(1, "test.topic").await;
 
         // Now unsubscribe
         let request = JsonRpcRequest::new(
@@ -1311,13 +2191,180 @@ mod tests {
         );
 
         let (tx, _rx) = mpsc::unbounded_channel();
-        let response = process_request(request, &router, 1, &sub_manager, &filtered_sub_manager, &None, &None, &tx).await;
+        let response = process_request(request, &router, 1, &publisher, &None, &None, &tx, None, None).await;
 
         assert!(response.is_success());
         assert!(response.result.unwrap()["unsubscribed"].as_bool().unwrap());
 
         // Verify subscription was removed
-        let subscribers = sub_manager.get_subscribers("test.topic").await;
+        let subscribers = publisher.sub_manager.get_subscribers("test.topic").await;
         assert!(subscribers.is_empty());
     }
+
+    #[test]
+    fn test_encode_shared_matches_codec() {
+        let params = serde_json::json!({"a": [1, 2], "b": null});
+        let notification = JsonRpcNotification::new("topic", Some(params.clone()));
+        let expected = codec::encode_notification(&notification).unwrap();
+        assert_eq!(&*encode_shared("topic", Some(&params)).unwrap(), expected);
+
+        let notification = JsonRpcNotification::new("ping", None);
+        let expected = codec::encode_notification(&notification).unwrap();
+        assert_eq!(&*encode_shared::<serde_json::Value>("ping", None).unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_publish_shares_encoded_text() {
+        let publisher = Publisher::default();
+
+        let mut receivers = Vec::new();
+        for conn_id in 0..3 {
+            let (tx, rx) = mpsc::unbounded_channel();
+            publisher.conn_registry.insert(conn_id, Connection::new(conn_id, tx));
+            receivers.push(rx);
+        }
+        publisher.sub_manager.subscribe(0, "orders.created").await;
+        publisher.sub_manager.subscribe(1, "orders.created").await;
+        publisher.filtered_sub_manager
+            .lock()
+            .await
+            .subscribe(2, crate::TopicFilter::new("orders.*").unwrap());
+        // Unknown connections are skipped
+        publisher.sub_manager.subscribe(9, "orders.created").await;
+
+        let data = serde_json::json!({"id": 1});
+        let sent = publisher.publish("orders.created", &data).await.unwrap();
+        assert_eq!(sent, 3);
+
+        let shared = |rx: &mut mpsc::UnboundedReceiver<Outbound>| match rx.try_recv().unwrap() {
+            Outbound::Shared(text) => text,
+            Outbound::Frame(_) => panic!("expected shared text"),
+        };
+        let first = shared(&mut receivers[0]);
+        let second = shared(&mut receivers[1]);
+        assert_eq!(first.as_str().as_ptr(), second.as_str().as_ptr());
+
+        let wrapped: serde_json::Value = serde_json::from_str(&shared(&mut receivers[2])).unwrap();
+        assert_eq!(wrapped["method"], "orders.*");
+        assert_eq!(wrapped["params"], serde_json::json!({"topic": "orders.created", "data": {"id": 1}}));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_snapshot_then_deltas() {
+        let router = Router::new();
+        let provider: crate::snapshot::SnapshotProvider =
+            Arc::new(|topic| Box::pin(async move { Ok(serde_json::json!({"state": topic})) }));
+        let publisher = Publisher {
+            snapshots: Arc::new(Snapshots::new(vec![(crate::TopicFilter::new("stats.*").unwrap(), provider)])),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        publisher.conn_registry.insert(1, Connection::new(1, tx.clone()));
+
+        // Nobody receives the first delta, but it still counts
+        let data = serde_json::json!({"opened": 1});
+        assert_eq!(publisher.publish("stats.orders", &data).await.unwrap(), 0);
+
+        let request = JsonRpcRequest::new(
+            "rpc.subscribe",
+            Some(serde_json::json!({"topic": "stats.orders"})),
+            jrow_core::Id::Number(1),
+        );
+        let response = process_request(request, &router, 1, &publisher, &None, &None, &tx, None, None).await;
+        assert!(response.is_success());
+        assert_eq!(publisher.publish("stats.orders", &data).await.unwrap(), 1);
+
+        let mut params = Vec::new();
+        while let Ok(outbound) = rx.try_recv() {
+            let notification: serde_json::Value = serde_json::from_str(outbound.into_message().to_text().unwrap()).unwrap();
+            assert_eq!(notification["method"], "stats.orders");
+            params.push(notification["params"].clone());
+        }
+        assert_eq!(
+            params,
+            vec![
+                serde_json::json!({"kind": "snapshot", "version": 1, "data": {"state": "stats.orders"}}),
+                serde_json::json!({"kind": "delta", "version": 2, "data": {"opened": 1}}),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_topic_requests_capped_and_aborted() {
+        let publisher = Publisher {
+            inboxes: Arc::new(Inboxes::new(Duration::from_millis(50))),
+            ..Default::default()
+        };
+        // A subscriber that never replies
+        let (sub_tx, _sub_rx) = mpsc::unbounded_channel();
+        publisher.conn_registry.insert(2, Connection::new(2, sub_tx));
+        publisher.sub_manager.subscribe(2, "orders").await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let dispatcher = Dispatcher {
+            router: Arc::new(Router::new()),
+            conn_id: 1,
+            publisher: publisher.clone(),
+            persistent_storage: None,
+            persistent_sub_manager: None,
+            tx,
+            gateway: None,
+            presence: None,
+        };
+        let batch_processor = crate::BatchProcessor::new(crate::BatchMode::default());
+        let mut topic_requests = JoinSet::new();
+        let text = r#"{"jsonrpc":"2.0","method":"rpc.request_topic","params":{"topic":"orders","timeout_ms":3600000},"id":1}"#;
+
+        // An hour-long wait is cut to the server's limit
+        handle_message(text, &dispatcher, &batch_processor, &None, &mut topic_requests).await.unwrap();
+        let outbound = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await.unwrap().unwrap();
+        let response: serde_json::Value = serde_json::from_str(outbound.into_message().to_text().unwrap()).unwrap();
+        assert!(response["error"].is_object());
+
+        // Requests still waiting are dropped with the connection's tasks
+        handle_message(text, &dispatcher, &batch_processor, &None, &mut topic_requests).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!publisher.inboxes.is_empty());
+        drop(topic_requests);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(publisher.inboxes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_publish_to_queue_group() {
+        let publisher = Publisher {
+            queue_groups: Arc::new(QueueGroups::new(crate::QueueStrategy::LeastLoaded)),
+            ..Default::default()
+        };
+
+        let mut receivers = Vec::new();
+        for conn_id in 0..3 {
+            let (tx, rx) = mpsc::unbounded_channel();
+            publisher.conn_registry.insert(conn_id, Connection::new(conn_id, tx));
+            receivers.push(rx);
+        }
+        let workers = || Some("workers".to_string());
+        publisher.sub_manager.subscribe(0, "jobs.build").await;
+        publisher.sub_manager.subscribe_queue(1, "jobs.build", workers(), None).await;
+        publisher.filtered_sub_manager
+            .lock()
+            .await
+            .subscribe_queue(2, crate::TopicFilter::new("jobs.*").unwrap(), workers(), None);
+
+        // Each message reaches the plain subscriber and one worker, and the
+        // send queues stay unread so the load alternates between workers
+        let data = serde_json::json!({"id": 1});
+        for _ in 0..4 {
+            let sent = publisher.publish("jobs.build", &data).await.unwrap();
+            assert_eq!(sent, 2);
+        }
+        let pending: Vec<usize> = (0..3).map(|conn_id| publisher.conn_registry.get(&conn_id).unwrap().pending()).collect();
+        assert_eq!(pending, vec![4, 2, 2]);
+
+        let Ok(Outbound::Shared(text)) = receivers[2].try_recv() else {
+            panic!("expected shared text");
+        };
+        let wrapped: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(wrapped["method"], "jobs.*");
+    }
 }
@@ -37,6 +37,8 @@
 //! ```
 
 use crate::nats_pattern::NatsPattern;
+use crate::subject_index::SubjectIndex;
+use jrow_core::ContentFilter;
 use std::collections::HashMap;
 
 /// Topic filter for matching subscription patterns
@@ -110,10 +112,30 @@ impl TopicFilter {
 }
 
 /// Manages topic subscriptions with pattern matching support
+///
+/// Lookups go through a token trie (see `SubjectIndex`), so finding the
+/// subscribers of a topic doesn't scan every subscription.
 #[derive(Debug)]
 pub struct FilteredSubscriptionManager {
     /// Map of connection ID to their subscription filters
     subscriptions: HashMap<u64, Vec<TopicFilter>>,
+    /// Index of subscriptions by pattern
+    index: SubjectIndex<PatternSubscription>,
+}
+
+/// A connection's subscription to a pattern
+#[derive(Debug)]
+struct PatternSubscription {
+    conn_id: u64,
+    pattern: String,
+    filter: Option<ContentFilter>,
+    queue_group: Option<String>,
+}
+
+impl PatternSubscription {
+    fn accepts(&self, data: &serde_json::Value) -> bool {
+        self.filter.as_ref().is_none_or(|f| f.matches(data))
+    }
 }
 
 impl FilteredSubscriptionManager {
@@ -121,23 +143,62 @@ impl FilteredSubscriptionManager {
     pub fn new() -> Self {
         Self {
             subscriptions: HashMap::new(),
+            index: SubjectIndex::new(),
         }
     }
 
     /// Subscribe a connection to a topic pattern
     pub fn subscribe(&mut self, conn_id: u64, pattern: TopicFilter) {
+        self.insert(conn_id, pattern, None, None);
+    }
+
+    /// Subscribe a connection to a topic pattern, delivering only data
+    /// matching `filter`
+    ///
+    /// Replaces an existing subscription of the connection to the same
+    /// pattern, along with its filter.
+    pub fn subscribe_filtered(&mut self, conn_id: u64, pattern: TopicFilter, filter: Option<ContentFilter>) {
+        self.subscribe_queue(conn_id, pattern, None, filter);
+    }
+
+    /// Subscribe a connection to a topic pattern as a member of `queue_group`
+    ///
+    /// Members of a group share the group's messages instead of each
+    /// receiving a copy; `None` subscribes normally. Replaces an existing
+    /// subscription of the connection to the same pattern.
+    pub fn subscribe_queue(
+        &mut self,
+        conn_id: u64,
+        pattern: TopicFilter,
+        queue_group: Option<String>,
+        filter: Option<ContentFilter>,
+    ) {
+        self.unsubscribe(conn_id, pattern.as_str());
+        self.insert(conn_id, pattern, queue_group, filter);
+    }
+
+    fn insert(&mut self, conn_id: u64, pattern: TopicFilter, queue_group: Option<String>, filter: Option<ContentFilter>) {
+        let subscription = PatternSubscription {
+            conn_id,
+            pattern: pattern.as_str().to_string(),
+            filter,
+            queue_group,
+        };
+        self.index.insert(pattern.as_str(), subscription);
         self.subscriptions
             .entry(conn_id)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(pattern);
     }
 
     /// Unsubscribe a connection from a specific pattern
     pub fn unsubscribe(&mut self, conn_id: u64, pattern: &str) -> bool {
         if let Some(filters) = self.subscriptions.get_mut(&conn_id) {
-            let before_len = filters.len();
             filters.retain(|f| f.as_str() != pattern);
-            filters.len() < before_len
+            if filters.is_empty() {
+                self.subscriptions.remove(&conn_id);
+            }
+            self.index.remove(pattern, |s| s.conn_id == conn_id) > 0
         } else {
             false
         }
@@ -145,39 +206,63 @@ impl FilteredSubscriptionManager {
 
     /// Get all connection IDs that match a given topic
     pub fn get_subscribers(&self, topic: &str) -> Vec<u64> {
-        let mut subscribers = Vec::new();
-        
-        for (&conn_id, filters) in &self.subscriptions {
-            for filter in filters {
-                if filter.matches(topic) {
-                    subscribers.push(conn_id);
-                    break; // Only add each connection once
-                }
-            }
-        }
-        
+        let mut subscribers: Vec<u64> = self
+            .index
+            .matches(topic)
+            .into_iter()
+            .map(|s| s.conn_id)
+            .collect();
+
+        // Only add each connection once
+        subscribers.sort_unstable();
+        subscribers.dedup();
         subscribers
     }
 
     /// Get all subscribers with their matching patterns for a given topic
     /// Returns a vector of (connection_id, pattern_string) tuples
     pub fn get_subscribers_with_patterns(&self, topic: &str) -> Vec<(u64, String)> {
-        let mut result = Vec::new();
-        
-        for (&conn_id, filters) in &self.subscriptions {
-            for filter in filters {
-                if filter.matches(topic) {
-                    result.push((conn_id, filter.as_str().to_string()));
-                }
-            }
-        }
-        
-        result
+        self.index
+            .matches(topic)
+            .into_iter()
+            .map(|s| (s.conn_id, s.pattern.clone()))
+            .collect()
+    }
+
+    /// Get the subscribers that should receive `data` published to a topic,
+    /// with their matching patterns
+    ///
+    /// Like `get_subscribers_with_patterns`, but leaves out subscriptions
+    /// whose content filter doesn't match `data` and queue group members.
+    pub fn get_recipients_with_patterns(&self, topic: &str, data: &serde_json::Value) -> Vec<(u64, String)> {
+        self.index
+            .matches(topic)
+            .into_iter()
+            .filter(|s| s.queue_group.is_none() && s.accepts(data))
+            .map(|s| (s.conn_id, s.pattern.clone()))
+            .collect()
+    }
+
+    /// Get the queue group members whose filter matches `data` published to
+    /// a topic
+    ///
+    /// Returns `(queue_group, connection_id, pattern)` tuples.
+    pub fn get_queue_members(&self, topic: &str, data: &serde_json::Value) -> Vec<(String, u64, String)> {
+        self.index
+            .matches(topic)
+            .into_iter()
+            .filter(|s| s.accepts(data))
+            .filter_map(|s| Some((s.queue_group.clone()?, s.conn_id, s.pattern.clone())))
+            .collect()
     }
 
     /// Remove all subscriptions for a connection
     pub fn remove_connection(&mut self, conn_id: u64) {
-        self.subscriptions.remove(&conn_id);
+        if let Some(filters) = self.subscriptions.remove(&conn_id) {
+            for filter in filters {
+                self.index.remove(filter.as_str(), |s| s.conn_id == conn_id);
+            }
+        }
     }
 
     /// Get all patterns for a connection
@@ -188,9 +273,14 @@ impl FilteredSubscriptionManager {
             .unwrap_or_default()
     }
 
+    /// Get the pattern subscriptions of a connection
+    pub(crate) fn get_filters(&self, conn_id: u64) -> &[TopicFilter] {
+        self.subscriptions.get(&conn_id).map_or(&[], Vec::as_slice)
+    }
+
     /// Get total number of subscriptions across all connections
     pub fn subscription_count(&self) -> usize {
-        self.subscriptions.values().map(|v| v.len()).sum()
+        self.index.len()
     }
 }
 
@@ -390,6 +480,83 @@ mod tests {
         assert!(filter.matches("events.web.user.456.action"));
         assert!(!filter.matches("events.app.admin.123.action"));
     }
-}
 
+    #[test]
+    fn test_subscribers_with_patterns() {
+        let mut manager = FilteredSubscriptionManager::new();
+
+        manager.subscribe(1, TopicFilter::new("events.*").unwrap());
+        manager.subscribe(1, TopicFilter::new("events.>").unwrap());
+        manager.subscribe(2, TopicFilter::new("events.login").unwrap());
+        manager.subscribe(3, TopicFilter::new("*.login").unwrap());
+
+        let mut matches = manager.get_subscribers_with_patterns("events.login");
+        matches.sort();
+        assert_eq!(
+            matches,
+            vec![
+                (1, "events.*".to_string()),
+                (1, "events.>".to_string()),
+                (2, "events.login".to_string()),
+                (3, "*.login".to_string()),
+            ]
+        );
+        assert_eq!(manager.get_subscribers("events.login"), vec![1, 2, 3]);
+
+        // Other connections keep their subscriptions
+        assert!(manager.unsubscribe(1, "events.*"));
+        manager.remove_connection(3);
+        assert_eq!(manager.get_subscribers("events.login"), vec![1, 2]);
+        assert_eq!(manager.subscription_count(), 2);
+    }
+
+    #[test]
+    fn test_filtered_recipients() {
+        let mut manager = FilteredSubscriptionManager::new();
 
+        manager.subscribe(1, TopicFilter::new("orders.>").unwrap());
+        manager.subscribe_filtered(
+            2,
+            TopicFilter::new("orders.>").unwrap(),
+            Some(ContentFilter::eq("/region", "eu")),
+        );
+
+        let eu = serde_json::json!({"region": "eu"});
+        let us = serde_json::json!({"region": "us"});
+        let mut recipients = manager.get_recipients_with_patterns("orders.new", &eu);
+        recipients.sort();
+        assert_eq!(recipients, vec![(1, "orders.>".to_string()), (2, "orders.>".to_string())]);
+        assert_eq!(manager.get_recipients_with_patterns("orders.new", &us), vec![(1, "orders.>".to_string())]);
+
+        // Subscribing again replaces the subscription and its filter
+        manager.subscribe_filtered(2, TopicFilter::new("orders.>").unwrap(), None);
+        assert_eq!(manager.get_recipients_with_patterns("orders.new", &us).len(), 2);
+        assert_eq!(manager.subscription_count(), 2);
+    }
+
+    #[test]
+    fn test_queue_members() {
+        let mut manager = FilteredSubscriptionManager::new();
+        let data = serde_json::json!({});
+
+        manager.subscribe(1, TopicFilter::new("jobs.*").unwrap());
+        manager.subscribe_queue(2, TopicFilter::new("jobs.*").unwrap(), Some("workers".to_string()), None);
+        manager.subscribe_queue(
+            3,
+            TopicFilter::new("jobs.>").unwrap(),
+            Some("workers".to_string()),
+            Some(ContentFilter::exists("/urgent")),
+        );
+
+        assert_eq!(manager.get_recipients_with_patterns("jobs.build", &data), vec![(1, "jobs.*".to_string())]);
+        assert_eq!(
+            manager.get_queue_members("jobs.build", &data),
+            vec![("workers".to_string(), 2, "jobs.*".to_string())]
+        );
+        let mut members = manager.get_queue_members("jobs.build", &serde_json::json!({"urgent": true}));
+        members.sort();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[1], ("workers".to_string(), 3, "jobs.>".to_string()));
+        assert_eq!(manager.get_subscribers("jobs.build"), vec![1, 2, 3]);
+    }
+}
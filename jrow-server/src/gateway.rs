@@ -0,0 +1,373 @@
+//! Gateway mode: forwarding methods to upstream jrow servers
+//!
+//! A gateway exposes several jrow backends behind one endpoint. Each
+//! upstream is mapped to a method prefix; requests, notifications and
+//! subscriptions whose name falls under the prefix are forwarded to it
+//! instead of the local router.
+//!
+//! # Routing
+//!
+//! A name is routed to the upstream with the longest prefix it falls under:
+//! with upstreams for `users` and `users.admin`, `users.admin.ban` goes to
+//! the second and `users.get` to the first. Names outside every prefix,
+//! and the built-in `rpc.*` methods other than `rpc.subscribe` and
+//! `rpc.unsubscribe`, are handled locally. Subscriptions are routed by
+//! their topic, so `rpc.subscribe` for `users.events.*` subscribes on the
+//! `users` upstream.
+//!
+//! # Upstream Connections
+//!
+//! Every downstream connection gets its own upstream connections, opened on
+//! first use and closed when it disconnects. Notifications an upstream sends
+//! (published messages as well as direct notifications) therefore reach
+//! exactly the downstream connection they belong to. A lost upstream
+//! connection is re-established according to the upstream's
+//! `ReconnectionStrategy`, and its subscriptions are restored.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use jrow_server::{JrowServer, Upstream};
+//!
+//! # async fn example() -> jrow_core::Result<()> {
+//! let server = JrowServer::builder()
+//!     .bind_str("0.0.0.0:8080")?
+//!     .upstream("users", Upstream::new("ws://users.internal:8080"))
+//!     .upstream("billing", Upstream::new("ws://billing.internal:8080").strip_prefix())
+//!     .build()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::connection::Connection;
+use jrow_client::{ClientBuilder, ExponentialBackoff, JrowClient, ReconnectionStrategy};
+use jrow_core::{Error, Result};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// Factory for the reconnection strategy of each upstream connection
+type StrategyFactory = Arc<dyn Fn() -> Box<dyn ReconnectionStrategy> + Send + Sync>;
+
+/// An upstream jrow server a gateway forwards to
+///
+/// By default names are forwarded unchanged and lost connections are
+/// re-established with `ExponentialBackoff::default()`.
+#[derive(Clone)]
+pub struct Upstream {
+    /// WebSocket URL of the upstream server
+    url: String,
+    /// Strategy for re-establishing lost connections, `None` to give up
+    reconnect: Option<StrategyFactory>,
+    /// Whether the prefix is removed before forwarding
+    strip_prefix: bool,
+}
+
+impl Upstream {
+    /// Forward to the server at `url` (e.g. `ws://users.internal:8080`)
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            reconnect: Some(Arc::new(|| Box::new(ExponentialBackoff::default()))),
+            strip_prefix: false,
+        }
+    }
+
+    /// Re-establish lost connections with the strategy `factory` creates
+    ///
+    /// Each upstream connection needs its own strategy state, so a new one
+    /// is created per connection.
+    pub fn with_reconnect<F>(mut self, factory: F) -> Self
+    where
+        F: Fn() -> Box<dyn ReconnectionStrategy> + Send + Sync + 'static,
+    {
+        self.reconnect = Some(Arc::new(factory));
+        self
+    }
+
+    /// Don't re-establish lost connections
+    ///
+    /// Once the connection is lost, forwarded calls fail until the
+    /// downstream client reconnects.
+    pub fn without_reconnect(mut self) -> Self {
+        self.reconnect = None;
+        self
+    }
+
+    /// Remove the prefix before forwarding
+    ///
+    /// With prefix `users`, `users.get` is called as `get` upstream, and
+    /// upstream notifications are relayed with the prefix added back.
+    pub fn strip_prefix(mut self) -> Self {
+        self.strip_prefix = true;
+        self
+    }
+
+    /// The upstream server's URL
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl std::fmt::Debug for Upstream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Upstream")
+            .field("url", &self.url)
+            .field("reconnect", &self.reconnect.is_some())
+            .field("strip_prefix", &self.strip_prefix)
+            .finish()
+    }
+}
+
+/// Prefix table of a gateway server
+pub(crate) struct Gateway {
+    /// Upstreams with their prefixes, longest prefix first
+    upstreams: Vec<(String, Upstream)>,
+}
+
+impl Gateway {
+    /// Create a gateway, rejecting empty, malformed and duplicate prefixes
+    pub(crate) fn new(mut upstreams: Vec<(String, Upstream)>) -> Result<Self> {
+        for (prefix, _) in &upstreams {
+            if prefix.is_empty() || prefix.split('.').any(|token| token.is_empty()) {
+                return Err(Error::InvalidRequest(format!(
+                    "Invalid upstream prefix: '{}'",
+                    prefix
+                )));
+            }
+        }
+
+        upstreams.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        if let Some(pair) = upstreams.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(Error::InvalidRequest(format!(
+                "Duplicate upstream prefix: '{}'",
+                pair[0].0
+            )));
+        }
+
+        Ok(Self { upstreams })
+    }
+
+    /// Find the upstream for `name`, with the name to use upstream
+    fn route(&self, name: &str) -> Option<(usize, String)> {
+        self.upstreams
+            .iter()
+            .enumerate()
+            .find_map(|(index, (prefix, upstream))| {
+                let rest = name.strip_prefix(prefix.as_str())?.strip_prefix('.')?;
+                let forwarded = if upstream.strip_prefix { rest } else { name };
+                Some((index, forwarded.to_string()))
+            })
+    }
+}
+
+/// Upstream connections of one downstream connection
+pub(crate) struct GatewaySession {
+    gateway: Arc<Gateway>,
+    /// Downstream connection notifications are relayed to
+    conn: Connection,
+    /// Upstream connections by upstream index, each opened on first use
+    ///
+    /// Every upstream has a cell of its own, so connecting to a slow or
+    /// unreachable upstream doesn't hold up calls to the others.
+    clients: Vec<OnceCell<JrowClient>>,
+}
+
+impl GatewaySession {
+    pub(crate) fn new(gateway: Arc<Gateway>, conn: Connection) -> Self {
+        let clients = gateway.upstreams.iter().map(|_| OnceCell::new()).collect();
+        Self {
+            gateway,
+            conn,
+            clients,
+        }
+    }
+
+    /// Forward a request, or return `None` if it is handled locally
+    pub(crate) async fn request(
+        &self,
+        method: &str,
+        params: Option<&Value>,
+    ) -> Option<Result<Value>> {
+        match method {
+            "rpc.subscribe" | "rpc.unsubscribe" => {
+                let topic = params?.get("topic")?.as_str()?;
+                let (index, upstream_topic) = self.gateway.route(topic)?;
+                Some(if method == "rpc.subscribe" {
+                    self.subscribe(index, topic, upstream_topic).await
+                } else {
+                    self.unsubscribe(index, topic, upstream_topic).await
+                })
+            }
+            _ if method.starts_with("rpc.") => None,
+            _ => {
+                let (index, upstream_method) = self.gateway.route(method)?;
+                Some(self.forward_request(index, upstream_method, params.cloned()).await)
+            }
+        }
+    }
+
+    /// Forward a notification, or return `None` if it is handled locally
+    pub(crate) async fn notify(&self, method: &str, params: Option<&Value>) -> Option<Result<()>> {
+        let (index, upstream_method) = self.gateway.route(method)?;
+        Some(match self.client(index).await {
+            Ok(client) => client.notify(upstream_method, params).await,
+            Err(e) => Err(e),
+        })
+    }
+
+    /// Close all upstream connections
+    pub(crate) async fn close(&self) {
+        for client in self.clients.iter().filter_map(OnceCell::get) {
+            client.disconnect().await;
+        }
+    }
+
+    async fn forward_request(
+        &self,
+        index: usize,
+        method: String,
+        params: Option<Value>,
+    ) -> Result<Value> {
+        let client = self.client(index).await?;
+        client.request(method, params).await
+    }
+
+    async fn subscribe(&self, index: usize, topic: &str, upstream_topic: String) -> Result<Value> {
+        let client = self.client(index).await?;
+
+        // Relay under the topic the downstream client subscribed to. Pattern
+        // payloads name the upstream topic, so add back a stripped prefix
+        let is_pattern = topic.contains('*') || topic.contains('>');
+        let (prefix, upstream) = &self.gateway.upstreams[index];
+        let prefix = (is_pattern && upstream.strip_prefix).then(|| prefix.clone());
+        let conn = self.conn.clone();
+        let downstream_topic = topic.to_string();
+        client
+            .subscribe(upstream_topic, move |mut data| {
+                if let (Some(prefix), Some(Value::String(inner))) = (&prefix, data.get_mut("topic")) {
+                    *inner = format!("{}.{}", prefix, inner);
+                }
+                let result = conn.notify(&downstream_topic, Some(data));
+                async move {
+                    if result.is_err() {
+                        tracing::debug!("Downstream connection closed, dropping notification");
+                    }
+                }
+            })
+            .await?;
+
+        Ok(serde_json::json!({
+            "subscribed": true,
+            "topic": topic,
+            "pattern": is_pattern
+        }))
+    }
+
+    async fn unsubscribe(&self, index: usize, topic: &str, upstream_topic: String) -> Result<Value> {
+        // Without an open connection there is nothing to unsubscribe from
+        let client = self.clients[index].get();
+        let was_subscribed = match client {
+            Some(client) => {
+                let was_subscribed = client.subscriptions().await.contains(&upstream_topic);
+                client.unsubscribe(upstream_topic).await?;
+                was_subscribed
+            }
+            None => false,
+        };
+
+        Ok(serde_json::json!({
+            "unsubscribed": was_subscribed,
+            "topic": topic
+        }))
+    }
+
+    /// Get the connection to an upstream, connecting on first use
+    ///
+    /// Concurrent first uses of the same upstream wait for one connection
+    /// attempt; if it fails, the next use tries again.
+    async fn client(&self, index: usize) -> Result<JrowClient> {
+        self.clients[index]
+            .get_or_try_init(|| self.connect(index))
+            .await
+            .cloned()
+    }
+
+    /// Open a connection to an upstream and relay its notifications
+    async fn connect(&self, index: usize) -> Result<JrowClient> {
+        let (prefix, upstream) = &self.gateway.upstreams[index];
+        let builder = ClientBuilder::new(upstream.url.clone());
+        let builder = match &upstream.reconnect {
+            Some(factory) => builder.with_reconnect(factory()),
+            None => builder.without_reconnect(),
+        };
+        let client = builder.connect().await.map_err(|e| {
+            Error::Internal(format!("Upstream {} unavailable: {}", upstream.url, e))
+        })?;
+        tracing::debug!(conn_id = self.conn.id, url = %upstream.url, "Upstream connected");
+
+        // Relay direct notifications from the upstream
+        let conn = self.conn.clone();
+        let prefix = upstream.strip_prefix.then(|| prefix.clone());
+        client
+            .on_unhandled_notification(move |notification| {
+                let method = match &prefix {
+                    Some(prefix) => format!("{}.{}", prefix, notification.method),
+                    None => notification.method,
+                };
+                let result = conn.notify(method, notification.params);
+                async move {
+                    if result.is_err() {
+                        tracing::debug!("Downstream connection closed, dropping notification");
+                    }
+                }
+            })
+            .await;
+
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gateway(prefixes: &[(&str, bool)]) -> Result<Gateway> {
+        Gateway::new(
+            prefixes
+                .iter()
+                .map(|(prefix, strip)| {
+                    let upstream = Upstream::new(format!("ws://{}", prefix));
+                    let upstream = if *strip { upstream.strip_prefix() } else { upstream };
+                    (prefix.to_string(), upstream)
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let gateway = gateway(&[("users", false), ("users.admin", true)]).unwrap();
+
+        let (index, method) = gateway.route("users.admin.ban").unwrap();
+        assert_eq!(gateway.upstreams[index].0, "users.admin");
+        assert_eq!(method, "ban");
+
+        let (index, method) = gateway.route("users.get").unwrap();
+        assert_eq!(gateway.upstreams[index].0, "users");
+        assert_eq!(method, "users.get");
+
+        assert!(gateway.route("users").is_none());
+        assert!(gateway.route("usersx.get").is_none());
+        assert!(gateway.route("rpc.discover").is_none());
+    }
+
+    #[test]
+    fn test_invalid_prefixes_rejected() {
+        assert!(gateway(&[("", false)]).is_err());
+        assert!(gateway(&[("users.", false)]).is_err());
+        let err = gateway(&[("users", false), ("users", true)]).err().unwrap();
+        assert!(err.to_string().contains("Duplicate upstream prefix: 'users'"));
+    }
+}
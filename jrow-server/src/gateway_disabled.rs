@@ -0,0 +1,34 @@
+//! Stand-in for gateway mode when the `gateway` feature is disabled
+//!
+//! Connections carry an optional gateway session. Without the feature these
+//! types have no values, so that option is always `None` and forwarding
+//! compiles away.
+
+use crate::connection::Connection;
+use jrow_core::Result;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Prefix table of a gateway server (never constructed)
+pub(crate) enum Gateway {}
+
+/// Upstream connections of one downstream connection (never constructed)
+pub(crate) enum GatewaySession {}
+
+impl GatewaySession {
+    pub(crate) fn new(gateway: Arc<Gateway>, _conn: Connection) -> Self {
+        match *gateway {}
+    }
+
+    pub(crate) async fn request(&self, _method: &str, _params: Option<&Value>) -> Option<Result<Value>> {
+        match *self {}
+    }
+
+    pub(crate) async fn notify(&self, _method: &str, _params: Option<&Value>) -> Option<Result<()>> {
+        match *self {}
+    }
+
+    pub(crate) async fn close(&self) {
+        match *self {}
+    }
+}
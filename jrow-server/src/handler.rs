@@ -18,7 +18,9 @@
 //!
 //! 1. **from_fn**: Wrap an async closure that works with raw JSON values
 //! 2. **from_typed_fn**: Wrap an async closure with automatic type conversion
-//! 3. **#[handler] macro**: Annotate a function to generate a handler (via jrow-macros)
+//! 3. **from_typed_fn_with_schema**: Like `from_typed_fn`, plus JSON Schemas for discovery
+//! 4. **from_args_fn**: Named arguments accepted by name (object) or by position (array)
+//! 5. **#[handler] macro**: Annotate a function to generate a handler (via jrow-macros)
 //!
 //! # Why Box<dyn Future>?
 //!
@@ -49,6 +51,7 @@
 //! });
 //! ```
 
+use crate::openrpc::MethodSchema;
 use jrow_core::{Error, Result};
 use serde_json::Value;
 use std::future::Future;
@@ -115,7 +118,7 @@ pub trait Handler: Send + Sync {
     /// # Arguments
     ///
     /// * `params` - Optional JSON value containing the request parameters.
-    ///              `None` if the request had no params field.
+    ///   `None` if the request had no params field.
     ///
     /// # Returns
     ///
@@ -129,8 +132,18 @@ pub trait Handler: Send + Sync {
     /// JSON-RPC error responses with appropriate error codes:
     /// - `Error::InvalidParams` → -32602 (Invalid params)
     /// - `Error::MethodNotFound` → -32601 (Method not found)
+    /// - `Error::JsonRpc` → sent as-is, keeping its code and `data`
     /// - `Error::Internal` → -32603 (Internal error)
     fn handle(&self, params: Option<Value>) -> HandlerResult;
+
+    /// JSON Schemas for this handler's params and result, if known
+    ///
+    /// Used when generating the OpenRPC discovery document. Handlers created
+    /// with `from_typed_fn_with_schema` or the `#[handler]` macro return
+    /// `Some`; the default implementation returns `None`.
+    fn schema(&self) -> Option<MethodSchema> {
+        None
+    }
 }
 
 /// Wrapper that adapts an async function into a Handler
@@ -180,7 +193,7 @@ where
     /// # Arguments
     ///
     /// * `func` - An async function or closure that takes optional JSON params
-    ///            and returns a future producing a Result<Value>
+    ///   and returns a future producing a Result<Value>
     ///
     /// # Examples
     ///
@@ -252,6 +265,56 @@ where
     Box::new(AsyncHandler::new(func))
 }
 
+/// Handler that also receives the name of the method being called
+///
+/// Used for pattern routes (`Router::register_pattern`) and the fallback
+/// handler (`Router::set_fallback`), where one handler serves many method
+/// names and needs to know which one was requested.
+pub trait MethodHandler: Send + Sync {
+    /// Handle a call to `method` with the given params
+    ///
+    /// Errors are converted to JSON-RPC error responses exactly as for
+    /// [`Handler::handle`].
+    fn handle(&self, method: &str, params: Option<Value>) -> HandlerResult;
+}
+
+/// Wrapper that adapts an async function into a MethodHandler
+struct AsyncMethodHandler<F> {
+    func: F,
+}
+
+impl<F, Fut> MethodHandler for AsyncMethodHandler<F>
+where
+    F: Fn(String, Option<Value>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Value>> + Send + 'static,
+{
+    fn handle(&self, method: &str, params: Option<Value>) -> HandlerResult {
+        Box::pin((self.func)(method.to_string(), params))
+    }
+}
+
+/// Create a method handler from an async function of the method name and params
+///
+/// # Examples
+///
+/// ```rust
+/// use jrow_server::{from_method_fn, Router};
+///
+/// let mut router = Router::new();
+/// router
+///     .register_pattern("plugin.*", from_method_fn(|method, params| async move {
+///         Ok(serde_json::json!({"called": method, "params": params}))
+///     }))
+///     .unwrap();
+/// ```
+pub fn from_method_fn<F, Fut>(func: F) -> Box<dyn MethodHandler>
+where
+    F: Fn(String, Option<Value>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Value>> + Send + 'static,
+{
+    Box::new(AsyncMethodHandler { func })
+}
+
 /// Create a handler from an async function with automatic type conversion
 ///
 /// This is the preferred way to create handlers when you want type safety.
@@ -328,13 +391,7 @@ where
         async move {
             // Deserialize params to the expected type P
             // If params is None, try to deserialize from null (works for unit type)
-            let params: P = match params {
-                Some(p) => {
-                    serde_json::from_value(p).map_err(|e| Error::InvalidParams(e.to_string()))?
-                }
-                None => serde_json::from_value(Value::Null)
-                    .map_err(|e| Error::InvalidParams(e.to_string()))?,
-            };
+            let params: P = deserialize_params(params.unwrap_or(Value::Null))?;
 
             // Call the user's function with the deserialized params
             let result = func(params).await?;
@@ -347,6 +404,199 @@ where
     })
 }
 
+/// Create a handler whose params are a set of named arguments
+///
+/// `P` is a struct with one field per argument, in declaration order, and
+/// `names` lists those fields in the same order. Requests may pass the
+/// arguments either by name (a JSON object) or by position (a JSON array);
+/// positional params are mapped onto the names before deserialization, so
+/// errors always name the offending argument. Omitted params are treated as
+/// an empty object, letting `Option` and defaulted fields fill in.
+///
+/// This is what `#[handler]` generates for functions with several arguments;
+/// the handler also reports its schema for discovery.
+///
+/// # Error Handling
+///
+/// - More positional params than `names`: `Error::InvalidParams`
+/// - Params that are neither an object nor an array: `Error::InvalidParams`
+/// - A missing or mistyped argument: `Error::InvalidParams` naming the argument
+///
+/// # Examples
+///
+/// ```rust
+/// use jrow_server::{from_args_fn, Handler};
+/// use schemars::JsonSchema;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, JsonSchema)]
+/// struct AddArgs {
+///     a: i32,
+///     #[serde(default)]
+///     b: Option<i32>,
+/// }
+///
+/// let handler = from_args_fn(&["a", "b"], |args: AddArgs| async move {
+///     Ok(args.a + args.b.unwrap_or(0))
+/// });
+///
+/// # tokio_test(async {
+/// let by_name = handler.handle(Some(serde_json::json!({"a": 1, "b": 2}))).await.unwrap();
+/// let by_position = handler.handle(Some(serde_json::json!([1, 2]))).await.unwrap();
+/// assert_eq!(by_name, by_position);
+/// # });
+/// # fn tokio_test(f: impl std::future::Future<Output = ()>) {
+/// #     tokio::runtime::Runtime::new().unwrap().block_on(f)
+/// # }
+/// ```
+pub fn from_args_fn<P, R, F, Fut>(names: &'static [&'static str], func: F) -> Box<dyn Handler>
+where
+    P: serde::de::DeserializeOwned + schemars::JsonSchema + Send + 'static,
+    R: serde::Serialize + schemars::JsonSchema + Send + 'static,
+    F: Fn(P) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<R>> + Send + 'static,
+{
+    positional_args(names, from_typed_fn_with_schema(func))
+}
+
+/// Accept positional params for a handler that takes named arguments
+///
+/// Array params are turned into an object by pairing each value with the
+/// name at the same index, so the wrapped handler (and any validation in
+/// front of it) only ever sees objects. Omitted or `null` params become an
+/// empty object. `from_args_fn` is this wrapper around
+/// `from_typed_fn_with_schema`.
+pub fn positional_args(names: &'static [&'static str], handler: Box<dyn Handler>) -> Box<dyn Handler> {
+    Box::new(PositionalHandler {
+        names,
+        inner: handler,
+    })
+}
+
+/// Handler wrapper that maps positional params onto argument names
+struct PositionalHandler {
+    names: &'static [&'static str],
+    inner: Box<dyn Handler>,
+}
+
+impl Handler for PositionalHandler {
+    fn handle(&self, params: Option<Value>) -> HandlerResult {
+        let args = match params {
+            None | Some(Value::Null) => Value::Object(serde_json::Map::new()),
+            Some(Value::Object(map)) => Value::Object(map),
+            Some(Value::Array(values)) => {
+                if values.len() > self.names.len() {
+                    let err = Error::InvalidParams(format!(
+                        "expected at most {} positional params, got {}",
+                        self.names.len(),
+                        values.len()
+                    ));
+                    return Box::pin(async move { Err(err) });
+                }
+                // Map positions onto argument names
+                Value::Object(
+                    self.names
+                        .iter()
+                        .map(|name| name.to_string())
+                        .zip(values)
+                        .collect(),
+                )
+            }
+            Some(_) => {
+                let err = Error::InvalidParams("params must be an object or an array".to_string());
+                return Box::pin(async move { Err(err) });
+            }
+        };
+
+        self.inner.handle(Some(args))
+    }
+
+    fn schema(&self) -> Option<MethodSchema> {
+        let mut schema = self.inner.schema()?;
+        schema.positional = Some(self.names.iter().map(|name| name.to_string()).collect());
+        Some(schema)
+    }
+}
+
+/// Attach params/result schemas to an existing handler
+///
+/// Useful for handlers built with `from_fn` that still want a precise
+/// entry in the OpenRPC discovery document.
+pub fn with_schema(handler: Box<dyn Handler>, schema: MethodSchema) -> Box<dyn Handler> {
+    Box::new(SchemaHandler {
+        inner: handler,
+        schema,
+    })
+}
+
+/// Deserialize params, reporting the path of the field that failed
+///
+/// Errors read like ``invalid params at `user.age`: invalid type: string "x",
+/// expected u32``, so clients can tell which argument was wrong.
+fn deserialize_params<P: serde::de::DeserializeOwned>(params: Value) -> Result<P> {
+    serde_path_to_error::deserialize(params).map_err(|e| {
+        let path = e.path().to_string();
+        if path == "." {
+            Error::InvalidParams(e.into_inner().to_string())
+        } else {
+            Error::InvalidParams(format!("invalid params at `{}`: {}", path, e.into_inner()))
+        }
+    })
+}
+
+/// Handler wrapper that attaches params/result schemas to another handler
+struct SchemaHandler {
+    /// The handler performing the actual work
+    inner: Box<dyn Handler>,
+    /// Schemas reported for discovery
+    schema: MethodSchema,
+}
+
+impl Handler for SchemaHandler {
+    fn handle(&self, params: Option<Value>) -> HandlerResult {
+        self.inner.handle(params)
+    }
+
+    fn schema(&self) -> Option<MethodSchema> {
+        Some(self.schema.clone())
+    }
+}
+
+/// Create a typed handler that also describes itself for discovery
+///
+/// Behaves exactly like [`from_typed_fn`], but additionally records JSON
+/// Schemas for `P` and `R` so the method appears fully typed in the
+/// OpenRPC document served by `rpc.discover`.
+///
+/// # Examples
+///
+/// ```rust
+/// use jrow_server::{from_typed_fn_with_schema, Handler};
+/// use schemars::JsonSchema;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize, JsonSchema)]
+/// struct AddParams {
+///     a: i32,
+///     b: i32,
+/// }
+///
+/// let handler = from_typed_fn_with_schema(|params: AddParams| async move {
+///     Ok(params.a + params.b)
+/// });
+///
+/// assert!(handler.schema().is_some());
+/// ```
+pub fn from_typed_fn_with_schema<P, R, F, Fut>(func: F) -> Box<dyn Handler>
+where
+    P: serde::de::DeserializeOwned + schemars::JsonSchema + Send + 'static,
+    R: serde::Serialize + schemars::JsonSchema + Send + 'static,
+    F: Fn(P) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<R>> + Send + 'static,
+{
+    with_schema(from_typed_fn(func), MethodSchema::of::<P, R>())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -377,4 +627,86 @@ mod tests {
         let sum: AddResult = serde_json::from_value(result).unwrap();
         assert_eq!(sum.sum, 8);
     }
+
+    #[tokio::test]
+    async fn test_typed_handler_error_names_field() {
+        let handler = from_typed_fn(|params: AddParams| async move { Ok(params.a + params.b) });
+
+        let err = handler
+            .handle(Some(serde_json::json!({"a": 1, "b": "two"})))
+            .await
+            .unwrap_err();
+        match err {
+            Error::InvalidParams(msg) => assert!(msg.starts_with("invalid params at `b`"), "{}", msg),
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let err = handler
+            .handle(Some(serde_json::json!({"a": 1})))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("missing field `b`"));
+    }
+
+    #[tokio::test]
+    async fn test_args_handler_by_name_and_position() {
+        fn default_scale() -> i32 {
+            1
+        }
+
+        #[derive(Deserialize, schemars::JsonSchema)]
+        struct Args {
+            a: i32,
+            #[serde(default)]
+            b: Option<i32>,
+            #[serde(default = "default_scale")]
+            scale: i32,
+        }
+
+        let handler = from_args_fn(&["a", "b", "scale"], |args: Args| async move {
+            Ok((args.a + args.b.unwrap_or(0)) * args.scale)
+        });
+
+        let call = |params| handler.handle(params);
+        assert_eq!(call(Some(serde_json::json!({"a": 2, "b": 3}))).await.unwrap(), 5);
+        assert_eq!(call(Some(serde_json::json!([2, 3, 10]))).await.unwrap(), 50);
+        assert_eq!(call(Some(serde_json::json!([2]))).await.unwrap(), 2);
+
+        let err = call(Some(serde_json::json!([1, 2, 3, 4]))).await.unwrap_err();
+        assert!(err.to_string().contains("at most 3 positional params"));
+
+        let err = call(Some(serde_json::json!([1, "x"]))).await.unwrap_err();
+        assert!(err.to_string().contains("`b`"), "{}", err);
+
+        let err = call(None).await.unwrap_err();
+        assert!(err.to_string().contains("missing field `a`"));
+
+        let schema = handler.schema().unwrap();
+        assert_eq!(schema.params["required"], serde_json::json!(["a"]));
+    }
+
+    #[tokio::test]
+    async fn test_typed_handler_with_schema() {
+        #[derive(Deserialize, schemars::JsonSchema)]
+        struct EchoParams {
+            message: String,
+        }
+
+        let handler = from_typed_fn_with_schema(|params: EchoParams| async move {
+            Ok(params.message)
+        });
+
+        let result = handler
+            .handle(Some(serde_json::json!({"message": "hi"})))
+            .await
+            .unwrap();
+        assert_eq!(result, serde_json::json!("hi"));
+
+        let schema = handler.schema().unwrap();
+        assert_eq!(schema.params["required"], serde_json::json!(["message"]));
+        assert_eq!(schema.result["type"], "string");
+
+        // Untyped handlers carry no schema
+        assert!(from_typed_fn(|_: ()| async { Ok(()) }).schema().is_none());
+    }
 }
@@ -0,0 +1,142 @@
+//! Identities of authenticated connections
+//!
+//! jrow doesn't authenticate clients itself; that is left to application
+//! middleware. Once a connection is authenticated, the middleware binds the
+//! connection to an identity (a user ID, say), so the server can reach every
+//! connection of that user with `JrowServer::notify_identity`.
+//!
+//! A connection has at most one identity; an identity can have any number
+//! of connections (several tabs or devices). Bindings are removed when the
+//! connection closes.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use jrow_server::{JrowServer, MiddlewareAction, MiddlewareContext, SyncMiddleware};
+//! use jrow_core::Result;
+//!
+//! struct Login(jrow_server::IdentityRegistry);
+//!
+//! impl SyncMiddleware for Login {
+//!     fn pre_handle(&self, ctx: &mut MiddlewareContext) -> Result<MiddlewareAction> {
+//!         if ctx.method == "login" {
+//!             if let Some(user) = ctx.params.as_ref().and_then(|p| p["user"].as_str()) {
+//!                 self.0.bind(ctx.conn_id, user);
+//!             }
+//!         }
+//!         Ok(MiddlewareAction::Continue)
+//!     }
+//!
+//!     fn post_handle(&self, _ctx: &mut MiddlewareContext, _result: &Result<serde_json::Value>) -> Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let builder = JrowServer::builder();
+//! let login = Login(builder.identities());
+//! let builder = builder.use_sync_middleware(login);
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Maps connections to the identities they authenticated as
+///
+/// Cloning yields a handle to the same registry. Methods are synchronous so
+/// both `Middleware` and `SyncMiddleware` implementations can bind
+/// identities.
+#[derive(Clone, Default)]
+pub struct IdentityRegistry {
+    inner: Arc<Mutex<Identities>>,
+}
+
+/// Both directions of the mapping, kept in sync
+#[derive(Default)]
+struct Identities {
+    /// Identity of each authenticated connection
+    by_connection: HashMap<u64, String>,
+    /// Connections of each identity
+    by_identity: HashMap<String, HashSet<u64>>,
+}
+
+impl IdentityRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind a connection to an identity, replacing its previous identity
+    pub fn bind(&self, conn_id: u64, identity: impl Into<String>) {
+        let identity = identity.into();
+        let mut inner = self.lock();
+        inner.remove(conn_id);
+        inner
+            .by_identity
+            .entry(identity.clone())
+            .or_default()
+            .insert(conn_id);
+        inner.by_connection.insert(conn_id, identity);
+    }
+
+    /// Remove a connection's identity, returning it
+    pub fn unbind(&self, conn_id: u64) -> Option<String> {
+        self.lock().remove(conn_id)
+    }
+
+    /// The identity a connection is bound to
+    pub fn identity_of(&self, conn_id: u64) -> Option<String> {
+        self.lock().by_connection.get(&conn_id).cloned()
+    }
+
+    /// The connections bound to an identity
+    pub fn connections_of(&self, identity: &str) -> Vec<u64> {
+        self.lock()
+            .by_identity
+            .get(identity)
+            .map(|conns| conns.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Identities> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Identities {
+    fn remove(&mut self, conn_id: u64) -> Option<String> {
+        let identity = self.by_connection.remove(&conn_id)?;
+        if let Some(conns) = self.by_identity.get_mut(&identity) {
+            conns.remove(&conn_id);
+            if conns.is_empty() {
+                self.by_identity.remove(&identity);
+            }
+        }
+        Some(identity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind_and_unbind() {
+        let registry = IdentityRegistry::new();
+        registry.bind(1, "alice");
+        registry.bind(2, "alice");
+        registry.bind(3, "bob");
+
+        let mut alice = registry.connections_of("alice");
+        alice.sort();
+        assert_eq!(alice, vec![1, 2]);
+        assert_eq!(registry.identity_of(3).as_deref(), Some("bob"));
+
+        // Rebinding moves the connection
+        registry.bind(2, "bob");
+        assert_eq!(registry.connections_of("alice"), vec![1]);
+
+        assert_eq!(registry.unbind(1).as_deref(), Some("alice"));
+        assert!(registry.connections_of("alice").is_empty());
+        assert_eq!(registry.unbind(1), None);
+    }
+}
@@ -0,0 +1,153 @@
+//! Reply inboxes for request-reply over topics
+//!
+//! `JrowServer::request_topic` and `rpc.request_topic` publish a request
+//! with a fresh inbox topic (see `jrow_core::request_reply`) and wait for a
+//! responder to publish to it. The inbox isn't a subscription: publishing
+//! to a waiting inbox hands the message straight to the request, and the
+//! inbox is closed as soon as it's answered, times out or the request is
+//! dropped.
+//!
+//! Inbox names end with 128 bits derived from a per-process random key, so
+//! other clients can't guess them and steal replies. Clients can keep an
+//! inbox open for at most `max_timeout`, however long they ask to wait.
+
+use jrow_core::request_reply::INBOX_PREFIX;
+use serde_json::Value;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Longest a client's topic request waits for a reply by default
+pub(crate) const DEFAULT_MAX_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Inboxes of requests waiting for a reply
+#[derive(Debug)]
+pub(crate) struct Inboxes {
+    waiting: Mutex<HashMap<String, oneshot::Sender<Value>>>,
+    next: AtomicU64,
+    keys: RandomState,
+    /// Longest a client's topic request may wait
+    max_timeout: Duration,
+}
+
+/// An open inbox, closed when dropped
+pub(crate) struct Inbox<'a> {
+    inboxes: &'a Inboxes,
+    topic: String,
+    reply: oneshot::Receiver<Value>,
+}
+
+impl Inboxes {
+    /// Create inboxes whose client requests wait at most `max_timeout`
+    pub(crate) fn new(max_timeout: Duration) -> Self {
+        Self {
+            waiting: Mutex::default(),
+            next: AtomicU64::new(0),
+            keys: RandomState::new(),
+            max_timeout,
+        }
+    }
+
+    /// Longest a client's topic request may wait for a reply
+    pub(crate) fn max_timeout(&self) -> Duration {
+        self.max_timeout
+    }
+
+    /// Open an inbox with a new unique topic
+    pub(crate) fn open(&self) -> Inbox<'_> {
+        let seq = self.next.fetch_add(1, Ordering::Relaxed);
+        let topic = format!(
+            "{}.{:016x}{:016x}",
+            INBOX_PREFIX,
+            self.keys.hash_one((seq, 0u8)),
+            self.keys.hash_one((seq, 1u8))
+        );
+
+        let (tx, reply) = oneshot::channel();
+        self.waiting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(topic.clone(), tx);
+        Inbox {
+            inboxes: self,
+            topic,
+            reply,
+        }
+    }
+
+    /// Hand `data` to the request waiting on `topic`, if any
+    pub(crate) fn reply(&self, topic: &str, data: &Value) -> bool {
+        let waiting = self
+            .waiting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(topic);
+        waiting.is_some_and(|tx| tx.send(data.clone()).is_ok())
+    }
+
+    /// Whether no request is waiting for a reply
+    #[cfg(test)]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.waiting.lock().unwrap_or_else(PoisonError::into_inner).is_empty()
+    }
+}
+
+impl Default for Inboxes {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TIMEOUT)
+    }
+}
+
+impl Inbox<'_> {
+    /// Topic responders publish the reply to
+    pub(crate) fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Wait for the reply
+    pub(crate) async fn reply(&mut self) -> Option<Value> {
+        (&mut self.reply).await.ok()
+    }
+}
+
+impl Drop for Inbox<'_> {
+    fn drop(&mut self) {
+        self.inboxes
+            .waiting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.topic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jrow_core::request_reply::is_inbox;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_first_reply_wins() {
+        let inboxes = Inboxes::default();
+        let mut inbox = inboxes.open();
+        assert!(is_inbox(inbox.topic()));
+        assert_ne!(inbox.topic(), inboxes.open().topic());
+
+        let topic = inbox.topic().to_string();
+        assert!(inboxes.reply(&topic, &json!(1)));
+        assert!(!inboxes.reply(&topic, &json!(2)));
+        assert_eq!(inbox.reply().await, Some(json!(1)));
+    }
+
+    #[test]
+    fn test_closed_when_dropped() {
+        let inboxes = Inboxes::default();
+        let topic = inboxes.open().topic().to_string();
+        assert!(!inboxes.reply(&topic, &json!(1)));
+        assert!(inboxes.is_empty());
+    }
+}
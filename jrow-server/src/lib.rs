@@ -7,13 +7,22 @@
 //! # Core Features
 //!
 //! - **WebSocket Transport**: Full-duplex communication using async WebSockets
-//! - **Method Routing**: Register handlers for JSON-RPC methods
+//! - **Method Routing**: Register handlers for JSON-RPC methods, also while running
 //! - **Pub/Sub**: Built-in support for topic subscriptions and notifications
 //! - **Pattern Matching**: NATS-style wildcard subscriptions (`*` and `>`)
+//! - **Queue Groups**: Subscribers sharing a topic's messages, one member each
+//! - **Retained Messages**: Last messages of a topic replayed to new subscribers
+//! - **Snapshots**: A topic's state sent to new subscribers, followed by versioned deltas
+//! - **Throttling**: Per-subscription rate limits, keeping the latest message per key
+//! - **Request-Reply**: Requests published to topics and answered by subscribers
 //! - **Batch Processing**: Handle multiple requests in a single message
 //! - **Middleware**: Request/response interceptors for cross-cutting concerns
 //! - **Persistence**: Durable subscriptions with message replay
 //! - **Observability**: OpenTelemetry integration for traces and metrics
+//! - **Discovery**: OpenRPC document generated from handlers, served via `rpc.discover`
+//! - **Client Bindings**: TypeScript module generated from the OpenRPC document
+//! - **Validation**: Params checked against JSON Schemas before handlers run
+//! - **Gateway**: Method prefixes forwarded to upstream jrow servers (`gateway` feature)
 //!
 //! # Quick Start
 //!
@@ -59,9 +68,19 @@
 //!
 //! - `rpc.subscribe` - Subscribe to a topic or pattern
 //! - `rpc.unsubscribe` - Unsubscribe from a topic
+//! - `rpc.request_topic` / `rpc.publish` - Send a request to a topic's
+//!   subscribers and publish the reply to its inbox
+//! - `rpc.publish` / `rpc.publish_persistent` - Publish from the client,
+//!   one message or a batch, to the topics the publish ACL allows
 //! - Server-to-client notifications for published messages
 //!
 //! Publishers use `server.publish(topic, data)` to broadcast to subscribers.
+//! Clients can only send topic requests and publish replies until
+//! `ServerBuilder::publish_acl` decides which [`PublishAttempt`]s to allow.
+//! To reach clients regardless of subscriptions, use `notify_connection`,
+//! `broadcast`, or `notify_identity` for connections bound to a user through
+//! the [`IdentityRegistry`]. Each queues the notification on the connections'
+//! outbound channels without waiting and returns a [`DeliveryReport`].
 //!
 //! # Persistence
 //!
@@ -85,44 +104,94 @@
 //!
 //! Persistent subscriptions survive disconnects and replay missed messages.
 
+mod acl;
 mod batch;
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench;
 mod builder;
 mod connection;
 mod filter;
+#[cfg(feature = "gateway")]
+mod gateway;
+#[cfg(not(feature = "gateway"))]
+#[path = "gateway_disabled.rs"]
+mod gateway;
 mod handler;
+mod identity;
+mod inbox;
+mod lifecycle;
 mod metrics;
 mod middleware;
 mod nats_pattern;
+mod openrpc;
 mod persistent_storage;
 mod persistent_subscription;
+mod presence;
+mod queue_group;
+mod retained;
+mod snapshot;
 mod retention;
 mod retention_task;
 mod router;
+mod session;
+mod subject_index;
 mod subscription;
+mod throttle;
+mod typescript;
+mod validation;
 
+pub use acl::{PublishAttempt, PublishKind};
 pub use batch::{BatchMode, BatchProcessor};
 pub use builder::ServerBuilder;
+pub use connection::DeliveryReport;
 pub use filter::{FilteredSubscriptionManager, TopicFilter};
-pub use handler::{from_fn, from_typed_fn, Handler, HandlerResult};
+#[cfg(feature = "gateway")]
+pub use gateway::Upstream;
+pub use handler::{
+    from_args_fn, from_fn, from_method_fn, from_typed_fn, from_typed_fn_with_schema,
+    positional_args, with_schema, Handler, HandlerResult, MethodHandler,
+};
+pub use identity::IdentityRegistry;
+pub use lifecycle::{ConnectionInfo, DisconnectReason, Handshake};
 pub use metrics::ServerMetrics;
 pub use middleware::{
     LoggingMiddleware, MetricsMiddleware, Middleware, MiddlewareAction, MiddlewareChain,
-    MiddlewareContext, SyncMiddleware, TracingMiddleware,
+    MiddlewareContext, MiddlewareInfo, SyncMiddleware, TracingMiddleware,
 };
 pub use nats_pattern::{NatsPattern, PatternError, Token};
+pub use openrpc::{openrpc_document, MethodSchema, OpenRpcInfo, DISCOVER_METHOD, OPENRPC_VERSION};
 pub use persistent_storage::{PersistentMessage, PersistentStorage, SubscriptionState, TopicMetadata};
 pub use persistent_subscription::PersistentSubscriptionManager;
+pub use queue_group::QueueStrategy;
 pub use retention::RetentionPolicy;
-pub use router::{Router, RouterBuilder};
+pub use router::{Router, RouterBuilder, RouterHandle};
+pub use session::Session;
 pub use subscription::SubscriptionManager;
+pub use typescript::TypeScriptGenerator;
+pub use validation::{validated, with_validation, ParamsValidator};
+
+/// Re-export of `schemars` so handler types can derive `JsonSchema`
+pub use schemars;
+
+/// Items used by code generated with `jrow-macros`; not part of the public API
+#[doc(hidden)]
+pub mod __private {
+    pub use jrow_core;
+    pub use serde_json;
+
+    /// Check that `topic` is a concrete topic matching `pattern`
+    pub fn check_topic(topic: &str, pattern: &str) -> jrow_core::Result<()> {
+        crate::check_published_topic(topic, pattern)
+    }
+}
 
 use connection::Connection;
 use jrow_core::{Error, Result};
-use std::collections::HashMap;
+use dashmap::DashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::net::TcpListener;
-use tokio::sync::Mutex;
 
 /// Registry of active connections
 ///
@@ -130,7 +199,14 @@ use tokio::sync::Mutex;
 /// to send notifications to specific connections or broadcast to all connections.
 ///
 /// It's wrapped in Arc<Mutex> for thread-safe shared access across connection tasks.
-pub type ConnectionRegistry = Arc<Mutex<HashMap<u64, Connection>>>;
+#[deprecated(note = "the server now tracks connections in a sharded map of its own")]
+pub type ConnectionRegistry = Arc<tokio::sync::Mutex<std::collections::HashMap<u64, Connection>>>;
+
+/// Active connections by ID, as the server tracks them
+///
+/// A sharded concurrent map, so publishing and connection setup and
+/// teardown don't contend on a single lock.
+pub(crate) type Connections = Arc<DashMap<u64, Connection>>;
 
 /// JSON-RPC 2.0 server over WebSocket
 ///
@@ -156,14 +232,12 @@ pub type ConnectionRegistry = Arc<Mutex<HashMap<u64, Connection>>>;
 pub struct JrowServer {
     /// TCP listener for accepting incoming connections
     listener: TcpListener,
-    /// Router that dispatches requests to handler functions
-    router: Router,
+    /// Live router that dispatches requests to handler functions
+    router: RouterHandle,
     /// Manages exact-match topic subscriptions
     subscription_manager: SubscriptionManager,
-    /// Manages pattern-based (wildcard) subscriptions
-    filtered_subscription_manager: Arc<Mutex<FilteredSubscriptionManager>>,
     /// Registry of all active connections for broadcasting
-    connection_registry: ConnectionRegistry,
+    connection_registry: Connections,
     /// Processor for batch JSON-RPC requests
     batch_processor: BatchProcessor,
     /// Optional OpenTelemetry metrics collector
@@ -174,6 +248,19 @@ pub struct JrowServer {
     persistent_sub_manager: Option<Arc<PersistentSubscriptionManager>>,
     /// Channel to signal shutdown to the retention task
     retention_shutdown_tx: Option<tokio::sync::watch::Sender<bool>>,
+    /// Discovery settings (info, persistent methods) if `rpc.discover` is enabled
+    discovery: Option<(OpenRpcInfo, bool)>,
+    /// Upstreams that method prefixes are forwarded to, if any
+    gateway: Option<Arc<gateway::Gateway>>,
+    /// Identities of authenticated connections
+    identities: IdentityRegistry,
+    /// Callbacks run when connections open and close
+    hooks: lifecycle::LifecycleHooks,
+    /// Topic membership listing and join/leave events
+    presence: Arc<presence::Presence>,
+    /// Fans published messages out, with queue groups, retained messages
+    /// and snapshot versions
+    publisher: connection::Publisher,
 }
 
 impl JrowServer {
@@ -252,13 +339,15 @@ impl JrowServer {
                 .map_err(|e| jrow_core::Error::Io(e.to_string()))?;
             let conn_id = conn_counter.fetch_add(1, Ordering::SeqCst);
             let router = self.router.clone();
-            let sub_manager = self.subscription_manager.clone();
-            let filtered_sub_manager = Arc::clone(&self.filtered_subscription_manager);
-            let conn_registry = Arc::clone(&self.connection_registry);
+            let publisher = self.publisher.clone();
             let batch_processor = self.batch_processor.clone();
             let metrics = self.metrics.clone();
             let persistent_storage = self.persistent_storage.clone();
             let persistent_sub_manager = self.persistent_sub_manager.clone();
+            let gateway = self.gateway.clone();
+            let identities = self.identities.clone();
+            let hooks = self.hooks.clone();
+            let presence = Arc::clone(&self.presence);
 
             tracing::info!(conn_id = conn_id, addr = %addr, "New connection accepted");
 
@@ -273,14 +362,17 @@ impl JrowServer {
                 if let Err(e) = connection::handle_connection(
                     stream,
                     conn_id,
+                    addr,
                     router,
-                    sub_manager,
-                    filtered_sub_manager,
-                    conn_registry,
+                    publisher,
                     batch_processor,
                     metrics,
                     persistent_storage,
                     persistent_sub_manager,
+                    gateway,
+                    identities,
+                    hooks,
+                    presence,
                 )
                 .await
                 {
@@ -342,53 +434,142 @@ impl JrowServer {
         data: serde_json::Value,
     ) -> Result<usize> {
         let topic = topic.into();
-        
-        // Get exact subscribers
-        let exact_subscribers = self.subscription_manager.get_subscribers(&topic).await;
-        
-        // Get pattern-based subscribers with their patterns
-        let filtered_subs = self.filtered_subscription_manager.lock().await;
-        let pattern_subscribers = filtered_subs.get_subscribers_with_patterns(&topic);
-        drop(filtered_subs);
-        
-        let conn_registry = self.connection_registry.lock().await;
+        let sent_count = self.publisher.publish(&topic, &data).await?;
 
-        let mut sent_count = 0;
-        
-        // Send to exact subscribers (original behavior)
-        for conn_id in exact_subscribers {
-            if let Some(conn) = conn_registry.get(&conn_id) {
-                if conn.notify(&topic, Some(data.clone())).is_ok() {
-                    sent_count += 1;
-                }
-            }
+        // Record metrics
+        if let Some(ref m) = self.metrics {
+            m.record_publish(&topic);
         }
 
-        // Send to pattern subscribers (send to pattern, include actual topic in data)
-        for (conn_id, pattern) in pattern_subscribers {
-            if let Some(conn) = conn_registry.get(&conn_id) {
-                // Wrap data to include the actual topic for pattern subscriptions
-                let notification_data = serde_json::json!({
-                    "topic": topic,
-                    "data": data.clone(),
-                });
-                
-                // Send notification to the pattern, not the actual topic
-                if conn.notify(&pattern, Some(notification_data)).is_ok() {
-                    sent_count += 1;
-                }
-            }
-        }
+        tracing::debug!(topic = %topic, sent_count = sent_count, "Message published");
+        Ok(sent_count)
+    }
+
+    /// Publish the message computed by `update` to a topic
+    ///
+    /// For topics with a snapshot provider, `update` runs while no snapshot
+    /// of the topic is being taken, so use it to change the state the
+    /// provider reads: each change then appears either in a subscriber's
+    /// snapshot or in a later delta, never in both or neither. For other
+    /// topics this is the same as `publish(topic, update())`. `update`
+    /// must not publish to the same topic.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use jrow_server::JrowServer;
+    /// # use std::sync::{Arc, Mutex};
+    /// # async fn example(server: &JrowServer, open_orders: Arc<Mutex<u64>>) -> jrow_core::Result<()> {
+    /// use serde_json::json;
+    ///
+    /// server
+    ///     .publish_update("orders.stats", || {
+    ///         *open_orders.lock().unwrap() += 1;
+    ///         json!({"opened": 1})
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, update), fields(topic = %topic))]
+    pub async fn publish_update(
+        &self,
+        topic: &str,
+        update: impl FnOnce() -> serde_json::Value,
+    ) -> Result<usize> {
+        let sent_count = self.publisher.publish_update(topic, update).await?;
 
-        // Record metrics
         if let Some(ref m) = self.metrics {
-            m.record_publish(&topic);
+            m.record_publish(topic);
         }
 
         tracing::debug!(topic = %topic, sent_count = sent_count, "Message published");
         Ok(sent_count)
     }
 
+    /// Publish a request to a topic and wait for the first reply
+    ///
+    /// Subscribers of `topic` receive `{"reply_to": "_INBOX...", "data": data}`
+    /// and answer by publishing to the inbox with `rpc.publish` (see
+    /// `jrow_core::request_reply`), which lets services connected as clients
+    /// act as responders behind topic names. Use a queue group for the
+    /// responders to have each request handled once.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no subscriber received the request, or with
+    /// `Error::Timeout` if no reply arrives within `timeout`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use jrow_server::JrowServer;
+    /// # async fn example(server: &JrowServer) -> jrow_core::Result<()> {
+    /// use serde_json::json;
+    /// use std::time::Duration;
+    ///
+    /// let status = server
+    ///     .request_topic("orders.status", json!({"order_id": 42}), Duration::from_secs(5))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, data), fields(topic = %topic))]
+    pub async fn request_topic(
+        &self,
+        topic: &str,
+        data: serde_json::Value,
+        timeout: std::time::Duration,
+    ) -> Result<serde_json::Value> {
+        let reply = self.publisher.request(topic, data, timeout).await;
+
+        if let Some(ref m) = self.metrics {
+            m.record_publish(topic);
+        }
+        reply
+    }
+
+    /// Publish a typed message to the topic bound to its type
+    ///
+    /// The topic comes from the message's [`Topic`](jrow_core::Topic)
+    /// implementation (usually `#[derive(Topic)]`), so subscribers using
+    /// `JrowClient::subscribe_typed::<T>()` are guaranteed to expect the same
+    /// payload type.
+    ///
+    /// Returns `Error::InvalidParams` without publishing if the message's
+    /// topic isn't a concrete topic matching `T::TOPIC`, e.g. when a derived
+    /// topic field holds `.` and would fill several tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use jrow_server::JrowServer;
+    /// use jrow_core::Topic;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct OrderCreated { order_id: u64 }
+    ///
+    /// impl Topic for OrderCreated {
+    ///     const TOPIC: &'static str = "orders.created";
+    /// }
+    ///
+    /// # async fn example(server: &JrowServer) -> jrow_core::Result<()> {
+    /// server.publish_typed(&OrderCreated { order_id: 42 }).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn publish_typed<T>(&self, message: &T) -> Result<usize>
+    where
+        T: jrow_core::Topic + serde::Serialize,
+    {
+        let topic = message.topic();
+        check_published_topic(&topic, T::TOPIC)?;
+        let data =
+            serde_json::to_value(message).map_err(|e| Error::Serialization(e.to_string()))?;
+        self.publish(topic, data).await
+    }
+
     /// Publish messages to multiple topics at once
     ///
     /// Returns a vector of (topic, subscriber_count) pairs in the same order as input.
@@ -417,56 +598,105 @@ impl JrowServer {
     ) -> Result<Vec<(String, usize)>> {
         let mut results = Vec::with_capacity(messages.len());
 
-        // Lock the connection registry once for all publishes
-        let conn_registry = self.connection_registry.lock().await;
-        let filtered_subs = self.filtered_subscription_manager.lock().await;
-
         for (topic, data) in messages {
-            // Get exact subscribers
-            let exact_subscribers = self.subscription_manager.get_subscribers(&topic).await;
-            
-            // Get pattern-based subscribers with their patterns
-            let pattern_subscribers = filtered_subs.get_subscribers_with_patterns(&topic);
-
-            let mut sent_count = 0;
-            
-            // Send to exact subscribers (original behavior)
-            for conn_id in exact_subscribers {
-                if let Some(conn) = conn_registry.get(&conn_id) {
-                    if conn.notify(&topic, Some(data.clone())).is_ok() {
-                        sent_count += 1;
-                    }
-                }
-            }
-            
-            // Send to pattern subscribers (send to pattern, include actual topic in data)
-            for (conn_id, pattern) in pattern_subscribers {
-                if let Some(conn) = conn_registry.get(&conn_id) {
-                    // Wrap data to include the actual topic for pattern subscriptions
-                    let notification_data = serde_json::json!({
-                        "topic": topic,
-                        "data": data.clone(),
-                    });
-                    
-                    // Send notification to the pattern, not the actual topic
-                    if conn.notify(&pattern, Some(notification_data)).is_ok() {
-                        sent_count += 1;
-                    }
-                }
-            }
+            let sent_count = self.publisher.publish(&topic, &data).await?;
 
             // Record metrics for each topic
             if let Some(ref m) = self.metrics {
                 m.record_publish(&topic);
             }
 
-            results.push((topic.clone(), sent_count));
+            results.push((topic, sent_count));
         }
 
         tracing::debug!(batch_size = results.len(), "Batch publish completed");
         Ok(results)
     }
 
+    /// Send a notification to a single connection
+    ///
+    /// The report lists `conn_id` as failed if the connection is closed or
+    /// was never opened.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use jrow_server::JrowServer;
+    /// # fn example(server: &JrowServer, conn_id: u64) -> jrow_core::Result<()> {
+    /// let report = server.notify_connection(
+    ///     conn_id,
+    ///     "session.expiring",
+    ///     Some(serde_json::json!({"in_secs": 60})),
+    /// )?;
+    /// if !report.is_complete() {
+    ///     println!("Connection {} is gone", conn_id);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn notify_connection(
+        &self,
+        conn_id: u64,
+        method: impl Into<String>,
+        params: Option<serde_json::Value>,
+    ) -> Result<DeliveryReport> {
+        connection::deliver(&self.connection_registry, [conn_id], &method.into(), params.as_ref())
+    }
+
+    /// Send a notification to every open connection
+    ///
+    /// Unlike `publish`, this ignores subscriptions.
+    pub fn broadcast(
+        &self,
+        method: impl Into<String>,
+        params: Option<serde_json::Value>,
+    ) -> Result<DeliveryReport> {
+        let conn_ids: Vec<u64> = self.connection_registry.iter().map(|entry| *entry.key()).collect();
+        connection::deliver(&self.connection_registry, conn_ids, &method.into(), params.as_ref())
+    }
+
+    /// Send a notification to every connection bound to an identity
+    ///
+    /// Connections are bound with the server's [`IdentityRegistry`],
+    /// typically by authentication middleware. The report is empty if no
+    /// connection is bound to `identity`.
+    pub fn notify_identity(
+        &self,
+        identity: &str,
+        method: impl Into<String>,
+        params: Option<serde_json::Value>,
+    ) -> Result<DeliveryReport> {
+        let conn_ids = self.identities.connections_of(identity);
+        connection::deliver(&self.connection_registry, conn_ids, &method.into(), params.as_ref())
+    }
+
+    /// Get the registry of authenticated connection identities
+    pub fn identities(&self) -> IdentityRegistry {
+        self.identities.clone()
+    }
+
+    /// List the connections subscribed to `topic` or to a pattern matching it
+    ///
+    /// Members are ordered by connection ID and carry the identity they are
+    /// bound to, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use jrow_server::JrowServer;
+    /// # async fn example(server: &JrowServer) {
+    /// let online: Vec<String> = server
+    ///     .presence("rooms.lobby")
+    ///     .await
+    ///     .into_iter()
+    ///     .filter_map(|member| member.identity)
+    ///     .collect();
+    /// # }
+    /// ```
+    pub async fn presence(&self, topic: &str) -> Vec<jrow_core::presence::Member> {
+        self.presence.members(topic).await
+    }
+
     /// Get the subscription manager
     ///
     /// Returns a reference to the subscription manager for advanced use cases
@@ -542,53 +772,20 @@ impl JrowServer {
             )),
         };
         
-        // Store message and get sequence ID
-        let sequence_id = storage.store_message(&topic, data.clone()).await?;
-        
-        tracing::debug!(
-            topic = %topic,
-            sequence_id = sequence_id,
-            "Message stored persistently"
-        );
-        
-        // Find active persistent subscribers whose patterns match this topic
-        let matching_subs = sub_manager.get_matching_subscriptions(&topic).await;
-        let conn_registry = self.connection_registry.lock().await;
-        
-        let mut delivered_count = 0;
-        for (subscription_id, conn_id) in matching_subs {
-            if let Some(conn) = conn_registry.get(&conn_id) {
-                // Create persistent message notification
-                let notification_data = serde_json::json!({
-                    "sequence_id": sequence_id,
-                    "data": data.clone(),
-                });
-                
-                if conn.notify(&topic, Some(notification_data)).is_ok() {
-                    delivered_count += 1;
-                    tracing::trace!(
-                        subscription_id = %subscription_id,
-                        conn_id = conn_id,
-                        sequence_id = sequence_id,
-                        topic = %topic,
-                        "Delivered persistent message"
-                    );
-                }
-            }
-        }
-        
+        let sequence_id = connection::publish_persistent(
+            storage,
+            sub_manager,
+            &self.connection_registry,
+            &topic,
+            data,
+        )
+        .await?;
+
         // Record metrics
         if let Some(ref m) = self.metrics {
             m.record_publish(&topic);
         }
-        
-        tracing::debug!(
-            topic = %topic,
-            sequence_id = sequence_id,
-            delivered_count = delivered_count,
-            "Persistent message published"
-        );
-        
+
         Ok(sequence_id)
     }
 
@@ -611,6 +808,27 @@ impl JrowServer {
         self.persistent_sub_manager.as_ref()
     }
 
+    /// Get the OpenRPC discovery document (if enabled)
+    ///
+    /// Returns the document served by `rpc.discover` when discovery was
+    /// enabled via `ServerBuilder::with_discovery()`, otherwise `None`. The
+    /// document describes the methods registered right now, including those
+    /// added through `router_handle()` after the server was built.
+    pub fn openrpc_document(&self) -> Option<serde_json::Value> {
+        self.discovery.as_ref().map(|(info, persistent)| {
+            openrpc::openrpc_document(info, &self.router.snapshot(), *persistent)
+        })
+    }
+
+    /// Get a handle for changing the server's methods while it runs
+    ///
+    /// Methods registered, replaced or unregistered through the handle take
+    /// effect for every connection, including already connected clients,
+    /// from their next request on.
+    pub fn router_handle(&self) -> RouterHandle {
+        self.router.clone()
+    }
+
     /// Get the local address the server is listening on
     ///
     /// This is useful to discover the actual bound port when using port 0
@@ -637,6 +855,24 @@ impl JrowServer {
     }
 }
 
+/// Check that `topic` is a concrete topic delivered to subscribers of `pattern`
+///
+/// Typed publishers fill a pattern's wildcards from the message; a value
+/// spanning several tokens, or holding wildcards itself, would otherwise
+/// publish where the pattern's subscribers never see it.
+pub(crate) fn check_published_topic(topic: &str, pattern: &str) -> Result<()> {
+    let matches = topic.split('.').all(jrow_core::topic::is_literal_token)
+        && NatsPattern::new(pattern).is_ok_and(|pattern| pattern.matches(topic));
+    if matches {
+        Ok(())
+    } else {
+        Err(Error::InvalidParams(format!(
+            "Topic '{}' does not match '{}'",
+            topic, pattern
+        )))
+    }
+}
+
 impl Drop for JrowServer {
     /// Clean up server resources on drop
     ///
@@ -0,0 +1,127 @@
+//! Connection lifecycle hooks
+//!
+//! Application code can react to clients connecting and disconnecting by
+//! registering hooks on the `ServerBuilder`:
+//!
+//! - `on_connect` runs after the WebSocket handshake, before the connection
+//!   handles any message. Returning an error rejects the connection: it is
+//!   closed with a policy violation close frame carrying the error message.
+//! - `on_disconnect` runs after the connection has been cleaned up, with the
+//!   reason it ended. It doesn't run for rejected connections.
+//!
+//! Several hooks of each kind may be registered; they run in registration
+//! order, and the first rejecting `on_connect` hook stops the rest.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use jrow_server::JrowServer;
+//! use jrow_core::Error;
+//!
+//! # async fn example() -> jrow_core::Result<()> {
+//! let server = JrowServer::builder()
+//!     .bind_str("127.0.0.1:8080")?
+//!     .on_connect(|info| async move {
+//!         match info.handshake.header("authorization") {
+//!             Some(_) => Ok(()),
+//!             None => Err(Error::InvalidRequest("missing credentials".to_string())),
+//!         }
+//!     })
+//!     .on_disconnect(|info, reason| async move {
+//!         println!("connection {} from {} ended: {:?}", info.id, info.peer_addr, reason);
+//!     })
+//!     .build()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::session::Session;
+use jrow_core::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Details of the HTTP upgrade request that opened a connection
+#[derive(Debug, Clone, Default)]
+pub struct Handshake {
+    /// Request path including the query string (e.g. `/ws?room=1`)
+    pub path: String,
+    /// Request headers, with lowercase names
+    ///
+    /// Repeated headers are joined with `", "`.
+    pub headers: HashMap<String, String>,
+}
+
+impl Handshake {
+    /// Get a header by name, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+}
+
+/// What the lifecycle hooks know about a connection
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    /// Connection ID, as seen by middleware and `notify_connection`
+    pub id: u64,
+    /// Address of the remote peer
+    pub peer_addr: SocketAddr,
+    /// Identity the connection was bound to in the `IdentityRegistry`
+    ///
+    /// Always `None` in `on_connect`, since nothing can be bound yet.
+    pub identity: Option<String>,
+    /// The upgrade request that opened the connection
+    pub handshake: Handshake,
+    /// Storage of the connection
+    ///
+    /// Values stored by `on_connect` hooks are visible to middleware and
+    /// handlers; `on_disconnect` hooks see what they left behind.
+    pub session: Session,
+}
+
+/// Why a connection ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The client sent a close frame
+    ClientClosed,
+    /// The stream ended without a close frame
+    ConnectionLost,
+    /// A transport or protocol error ended the connection
+    Error(String),
+}
+
+type HookFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// Hook run when a connection opens
+pub(crate) type ConnectHook = Arc<dyn Fn(ConnectionInfo) -> HookFuture<Result<()>> + Send + Sync>;
+
+/// Hook run when a connection closes
+pub(crate) type DisconnectHook =
+    Arc<dyn Fn(ConnectionInfo, DisconnectReason) -> HookFuture<()> + Send + Sync>;
+
+/// Lifecycle hooks registered on the server
+#[derive(Clone, Default)]
+pub(crate) struct LifecycleHooks {
+    pub(crate) on_connect: Vec<ConnectHook>,
+    pub(crate) on_disconnect: Vec<DisconnectHook>,
+}
+
+impl LifecycleHooks {
+    /// Run the connect hooks, stopping at the first rejection
+    pub(crate) async fn connected(&self, info: &ConnectionInfo) -> Result<()> {
+        for hook in &self.on_connect {
+            hook(info.clone()).await?;
+        }
+        Ok(())
+    }
+
+    /// Run the disconnect hooks
+    pub(crate) async fn disconnected(&self, info: &ConnectionInfo, reason: &DisconnectReason) {
+        for hook in &self.on_disconnect {
+            hook(info.clone(), reason.clone()).await;
+        }
+    }
+}
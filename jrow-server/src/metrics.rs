@@ -13,6 +13,7 @@
 //! - **batch_size**: Batch request size distribution (histogram)
 //! - **subscribers_total**: Current number of active subscriptions (gauge)
 //! - **publish_total**: Total messages published (counter)
+//! - **conflated_total**: Messages replaced by a newer one on a throttled subscription (counter)
 //! - **errors_total**: Total errors encountered (counter)
 //!
 //! # Usage
@@ -60,6 +61,8 @@ pub struct ServerMetrics {
     pub subscribers_total: Gauge<i64>,
     /// Total number of messages published
     pub publish_total: Counter<u64>,
+    /// Total number of messages conflated by throttled subscriptions
+    pub conflated_total: Counter<u64>,
     /// Total number of errors
     pub errors_total: Counter<u64>,
 }
@@ -103,6 +106,10 @@ impl ServerMetrics {
                 .u64_counter("jrow.server.publish.total")
                 .with_description("Total number of messages published")
                 .build(),
+            conflated_total: meter
+                .u64_counter("jrow.server.conflated.total")
+                .with_description("Total number of messages replaced by a newer one before delivery")
+                .build(),
             errors_total: meter
                 .u64_counter("jrow.server.errors.total")
                 .with_description("Total number of errors encountered")
@@ -149,6 +156,12 @@ impl ServerMetrics {
         self.publish_total.add(1, attributes);
     }
 
+    /// Record messages on `topic` dropped for a newer one with the same key
+    pub fn record_conflated(&self, topic: &str, count: u64) {
+        let attributes = &[KeyValue::new("topic", topic.to_string())];
+        self.conflated_total.add(count, attributes);
+    }
+
     /// Record an error
     pub fn record_error(&self, error_type: &str) {
         let attributes = &[KeyValue::new("error_type", error_type.to_string())];
@@ -170,6 +183,7 @@ mod tests {
         metrics.record_batch(10, "parallel");
         metrics.update_subscribers("test_topic", 5);
         metrics.record_publish("test_topic");
+        metrics.record_conflated("test_topic", 2);
         metrics.record_error("test_error");
         metrics.record_disconnection(0);
     }
@@ -16,6 +16,17 @@
 //! - Inspect and modify the response after the handler
 //! - Pass metadata to subsequent middleware
 //!
+//! # Method-Scoped Middleware
+//!
+//! Middleware can be restricted to specific methods with a `NatsPattern`
+//! (`add_scoped`, `ServerBuilder::use_middleware_for`): `admin.*` matches
+//! `admin.ban` but not `admin.users.list`, `admin.>` matches both, and a
+//! pattern without wildcards matches exactly one method.
+//!
+//! Scoped and unscoped middleware share one chain, so for any method the
+//! middleware that applies runs in the order it was added. The rules can be
+//! inspected with `MiddlewareChain::describe` and `Router::middleware_for`.
+//!
 //! # Built-in Middleware
 //!
 //! - **LoggingMiddleware**: Logs all requests/responses
@@ -32,18 +43,19 @@
 //!
 //! // Use chain with ServerBuilder
 //! // builder.use_middleware(Arc::new(LoggingMiddleware))
+//!
+//! // Only log admin methods
+//! chain.add_sync_scoped("admin.>", LoggingMiddleware).unwrap();
 //! ```
 
+use crate::nats_pattern::NatsPattern;
+use crate::session::Session;
 use async_trait::async_trait;
-use jrow_core::Result;
+use jrow_core::{Error, Result};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
 
-// Import for tracing
-#[allow(unused_imports)]
-use tracing;
-
 /// Action to take after middleware pre-processing
 #[derive(Debug, Clone)]
 pub enum MiddlewareAction {
@@ -66,6 +78,11 @@ pub struct MiddlewareContext {
     pub request_id: Option<jrow_core::Id>,
     /// Metadata for passing data between middleware
     pub metadata: HashMap<String, Value>,
+    /// Storage of the connection, kept across requests
+    ///
+    /// Empty and detached from any connection when the context is created
+    /// outside of request handling.
+    pub session: Session,
 }
 
 impl MiddlewareContext {
@@ -77,6 +94,7 @@ impl MiddlewareContext {
             conn_id,
             request_id: None,
             metadata: HashMap::new(),
+            session: Session::current().unwrap_or_default(),
         }
     }
     
@@ -88,6 +106,7 @@ impl MiddlewareContext {
             conn_id,
             request_id: Some(request_id),
             metadata: HashMap::new(),
+            session: Session::current().unwrap_or_default(),
         }
     }
 
@@ -110,6 +129,11 @@ pub trait Middleware: Send + Sync {
 
     /// Called after handler execution
     async fn post_handle(&self, ctx: &mut MiddlewareContext, result: &Result<Value>) -> Result<()>;
+
+    /// Name reported by introspection (defaults to the type name)
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
 /// Trait for synchronous middleware (simpler, no async operations)
@@ -119,6 +143,11 @@ pub trait SyncMiddleware: Send + Sync {
 
     /// Called after handler execution
     fn post_handle(&self, ctx: &mut MiddlewareContext, result: &Result<Value>) -> Result<()>;
+
+    /// Name reported by introspection (defaults to the type name)
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
 /// Adapter to convert SyncMiddleware to Middleware
@@ -135,12 +164,49 @@ impl<T: SyncMiddleware + 'static> Middleware for SyncMiddlewareAdapter<T> {
     async fn post_handle(&self, ctx: &mut MiddlewareContext, result: &Result<Value>) -> Result<()> {
         self.inner.post_handle(ctx, result)
     }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+/// A middleware in a chain, optionally restricted to matching methods
+#[derive(Clone)]
+struct ChainEntry {
+    middleware: Arc<dyn Middleware>,
+    /// Methods this middleware applies to (`None` = every method)
+    scope: Option<NatsPattern>,
+}
+
+impl ChainEntry {
+    /// Whether this middleware runs for `method`
+    fn applies_to(&self, method: &str) -> bool {
+        self.scope
+            .as_ref()
+            .is_none_or(|pattern| pattern.matches(method))
+    }
+
+    fn info(&self) -> MiddlewareInfo {
+        MiddlewareInfo {
+            name: self.middleware.name(),
+            scope: self.scope.as_ref().map(|pattern| pattern.as_str().to_string()),
+        }
+    }
+}
+
+/// Description of a middleware in a chain, for introspection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MiddlewareInfo {
+    /// Middleware name (see `Middleware::name`)
+    pub name: &'static str,
+    /// Method pattern the middleware is restricted to, if any
+    pub scope: Option<String>,
 }
 
 /// Chain of middleware to execute in order
 #[derive(Clone)]
 pub struct MiddlewareChain {
-    middlewares: Vec<Arc<dyn Middleware>>,
+    middlewares: Vec<ChainEntry>,
 }
 
 impl MiddlewareChain {
@@ -153,16 +219,99 @@ impl MiddlewareChain {
 
     /// Add a middleware to the chain
     pub fn add(&mut self, middleware: Arc<dyn Middleware>) {
-        self.middlewares.push(middleware);
+        self.middlewares.push(ChainEntry {
+            middleware,
+            scope: None,
+        });
     }
 
     /// Add a sync middleware to the chain
     pub fn add_sync<T: SyncMiddleware + 'static>(&mut self, middleware: T) {
-        self.middlewares.push(Arc::new(SyncMiddlewareAdapter {
+        self.add(Arc::new(SyncMiddlewareAdapter {
             inner: middleware,
         }));
     }
 
+    /// Add a middleware that only runs for methods matching `pattern`
+    ///
+    /// `pattern` uses `NatsPattern` syntax (`admin.*`, `admin.>`, `user.get`).
+    /// Returns `Error::InvalidRequest` if the pattern is invalid.
+    pub fn add_scoped(&mut self, pattern: &str, middleware: Arc<dyn Middleware>) -> Result<()> {
+        let scope = NatsPattern::new(pattern)
+            .map_err(|e| Error::InvalidRequest(format!("Invalid method pattern: {}", e)))?;
+        self.middlewares.push(ChainEntry {
+            middleware,
+            scope: Some(scope),
+        });
+        Ok(())
+    }
+
+    /// Add a sync middleware that only runs for methods matching `pattern`
+    pub fn add_sync_scoped<T: SyncMiddleware + 'static>(
+        &mut self,
+        pattern: &str,
+        middleware: T,
+    ) -> Result<()> {
+        self.add_scoped(pattern, Arc::new(SyncMiddlewareAdapter { inner: middleware }))
+    }
+
+    /// Append every middleware of another chain, keeping its order
+    pub fn extend(&mut self, other: &MiddlewareChain) {
+        self.middlewares.extend(other.middlewares.iter().cloned());
+    }
+
+    /// Copy of this chain with every scope placed under a method prefix
+    ///
+    /// Used when a router is nested under `prefix`: a middleware scoped to
+    /// `get` in the nested router must match `prefix.get` in the parent.
+    /// Fails with `Error::InvalidRequest` if a prefixed scope is not a valid
+    /// pattern, e.g. when the prefix itself contains wildcards.
+    pub(crate) fn prefixed(&self, prefix: &str) -> Result<MiddlewareChain> {
+        if prefix.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let middlewares = self
+            .middlewares
+            .iter()
+            .map(|entry| {
+                let scope = match &entry.scope {
+                    Some(pattern) => {
+                        let pattern = format!("{}.{}", prefix, pattern.as_str());
+                        Some(NatsPattern::new(&pattern).map_err(|e| {
+                            Error::InvalidRequest(format!("Invalid method pattern '{}': {}", pattern, e))
+                        })?)
+                    }
+                    None => None,
+                };
+                Ok(ChainEntry {
+                    middleware: Arc::clone(&entry.middleware),
+                    scope,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(MiddlewareChain { middlewares })
+    }
+
+    /// Describe every middleware in the chain, in execution order
+    pub fn describe(&self) -> Vec<MiddlewareInfo> {
+        self.middlewares.iter().map(ChainEntry::info).collect()
+    }
+
+    /// Describe the middleware that runs for `method`, in execution order
+    pub fn describe_for(&self, method: &str) -> Vec<MiddlewareInfo> {
+        self.middlewares
+            .iter()
+            .filter(|entry| entry.applies_to(method))
+            .map(ChainEntry::info)
+            .collect()
+    }
+
+    /// Whether any middleware in the chain runs for `method`
+    pub fn applies_to(&self, method: &str) -> bool {
+        self.middlewares.iter().any(|entry| entry.applies_to(method))
+    }
+
     /// Execute the middleware chain with the given handler
     pub async fn execute<F, Fut>(
         &self,
@@ -173,8 +322,17 @@ impl MiddlewareChain {
         F: FnOnce(MiddlewareContext) -> Fut + Send,
         Fut: std::future::Future<Output = Result<Value>> + Send,
     {
+        // Only middleware whose scope matches the method takes part
+        let method = ctx.method.clone();
+        let active: Vec<&Arc<dyn Middleware>> = self
+            .middlewares
+            .iter()
+            .filter(|entry| entry.applies_to(&method))
+            .map(|entry| &entry.middleware)
+            .collect();
+
         // Execute pre_handle for each middleware
-        for middleware in &self.middlewares {
+        for middleware in &active {
             match middleware.pre_handle(&mut ctx).await? {
                 MiddlewareAction::Continue => continue,
                 MiddlewareAction::ShortCircuit(value) => {
@@ -189,7 +347,7 @@ impl MiddlewareChain {
         let result = handler(ctx.clone()).await;
 
         // Execute post_handle for each middleware in reverse order
-        for middleware in self.middlewares.iter().rev() {
+        for middleware in active.iter().rev() {
             // Ignore errors in post_handle to ensure all middleware run
             let _ = middleware.post_handle(&mut ctx, &result).await;
         }
@@ -412,6 +570,59 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_scoped_middleware() {
+        let mut chain = MiddlewareChain::new();
+        chain.add_sync(TestMiddleware::new("all"));
+        chain.add_sync_scoped("admin.*", TestMiddleware::new("admin")).unwrap();
+        chain.add_sync_scoped("user.get", TestMiddleware::new("cache")).unwrap();
+        assert!(chain.add_sync_scoped("admin.>.x", LoggingMiddleware).is_err());
+
+        let run = |method: &str| {
+            let chain = chain.clone();
+            let ctx = MiddlewareContext::new(method.to_string(), None, 1);
+            async move {
+                chain
+                    .execute(ctx, |ctx| async move {
+                        let mut ran: Vec<String> = ctx
+                            .metadata
+                            .keys()
+                            .filter_map(|k| k.strip_suffix("_pre").map(str::to_string))
+                            .collect();
+                        ran.sort();
+                        Ok(serde_json::json!(ran))
+                    })
+                    .await
+                    .unwrap()
+            }
+        };
+
+        assert_eq!(run("admin.ban").await, serde_json::json!(["admin", "all"]));
+        assert_eq!(run("admin.users.list").await, serde_json::json!(["all"]));
+        assert_eq!(run("user.get").await, serde_json::json!(["all", "cache"]));
+        assert_eq!(run("user.list").await, serde_json::json!(["all"]));
+    }
+
+    #[test]
+    fn test_describe_scoped_middleware() {
+        let mut chain = MiddlewareChain::new();
+        chain.add_sync_scoped("admin.>", LoggingMiddleware).unwrap();
+        chain.add(Arc::new(MetricsMiddleware::new()));
+
+        let all = chain.describe();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].name, std::any::type_name::<LoggingMiddleware>());
+        assert_eq!(all[0].scope.as_deref(), Some("admin.>"));
+        assert_eq!(all[1].name, std::any::type_name::<MetricsMiddleware>());
+        assert_eq!(all[1].scope, None);
+
+        assert_eq!(chain.describe_for("admin.users.ban").len(), 2);
+        assert_eq!(chain.describe_for("ping").len(), 1);
+
+        let nested = chain.prefixed("tenant").unwrap();
+        assert_eq!(nested.describe()[0].scope.as_deref(), Some("tenant.admin.>"));
+    }
+
     #[tokio::test]
     async fn test_logging_middleware() {
         let mut chain = MiddlewareChain::new();
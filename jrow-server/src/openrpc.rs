@@ -0,0 +1,771 @@
+//! OpenRPC discovery document generation
+//!
+//! This module builds an [OpenRPC](https://spec.open-rpc.org) document describing
+//! the methods a server exposes. The document can be published as API
+//! documentation or used by clients to validate their requests.
+//!
+//! # Where Schemas Come From
+//!
+//! Handlers created with `from_typed_fn_with_schema` (or the `#[handler]` macro)
+//! carry JSON Schemas for their params and result types, generated by
+//! [`schemars`]. Handlers without schema information (`from_fn`,
+//! `from_typed_fn`) are still listed, but with permissive schemas.
+//!
+//! Built-in pub/sub methods (`rpc.subscribe`, `rpc.unsubscribe`, `rpc.presence` and, when
+//! persistence is enabled, the `rpc.*_persistent` family) are described with
+//! hand-written schemas that mirror the server implementation.
+//!
+//! # Serving the Document
+//!
+//! Enable discovery on the builder and the document is served through the
+//! standard `rpc.discover` method:
+//!
+//! ```rust,no_run
+//! use jrow_server::{JrowServer, OpenRpcInfo};
+//!
+//! # async fn example() -> jrow_core::Result<()> {
+//! let addr: std::net::SocketAddr = "127.0.0.1:8080".parse().unwrap();
+//! let server = JrowServer::builder()
+//!     .bind(addr)
+//!     .with_discovery(OpenRpcInfo::new("Calculator API", "1.0.0"))
+//!     .build()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::Router;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+/// OpenRPC specification version emitted in generated documents
+pub const OPENRPC_VERSION: &str = "1.2.6";
+
+/// Name of the built-in discovery method
+pub const DISCOVER_METHOD: &str = "rpc.discover";
+
+/// JSON Schemas describing a method's params and result
+///
+/// Schemas reference shared types through `#/components/schemas/<Name>`;
+/// the referenced definitions are carried alongside so the document
+/// generator can collect them into the `components` section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodSchema {
+    /// Schema of the `params` value accepted by the method
+    pub params: Value,
+    /// Schema of the `result` value returned by the method
+    pub result: Value,
+    /// Definitions referenced from `params` or `result`, keyed by type name
+    pub definitions: Map<String, Value>,
+    /// Argument names in positional order, for methods that also accept
+    /// their named params as an array (see `positional_args`)
+    ///
+    /// When set, the params are advertised with `paramStructure: "either"`
+    /// and listed in this order.
+    pub positional: Option<Vec<String>>,
+}
+
+impl MethodSchema {
+    /// Generate schemas for a params type `P` and a result type `R`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use jrow_server::MethodSchema;
+    /// use schemars::JsonSchema;
+    ///
+    /// #[derive(JsonSchema)]
+    /// struct AddParams { a: i32, b: i32 }
+    ///
+    /// let schema = MethodSchema::of::<AddParams, i32>();
+    /// assert_eq!(schema.params["type"], "object");
+    /// assert_eq!(schema.result["type"], "integer");
+    /// ```
+    pub fn of<P: JsonSchema, R: JsonSchema>() -> Self {
+        let mut generator = schema_generator();
+        let params = generator.root_schema_for::<P>().schema;
+        let result = generator.root_schema_for::<R>().schema;
+
+        let mut definitions: Map<String, Value> = generator
+            .take_definitions()
+            .into_iter()
+            .map(|(name, schema)| (name, to_value(&schema)))
+            .collect();
+
+        // Named result structs become components so clients can refer to them
+        let mut result = to_value(&result);
+        let title = result.get("title").and_then(Value::as_str).map(str::to_string);
+        if let (Some(title), Some(_)) = (title, result.get("properties")) {
+            if let Some(object) = result.as_object_mut() {
+                object.remove("title");
+            }
+            definitions.insert(title.clone(), result);
+            result = json!({ "$ref": format!("#/components/schemas/{}", title) });
+        }
+
+        Self {
+            params: to_value(&params),
+            result,
+            definitions,
+            positional: None,
+        }
+    }
+}
+
+/// Descriptive metadata placed in the `info` section of the document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenRpcInfo {
+    /// Title of the API
+    pub title: String,
+    /// Version of the API (not of jrow)
+    pub version: String,
+    /// Optional longer description
+    pub description: Option<String>,
+}
+
+impl OpenRpcInfo {
+    /// Create info with a title and API version
+    pub fn new(title: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            version: version.into(),
+            description: None,
+        }
+    }
+
+    /// Set the API description
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// Build an OpenRPC document for a router
+///
+/// Methods are emitted in name order so that the output is stable. The
+/// built-in pub/sub methods are always included; the persistent variants are
+/// included only when `persistent` is true. `rpc.discover` itself is never
+/// listed, as required by the OpenRPC specification.
+pub fn openrpc_document(info: &OpenRpcInfo, router: &Router, persistent: bool) -> Value {
+    let mut methods = Vec::new();
+    let mut schemas = Map::new();
+
+    let mut names = router.methods();
+    names.sort();
+
+    for name in names {
+        if name == DISCOVER_METHOD {
+            continue;
+        }
+
+        let schema = router.get(&name).and_then(|handler| handler.schema());
+        match schema {
+            Some(schema) => {
+                schemas.extend(schema.definitions.clone());
+                methods.push(method_object(&name, &schema));
+            }
+            None => {
+                // No type information: accept any params, return any result
+                methods.push(json!({
+                    "name": name,
+                    "params": [{ "name": "params", "required": false, "schema": {} }],
+                    "paramStructure": "either",
+                    "result": { "name": "result", "schema": {} },
+                }));
+            }
+        }
+    }
+
+    methods.extend(builtin_methods(persistent));
+
+    let mut info_object = json!({
+        "title": info.title,
+        "version": info.version,
+    });
+    if let Some(ref description) = info.description {
+        info_object["description"] = json!(description);
+    }
+
+    json!({
+        "openrpc": OPENRPC_VERSION,
+        "info": info_object,
+        "methods": methods,
+        "components": { "schemas": schemas },
+    })
+}
+
+/// Create a schema generator that emits OpenRPC-style component references
+pub(crate) fn schema_generator() -> SchemaGenerator {
+    SchemaSettings::draft07()
+        .with(|settings| {
+            settings.definitions_path = "#/components/schemas/".to_string();
+            settings.meta_schema = None;
+        })
+        .into_generator()
+}
+
+/// Serialize a schemars schema to a JSON value
+pub(crate) fn to_value<T: serde::Serialize>(schema: &T) -> Value {
+    serde_json::to_value(schema).unwrap_or(Value::Bool(true))
+}
+
+/// Build an OpenRPC method object from params and result schemas
+///
+/// Object params become one content descriptor per property (by-name, or
+/// either when they are also accepted by position), tuple params become one
+/// descriptor per element (by-position), and unit params produce an empty
+/// parameter list.
+fn method_object(name: &str, schema: &MethodSchema) -> Value {
+    let (descriptors, structure) = param_descriptors(&schema.params, schema.positional.as_deref());
+
+    json!({
+        "name": name,
+        "params": descriptors,
+        "paramStructure": structure,
+        "result": { "name": "result", "schema": strip_title(&schema.result) },
+    })
+}
+
+/// Split a params schema into OpenRPC content descriptors
+///
+/// With `positional` names, object properties are listed in that order so
+/// their positions match the accepted arrays.
+fn param_descriptors(params: &Value, positional: Option<&[String]>) -> (Vec<Value>, &'static str) {
+    // Unit type `()` deserializes from null, i.e. no params
+    if params.get("type") == Some(&json!("null")) {
+        return (Vec::new(), "either");
+    }
+
+    if let Some(properties) = params.get("properties").and_then(Value::as_object) {
+        let required: Vec<&str> = params
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|r| r.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        let mut descriptors: Vec<Value> = properties
+            .iter()
+            .map(|(name, schema)| {
+                json!({
+                    "name": name,
+                    "required": required.contains(&name.as_str()),
+                    "schema": schema,
+                })
+            })
+            .collect();
+        let Some(positional) = positional else {
+            return (descriptors, "by-name");
+        };
+        // Properties missing from `positional` can only be passed by name
+        descriptors.sort_by_key(|descriptor| {
+            positional
+                .iter()
+                .position(|name| descriptor["name"] == name.as_str())
+                .unwrap_or(positional.len())
+        });
+        return (descriptors, "either");
+    }
+
+    if let Some(items) = params.get("items").and_then(Value::as_array) {
+        let descriptors = items
+            .iter()
+            .enumerate()
+            .map(|(i, schema)| {
+                json!({
+                    "name": format!("arg{}", i),
+                    "required": true,
+                    "schema": schema,
+                })
+            })
+            .collect();
+        return (descriptors, "by-position");
+    }
+
+    // Anything else is described as a single opaque params value
+    (
+        vec![json!({ "name": "params", "required": true, "schema": strip_title(params) })],
+        "either",
+    )
+}
+
+/// Remove the root `title` that schemars adds to subject schemas
+fn strip_title(schema: &Value) -> Value {
+    let mut schema = schema.clone();
+    if let Some(object) = schema.as_object_mut() {
+        object.remove("title");
+    }
+    schema
+}
+
+/// Positional param descriptor for the items of a batch built-in
+///
+/// Batch built-ins take the array of items itself as params, so each array
+/// element is one positional param. OpenRPC has no way to mark a param as
+/// repeated, so the description says so.
+fn batch_item(name: &str, description: &str, schema: Value) -> Value {
+    json!({
+        "name": name,
+        "required": true,
+        "description": description,
+        "schema": schema,
+    })
+}
+
+/// Descriptions of the built-in methods handled by each connection
+fn builtin_methods(persistent: bool) -> Vec<Value> {
+    let topic = json!({
+        "name": "topic",
+        "required": true,
+        "description": "Topic name or NATS-style pattern (`*`, `>`)",
+        "schema": { "type": "string" },
+    });
+    let filter = json!({
+        "name": "filter",
+        "required": false,
+        "description": "Content filter the published data must match (see `jrow_core::filter`)",
+        "schema": { "type": "object" },
+    });
+    let queue_group = json!({
+        "name": "queue_group",
+        "required": false,
+        "description": "Queue group to join; each message goes to one member of the group",
+        "schema": { "type": "string" },
+    });
+    let retained = json!({
+        "name": "retained",
+        "required": false,
+        "description": "Whether to receive the topic's retained messages right away (default true)",
+        "schema": { "type": "boolean" },
+    });
+    let max_rate = json!({
+        "name": "max_rate",
+        "required": false,
+        "description": "Maximum deliveries per second; messages in between are conflated",
+        "schema": { "type": "number", "exclusiveMinimum": 0 },
+    });
+    let conflate = json!({
+        "name": "conflate",
+        "required": false,
+        "description": "JSON pointer to the key of each message; only the latest per key is kept while the client is behind",
+        "schema": { "type": "string" },
+    });
+    let data = json!({
+        "name": "data",
+        "required": false,
+        "description": "Message data (default null)",
+    });
+    let publish_item = json!({
+        "type": "object",
+        "properties": {
+            "topic": { "type": "string" },
+            "data": {},
+        },
+        "required": ["topic"],
+    });
+    let subscription_id = json!({
+        "name": "subscription_id",
+        "required": true,
+        "schema": { "type": "string" },
+    });
+
+    let mut methods = vec![
+        json!({
+            "name": "rpc.subscribe",
+            "summary": "Subscribe to a topic or pattern",
+            "params": [topic, filter, queue_group, retained, max_rate, conflate],
+            "paramStructure": "by-name",
+            "result": {
+                "name": "result",
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "subscribed": { "type": "boolean" },
+                        "topic": { "type": "string" },
+                        "pattern": { "type": "boolean" },
+                        "queue_group": { "type": "string" },
+                    },
+                    "required": ["subscribed", "topic", "pattern"],
+                },
+            },
+        }),
+        json!({
+            "name": "rpc.unsubscribe",
+            "summary": "Unsubscribe from a topic or pattern",
+            "params": [topic],
+            "paramStructure": "by-name",
+            "result": {
+                "name": "result",
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "unsubscribed": { "type": "boolean" },
+                        "topic": { "type": "string" },
+                    },
+                    "required": ["unsubscribed", "topic"],
+                },
+            },
+        }),
+        json!({
+            "name": jrow_core::presence::PRESENCE_METHOD,
+            "summary": "List the connections subscribed to a topic",
+            "params": [{
+                "name": "topic",
+                "required": true,
+                "schema": { "type": "string" },
+            }],
+            "paramStructure": "by-name",
+            "result": {
+                "name": "result",
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "topic": { "type": "string" },
+                        "members": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "conn_id": { "type": "integer", "minimum": 0 },
+                                    "identity": { "type": ["string", "null"] },
+                                },
+                                "required": ["conn_id"],
+                            },
+                        },
+                    },
+                    "required": ["topic", "members"],
+                },
+            },
+        }),
+        json!({
+            "name": jrow_core::request_reply::PUBLISH_METHOD,
+            "summary": "Publish a message to a topic, if the publish ACL allows it",
+            "params": [{
+                "name": "topic",
+                "required": true,
+                "description": "Topic name, or a reply inbox from a request's `reply_to`",
+                "schema": { "type": "string" },
+            }, data.clone()],
+            "paramStructure": "by-name",
+            "result": {
+                "name": "result",
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "topic": { "type": "string" },
+                        "delivered": { "type": "integer", "minimum": 0 },
+                    },
+                    "required": ["topic", "delivered"],
+                },
+            },
+        }),
+        json!({
+            "name": "rpc.publish_batch",
+            "summary": "Publish several messages at once",
+            "params": [batch_item("message", "A message to publish; pass one per message", publish_item.clone())],
+            "paramStructure": "by-position",
+            "result": { "name": "result", "schema": { "type": "array", "items": { "type": "object" } } },
+        }),
+        json!({
+            "name": jrow_core::request_reply::REQUEST_TOPIC_METHOD,
+            "summary": "Publish a request to a topic and return the first reply",
+            "params": [{
+                "name": "topic",
+                "required": true,
+                "schema": { "type": "string" },
+            }, data.clone(), {
+                "name": "timeout_ms",
+                "required": true,
+                "description": "How long to wait for a reply, in milliseconds, up to the server's limit",
+                "schema": { "type": "integer", "minimum": 0 },
+            }],
+            "paramStructure": "by-name",
+            "result": {
+                "name": "result",
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "topic": { "type": "string" },
+                        "reply": {},
+                    },
+                    "required": ["topic", "reply"],
+                },
+            },
+        }),
+    ];
+
+    if !persistent {
+        return methods;
+    }
+
+    let persistent_filter = json!({
+        "name": "filter",
+        "required": false,
+        "description": "Content filter stored with the subscription; omit it to keep the stored filter or pass null to clear it",
+        "schema": { "type": ["object", "null"] },
+    });
+    let subscribe_item = json!({
+        "type": "object",
+        "properties": {
+            "subscription_id": { "type": "string" },
+            "topic": { "type": "string" },
+            "filter": { "type": ["object", "null"] },
+        },
+        "required": ["subscription_id", "topic"],
+    });
+    let ack_item = json!({
+        "type": "object",
+        "properties": {
+            "subscription_id": { "type": "string" },
+            "sequence_id": { "type": "integer", "minimum": 0 },
+        },
+        "required": ["subscription_id", "sequence_id"],
+    });
+
+    methods.extend([
+        json!({
+            "name": "rpc.subscribe_persistent",
+            "summary": "Subscribe durably and replay messages since the last acknowledgment",
+            "params": [subscription_id, topic, persistent_filter],
+            "paramStructure": "by-name",
+            "result": {
+                "name": "result",
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "subscribed": { "type": "boolean" },
+                        "subscription_id": { "type": "string" },
+                        "topic": { "type": "string" },
+                        "resumed_from_seq": { "type": "integer", "minimum": 0 },
+                        "undelivered_count": { "type": "integer", "minimum": 0 },
+                    },
+                },
+            },
+        }),
+        json!({
+            "name": "rpc.ack_persistent",
+            "summary": "Acknowledge a persistent message",
+            "params": [
+                subscription_id,
+                { "name": "sequence_id", "required": true, "schema": { "type": "integer", "minimum": 0 } },
+            ],
+            "paramStructure": "by-name",
+            "result": {
+                "name": "result",
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "acknowledged": { "type": "boolean" },
+                        "subscription_id": { "type": "string" },
+                        "sequence_id": { "type": "integer", "minimum": 0 },
+                    },
+                },
+            },
+        }),
+        json!({
+            "name": "rpc.unsubscribe_persistent",
+            "summary": "Deactivate a persistent subscription, keeping its stored position",
+            "params": [subscription_id],
+            "paramStructure": "by-name",
+            "result": {
+                "name": "result",
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "unsubscribed": { "type": "boolean" },
+                        "subscription_id": { "type": "string" },
+                    },
+                },
+            },
+        }),
+        json!({
+            "name": "rpc.publish_persistent",
+            "summary": "Store a message and send it to persistent subscribers, if the publish ACL allows it",
+            "params": [{
+                "name": "topic",
+                "required": true,
+                "schema": { "type": "string" },
+            }, data],
+            "paramStructure": "by-name",
+            "result": {
+                "name": "result",
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "topic": { "type": "string" },
+                        "sequence_id": { "type": "integer", "minimum": 0 },
+                    },
+                    "required": ["topic", "sequence_id"],
+                },
+            },
+        }),
+        json!({
+            "name": "rpc.publish_persistent_batch",
+            "summary": "Store and send several persistent messages at once",
+            "params": [batch_item("message", "A message to publish; pass one per message", publish_item.clone())],
+            "paramStructure": "by-position",
+            "result": { "name": "result", "schema": { "type": "array", "items": { "type": "object" } } },
+        }),
+        json!({
+            "name": "rpc.subscribe_persistent_batch",
+            "summary": "Subscribe to several persistent subscriptions at once",
+            "params": [batch_item("subscription", "A subscription to open; pass one per subscription", subscribe_item)],
+            "paramStructure": "by-position",
+            "result": { "name": "result", "schema": { "type": "array", "items": { "type": "object" } } },
+        }),
+        json!({
+            "name": "rpc.ack_persistent_batch",
+            "summary": "Acknowledge several persistent messages at once",
+            "params": [batch_item("acknowledgment", "A message to acknowledge; pass one per acknowledgment", ack_item)],
+            "paramStructure": "by-position",
+            "result": { "name": "result", "schema": { "type": "array", "items": { "type": "object" } } },
+        }),
+        json!({
+            "name": "rpc.unsubscribe_persistent_batch",
+            "summary": "Deactivate several persistent subscriptions at once",
+            "params": [batch_item("subscription_id", "A subscription to deactivate; pass one per subscription", json!({ "type": "string" }))],
+            "paramStructure": "by-position",
+            "result": { "name": "result", "schema": { "type": "array", "items": { "type": "object" } } },
+        }),
+    ]);
+
+    methods
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::{from_fn, from_typed_fn_with_schema};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, JsonSchema)]
+    struct AddParams {
+        a: i32,
+        #[serde(default)]
+        b: Option<i32>,
+    }
+
+    #[derive(Serialize, JsonSchema)]
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    #[derive(Serialize, JsonSchema)]
+    struct Shape {
+        points: Vec<Point>,
+    }
+
+    fn method<'a>(doc: &'a Value, name: &str) -> &'a Value {
+        doc["methods"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|m| m["name"] == name)
+            .unwrap_or_else(|| panic!("method {} not in document", name))
+    }
+
+    #[test]
+    fn test_typed_method_params_by_name() {
+        let mut router = Router::new();
+        router.register(
+            "add",
+            from_typed_fn_with_schema(|p: AddParams| async move { Ok(p.a + p.b.unwrap_or(0)) }),
+        );
+
+        let doc = openrpc_document(&OpenRpcInfo::new("Test", "1.0.0"), &router, false);
+        let add = method(&doc, "add");
+
+        assert_eq!(add["paramStructure"], "by-name");
+        let params = add["params"].as_array().unwrap();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0]["name"], "a");
+        assert_eq!(params[0]["required"], true);
+        assert_eq!(params[1]["name"], "b");
+        assert_eq!(params[1]["required"], false);
+        assert_eq!(add["result"]["schema"]["type"], "integer");
+    }
+
+    #[test]
+    fn test_args_method_params_either() {
+        #[derive(Deserialize, JsonSchema)]
+        #[allow(dead_code)]
+        struct MoveArgs {
+            to: String,
+            from: String,
+        }
+
+        let mut router = Router::new();
+        router.register(
+            "move",
+            crate::from_args_fn(&["to", "from"], |_: MoveArgs| async move { Ok(()) }),
+        );
+
+        let doc = openrpc_document(&OpenRpcInfo::new("Test", "1.0.0"), &router, false);
+        let moved = method(&doc, "move");
+
+        assert_eq!(moved["paramStructure"], "either");
+        let names: Vec<&str> = moved["params"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|p| p["name"].as_str())
+            .collect();
+        assert_eq!(names, vec!["to", "from"]);
+    }
+
+    #[test]
+    fn test_nested_types_become_components() {
+        let mut router = Router::new();
+        router.register(
+            "shape",
+            from_typed_fn_with_schema(|_: ()| async move { Ok(Shape { points: vec![] }) }),
+        );
+
+        let doc = openrpc_document(&OpenRpcInfo::new("Test", "1.0.0"), &router, false);
+        let shape = method(&doc, "shape");
+
+        assert!(shape["params"].as_array().unwrap().is_empty());
+        assert_eq!(shape["result"]["schema"]["$ref"], "#/components/schemas/Shape");
+        assert_eq!(
+            doc["components"]["schemas"]["Shape"]["properties"]["points"]["items"]["$ref"],
+            "#/components/schemas/Point"
+        );
+        assert!(doc["components"]["schemas"]["Point"].is_object());
+    }
+
+    #[test]
+    fn test_untyped_method_and_builtins() {
+        let mut router = Router::new();
+        router.register("echo", from_fn(|p| async move { Ok(p.unwrap_or_default()) }));
+        router.register(DISCOVER_METHOD, from_fn(|_| async { Ok(Value::Null) }));
+
+        let info = OpenRpcInfo::new("Test", "2.0.0").with_description("desc");
+        let doc = openrpc_document(&info, &router, false);
+
+        assert_eq!(doc["openrpc"], OPENRPC_VERSION);
+        assert_eq!(doc["info"]["version"], "2.0.0");
+        assert_eq!(doc["info"]["description"], "desc");
+        assert_eq!(method(&doc, "echo")["paramStructure"], "either");
+        assert_eq!(method(&doc, "rpc.subscribe")["params"][1]["name"], "filter");
+        assert_eq!(method(&doc, "rpc.subscribe")["params"][2]["name"], "queue_group");
+        method(&doc, "rpc.unsubscribe");
+        method(&doc, "rpc.presence");
+        method(&doc, "rpc.publish_batch");
+        assert_eq!(method(&doc, "rpc.request_topic")["params"][2]["name"], "timeout_ms");
+
+        let names: Vec<&str> = doc["methods"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|m| m["name"].as_str())
+            .collect();
+        assert!(!names.contains(&DISCOVER_METHOD));
+        assert!(!names.contains(&"rpc.subscribe_persistent"));
+
+        let doc = openrpc_document(&info, &router, true);
+        method(&doc, "rpc.subscribe_persistent");
+        method(&doc, "rpc.ack_persistent_batch");
+        method(&doc, "rpc.publish_persistent_batch");
+    }
+}
@@ -54,7 +54,7 @@
 //! ```
 
 use crate::retention::RetentionPolicy;
-use jrow_core::{Error, Result};
+use jrow_core::{ContentFilter, Error, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
@@ -90,6 +90,51 @@ pub struct SubscriptionState {
     pub last_ack_topic: Option<String>,  // Track which topic was last acked (for patterns)
     pub created_at: u64,
     pub last_activity: u64,
+    pub filter: Option<String>,  // Content filter as a JSON string, for bincode compatibility
+}
+
+impl SubscriptionState {
+    /// Get the content filter messages are delivered through, if any
+    pub fn content_filter(&self) -> Result<Option<ContentFilter>> {
+        self.filter
+            .as_deref()
+            .map(|filter| {
+                serde_json::from_str(filter)
+                    .map_err(|e| Error::Internal(format!("Failed to parse subscription filter: {}", e)))
+            })
+            .transpose()
+    }
+
+    /// Decode a stored state, including states stored before filters existed
+    fn decode(value: &[u8]) -> Result<Self> {
+        /// Layout of states stored without a filter
+        #[derive(Deserialize)]
+        struct Unfiltered {
+            subscription_id: String,
+            topic: String,
+            topic_pattern: Option<String>,
+            last_ack_seq: u64,
+            last_ack_topic: Option<String>,
+            created_at: u64,
+            last_activity: u64,
+        }
+
+        if let Ok(state) = bincode::deserialize(value) {
+            return Ok(state);
+        }
+        let state: Unfiltered = bincode::deserialize(value)
+            .map_err(|e| Error::Internal(format!("Failed to deserialize subscription: {}", e)))?;
+        Ok(Self {
+            subscription_id: state.subscription_id,
+            topic: state.topic,
+            topic_pattern: state.topic_pattern,
+            last_ack_seq: state.last_ack_seq,
+            last_ack_topic: state.last_ack_topic,
+            created_at: state.created_at,
+            last_activity: state.last_activity,
+            filter: None,
+        })
+    }
 }
 
 /// Topic metadata including retention configuration
@@ -133,14 +178,12 @@ impl PersistentStorage {
         let metadata_tree_clone = metadata_tree.clone();
         let mut initial_cache = HashMap::new();
         
-        for item in metadata_tree_clone.iter() {
-            if let Ok((key, value)) = item {
-                if let (Ok(topic), Ok(metadata)) = (
-                    String::from_utf8(key.to_vec()),
-                    bincode::deserialize::<TopicMetadata>(&value)
-                ) {
-                    initial_cache.insert(topic, metadata);
-                }
+        for (key, value) in metadata_tree_clone.iter().flatten() {
+            if let (Ok(topic), Ok(metadata)) = (
+                String::from_utf8(key.to_vec()),
+                bincode::deserialize::<TopicMetadata>(&value)
+            ) {
+                initial_cache.insert(topic, metadata);
             }
         }
         
@@ -154,6 +197,7 @@ impl PersistentStorage {
     }
 
     /// Load all topic metadata into the cache (synchronous)
+    #[allow(dead_code)]
     fn load_metadata_cache_sync(&self) -> Result<HashMap<String, TopicMetadata>> {
         let mut cache = HashMap::new();
         
@@ -382,6 +426,7 @@ impl PersistentStorage {
                 last_ack_topic: None,
                 created_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
                 last_activity: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+                filter: None,
             });
         
         state.last_ack_seq = sequence_id;
@@ -406,6 +451,37 @@ impl PersistentStorage {
         Ok(())
     }
 
+    /// Set the content filter of a subscription, `None` removing it
+    pub async fn set_subscription_filter(
+        &self,
+        subscription_id: &str,
+        filter: Option<&ContentFilter>,
+    ) -> Result<SubscriptionState> {
+        let mut state = self.get_subscription_state(subscription_id).await?.ok_or_else(|| {
+            Error::Internal(format!("Subscription '{}' not found", subscription_id))
+        })?;
+
+        state.filter = filter
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+
+        let key = subscription_id.as_bytes();
+        let value = bincode::serialize(&state)
+            .map_err(|e| Error::Internal(format!("Failed to serialize subscription: {}", e)))?;
+
+        self.subscriptions_tree
+            .insert(key, value)
+            .map_err(|e| Error::Internal(format!("Failed to update subscription: {}", e)))?;
+
+        self.subscriptions_tree
+            .flush_async()
+            .await
+            .map_err(|e| Error::Internal(format!("Failed to flush subscription: {}", e)))?;
+
+        Ok(state)
+    }
+
     /// Get subscription state
     pub async fn get_subscription_state(&self, subscription_id: &str) -> Result<Option<SubscriptionState>> {
         let key = subscription_id.as_bytes();
@@ -413,9 +489,7 @@ impl PersistentStorage {
         match self.subscriptions_tree.get(key)
             .map_err(|e| Error::Internal(format!("Failed to get subscription: {}", e)))? {
             Some(value) => {
-                let state: SubscriptionState = bincode::deserialize(&value)
-                    .map_err(|e| Error::Internal(format!("Failed to deserialize subscription: {}", e)))?;
-                Ok(Some(state))
+                Ok(Some(SubscriptionState::decode(&value)?))
             }
             None => Ok(None),
         }
@@ -457,6 +531,7 @@ impl PersistentStorage {
             last_ack_topic: None,
             created_at: now,
             last_activity: now,
+            filter: None,
         };
         
         let key = subscription_id.as_bytes();
@@ -616,9 +691,7 @@ impl PersistentStorage {
         
         for item in self.subscriptions_tree.iter() {
             let (_, value) = item.map_err(|e| Error::Internal(format!("Failed to read subscription: {}", e)))?;
-            let state: SubscriptionState = bincode::deserialize(&value)
-                .map_err(|e| Error::Internal(format!("Failed to deserialize subscription: {}", e)))?;
-            subscriptions.push(state);
+            subscriptions.push(SubscriptionState::decode(&value)?);
         }
         
         Ok(subscriptions)
@@ -699,5 +772,44 @@ mod tests {
         let messages = storage.get_messages_since("test", 0).await.unwrap();
         assert_eq!(messages.len(), 3);
     }
-}
 
+    #[tokio::test]
+    async fn test_subscription_filter() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = PersistentStorage::new(temp_dir.path()).unwrap();
+        storage.create_subscription("sub1", "orders").await.unwrap();
+
+        let filter = ContentFilter::eq("/region", "eu");
+        storage.set_subscription_filter("sub1", Some(&filter)).await.unwrap();
+        let state = storage.get_subscription_state("sub1").await.unwrap().unwrap();
+        assert_eq!(state.content_filter().unwrap(), Some(filter));
+
+        // States stored before filters existed still load
+        #[derive(Serialize)]
+        struct Unfiltered<'a> {
+            subscription_id: &'a str,
+            topic: &'a str,
+            topic_pattern: Option<String>,
+            last_ack_seq: u64,
+            last_ack_topic: Option<String>,
+            created_at: u64,
+            last_activity: u64,
+        }
+        let old = Unfiltered {
+            subscription_id: "old",
+            topic: "orders",
+            topic_pattern: None,
+            last_ack_seq: 7,
+            last_ack_topic: None,
+            created_at: 1,
+            last_activity: 1,
+        };
+        storage
+            .subscriptions_tree
+            .insert("old", bincode::serialize(&old).unwrap())
+            .unwrap();
+        let state = storage.get_subscription_state("old").await.unwrap().unwrap();
+        assert_eq!(state.last_ack_seq, 7);
+        assert_eq!(state.filter, None);
+    }
+}
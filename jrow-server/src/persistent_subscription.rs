@@ -19,6 +19,12 @@
 //! Subscriptions are exclusive - only one connection can be active for
 //! a subscription ID at a time. This prevents duplicate delivery.
 //!
+//! # Content Filters
+//!
+//! A subscription can deliver only messages whose data matches a
+//! `ContentFilter`. The filter is stored with the subscription state, so
+//! resuming without passing a filter keeps the stored one.
+//!
 //! # Inactivity Timeout
 //!
 //! Optionally, subscriptions can expire after a period of inactivity.
@@ -48,8 +54,9 @@
 //! ```
 
 use crate::persistent_storage::{PersistentStorage, SubscriptionState};
+use crate::subject_index::SubjectIndex;
 use crate::NatsPattern;
-use jrow_core::{Error, Result};
+use jrow_core::{ContentFilter, Error, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -61,6 +68,7 @@ struct SubscriptionInfo {
     subscription_id: String,
     pattern: NatsPattern,
     connection_id: u64,
+    filter: Option<ContentFilter>,
 }
 
 /// Manages persistent subscriptions and their active connections
@@ -68,6 +76,8 @@ struct SubscriptionInfo {
 pub struct PersistentSubscriptionManager {
     /// Map of subscription_id -> SubscriptionInfo for active subscriptions
     active_subscriptions: Arc<RwLock<HashMap<String, SubscriptionInfo>>>,
+    /// Index of active subscription_ids by pattern
+    index: Arc<RwLock<SubjectIndex<String>>>,
     /// Map of connection_id -> set of subscription_ids
     connection_subscriptions: Arc<RwLock<HashMap<u64, Vec<String>>>>,
     /// Storage backend
@@ -81,6 +91,7 @@ impl PersistentSubscriptionManager {
     pub fn new(storage: Arc<PersistentStorage>, inactivity_timeout: Option<Duration>) -> Self {
         Self {
             active_subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            index: Arc::new(RwLock::new(SubjectIndex::new())),
             connection_subscriptions: Arc::new(RwLock::new(HashMap::new())),
             storage,
             inactivity_timeout,
@@ -94,6 +105,21 @@ impl PersistentSubscriptionManager {
         subscription_id: String,
         topic: String,
         connection_id: u64,
+    ) -> Result<SubscriptionState> {
+        self.register_filtered_subscription(subscription_id, topic, connection_id, None)
+            .await
+    }
+
+    /// Register a persistent subscription delivering only data matching `filter`
+    ///
+    /// `Some(Some(filter))` replaces the filter stored with the subscription,
+    /// `Some(None)` clears it and `None` keeps the stored filter, if any.
+    pub async fn register_filtered_subscription(
+        &self,
+        subscription_id: String,
+        topic: String,
+        connection_id: u64,
+        filter: Option<Option<ContentFilter>>,
     ) -> Result<SubscriptionState> {
         // Parse the pattern
         let pattern = NatsPattern::new(&topic)
@@ -113,15 +139,29 @@ impl PersistentSubscriptionManager {
         
         // Get or create subscription state in storage
         let mut state = self.storage.create_subscription(&subscription_id, &topic).await?;
+        if let Some(filter) = filter {
+            state = self
+                .storage
+                .set_subscription_filter(&subscription_id, filter.as_ref())
+                .await?;
+        }
+        let filter = state.content_filter()?;
         
         // Update state with pattern info
         state.topic_pattern = Some(topic.clone());
         
-        // Mark as active with pattern info
+        // Mark as active with pattern info, replacing a previous registration
+        // on this connection
+        let mut index = self.index.write().await;
+        if let Some(previous) = active.get(&subscription_id) {
+            Self::unindex(&mut index, previous);
+        }
+        index.insert(pattern.as_str(), subscription_id.clone());
         active.insert(subscription_id.clone(), SubscriptionInfo {
             subscription_id: subscription_id.clone(),
             pattern,
             connection_id,
+            filter,
         });
         
         // Track for this connection
@@ -168,7 +208,9 @@ impl PersistentSubscriptionManager {
         // Check if subscription exists and belongs to this connection
         match active.get(subscription_id) {
             Some(info) if info.connection_id == connection_id => {
-                active.remove(subscription_id);
+                if let Some(info) = active.remove(subscription_id) {
+                    Self::unindex(&mut *self.index.write().await, &info);
+                }
                 
                 // Remove from connection tracking
                 let mut conn_subs = self.connection_subscriptions.write().await;
@@ -195,8 +237,11 @@ impl PersistentSubscriptionManager {
         
         if let Some(subscription_ids) = conn_subs.remove(&connection_id) {
             let mut active = self.active_subscriptions.write().await;
+            let mut index = self.index.write().await;
             for sub_id in subscription_ids {
-                active.remove(&sub_id);
+                if let Some(info) = active.remove(&sub_id) {
+                    Self::unindex(&mut index, &info);
+                }
             }
         }
     }
@@ -217,9 +262,27 @@ impl PersistentSubscriptionManager {
     /// Returns a vector of (subscription_id, connection_id) pairs
     pub async fn get_matching_subscriptions(&self, topic: &str) -> Vec<(String, u64)> {
         let active = self.active_subscriptions.read().await;
-        active
-            .values()
-            .filter(|info| info.pattern.matches(topic))
+        let index = self.index.read().await;
+        index
+            .matches(topic)
+            .into_iter()
+            .filter_map(|sub_id| active.get(sub_id))
+            .map(|info| (info.subscription_id.clone(), info.connection_id))
+            .collect()
+    }
+
+    /// Get the active subscriptions that should receive `data` published to `topic`
+    ///
+    /// Like `get_matching_subscriptions`, but leaves out subscriptions whose
+    /// content filter doesn't match `data`.
+    pub async fn get_recipients(&self, topic: &str, data: &serde_json::Value) -> Vec<(String, u64)> {
+        let active = self.active_subscriptions.read().await;
+        let index = self.index.read().await;
+        index
+            .matches(topic)
+            .into_iter()
+            .filter_map(|sub_id| active.get(sub_id))
+            .filter(|info| info.filter.as_ref().is_none_or(|f| f.matches(data)))
             .map(|info| (info.subscription_id.clone(), info.connection_id))
             .collect()
     }
@@ -282,6 +345,11 @@ impl PersistentSubscriptionManager {
         active.len()
     }
 
+    /// Remove an active subscription from the pattern index
+    fn unindex(index: &mut SubjectIndex<String>, info: &SubscriptionInfo) {
+        index.remove(info.pattern.as_str(), |sub_id| *sub_id == info.subscription_id);
+    }
+
     /// Get storage reference
     pub fn storage(&self) -> &Arc<PersistentStorage> {
         &self.storage
@@ -630,4 +698,76 @@ mod tests {
         assert_eq!(messages[0].topic, "orders.new");
         assert_eq!(messages[0].sequence_id, 2);
     }
+
+    #[tokio::test]
+    async fn test_pattern_index_follows_lifecycle() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(PersistentStorage::new(temp_dir.path()).unwrap());
+        let manager = PersistentSubscriptionManager::new(storage, None);
+
+        manager
+            .register_subscription("sub1".to_string(), "orders.*".to_string(), 1)
+            .await
+            .unwrap();
+        // Re-registering on the same connection replaces the pattern
+        manager
+            .register_subscription("sub1".to_string(), "orders.>".to_string(), 1)
+            .await
+            .unwrap();
+        manager
+            .register_subscription("sub2".to_string(), "orders.new".to_string(), 2)
+            .await
+            .unwrap();
+
+        let mut matches = manager.get_matching_subscriptions("orders.new").await;
+        matches.sort();
+        assert_eq!(matches, vec![("sub1".to_string(), 1), ("sub2".to_string(), 2)]);
+        assert_eq!(manager.get_matching_subscriptions("orders.new.fast").await.len(), 1);
+
+        manager.unsubscribe("sub1", 1).await.unwrap();
+        manager.remove_connection(2).await;
+        assert!(manager.get_matching_subscriptions("orders.new").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_filtered_subscription() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(PersistentStorage::new(temp_dir.path()).unwrap());
+        let manager = PersistentSubscriptionManager::new(storage, None);
+
+        let eu = serde_json::json!({"region": "eu"});
+        let us = serde_json::json!({"region": "us"});
+
+        let state = manager
+            .register_filtered_subscription(
+                "sub1".to_string(),
+                "orders.*".to_string(),
+                1,
+                Some(Some(ContentFilter::eq("/region", "eu"))),
+            )
+            .await
+            .unwrap();
+        assert!(state.filter.is_some());
+        assert_eq!(manager.get_recipients("orders.new", &eu).await.len(), 1);
+        assert!(manager.get_recipients("orders.new", &us).await.is_empty());
+        // Unfiltered lookups still see the subscription
+        assert_eq!(manager.get_matching_subscriptions("orders.new").await.len(), 1);
+
+        // Resuming without a filter keeps the stored one
+        manager.remove_connection(1).await;
+        manager
+            .register_subscription("sub1".to_string(), "orders.*".to_string(), 2)
+            .await
+            .unwrap();
+        assert!(manager.get_recipients("orders.new", &us).await.is_empty());
+
+        // Clearing the filter delivers everything again
+        manager.remove_connection(2).await;
+        let state = manager
+            .register_filtered_subscription("sub1".to_string(), "orders.*".to_string(), 3, Some(None))
+            .await
+            .unwrap();
+        assert!(state.filter.is_none());
+        assert_eq!(manager.get_recipients("orders.new", &us).await.len(), 1);
+    }
 }
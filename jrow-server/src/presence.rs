@@ -0,0 +1,230 @@
+//! Topic presence tracking
+//!
+//! Presence answers "who is in this room": the members of a topic are the
+//! connections subscribed to that topic or to a pattern matching it, along
+//! with the identities they are bound to in the `IdentityRegistry`.
+//!
+//! Members are listed with `JrowServer::presence` on the server and the
+//! built-in `rpc.presence` method for clients.
+//!
+//! # Join and Leave Events
+//!
+//! For topics registered with `ServerBuilder::track_presence`, membership
+//! changes are published on the companion topic `presence.<topic>`:
+//!
+//! - `presence.join` when a connection subscribes to the topic
+//! - `presence.leave` when it unsubscribes or disconnects
+//!
+//! A pattern subscription joins the topics it matches that have exact
+//! subscribers at the time, and leaves those that still have them when it
+//! ends. Topics that gain or lose their exact subscribers later don't
+//! produce events for pattern subscribers, although `members` always lists
+//! them. A connection only joins a topic once, however many of its
+//! subscriptions match it.
+//!
+//! See `jrow_core::presence` for the event format. Companion topics are
+//! never tracked themselves.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use jrow_server::JrowServer;
+//!
+//! # async fn example() -> jrow_core::Result<()> {
+//! let server = JrowServer::builder()
+//!     .bind_str("127.0.0.1:8080")?
+//!     .track_presence("rooms.*")?
+//!     .build()
+//!     .await?;
+//!
+//! for member in server.presence("rooms.lobby").await {
+//!     println!("{} ({:?})", member.conn_id, member.identity);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::connection::Publisher;
+use crate::{IdentityRegistry, TopicFilter};
+use jrow_core::presence::{presence_topic, Member, PresenceChange, PresenceEvent, PRESENCE_PREFIX};
+use std::collections::BTreeSet;
+
+/// Lists topic members and announces membership changes
+pub(crate) struct Presence {
+    publisher: Publisher,
+    identities: IdentityRegistry,
+    /// Topics whose membership changes are published
+    tracked: Vec<TopicFilter>,
+}
+
+impl Presence {
+    pub(crate) fn new(publisher: Publisher, identities: IdentityRegistry, tracked: Vec<TopicFilter>) -> Self {
+        Self {
+            publisher,
+            identities,
+            tracked,
+        }
+    }
+
+    /// Members of `topic`, ordered by connection ID
+    pub(crate) async fn members(&self, topic: &str) -> Vec<Member> {
+        let mut conn_ids = self.publisher.sub_manager.get_subscribers(topic).await;
+        conn_ids.extend(self.publisher.filtered_sub_manager.lock().await.get_subscribers(topic));
+        conn_ids.sort_unstable();
+        conn_ids.dedup();
+        conn_ids
+            .into_iter()
+            .map(|conn_id| Member {
+                conn_id,
+                identity: self.identities.identity_of(conn_id),
+            })
+            .collect()
+    }
+
+    /// Tracked topics a connection is a member of
+    ///
+    /// Its patterns only count for topics that have exact subscribers.
+    /// Taken before and after a subscription changes, for `announce_changes`.
+    pub(crate) async fn rooms_of(&self, conn_id: u64) -> BTreeSet<String> {
+        if self.tracked.is_empty() {
+            return BTreeSet::new();
+        }
+
+        let mut rooms: BTreeSet<String> = self.publisher.sub_manager.get_topics(conn_id).await.into_iter().collect();
+        let topics = self.publisher.sub_manager.get_all_topics().await;
+        {
+            let filtered_sub_manager = self.publisher.filtered_sub_manager.lock().await;
+            let patterns = filtered_sub_manager.get_filters(conn_id);
+            if !patterns.is_empty() {
+                rooms.extend(
+                    topics
+                        .into_iter()
+                        .filter(|topic| patterns.iter().any(|pattern| pattern.matches(topic))),
+                );
+            }
+        }
+        rooms.retain(|topic| self.tracks(topic));
+        rooms
+    }
+
+    /// Announce the topics a connection joined and left between two
+    /// `rooms_of` calls
+    ///
+    /// The identity is passed in since a disconnected connection is already
+    /// unbound from the registry.
+    pub(crate) async fn announce_changes(
+        &self,
+        conn_id: u64,
+        before: &BTreeSet<String>,
+        after: &BTreeSet<String>,
+        identity: Option<String>,
+    ) {
+        for topic in after.difference(before) {
+            let member = Member { conn_id, identity: identity.clone() };
+            self.announce(PresenceChange::Join, topic, member).await;
+        }
+        for topic in before.difference(after) {
+            let member = Member { conn_id, identity: identity.clone() };
+            self.announce(PresenceChange::Leave, topic, member).await;
+        }
+    }
+
+    /// Identity a connection is bound to
+    pub(crate) fn identity_of(&self, conn_id: u64) -> Option<String> {
+        self.identities.identity_of(conn_id)
+    }
+
+    fn tracks(&self, topic: &str) -> bool {
+        let companion = topic
+            .strip_prefix(PRESENCE_PREFIX)
+            .is_some_and(|rest| rest.starts_with('.'));
+        !companion && self.tracked.iter().any(|filter| filter.matches(topic))
+    }
+
+    async fn announce(&self, event: PresenceChange, topic: &str, member: Member) {
+        if !self.tracks(topic) {
+            return;
+        }
+
+        let event = PresenceEvent {
+            event,
+            topic: topic.to_string(),
+            member,
+        };
+        let data = match serde_json::to_value(&event) {
+            Ok(data) => data,
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to encode presence event");
+                return;
+            }
+        };
+        let published = self.publisher.publish(&presence_topic(topic), &data).await;
+        if let Err(e) = published {
+            tracing::error!(error = %e, "Failed to publish presence event");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn presence(tracked: &[&str]) -> Presence {
+        Presence::new(
+            Publisher::default(),
+            IdentityRegistry::new(),
+            tracked.iter().map(|t| TopicFilter::new(*t).unwrap()).collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_members_include_pattern_subscribers() {
+        let presence = presence(&[]);
+        presence.publisher.sub_manager.subscribe(2, "rooms.1").await;
+        presence.publisher.sub_manager.subscribe(1, "rooms.1").await;
+        {
+            let mut filtered_sub_manager = presence.publisher.filtered_sub_manager.lock().await;
+            filtered_sub_manager.subscribe(3, TopicFilter::new("rooms.*").unwrap());
+            filtered_sub_manager.subscribe(2, TopicFilter::new("rooms.>").unwrap());
+            filtered_sub_manager.subscribe(4, TopicFilter::new("chat.*").unwrap());
+        }
+        presence.identities.bind(2, "alice");
+
+        let members = presence.members("rooms.1").await;
+        assert_eq!(
+            members,
+            vec![
+                Member { conn_id: 1, identity: None },
+                Member { conn_id: 2, identity: Some("alice".to_string()) },
+                Member { conn_id: 3, identity: None },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pattern_rooms_are_occupied_topics() {
+        let presence = presence(&["rooms.*"]);
+        presence.publisher.sub_manager.subscribe(1, "rooms.1").await;
+        presence.publisher.sub_manager.subscribe(1, "rooms.2").await;
+        presence.publisher.sub_manager.subscribe(1, "chat.1").await;
+        presence.publisher.sub_manager.subscribe(2, "rooms.3").await;
+        presence
+            .publisher
+            .filtered_sub_manager
+            .lock()
+            .await
+            .subscribe(2, TopicFilter::new("rooms.*").unwrap());
+
+        let rooms: Vec<String> = presence.rooms_of(2).await.into_iter().collect();
+        assert_eq!(rooms, vec!["rooms.1", "rooms.2", "rooms.3"]);
+        assert!(presence.rooms_of(3).await.is_empty());
+    }
+
+    #[test]
+    fn test_companion_topics_not_tracked() {
+        let presence = presence(&[">"]);
+        assert!(presence.tracks("rooms.1"));
+        assert!(presence.tracks("presences"));
+        assert!(!presence.tracks("presence.rooms.1"));
+    }
+}
@@ -0,0 +1,204 @@
+//! Queue groups for load-balanced delivery
+//!
+//! Subscriptions made with a `queue_group` compete for messages instead of
+//! each receiving a copy, like NATS queue subscriptions. When a message is
+//! published, the matching subscriptions that belong to a queue group are
+//! grouped by group name, whether they are exact or pattern subscriptions,
+//! and one member of each group receives the message. Subscriptions
+//! without a group still receive every message.
+//!
+//! This lets worker clients share a stream of jobs:
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "method": "rpc.subscribe", "params": {"topic": "jobs.>", "queue_group": "workers"}, "id": 1}
+//! ```
+//!
+//! The chosen member gets the message the same way as a plain subscriber,
+//! so a member subscribed with a pattern receives it under the pattern,
+//! with the actual topic in the payload. Content filters are applied
+//! before choosing, so only members whose filter matches compete.
+//!
+//! # Strategies
+//!
+//! `QueueStrategy` decides which member is chosen; set it with
+//! `ServerBuilder::queue_strategy`. Members are ordered by connection ID
+//! and each group keeps its own position, so round-robin takes members in
+//! turn. Least-loaded compares how many published messages are waiting to
+//! be written to each member's socket, which grows when a client reads
+//! slowly, and breaks ties in round-robin order. A group's position is
+//! forgotten once its last member unsubscribes or disconnects.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+/// How a queue group member is chosen for each message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueueStrategy {
+    /// Take members in turn
+    #[default]
+    RoundRobin,
+
+    /// Take the member with the fewest published messages waiting to be sent
+    ///
+    /// Ties go to members in round-robin order.
+    LeastLoaded,
+}
+
+/// Round-robin position and size of a queue group
+#[derive(Debug, Default)]
+struct Group {
+    cursor: usize,
+    members: usize,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    groups: HashMap<String, Group>,
+    /// Group of each connection's subscriptions, by topic or pattern
+    memberships: HashMap<u64, HashMap<String, String>>,
+}
+
+impl State {
+    fn leave(&mut self, group: &str) {
+        if let Some(entry) = self.groups.get_mut(group) {
+            entry.members -= 1;
+            if entry.members == 0 {
+                self.groups.remove(group);
+            }
+        }
+    }
+}
+
+/// Chooses the member of a queue group that receives a message
+#[derive(Debug, Default)]
+pub(crate) struct QueueGroups {
+    strategy: QueueStrategy,
+    state: Mutex<State>,
+}
+
+impl QueueGroups {
+    /// Create a chooser using `strategy`
+    pub(crate) fn new(strategy: QueueStrategy) -> Self {
+        Self {
+            strategy,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Set or clear the group of a connection's subscription
+    pub(crate) fn set(&self, conn_id: u64, subscription: &str, group: Option<String>) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = match group {
+            Some(group) => {
+                state.groups.entry(group.clone()).or_default().members += 1;
+                state.memberships.entry(conn_id).or_default().insert(subscription.to_string(), group)
+            }
+            None => state.memberships.get_mut(&conn_id).and_then(|groups| groups.remove(subscription)),
+        };
+        if let Some(previous) = previous {
+            state.leave(&previous);
+        }
+        if state.memberships.get(&conn_id).is_some_and(HashMap::is_empty) {
+            state.memberships.remove(&conn_id);
+        }
+    }
+
+    /// Leave the groups of a closed connection
+    pub(crate) fn remove_connection(&self, conn_id: u64) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(groups) = state.memberships.remove(&conn_id) {
+            groups.values().for_each(|group| state.leave(group));
+        }
+    }
+
+    /// Choose one of `members` of `group`
+    ///
+    /// `load` is only called for `QueueStrategy::LeastLoaded`. Returns
+    /// `None` if there are no members.
+    pub(crate) fn pick<'a, T>(&self, group: &str, members: &'a [T], load: impl Fn(&T) -> usize) -> Option<&'a T> {
+        if members.is_empty() {
+            return None;
+        }
+
+        let start = {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            state.groups.get_mut(group).map_or(0, |entry| {
+                let start = entry.cursor;
+                entry.cursor = entry.cursor.wrapping_add(1);
+                start
+            })
+        };
+
+        let in_turn = (0..members.len()).map(|offset| start.wrapping_add(offset) % members.len());
+        let index = match self.strategy {
+            QueueStrategy::RoundRobin => start % members.len(),
+            // `min_by_key` keeps the first minimum, so ties go in turn
+            QueueStrategy::LeastLoaded => in_turn.min_by_key(|&i| load(&members[i]))?,
+        };
+        members.get(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn join(groups: &QueueGroups, group: &str, members: &[u64]) {
+        for &conn_id in members {
+            groups.set(conn_id, "jobs.>", Some(group.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let groups = QueueGroups::new(QueueStrategy::RoundRobin);
+        let members = [1, 2, 3];
+        join(&groups, "workers", &members);
+        join(&groups, "other", &[4]);
+
+        let picked: Vec<u64> = (0..6)
+            .map(|_| *groups.pick("workers", &members, |_| 0).unwrap())
+            .collect();
+        assert_eq!(picked, vec![1, 2, 3, 1, 2, 3]);
+
+        // Each group keeps its own position
+        assert_eq!(groups.pick("other", &members, |_| 0), Some(&1));
+        assert_eq!(groups.pick("workers", &[] as &[u64], |_| 0), None);
+    }
+
+    #[test]
+    fn test_least_loaded() {
+        let groups = QueueGroups::new(QueueStrategy::LeastLoaded);
+        let members = [1, 2, 3];
+        join(&groups, "workers", &members);
+        let load = |id: &u64| if *id == 2 { 5 } else { 0 };
+
+        let picked: Vec<u64> = (0..4)
+            .map(|_| *groups.pick("workers", &members, load).unwrap())
+            .collect();
+        assert_eq!(picked, vec![1, 3, 3, 1]);
+    }
+
+    #[test]
+    fn test_group_forgotten_when_empty() {
+        let groups = QueueGroups::new(QueueStrategy::RoundRobin);
+        join(&groups, "workers", &[1, 2]);
+        groups.set(1, "jobs.created", Some("workers".to_string()));
+        groups.pick("workers", &[1, 2], |_| 0);
+
+        // Unsubscribing and disconnecting both leave the group
+        groups.set(2, "jobs.>", None);
+        groups.set(1, "jobs.created", None);
+        assert_eq!(groups.state.lock().unwrap().groups["workers"].members, 1);
+        groups.remove_connection(1);
+
+        let state = groups.state.lock().unwrap();
+        assert!(state.groups.is_empty());
+        assert!(state.memberships.is_empty());
+        drop(state);
+
+        // A group that starts over is taken from its first member
+        join(&groups, "workers", &[1, 2]);
+        assert_eq!(groups.pick("workers", &[1, 2], |_| 0), Some(&1));
+    }
+}
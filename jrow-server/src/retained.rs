@@ -0,0 +1,157 @@
+//! Retained messages for ephemeral topics
+//!
+//! A subscriber normally sees nothing until the next publish. For topics
+//! registered with `ServerBuilder::retain_messages`, the server keeps the
+//! last messages published to each matching topic in memory and sends them
+//! to new subscribers right after `rpc.subscribe`, so a dashboard can show
+//! the current value straight away.
+//!
+//! # Delivery
+//!
+//! Retained messages are delivered exactly like live ones: under the topic
+//! for exact subscriptions, and under the pattern with the topic in the
+//! payload for pattern subscriptions, which receive the retained messages
+//! of every matching topic in publish order. They are queued before the
+//! subscribe response and only those matching the subscription's content
+//! filter are sent. Queue group members don't receive them, since the
+//! group would otherwise see them once per member.
+//!
+//! A subscriber can opt out with `"retained": false`. A message published
+//! while a subscription is being set up may be delivered both as a
+//! retained and as a live message.
+//!
+//! # Retention Rules
+//!
+//! Each rule pairs a topic pattern with the number of messages to keep per
+//! topic. The first registered rule matching a topic applies; topics
+//! matching no rule aren't retained. Nothing is written to disk, so
+//! retained messages are lost on restart.
+
+use crate::TopicFilter;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, PoisonError};
+
+/// Last messages of topics matching the retention rules
+#[derive(Debug, Default)]
+pub(crate) struct RetainedMessages {
+    /// Topic patterns with the number of messages kept per topic
+    rules: Vec<(TopicFilter, usize)>,
+    store: Mutex<Store>,
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    /// Publish order across topics, for replaying to pattern subscriptions
+    next_seq: u64,
+    topics: HashMap<String, VecDeque<(u64, Value)>>,
+}
+
+impl RetainedMessages {
+    /// Create a store with the given retention rules
+    pub(crate) fn new(rules: Vec<(TopicFilter, usize)>) -> Self {
+        Self {
+            rules,
+            store: Mutex::default(),
+        }
+    }
+
+    /// Keep `data` if `topic` matches a retention rule
+    pub(crate) fn record(&self, topic: &str, data: &Value) {
+        let Some(&(_, limit)) = self.rules.iter().find(|(filter, _)| filter.matches(topic)) else {
+            return;
+        };
+
+        let mut store = self.store.lock().unwrap_or_else(PoisonError::into_inner);
+        let seq = store.next_seq;
+        store.next_seq += 1;
+        let messages = store.topics.entry(topic.to_string()).or_default();
+        if messages.len() == limit {
+            messages.pop_front();
+        }
+        messages.push_back((seq, data.clone()));
+    }
+
+    /// Retained messages for a subscription to `filter`, oldest first
+    ///
+    /// Returns `(topic, data)` pairs.
+    pub(crate) fn replay(&self, filter: &TopicFilter) -> Vec<(String, Value)> {
+        if self.rules.is_empty() {
+            return Vec::new();
+        }
+
+        let store = self.store.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut messages: Vec<(u64, &str, &Value)> = match filter {
+            TopicFilter::Exact(topic) => store
+                .topics
+                .get_key_value(topic)
+                .map(|(topic, messages)| {
+                    messages.iter().map(|(seq, data)| (*seq, topic.as_str(), data)).collect()
+                })
+                .unwrap_or_default(),
+            TopicFilter::Pattern { .. } => store
+                .topics
+                .iter()
+                .filter(|(topic, _)| filter.matches(topic))
+                .flat_map(|(topic, messages)| {
+                    messages.iter().map(|(seq, data)| (*seq, topic.as_str(), data))
+                })
+                .collect(),
+        };
+        messages.sort_unstable_by_key(|(seq, _, _)| *seq);
+        messages
+            .into_iter()
+            .map(|(_, topic, data)| (topic.to_string(), data.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn retained(rules: &[(&str, usize)]) -> RetainedMessages {
+        RetainedMessages::new(
+            rules
+                .iter()
+                .map(|(pattern, limit)| (TopicFilter::new(*pattern).unwrap(), *limit))
+                .collect(),
+        )
+    }
+
+    fn replay(retained: &RetainedMessages, topic: &str) -> Vec<(String, Value)> {
+        retained.replay(&TopicFilter::new(topic).unwrap())
+    }
+
+    #[test]
+    fn test_keeps_last_messages() {
+        let retained = retained(&[("prices.*", 1), ("logs.>", 2)]);
+        for i in 0..3 {
+            retained.record("prices.btc", &json!(i));
+            retained.record("logs.app", &json!(i));
+            retained.record("other", &json!(i));
+        }
+
+        assert_eq!(replay(&retained, "prices.btc"), vec![("prices.btc".to_string(), json!(2))]);
+        assert_eq!(
+            replay(&retained, "logs.app"),
+            vec![("logs.app".to_string(), json!(1)), ("logs.app".to_string(), json!(2))]
+        );
+        assert!(replay(&retained, "other").is_empty());
+    }
+
+    #[test]
+    fn test_pattern_replay_in_publish_order() {
+        let retained = retained(&[("prices.>", 1), (">", 5)]);
+        retained.record("prices.eth", &json!(1));
+        retained.record("prices.btc", &json!(2));
+        retained.record("prices.eth", &json!(3));
+
+        assert_eq!(
+            replay(&retained, "prices.*"),
+            vec![("prices.btc".to_string(), json!(2)), ("prices.eth".to_string(), json!(3))]
+        );
+        assert!(replay(&retained, "orders.*").is_empty());
+    }
+}
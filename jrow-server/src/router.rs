@@ -11,6 +11,29 @@
 //! - **Middleware execution**: Run middleware chain before/after handlers
 //! - **Error handling**: Convert handler errors to JSON-RPC error responses
 //!
+//! # Nested Routers
+//!
+//! Large services can split their methods across several routers, each owned
+//! by the module (or crate) that implements them. `RouterBuilder::nest` and
+//! `Router::nest` mount a router under a namespace, so a `create` method
+//! nested under `user` is served as `user.create`; `merge` mounts it without
+//! a prefix.
+//!
+//! A nested router keeps its own middleware: it runs, after the parent's
+//! middleware, only for the methods that router contributed. Method-scoped
+//! middleware patterns in a nested router are relative to it, so `get` in a
+//! router nested under `user` applies to `user.get`. Registering the
+//! same method name twice is reported as an error by `RouterBuilder::build`
+//! (or immediately by `Router::nest` / `Router::merge`).
+//!
+//! # Live Routing
+//!
+//! A running server dispatches through a `RouterHandle`
+//! (`JrowServer::router_handle()`). Methods registered, replaced or removed
+//! through the handle are visible to every connection from its next
+//! message on; requests already being processed finish with the handler they
+//! started with.
+//!
 //! # Thread Safety
 //!
 //! Routers are cheaply cloneable (`Arc`-based) and thread-safe, allowing
@@ -32,12 +55,36 @@
 //!     Ok(params.unwrap_or_default())
 //! }));
 //! ```
+//!
+//! Nesting a module's router:
+//!
+//! ```rust
+//! use jrow_server::{from_fn, LoggingMiddleware, MiddlewareChain, RouterBuilder};
+//!
+//! let mut user_middleware = MiddlewareChain::new();
+//! user_middleware.add_sync(LoggingMiddleware::new());
+//!
+//! let users = RouterBuilder::with_middleware(user_middleware)
+//!     .handler("create", from_fn(|params| async move { Ok(params.unwrap_or_default()) }))
+//!     .try_build()
+//!     .unwrap();
+//!
+//! let router = RouterBuilder::new()
+//!     .handler("ping", from_fn(|_| async { Ok(serde_json::json!("pong")) }))
+//!     .nest("user", users)
+//!     .try_build()
+//!     .unwrap();
+//!
+//! assert!(router.has_method("user.create"));
+//! ```
 
-use crate::handler::Handler;
-use crate::middleware::{MiddlewareChain, MiddlewareContext};
+use crate::handler::{Handler, HandlerResult, MethodHandler};
+use crate::middleware::{MiddlewareChain, MiddlewareContext, MiddlewareInfo};
+use crate::nats_pattern::NatsPattern;
+use crate::validation::{with_validation, ParamsValidator};
 use jrow_core::{Error, Result};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError, RwLock, Weak};
 
 /// Router for JSON-RPC methods
 ///
@@ -49,35 +96,268 @@ use std::sync::Arc;
 /// The router uses a HashMap for O(1) method lookup. Handlers are wrapped
 /// in `Arc` so they can be shared across threads without cloning the
 /// actual handler logic.
+///
+/// # Resolution Order
+///
+/// 1. A method registered under its exact name
+/// 2. The first pattern route (`register_pattern`) matching the name, in
+///    registration order
+/// 3. The fallback handler (`set_fallback`)
+///
+/// If none applies the call fails with `Error::MethodNotFound`.
 #[derive(Clone)]
 pub struct Router {
-    /// Map of method names to their handler implementations
-    handlers: Arc<HashMap<String, Arc<dyn Handler>>>,
+    /// Map of method names to their routes
+    routes: Arc<HashMap<String, Route>>,
+    /// Pattern routes, checked in registration order
+    patterns: Arc<Vec<(NatsPattern, Route)>>,
+    /// Route for methods matched by nothing else
+    fallback: Option<Route>,
     /// Middleware chain for request/response processing
     middleware_chain: MiddlewareChain,
 }
 
+/// A registered method, pattern or fallback
+#[derive(Clone)]
+struct Route {
+    /// Handler implementation
+    endpoint: Endpoint,
+    /// Middleware of the nested routers this route came from, outermost first
+    middleware: MiddlewareChain,
+}
+
+/// The handler behind a route
+#[derive(Clone)]
+enum Endpoint {
+    /// Handler for a single method
+    Method(Arc<dyn Handler>),
+    /// Handler told which method was called
+    Dispatch(Arc<dyn MethodHandler>),
+}
+
+impl Endpoint {
+    fn call(&self, method: &str, params: Option<serde_json::Value>) -> HandlerResult {
+        match self {
+            Endpoint::Method(handler) => handler.handle(params),
+            Endpoint::Dispatch(handler) => handler.handle(method, params),
+        }
+    }
+}
+
+impl Route {
+    fn new(endpoint: Endpoint) -> Self {
+        Self {
+            endpoint,
+            middleware: MiddlewareChain::new(),
+        }
+    }
+}
+
 impl Router {
     /// Create a new empty router
     pub fn new() -> Self {
-        Self {
-            handlers: Arc::new(HashMap::new()),
-            middleware_chain: MiddlewareChain::new(),
-        }
+        Self::with_middleware(MiddlewareChain::new())
     }
 
     /// Create a router with middleware
     pub fn with_middleware(middleware_chain: MiddlewareChain) -> Self {
         Self {
-            handlers: Arc::new(HashMap::new()),
+            routes: Arc::new(HashMap::new()),
+            patterns: Arc::new(Vec::new()),
+            fallback: None,
             middleware_chain,
         }
     }
 
     /// Register a handler for a method
+    ///
+    /// Replaces any handler previously registered under the same name.
     pub fn register(&mut self, method: impl Into<String>, handler: Box<dyn Handler>) {
-        let handlers = Arc::make_mut(&mut self.handlers);
-        handlers.insert(method.into(), Arc::from(handler));
+        let routes = Arc::make_mut(&mut self.routes);
+        routes.insert(method.into(), Route::new(Endpoint::Method(Arc::from(handler))));
+    }
+
+    /// Remove a method, returning whether it was registered
+    pub fn unregister(&mut self, method: &str) -> bool {
+        Arc::make_mut(&mut self.routes).remove(method).is_some()
+    }
+
+    /// Register a handler whose params are validated against a JSON Schema
+    ///
+    /// Requests whose params violate `schema` are rejected with `-32602`
+    /// before `handler` runs; the error's `data` lists each violation with
+    /// its JSON pointer. The schema is also reported for discovery.
+    ///
+    /// Returns `Error::InvalidRequest` if `schema` is not a valid JSON Schema.
+    pub fn register_with_schema(
+        &mut self,
+        method: impl Into<String>,
+        schema: serde_json::Value,
+        handler: Box<dyn Handler>,
+    ) -> Result<()> {
+        let validator = ParamsValidator::new(schema)?;
+        self.register(method, with_validation(handler, validator));
+        Ok(())
+    }
+
+    /// Register a handler for every method matching a `NatsPattern`
+    ///
+    /// `plugin.*` matches `plugin.run` but not `plugin.a.run`; `proxy.>`
+    /// matches any method below `proxy`. The handler receives the method name
+    /// that was called. Exact registrations take precedence over patterns,
+    /// and earlier patterns over later ones.
+    ///
+    /// Replaces a handler registered for the same pattern. Returns
+    /// `Error::InvalidRequest` if the pattern is invalid.
+    pub fn register_pattern(&mut self, pattern: &str, handler: Box<dyn MethodHandler>) -> Result<()> {
+        let pattern = NatsPattern::new(pattern)
+            .map_err(|e| Error::InvalidRequest(format!("Invalid method pattern: {}", e)))?;
+        let route = Route::new(Endpoint::Dispatch(Arc::from(handler)));
+
+        let patterns = Arc::make_mut(&mut self.patterns);
+        match patterns.iter_mut().find(|(p, _)| p.as_str() == pattern.as_str()) {
+            Some(existing) => existing.1 = route,
+            None => patterns.push((pattern, route)),
+        }
+        Ok(())
+    }
+
+    /// Remove a pattern route, returning whether it was registered
+    pub fn unregister_pattern(&mut self, pattern: &str) -> bool {
+        let patterns = Arc::make_mut(&mut self.patterns);
+        let before = patterns.len();
+        patterns.retain(|(p, _)| p.as_str() != pattern);
+        patterns.len() != before
+    }
+
+    /// Set the handler for methods matched by no registration or pattern
+    pub fn set_fallback(&mut self, handler: Box<dyn MethodHandler>) {
+        self.fallback = Some(Route::new(Endpoint::Dispatch(Arc::from(handler))));
+    }
+
+    /// Remove the fallback handler, returning whether one was set
+    pub fn remove_fallback(&mut self) -> bool {
+        self.fallback.take().is_some()
+    }
+
+    /// Mount another router's methods under `prefix`
+    ///
+    /// Each method `m` of `other` is registered as `prefix.m`, and keeps
+    /// running through `other`'s middleware (after this router's own).
+    /// Pattern routes are prefixed the same way, and `other`'s fallback
+    /// becomes a `prefix.>` pattern route. Their handlers receive the full,
+    /// prefixed method name.
+    ///
+    /// Returns `Error::InvalidRequest` if `prefix` is not one or more literal
+    /// tokens, naming the duplicates if any of the prefixed names is already
+    /// registered, or if a prefixed pattern is not a valid `NatsPattern`;
+    /// nothing is mounted in any of these cases.
+    pub fn nest(&mut self, prefix: &str, other: Router) -> Result<()> {
+        validate_prefix(prefix)?;
+        self.attach(prefix, other)
+    }
+
+    /// Mount another router's methods without a prefix
+    ///
+    /// Like `nest`, but `other`'s methods keep their names and its fallback
+    /// becomes this router's fallback.
+    pub fn merge(&mut self, other: Router) -> Result<()> {
+        self.attach("", other)
+    }
+
+    /// Mount `other` under an already validated `prefix`, failing on duplicates
+    fn attach(&mut self, prefix: &str, other: Router) -> Result<()> {
+        let duplicates = self.mount(prefix, other, false)?;
+        if duplicates.is_empty() {
+            Ok(())
+        } else {
+            Err(duplicate_methods_error(duplicates))
+        }
+    }
+
+    /// Mount `other` under `prefix`, returning the colliding names
+    ///
+    /// With `replace`, colliding routes of `other` replace the existing ones;
+    /// otherwise nothing is mounted when there are collisions. Nothing is
+    /// mounted either when a prefixed route or middleware pattern is invalid.
+    pub(crate) fn mount(&mut self, prefix: &str, other: Router, replace: bool) -> Result<Vec<String>> {
+        let prefixed = |method: &str| {
+            if prefix.is_empty() {
+                method.to_string()
+            } else {
+                format!("{}.{}", prefix, method)
+            }
+        };
+
+        // Scoped middleware patterns are relative to the nested router
+        let other_chain = other.middleware_chain.prefixed(prefix)?;
+        let mount_route = |route: &Route| -> Result<Route> {
+            // The nested router's own middleware wraps its routes' middleware
+            let mut middleware = other_chain.clone();
+            middleware.extend(&route.middleware.prefixed(prefix)?);
+            Ok(Route {
+                endpoint: route.endpoint.clone(),
+                middleware,
+            })
+        };
+        let routes: Vec<(String, Route)> = other
+            .routes
+            .iter()
+            .map(|(method, route)| Ok((prefixed(method), mount_route(route)?)))
+            .collect::<Result<_>>()?;
+
+        // A nested fallback only covers the nested namespace
+        let nested_pattern = |pattern: String| {
+            NatsPattern::new(&pattern).map_err(|e| {
+                Error::InvalidRequest(format!("Invalid method pattern '{}': {}", pattern, e))
+            })
+        };
+        let mut patterns: Vec<(NatsPattern, Route)> = other
+            .patterns
+            .iter()
+            .map(|(pattern, route)| {
+                Ok((nested_pattern(prefixed(pattern.as_str()))?, mount_route(route)?))
+            })
+            .collect::<Result<_>>()?;
+        let mut fallback = None;
+        if let Some(route) = &other.fallback {
+            if prefix.is_empty() {
+                fallback = Some(mount_route(route)?);
+            } else {
+                patterns.push((nested_pattern(format!("{}.>", prefix))?, mount_route(route)?));
+            }
+        }
+
+        let mut duplicates: Vec<String> = routes
+            .iter()
+            .map(|(method, _)| method)
+            .filter(|method| self.routes.contains_key(*method))
+            .cloned()
+            .collect();
+        duplicates.extend(
+            patterns
+                .iter()
+                .map(|(pattern, _)| pattern.as_str())
+                .filter(|pattern| self.patterns.iter().any(|(p, _)| p.as_str() == *pattern))
+                .map(str::to_string),
+        );
+        if fallback.is_some() && self.fallback.is_some() {
+            duplicates.push("<fallback>".to_string());
+        }
+        duplicates.sort();
+        if !duplicates.is_empty() && !replace {
+            return Ok(duplicates);
+        }
+
+        Arc::make_mut(&mut self.routes).extend(routes);
+        let existing = Arc::make_mut(&mut self.patterns);
+        existing.retain(|(p, _)| !patterns.iter().any(|(pattern, _)| pattern.as_str() == p.as_str()));
+        existing.extend(patterns);
+        if fallback.is_some() {
+            self.fallback = fallback;
+        }
+
+        Ok(duplicates)
     }
 
     /// Set the middleware chain for this router
@@ -86,18 +366,77 @@ impl Router {
     }
 
     /// Get a handler for a method
+    ///
+    /// Only methods registered under their exact name are returned.
     pub fn get(&self, method: &str) -> Option<Arc<dyn Handler>> {
-        self.handlers.get(method).cloned()
+        match self.routes.get(method).map(|route| &route.endpoint) {
+            Some(Endpoint::Method(handler)) => Some(Arc::clone(handler)),
+            _ => None,
+        }
     }
 
-    /// Check if a method is registered
+    /// Check if a method is registered under its exact name
     pub fn has_method(&self, method: &str) -> bool {
-        self.handlers.contains_key(method)
+        self.routes.contains_key(method)
+    }
+
+    /// Check if a call to `method` would reach a handler
+    ///
+    /// Unlike `has_method`, this includes pattern routes and the fallback.
+    pub fn resolves(&self, method: &str) -> bool {
+        self.resolve(method).is_some()
     }
 
     /// Get all registered method names
     pub fn methods(&self) -> Vec<String> {
-        self.handlers.keys().cloned().collect()
+        self.routes.keys().cloned().collect()
+    }
+
+    /// Get all registered method patterns, in matching order
+    pub fn patterns(&self) -> Vec<String> {
+        self.patterns
+            .iter()
+            .map(|(pattern, _)| pattern.as_str().to_string())
+            .collect()
+    }
+
+    /// Check if `pattern` is registered as a pattern route
+    pub(crate) fn has_pattern(&self, pattern: &str) -> bool {
+        self.patterns.iter().any(|(p, _)| p.as_str() == pattern)
+    }
+
+    /// Check if a fallback handler is set
+    pub fn has_fallback(&self) -> bool {
+        self.fallback.is_some()
+    }
+
+    /// Find the route serving `method`
+    fn resolve(&self, method: &str) -> Option<&Route> {
+        self.routes
+            .get(method)
+            .or_else(|| {
+                self.patterns
+                    .iter()
+                    .find(|(pattern, _)| pattern.matches(method))
+                    .map(|(_, route)| route)
+            })
+            .or(self.fallback.as_ref())
+    }
+
+    /// Describe the middleware that runs for `method`, in execution order
+    ///
+    /// Includes this router's middleware followed by that of the nested
+    /// routers the method came from, skipping middleware scoped to other
+    /// methods. Unknown methods run no middleware and return an empty list.
+    pub fn middleware_for(&self, method: &str) -> Vec<MiddlewareInfo> {
+        match self.resolve(method) {
+            Some(route) => {
+                let mut info = self.middleware_chain.describe_for(method);
+                info.extend(route.middleware.describe_for(method));
+                info
+            }
+            None => Vec::new(),
+        }
     }
 
     /// Route a method call to the appropriate handler
@@ -116,22 +455,29 @@ impl Router {
         params: Option<serde_json::Value>,
         conn_id: u64,
     ) -> Result<serde_json::Value> {
-        let handler = self
-            .get(method)
+        let route = self
+            .resolve(method)
             .ok_or_else(|| Error::MethodNotFound(method.to_string()))?;
+        let endpoint = &route.endpoint;
 
-        // If no middleware, execute handler directly
-        if self.middleware_chain.is_empty() {
-            return handler.handle(params).await;
+        // If no middleware applies, execute handler directly
+        if !self.middleware_chain.applies_to(method) && !route.middleware.applies_to(method) {
+            return endpoint.call(method, params).await;
         }
 
         // Create middleware context
         let ctx = MiddlewareContext::new(method.to_string(), params.clone(), conn_id);
 
-        // Execute middleware chain with handler
+        // Execute the router's middleware, then the nested routers' middleware
+        let scoped = &route.middleware;
         self.middleware_chain
             .execute(ctx, |ctx| async move {
-                handler.handle(ctx.params).await
+                if scoped.is_empty() {
+                    return endpoint.call(method, ctx.params).await;
+                }
+                scoped
+                    .execute(ctx, |ctx| async move { endpoint.call(method, ctx.params).await })
+                    .await
             })
             .await
     }
@@ -143,9 +489,168 @@ impl Default for Router {
     }
 }
 
+/// Shared, updatable router used by a running server
+///
+/// Cloning the handle is cheap and every clone refers to the same router.
+/// Each incoming message is dispatched through a snapshot of the router
+/// taken when the message arrives, so updates never block or disturb
+/// requests that are already running.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use jrow_server::{from_fn, JrowServer};
+///
+/// # async fn example() -> jrow_core::Result<()> {
+/// let server = JrowServer::builder().bind_str("127.0.0.1:8080")?.build().await?;
+/// let routes = server.router_handle();
+///
+/// // Later, e.g. when a plugin is loaded
+/// routes.register("plugin.hello", from_fn(|_| async { Ok(serde_json::json!("hi")) }));
+///
+/// // And when it is unloaded
+/// routes.unregister("plugin.hello");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RouterHandle {
+    current: Arc<RwLock<Arc<Router>>>,
+}
+
+impl RouterHandle {
+    /// Create a handle serving `router`
+    pub fn new(router: Router) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Arc::new(router))),
+        }
+    }
+
+    /// The router as it is right now
+    ///
+    /// Later updates through the handle do not affect the returned router.
+    pub fn snapshot(&self) -> Arc<Router> {
+        let current = self.current.read().unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(&current)
+    }
+
+    /// Apply a change to the router
+    ///
+    /// The change is made on a copy and published atomically, so
+    /// connections see either the old or the new router, never a mix.
+    pub fn update<R>(&self, change: impl FnOnce(&mut Router) -> R) -> R {
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        change(Arc::make_mut(&mut current))
+    }
+
+    /// Register or replace a handler for a method
+    pub fn register(&self, method: impl Into<String>, handler: Box<dyn Handler>) {
+        self.update(|router| router.register(method, handler));
+    }
+
+    /// Register a handler whose params are validated against a JSON Schema
+    pub fn register_with_schema(
+        &self,
+        method: impl Into<String>,
+        schema: serde_json::Value,
+        handler: Box<dyn Handler>,
+    ) -> Result<()> {
+        self.update(|router| router.register_with_schema(method, schema, handler))
+    }
+
+    /// Remove a method, returning whether it was registered
+    pub fn unregister(&self, method: &str) -> bool {
+        self.update(|router| router.unregister(method))
+    }
+
+    /// Register or replace a handler for every method matching a pattern
+    pub fn register_pattern(&self, pattern: &str, handler: Box<dyn MethodHandler>) -> Result<()> {
+        self.update(|router| router.register_pattern(pattern, handler))
+    }
+
+    /// Remove a pattern route, returning whether it was registered
+    pub fn unregister_pattern(&self, pattern: &str) -> bool {
+        self.update(|router| router.unregister_pattern(pattern))
+    }
+
+    /// Set the handler for methods matched by nothing else
+    pub fn set_fallback(&self, handler: Box<dyn MethodHandler>) {
+        self.update(|router| router.set_fallback(handler));
+    }
+
+    /// Remove the fallback handler, returning whether one was set
+    pub fn remove_fallback(&self) -> bool {
+        self.update(|router| router.remove_fallback())
+    }
+
+    /// Mount a router's methods under `prefix`, failing on duplicate names
+    pub fn nest(&self, prefix: &str, router: Router) -> Result<()> {
+        self.update(|current| current.nest(prefix, router))
+    }
+
+    /// Mount a router's methods without a prefix, failing on duplicate names
+    pub fn merge(&self, router: Router) -> Result<()> {
+        self.update(|current| current.merge(router))
+    }
+
+    /// Check if a method is currently registered
+    pub fn has_method(&self, method: &str) -> bool {
+        self.snapshot().has_method(method)
+    }
+
+    /// Get all currently registered method names
+    pub fn methods(&self) -> Vec<String> {
+        self.snapshot().methods()
+    }
+
+    /// Route a method call through the current router
+    pub async fn route(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        self.snapshot().route(method, params).await
+    }
+
+    /// Weak reference for handlers that need the live router
+    ///
+    /// A handler registered in the router must not keep the router alive.
+    pub(crate) fn downgrade(&self) -> Weak<RwLock<Arc<Router>>> {
+        Arc::downgrade(&self.current)
+    }
+}
+
+/// Error reporting methods registered more than once
+pub(crate) fn duplicate_methods_error(methods: Vec<String>) -> Error {
+    Error::InvalidRequest(format!("Duplicate methods: {}", methods.join(", ")))
+}
+
+/// Check that a nest prefix is one or more literal tokens (e.g. `admin.user`)
+///
+/// Mounting without a prefix is spelled `merge`, so an empty prefix is
+/// rejected along with empty tokens and wildcards.
+pub(crate) fn validate_prefix(prefix: &str) -> Result<()> {
+    if prefix.split('.').all(jrow_core::topic::is_literal_token) {
+        Ok(())
+    } else {
+        Err(Error::InvalidRequest(format!(
+            "Invalid nest prefix '{}': expected literal tokens separated by '.'",
+            prefix
+        )))
+    }
+}
+
 /// Builder for constructing a router
+///
+/// Like `Router::register`, the builder keeps the last handler registered
+/// for a method, but it also records methods registered twice (directly or
+/// through `nest`/`merge`), which `try_build` reports as a mistake.
 pub struct RouterBuilder {
     router: Router,
+    /// Methods registered more than once, reported by `try_build`
+    duplicates: Vec<String>,
+    /// First invalid pattern error, reported by `try_build`
+    invalid: Option<Error>,
 }
 
 impl RouterBuilder {
@@ -153,6 +658,8 @@ impl RouterBuilder {
     pub fn new() -> Self {
         Self {
             router: Router::new(),
+            duplicates: Vec::new(),
+            invalid: None,
         }
     }
 
@@ -160,17 +667,104 @@ impl RouterBuilder {
     pub fn with_middleware(middleware_chain: MiddlewareChain) -> Self {
         Self {
             router: Router::with_middleware(middleware_chain),
+            duplicates: Vec::new(),
+            invalid: None,
         }
     }
 
     /// Add a handler for a method
     pub fn handler(mut self, method: impl Into<String>, handler: Box<dyn Handler>) -> Self {
+        let method = method.into();
+        if self.router.has_method(&method) {
+            self.duplicates.push(method.clone());
+        }
         self.router.register(method, handler);
         self
     }
 
+    /// Add a handler for every method matching a `NatsPattern`
+    ///
+    /// Invalid and duplicate patterns are reported by `try_build`.
+    pub fn pattern_handler(mut self, pattern: &str, handler: Box<dyn MethodHandler>) -> Self {
+        if self.router.has_pattern(pattern) {
+            self.duplicates.push(pattern.to_string());
+        }
+        if let Err(e) = self.router.register_pattern(pattern, handler) {
+            self.invalid.get_or_insert(e);
+        }
+        self
+    }
+
+    /// Set the handler for methods matched by nothing else
+    pub fn fallback(mut self, handler: Box<dyn MethodHandler>) -> Self {
+        if self.router.has_fallback() {
+            self.duplicates.push("<fallback>".to_string());
+        }
+        self.router.set_fallback(handler);
+        self
+    }
+
+    /// Mount a router's methods under `prefix` (e.g. `user` for `user.create`)
+    ///
+    /// The nested router keeps its own middleware, which runs only for its
+    /// methods.
+    ///
+    /// Invalid prefixes and prefixed patterns that are not valid are
+    /// reported by `try_build`.
+    pub fn nest(mut self, prefix: &str, router: Router) -> Self {
+        match validate_prefix(prefix) {
+            Ok(()) => self.mount(prefix, router),
+            Err(e) => {
+                self.invalid.get_or_insert(e);
+                self
+            }
+        }
+    }
+
+    /// Mount a router's methods without a prefix
+    pub fn merge(self, router: Router) -> Self {
+        self.mount("", router)
+    }
+
+    fn mount(mut self, prefix: &str, router: Router) -> Self {
+        match self.router.mount(prefix, router, true) {
+            Ok(duplicates) => self.duplicates.extend(duplicates),
+            Err(e) => {
+                self.invalid.get_or_insert(e);
+            }
+        }
+        self
+    }
+
+    /// Build the router, reporting invalid patterns and duplicate method names
+    pub fn try_build(mut self) -> Result<Router> {
+        if let Some(e) = self.invalid {
+            return Err(e);
+        }
+        if self.duplicates.is_empty() {
+            Ok(self.router)
+        } else {
+            self.duplicates.sort();
+            self.duplicates.dedup();
+            Err(duplicate_methods_error(self.duplicates))
+        }
+    }
+
     /// Build the router
-    pub fn build(self) -> Router {
+    ///
+    /// A method registered more than once keeps its last handler, and
+    /// invalid patterns and nested routers are left out; both are logged as
+    /// warnings. Use `try_build` to have them reported as an error instead.
+    #[deprecated(note = "use `try_build`, which reports duplicate methods and invalid patterns")]
+    pub fn build(mut self) -> Router {
+        if let Some(e) = self.invalid {
+            tracing::warn!(error = %e, "Router built without an invalid registration");
+        }
+        if !self.duplicates.is_empty() {
+            self.duplicates.sort();
+            self.duplicates.dedup();
+            tracing::warn!(methods = ?self.duplicates, "Router built with methods registered more than once");
+        }
         self.router
     }
 }
@@ -207,14 +801,398 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_router_register_with_schema() {
+        let mut router = Router::new();
+        router
+            .register_with_schema(
+                "square",
+                serde_json::json!({"type": "array", "items": {"type": "number"}, "maxItems": 1}),
+                from_fn(|params| async move {
+                    let n = params.unwrap()[0].as_f64().unwrap();
+                    Ok(serde_json::json!(n * n))
+                }),
+            )
+            .unwrap();
+
+        let result = router.route("square", Some(serde_json::json!([3]))).await.unwrap();
+        assert_eq!(result, serde_json::json!(9.0));
+
+        match router.route("square", Some(serde_json::json!(["x"]))).await {
+            Err(Error::JsonRpc(data)) => {
+                assert_eq!(data.code, -32602);
+                assert_eq!(data.data.unwrap()[0]["pointer"], "/0");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let schema = router.get("square").unwrap().schema().unwrap();
+        assert_eq!(schema.params["maxItems"], 1);
+
+        let handler = from_fn(|_| async { Ok(serde_json::Value::Null) });
+        assert!(router
+            .register_with_schema("bad", serde_json::json!({"type": 1}), handler)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_nested_routers() {
+        let users = RouterBuilder::new()
+            .handler("create", from_fn(|_| async { Ok(serde_json::json!("created")) }))
+            .try_build()
+            .unwrap();
+        let billing = RouterBuilder::new()
+            .handler("invoice.get", from_fn(|_| async { Ok(serde_json::json!("invoice")) }))
+            .try_build()
+            .unwrap();
+
+        let router = RouterBuilder::new()
+            .handler("ping", from_fn(|_| async { Ok(serde_json::json!("pong")) }))
+            .nest("user", users)
+            .merge(billing)
+            .try_build()
+            .unwrap();
+
+        let mut methods = router.methods();
+        methods.sort();
+        assert_eq!(methods, vec!["invoice.get", "ping", "user.create"]);
+        assert_eq!(
+            router.route("user.create", None).await.unwrap(),
+            serde_json::json!("created")
+        );
+        assert!(router.route("create", None).await.is_err());
+    }
+
+    #[test]
+    fn test_nested_router_collisions() {
+        let handler = || from_fn(|_| async { Ok(serde_json::Value::Null) });
+        let users = RouterBuilder::new().handler("create", handler()).try_build().unwrap();
+
+        let err = RouterBuilder::new()
+            .handler("user.create", handler())
+            .handler("ping", handler())
+            .handler("ping", handler())
+            .nest("user", users.clone())
+            .try_build()
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Invalid request: Duplicate methods: ping, user.create");
+
+        let mut router = Router::new();
+        router.nest("user", users.clone()).unwrap();
+        assert!(router.nest("user", users).is_err());
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn test_build_keeps_last_handler() {
+        let value = |v: i64| from_fn(move |_| async move { Ok(serde_json::json!(v)) });
+        let users = RouterBuilder::new().handler("create", value(3)).build();
+
+        let router = RouterBuilder::new()
+            .handler("ping", value(1))
+            .handler("ping", value(2))
+            .handler("user.create", value(1))
+            .nest("user", users)
+            .build();
+        assert_eq!(router.route("ping", None).await.unwrap(), serde_json::json!(2));
+        assert_eq!(router.route("user.create", None).await.unwrap(), serde_json::json!(3));
+    }
+
+    #[tokio::test]
+    async fn test_nested_router_middleware_is_scoped() {
+        use crate::middleware::{MiddlewareAction, SyncMiddleware};
+
+        struct Tag(&'static str);
+
+        impl SyncMiddleware for Tag {
+            fn pre_handle(&self, ctx: &mut MiddlewareContext) -> Result<MiddlewareAction> {
+                let mut tags = ctx.params.take().unwrap_or_else(|| serde_json::json!([]));
+                tags.as_array_mut().unwrap().push(serde_json::json!(self.0));
+                ctx.params = Some(tags);
+                Ok(MiddlewareAction::Continue)
+            }
+
+            fn post_handle(
+                &self,
+                _ctx: &mut MiddlewareContext,
+                _result: &Result<serde_json::Value>,
+            ) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let echo = || from_fn(|params| async move { Ok(params.unwrap_or_default()) });
+
+        let mut inner_chain = MiddlewareChain::new();
+        inner_chain.add_sync(Tag("inner"));
+        let inner = RouterBuilder::with_middleware(inner_chain)
+            .handler("echo", echo())
+            .try_build()
+            .unwrap();
+
+        let mut user_chain = MiddlewareChain::new();
+        user_chain.add_sync(Tag("user"));
+        let users = RouterBuilder::with_middleware(user_chain)
+            .handler("echo", echo())
+            .nest("inner", inner)
+            .try_build()
+            .unwrap();
+
+        let mut root_chain = MiddlewareChain::new();
+        root_chain.add_sync(Tag("root"));
+        let router = RouterBuilder::with_middleware(root_chain)
+            .handler("echo", echo())
+            .nest("user", users)
+            .try_build()
+            .unwrap();
+
+        assert_eq!(router.route("echo", None).await.unwrap(), serde_json::json!(["root"]));
+        assert_eq!(
+            router.route("user.echo", None).await.unwrap(),
+            serde_json::json!(["root", "user"])
+        );
+        assert_eq!(
+            router.route("user.inner.echo", None).await.unwrap(),
+            serde_json::json!(["root", "user", "inner"])
+        );
+    }
+
+    #[test]
+    fn test_router_middleware_for() {
+        use crate::middleware::LoggingMiddleware;
+
+        let handler = || from_fn(|_| async { Ok(serde_json::Value::Null) });
+
+        let mut user_chain = MiddlewareChain::new();
+        user_chain.add_sync_scoped("get", LoggingMiddleware).unwrap();
+        let users = RouterBuilder::with_middleware(user_chain)
+            .handler("get", handler())
+            .handler("list", handler())
+            .try_build()
+            .unwrap();
+
+        let mut root_chain = MiddlewareChain::new();
+        root_chain.add_sync_scoped("admin.*", LoggingMiddleware).unwrap();
+        let router = RouterBuilder::with_middleware(root_chain)
+            .handler("admin.ban", handler())
+            .nest("user", users)
+            .try_build()
+            .unwrap();
+
+        let scopes = |method: &str| -> Vec<Option<String>> {
+            router.middleware_for(method).into_iter().map(|m| m.scope).collect()
+        };
+        assert_eq!(scopes("admin.ban"), vec![Some("admin.*".to_string())]);
+        assert_eq!(scopes("user.get"), vec![Some("user.get".to_string())]);
+        assert!(scopes("user.list").is_empty());
+        assert!(scopes("missing").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_nested_scoped_middleware() {
+        use crate::middleware::{MiddlewareAction, SyncMiddleware};
+
+        struct Deny;
+
+        impl SyncMiddleware for Deny {
+            fn pre_handle(&self, _ctx: &mut MiddlewareContext) -> Result<MiddlewareAction> {
+                Err(Error::Internal("denied".to_string()))
+            }
+
+            fn post_handle(
+                &self,
+                _ctx: &mut MiddlewareContext,
+                _result: &Result<serde_json::Value>,
+            ) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let handler = || from_fn(|_| async { Ok(serde_json::json!("ok")) });
+        let nested = || {
+            let mut chain = MiddlewareChain::new();
+            chain.add_sync_scoped("admin.>", Deny).unwrap();
+            RouterBuilder::with_middleware(chain)
+                .handler("admin.ban", handler())
+                .handler("get", handler())
+                .try_build()
+                .unwrap()
+        };
+
+        let router = RouterBuilder::new()
+            .nest("tenant.user", nested())
+            .try_build()
+            .unwrap();
+        assert!(router.route("tenant.user.admin.ban", None).await.is_err());
+        assert_eq!(
+            router.route("tenant.user.get", None).await.unwrap(),
+            serde_json::json!("ok")
+        );
+
+        // A scope that can't be prefixed fails the mount instead of panicking
+        let mut router = Router::new();
+        assert!(router.mount("a.*", nested(), false).is_err());
+        assert!(router.methods().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_router_handle_updates() {
+        let handle = RouterHandle::new(Router::new());
+        let before = handle.snapshot();
+
+        handle.register("plugin.hello", from_fn(|_| async { Ok(serde_json::json!("v1")) }));
+        assert_eq!(handle.route("plugin.hello", None).await.unwrap(), serde_json::json!("v1"));
+
+        // Snapshots are unaffected by later updates
+        assert!(!before.has_method("plugin.hello"));
+
+        handle.register("plugin.hello", from_fn(|_| async { Ok(serde_json::json!("v2")) }));
+        assert_eq!(handle.route("plugin.hello", None).await.unwrap(), serde_json::json!("v2"));
+
+        assert!(handle.unregister("plugin.hello"));
+        assert!(!handle.unregister("plugin.hello"));
+        assert!(matches!(
+            handle.route("plugin.hello", None).await,
+            Err(Error::MethodNotFound(_))
+        ));
+
+        let plugin = RouterBuilder::new()
+            .handler("run", from_fn(|_| async { Ok(serde_json::Value::Null) }))
+            .try_build()
+            .unwrap();
+        handle.nest("plugin", plugin.clone()).unwrap();
+        assert!(handle.has_method("plugin.run"));
+        assert!(handle.nest("plugin", plugin).is_err());
+    }
+
     #[tokio::test]
     async fn test_router_builder() {
         let handler = from_fn(|_| async { Ok(serde_json::json!(42)) });
 
-        let router = RouterBuilder::new().handler("method1", handler).build();
+        let router = RouterBuilder::new().handler("method1", handler).try_build().unwrap();
 
         assert!(router.has_method("method1"));
         let result = router.route("method1", None).await.unwrap();
         assert_eq!(result, serde_json::json!(42));
     }
+
+    #[tokio::test]
+    async fn test_pattern_and_fallback_routes() {
+        use crate::handler::from_method_fn;
+
+        let mut router = Router::new();
+        router.register("plugin.exact", from_fn(|_| async { Ok(serde_json::json!("exact")) }));
+        router
+            .register_pattern(
+                "plugin.*",
+                from_method_fn(|method, _| async move { Ok(serde_json::json!(["plugin", method])) }),
+            )
+            .unwrap();
+        router
+            .register_pattern(
+                "plugin.>",
+                from_method_fn(|method, _| async move { Ok(serde_json::json!(["deep", method])) }),
+            )
+            .unwrap();
+
+        // Exact names win over patterns, earlier patterns over later ones
+        let result = router.route("plugin.exact", None).await.unwrap();
+        assert_eq!(result, serde_json::json!("exact"));
+        let result = router.route("plugin.run", None).await.unwrap();
+        assert_eq!(result, serde_json::json!(["plugin", "plugin.run"]));
+        let result = router.route("plugin.a.run", None).await.unwrap();
+        assert_eq!(result, serde_json::json!(["deep", "plugin.a.run"]));
+        assert!(matches!(
+            router.route("other", None).await,
+            Err(Error::MethodNotFound(_))
+        ));
+
+        router.set_fallback(from_method_fn(|method, params| async move {
+            Ok(serde_json::json!({"fallback": method, "params": params}))
+        }));
+        let result = router.route("other", Some(serde_json::json!(1))).await.unwrap();
+        assert_eq!(result, serde_json::json!({"fallback": "other", "params": 1}));
+        assert!(router.resolves("other"));
+        assert!(!router.has_method("other"));
+        assert!(router.get("plugin.run").is_none());
+        assert_eq!(router.patterns(), vec!["plugin.*", "plugin.>"]);
+
+        assert!(router.unregister_pattern("plugin.*"));
+        let result = router.route("plugin.run", None).await.unwrap();
+        assert_eq!(result, serde_json::json!(["deep", "plugin.run"]));
+        assert!(router.remove_fallback());
+        assert!(!router.resolves("other"));
+
+        let noop = from_method_fn(|_, _| async { Ok(serde_json::Value::Null) });
+        assert!(router.register_pattern("bad..pattern", noop).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_nested_fallback_is_scoped() {
+        use crate::handler::from_method_fn;
+
+        let proxy = RouterBuilder::new()
+            .fallback(from_method_fn(|method, _| async move { Ok(serde_json::json!(method)) }))
+            .try_build()
+            .unwrap();
+        let router = RouterBuilder::new()
+            .handler("local", from_fn(|_| async { Ok(serde_json::json!("local")) }))
+            .nest("proxy", proxy.clone())
+            .try_build()
+            .unwrap();
+
+        let result = router.route("proxy.users.list", None).await.unwrap();
+        assert_eq!(result, serde_json::json!("proxy.users.list"));
+        assert!(!router.has_fallback());
+        assert!(!router.resolves("users.list"));
+
+        let err = RouterBuilder::new()
+            .nest("proxy", proxy.clone())
+            .nest("proxy", proxy)
+            .try_build()
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Invalid request: Duplicate methods: proxy.>");
+    }
+
+    #[test]
+    fn test_nest_rejects_invalid_prefix() {
+        let users = || {
+            RouterBuilder::new()
+                .handler("create", from_fn(|_| async { Ok(serde_json::Value::Null) }))
+                .try_build()
+                .unwrap()
+        };
+
+        let mut router = Router::new();
+        for prefix in ["", "user.", ".user", "admin..user", "user.*", ">"] {
+            assert!(router.nest(prefix, users()).is_err(), "{:?}", prefix);
+            assert!(RouterBuilder::new().nest(prefix, users()).try_build().is_err());
+        }
+        assert!(router.methods().is_empty());
+
+        router.nest("admin.user", users()).unwrap();
+        router.merge(users()).unwrap();
+        let mut methods = router.methods();
+        methods.sort();
+        assert_eq!(methods, vec!["admin.user.create", "create"]);
+    }
+
+    #[test]
+    fn test_nested_invalid_patterns() {
+        use crate::handler::from_method_fn;
+
+        let handler = || from_method_fn(|_, _| async { Ok(serde_json::Value::Null) });
+        let proxy = RouterBuilder::new().fallback(handler()).try_build().unwrap();
+        let deep = RouterBuilder::new().pattern_handler("x.>", handler()).try_build().unwrap();
+
+        let mut router = Router::new();
+        assert!(router.nest("user.", proxy.clone()).is_err());
+        assert!(router.nest("a.*", deep.clone()).is_err());
+        assert!(router.patterns().is_empty());
+
+        assert!(RouterBuilder::new().nest("user.", proxy).try_build().is_err());
+        assert!(RouterBuilder::new().nest("a.*", deep).try_build().is_err());
+    }
 }
@@ -0,0 +1,207 @@
+//! Per-connection session storage
+//!
+//! A `Session` is a typed map (one value per type) that lives as long as
+//! its connection. Unlike `MiddlewareContext` metadata, which is dropped
+//! after each request, values stored in the session are visible to every
+//! later request on the same connection.
+//!
+//! # Access
+//!
+//! - Middleware: `ctx.session`
+//! - Handlers: `Session::current()`, available while a request from a
+//!   connection is being handled
+//! - Lifecycle hooks: `info.session`
+//!
+//! # Examples
+//!
+//! ```rust
+//! use jrow_server::{from_fn, Router, Session};
+//! use jrow_core::Error;
+//!
+//! #[derive(Clone)]
+//! struct User(String);
+//!
+//! let mut router = Router::new();
+//! router.register("login", from_fn(|params| async move {
+//!     let name = params
+//!         .and_then(|p| p["user"].as_str().map(str::to_string))
+//!         .ok_or_else(|| Error::InvalidParams("missing user".to_string()))?;
+//!     if let Some(session) = Session::current() {
+//!         session.insert(User(name));
+//!     }
+//!     Ok(serde_json::json!(true))
+//! }));
+//! router.register("whoami", from_fn(|_| async move {
+//!     let user = Session::current().and_then(|session| session.get::<User>());
+//!     Ok(serde_json::json!(user.map(|User(name)| name)))
+//! }));
+//! ```
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, PoisonError, RwLock};
+
+tokio::task_local! {
+    /// Session of the connection whose request is being handled
+    static CURRENT: Session;
+}
+
+/// Typed storage scoped to one connection
+///
+/// Cloning yields a handle to the same storage. Methods are synchronous so
+/// the session can be used from `SyncMiddleware` as well.
+#[derive(Clone, Default)]
+pub struct Session {
+    values: Arc<RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>>,
+}
+
+impl Session {
+    /// Create an empty session
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Session of the connection whose request is being handled
+    ///
+    /// Returns `None` outside of request handling, e.g. when calling
+    /// `Router::route` directly.
+    pub fn current() -> Option<Session> {
+        CURRENT.try_with(Session::clone).ok()
+    }
+
+    /// Store a value, returning the previous value of the same type
+    pub fn insert<T: Send + Sync + 'static>(&self, value: T) -> Option<T> {
+        self.write()
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|previous| previous.downcast().ok().map(|previous| *previous))
+    }
+
+    /// Get a copy of the stored value of type `T`
+    pub fn get<T: Clone + Send + Sync + 'static>(&self) -> Option<T> {
+        self.read()
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+            .cloned()
+    }
+
+    /// Update the stored value of type `T` in place
+    ///
+    /// Returns `None` without calling `f` if no value of type `T` is stored.
+    pub fn update<T: Send + Sync + 'static, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        self.write()
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut::<T>())
+            .map(f)
+    }
+
+    /// Remove and return the stored value of type `T`
+    pub fn remove<T: Send + Sync + 'static>(&self) -> Option<T> {
+        self.write()
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok().map(|value| *value))
+    }
+
+    /// Check if a value of type `T` is stored
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.read().contains_key(&TypeId::of::<T>())
+    }
+
+    /// Number of stored values
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Check if the session is empty
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Remove all stored values
+    pub fn clear(&self) {
+        self.write().clear();
+    }
+
+    /// Run `future` with this session as `Session::current()`
+    pub(crate) async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<TypeId, Box<dyn Any + Send + Sync>>> {
+        self.values.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<TypeId, Box<dyn Any + Send + Sync>>> {
+        self.values.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl std::fmt::Debug for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Session").field("len", &self.len()).finish()
+    }
+}
+
+/// Make the current session, if any, the session of `future`
+///
+/// Task-locals don't carry over to spawned tasks; wrap futures passed to
+/// `tokio::spawn` during request handling with this. The session is
+/// captured when this is called, not when the future is first polled.
+pub(crate) fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let session = Session::current();
+    async move {
+        match session {
+            Some(session) => session.scope(future).await,
+            None => future.await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct User(&'static str);
+
+    #[test]
+    fn test_typed_values() {
+        let session = Session::new();
+        assert!(session.is_empty());
+
+        assert_eq!(session.insert(User("alice")), None);
+        assert_eq!(session.insert(7u32), None);
+        assert_eq!(session.insert(User("bob")), Some(User("alice")));
+        assert_eq!(session.get::<User>(), Some(User("bob")));
+        assert_eq!(session.len(), 2);
+
+        assert_eq!(session.update(|count: &mut u32| {
+            *count += 1;
+            *count
+        }), Some(8));
+        assert_eq!(session.update(|_: &mut String| ()), None);
+
+        // Clones share the storage
+        let clone = session.clone();
+        assert_eq!(clone.remove::<u32>(), Some(8));
+        assert!(!session.contains::<u32>());
+    }
+
+    #[tokio::test]
+    async fn test_current_session() {
+        assert!(Session::current().is_none());
+
+        let session = Session::new();
+        session
+            .clone()
+            .scope(async {
+                Session::current().unwrap().insert(User("alice"));
+
+                // Spawned tasks only see the session through `inherit`
+                let spawned = tokio::spawn(inherit(async { Session::current().is_some() }));
+                assert!(spawned.await.unwrap());
+            })
+            .await;
+        assert_eq!(session.get::<User>(), Some(User("alice")));
+    }
+}
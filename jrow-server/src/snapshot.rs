@@ -0,0 +1,138 @@
+//! Snapshot providers for snapshot-then-delta subscriptions
+//!
+//! A snapshot provider, registered with `ServerBuilder::snapshot_provider`,
+//! returns the current state of topics matching a pattern. Messages on those
+//! topics are versioned (see `jrow_core::snapshot`): an exact subscriber
+//! receives the provider's result as a snapshot right after `rpc.subscribe`,
+//! then each message published to the topic as a delta.
+//!
+//! # Ordering
+//!
+//! Each versioned topic has a lock holding its version. Publishing takes it
+//! to number and queue the delta, and subscribing holds it while calling
+//! the provider, registering the subscription and queuing the snapshot. No
+//! delta can therefore fall between the snapshot and the subscription or
+//! reach the subscriber before the snapshot.
+//!
+//! The state the provider reads has to change in step with the deltas. A
+//! change applied before `JrowServer::publish` waits for the lock would be
+//! in the snapshot and also arrive as the next delta, so apply changes with
+//! `JrowServer::publish_update`, which runs the change under the lock.
+//! Providers must not publish to their own topic, which would wait for the
+//! lock they are called under.
+//!
+//! Versioned topics aren't retained, since the snapshot takes the place of
+//! retained messages. Pattern subscribers and queue group members receive
+//! deltas but no snapshot.
+
+use crate::TopicFilter;
+use jrow_core::snapshot::Versioned;
+use jrow_core::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, PoisonError};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+type SnapshotFuture = Pin<Box<dyn Future<Output = Result<Value>> + Send>>;
+
+/// Provider returning the state of a topic
+pub(crate) type SnapshotProvider = Arc<dyn Fn(String) -> SnapshotFuture + Send + Sync>;
+
+/// Snapshot providers and the versions of their topics
+#[derive(Default)]
+pub(crate) struct Snapshots {
+    /// Topic patterns with their providers, in registration order
+    providers: Vec<(TopicFilter, SnapshotProvider)>,
+    /// Version of each versioned topic published or subscribed to
+    versions: std::sync::Mutex<HashMap<String, Arc<Mutex<u64>>>>,
+}
+
+/// Exclusive access to the version of a topic
+pub(crate) struct Sequence {
+    topic: String,
+    provider: SnapshotProvider,
+    version: OwnedMutexGuard<u64>,
+}
+
+impl Snapshots {
+    /// Create a registry with the given providers
+    pub(crate) fn new(providers: Vec<(TopicFilter, SnapshotProvider)>) -> Self {
+        Self {
+            providers,
+            versions: Default::default(),
+        }
+    }
+
+    /// Lock the version of `topic`, if it has a snapshot provider
+    ///
+    /// When several patterns match, the first registered provider applies.
+    pub(crate) async fn sequence(&self, topic: &str) -> Option<Sequence> {
+        let (_, provider) = self.providers.iter().find(|(filter, _)| filter.matches(topic))?;
+        let version = {
+            let mut versions = self.versions.lock().unwrap_or_else(PoisonError::into_inner);
+            Arc::clone(versions.entry(topic.to_string()).or_default())
+        };
+
+        Some(Sequence {
+            topic: topic.to_string(),
+            provider: Arc::clone(provider),
+            version: version.lock_owned().await,
+        })
+    }
+}
+
+impl Sequence {
+    /// Wrap data published to the topic as its next delta
+    pub(crate) fn delta<'a>(&mut self, data: &'a Value) -> Versioned<&'a Value> {
+        *self.version += 1;
+        Versioned::delta(*self.version, data)
+    }
+
+    /// Call the provider for a snapshot at the current version
+    pub(crate) async fn snapshot(&self) -> Result<Versioned> {
+        let data = (self.provider)(self.topic.clone()).await?;
+        Ok(Versioned::snapshot(*self.version, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn snapshots() -> Snapshots {
+        let provider: SnapshotProvider = Arc::new(|topic| Box::pin(async move { Ok(json!({ "topic": topic })) }));
+        Snapshots::new(vec![(TopicFilter::new("orders.*").unwrap(), provider)])
+    }
+
+    #[tokio::test]
+    async fn test_versions_per_topic() {
+        let snapshots = snapshots();
+        assert!(snapshots.sequence("users.1").await.is_none());
+
+        let mut sequence = snapshots.sequence("orders.eu").await.unwrap();
+        assert_eq!(sequence.snapshot().await.unwrap(), Versioned::snapshot(0, json!({"topic": "orders.eu"})));
+        assert_eq!(sequence.delta(&json!(1)).version, 1);
+        drop(sequence);
+
+        let mut sequence = snapshots.sequence("orders.eu").await.unwrap();
+        assert_eq!(sequence.delta(&json!(2)).version, 2);
+        assert_eq!(sequence.snapshot().await.unwrap().version, 2);
+        assert_eq!(snapshots.sequence("orders.us").await.unwrap().snapshot().await.unwrap().version, 0);
+    }
+
+    #[tokio::test]
+    async fn test_sequence_is_exclusive() {
+        let snapshots = snapshots();
+        let held = snapshots.sequence("orders.eu").await.unwrap();
+
+        let waiting = tokio::time::timeout(std::time::Duration::from_millis(50), snapshots.sequence("orders.eu"));
+        assert!(waiting.await.is_err());
+        assert!(snapshots.sequence("orders.us").await.is_some());
+
+        drop(held);
+        assert!(snapshots.sequence("orders.eu").await.is_some());
+    }
+}
@@ -0,0 +1,229 @@
+//! Token trie for matching topics against many subscriptions
+//!
+//! Subscriptions are stored by pattern in a trie with one level per
+//! dot-separated token, as NATS does for its subject lists. Each node has
+//! literal children, a `*` child and the values of patterns ending in `>`
+//! at that depth. Matching a topic walks its tokens through the literal
+//! and `*` branches only, so the cost depends on the topic's depth and the
+//! number of wildcard branches along the way, not on how many
+//! subscriptions are stored.
+//!
+//! Patterns are expected to be validated with `NatsPattern::new` before
+//! they are inserted.
+
+use std::collections::HashMap;
+
+/// Subscriptions indexed by NATS-style pattern
+#[derive(Debug)]
+pub(crate) struct SubjectIndex<V> {
+    root: Node<V>,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct Node<V> {
+    /// Children for literal tokens
+    literals: HashMap<String, Node<V>>,
+    /// Child for the `*` token
+    star: Option<Box<Node<V>>>,
+    /// Values of patterns ending at this node
+    values: Vec<V>,
+    /// Values of patterns ending with `>` after this node
+    full_wildcard: Vec<V>,
+}
+
+impl<V> Default for Node<V> {
+    fn default() -> Self {
+        Self {
+            literals: HashMap::new(),
+            star: None,
+            values: Vec::new(),
+            full_wildcard: Vec::new(),
+        }
+    }
+}
+
+impl<V> Node<V> {
+    fn is_empty(&self) -> bool {
+        self.values.is_empty()
+            && self.full_wildcard.is_empty()
+            && self.star.is_none()
+            && self.literals.is_empty()
+    }
+
+    fn remove(&mut self, tokens: &[&str], predicate: &mut impl FnMut(&V) -> bool) -> usize {
+        match tokens.split_first() {
+            None => retain_count(&mut self.values, predicate),
+            Some((&">", _)) => retain_count(&mut self.full_wildcard, predicate),
+            Some((&"*", rest)) => {
+                let Some(child) = self.star.as_mut() else {
+                    return 0;
+                };
+                let removed = child.remove(rest, predicate);
+                if child.is_empty() {
+                    self.star = None;
+                }
+                removed
+            }
+            Some((literal, rest)) => {
+                let Some(child) = self.literals.get_mut(*literal) else {
+                    return 0;
+                };
+                let removed = child.remove(rest, predicate);
+                if child.is_empty() {
+                    self.literals.remove(*literal);
+                }
+                removed
+            }
+        }
+    }
+
+    fn collect<'a>(&'a self, tokens: &[&str], out: &mut Vec<&'a V>) {
+        let Some((token, rest)) = tokens.split_first() else {
+            out.extend(&self.values);
+            return;
+        };
+
+        // `>` matches the remaining tokens, of which there is at least one
+        out.extend(&self.full_wildcard);
+        if let Some(child) = self.literals.get(*token) {
+            child.collect(rest, out);
+        }
+        if let Some(child) = &self.star {
+            child.collect(rest, out);
+        }
+    }
+}
+
+/// Remove values matching `predicate`, returning how many were removed
+fn retain_count<V>(values: &mut Vec<V>, predicate: &mut impl FnMut(&V) -> bool) -> usize {
+    let before = values.len();
+    values.retain(|value| !predicate(value));
+    before - values.len()
+}
+
+impl<V> SubjectIndex<V> {
+    /// Create an empty index
+    pub(crate) fn new() -> Self {
+        Self {
+            root: Node::default(),
+            len: 0,
+        }
+    }
+
+    /// Add a value under `pattern`
+    pub(crate) fn insert(&mut self, pattern: &str, value: V) {
+        let mut node = &mut self.root;
+        for token in pattern.split('.') {
+            node = match token {
+                ">" => {
+                    node.full_wildcard.push(value);
+                    self.len += 1;
+                    return;
+                }
+                "*" => node.star.get_or_insert_with(Default::default),
+                literal => node.literals.entry(literal.to_string()).or_default(),
+            };
+        }
+        node.values.push(value);
+        self.len += 1;
+    }
+
+    /// Remove the values under `pattern` matching `predicate`
+    ///
+    /// Returns how many values were removed. Nodes left empty are pruned.
+    pub(crate) fn remove(&mut self, pattern: &str, mut predicate: impl FnMut(&V) -> bool) -> usize {
+        let tokens: Vec<&str> = pattern.split('.').collect();
+        let removed = self.root.remove(&tokens, &mut predicate);
+        self.len -= removed;
+        removed
+    }
+
+    /// Values of all patterns matching `topic`
+    pub(crate) fn matches(&self, topic: &str) -> Vec<&V> {
+        let tokens: Vec<&str> = topic.split('.').collect();
+        let mut out = Vec::new();
+        self.root.collect(&tokens, &mut out);
+        out
+    }
+
+    /// Number of stored values
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<V> Default for SubjectIndex<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NatsPattern;
+
+    fn matching(index: &SubjectIndex<&'static str>, topic: &str) -> Vec<&'static str> {
+        let mut values: Vec<_> = index.matches(topic).into_iter().copied().collect();
+        values.sort_unstable();
+        values
+    }
+
+    #[test]
+    fn test_wildcards() {
+        let mut index = SubjectIndex::new();
+        index.insert("orders.new", "exact");
+        index.insert("orders.*", "single");
+        index.insert("*.new", "leading");
+        index.insert("orders.>", "multi");
+        index.insert(">", "all");
+
+        assert_eq!(matching(&index, "orders.new"), vec!["all", "exact", "leading", "multi", "single"]);
+        assert_eq!(matching(&index, "orders.shipped"), vec!["all", "multi", "single"]);
+        assert_eq!(matching(&index, "orders.new.fast"), vec!["all", "multi"]);
+        assert_eq!(matching(&index, "orders"), vec!["all"]);
+        assert_eq!(matching(&index, "users.new"), vec!["all", "leading"]);
+    }
+
+    #[test]
+    fn test_remove_prunes_nodes() {
+        let mut index = SubjectIndex::new();
+        index.insert("a.*.c", "x");
+        index.insert("a.*.c", "y");
+        index.insert("a.>", "z");
+        assert_eq!(index.len(), 3);
+
+        assert_eq!(index.remove("a.*.c", |v| *v == "x"), 1);
+        assert_eq!(index.remove("a.*.d", |_| true), 0);
+        assert_eq!(matching(&index, "a.b.c"), vec!["y", "z"]);
+
+        assert_eq!(index.remove("a.*.c", |_| true), 1);
+        assert_eq!(index.remove("a.>", |_| true), 1);
+        assert_eq!(index.len(), 0);
+        assert!(index.root.is_empty());
+    }
+
+    #[test]
+    fn test_agrees_with_nats_pattern() {
+        let patterns = [
+            "a", "a.b", "a.*", "*.b", "a.>", ">", "*", "a.*.c", "*.*.c", "a.b.>",
+        ];
+        let topics = ["a", "b", "a.b", "a.c", "x.b", "a.b.c", "a.x.c", "a.b.c.d", "x.y.c"];
+
+        let mut index = SubjectIndex::new();
+        for pattern in patterns {
+            index.insert(pattern, pattern);
+        }
+
+        for topic in topics {
+            let mut expected: Vec<&str> = patterns
+                .iter()
+                .copied()
+                .filter(|p| NatsPattern::new(p).unwrap().matches(topic))
+                .collect();
+            expected.sort_unstable();
+            assert_eq!(matching(&index, topic), expected, "topic {}", topic);
+        }
+    }
+}
@@ -8,11 +8,23 @@
 //! # Data Structures
 //!
 //! The manager uses two HashMaps for bidirectional lookups:
-//! - `topic -> Set<connection_id>`: Find subscribers for a topic
+//! - `topic -> Map<connection_id, filter>`: Find subscribers for a topic
 //! - `connection_id -> Set<topic>`: Find topics for a connection
 //!
 //! Both mappings are kept in sync to ensure consistency.
 //!
+//! # Content Filters
+//!
+//! A subscription can carry a `ContentFilter`. `get_recipients` skips
+//! subscribers whose filter doesn't match the published data, while
+//! `get_subscribers` lists every subscriber regardless of filters.
+//!
+//! # Queue Groups
+//!
+//! A subscription can belong to a queue group (see `crate::queue_group`).
+//! `get_recipients` leaves out group members, which `get_queue_members`
+//! returns with their group so the publisher can choose one per group.
+//!
 //! # Thread Safety
 //!
 //! The manager is `Clone` and thread-safe, using `Arc<Mutex<...>>` for
@@ -43,10 +55,27 @@
 //! # }
 //! ```
 
+use jrow_core::ContentFilter;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+/// Subscribers of one topic
+type Subscribers = HashMap<u64, Subscriber>;
+
+/// How a connection subscribed to a topic
+#[derive(Debug, Clone)]
+struct Subscriber {
+    filter: Option<ContentFilter>,
+    queue_group: Option<String>,
+}
+
+impl Subscriber {
+    fn accepts(&self, data: &serde_json::Value) -> bool {
+        self.filter.as_ref().is_none_or(|f| f.matches(data))
+    }
+}
+
 /// Manages topic subscriptions for connections
 ///
 /// Maintains bidirectional mappings for efficient lookup in both directions:
@@ -55,13 +84,14 @@ use tokio::sync::Mutex;
 ///
 /// # Implementation Notes
 ///
-/// Uses `HashSet` for subscriber lists to ensure uniqueness and O(1)
-/// insertion/removal. The dual-map design trades memory for speed.
+/// Uses `HashMap` keyed by connection ID for subscriber lists to ensure
+/// uniqueness and O(1) insertion/removal, with each subscriber's content
+/// filter and queue group as the value. The dual-map design trades memory for speed.
 #[derive(Clone)]
 pub struct SubscriptionManager {
-    /// Map of topic -> set of connection IDs subscribed to that topic
+    /// Map of topic -> connection IDs subscribed to that topic, with their options
     /// Used when publishing: quickly find who to send to
-    topic_subscribers: Arc<Mutex<HashMap<String, HashSet<u64>>>>,
+    topic_subscribers: Arc<Mutex<HashMap<String, Subscribers>>>,
     
     /// Map of connection ID -> set of topics that connection is subscribed to
     /// Used when disconnecting: quickly find what to clean up
@@ -79,12 +109,42 @@ impl SubscriptionManager {
 
     /// Subscribe a connection to a topic
     pub async fn subscribe(&self, connection_id: u64, topic: impl Into<String>) -> bool {
+        self.subscribe_filtered(connection_id, topic, None).await
+    }
+
+    /// Subscribe a connection to a topic, delivering only data matching `filter`
+    ///
+    /// Subscribing again replaces the filter. Returns whether the connection
+    /// wasn't subscribed to the topic before.
+    pub async fn subscribe_filtered(
+        &self,
+        connection_id: u64,
+        topic: impl Into<String>,
+        filter: Option<ContentFilter>,
+    ) -> bool {
+        self.subscribe_queue(connection_id, topic, None, filter).await
+    }
+
+    /// Subscribe a connection to a topic as a member of `queue_group`
+    ///
+    /// Members of a group share the group's messages instead of each
+    /// receiving a copy; `None` subscribes normally. Subscribing again
+    /// replaces the group and filter. Returns whether the connection wasn't
+    /// subscribed to the topic before.
+    pub async fn subscribe_queue(
+        &self,
+        connection_id: u64,
+        topic: impl Into<String>,
+        queue_group: Option<String>,
+        filter: Option<ContentFilter>,
+    ) -> bool {
         let topic = topic.into();
+        let subscriber = Subscriber { filter, queue_group };
 
         // Add to topic_subscribers
         let mut topic_subs = self.topic_subscribers.lock().await;
-        let subscribers = topic_subs.entry(topic.clone()).or_insert_with(HashSet::new);
-        let is_new = subscribers.insert(connection_id);
+        let subscribers = topic_subs.entry(topic.clone()).or_default();
+        let is_new = subscribers.insert(connection_id, subscriber).is_none();
         drop(topic_subs);
 
         // Add to connection_topics
@@ -104,7 +164,7 @@ impl SubscriptionManager {
         // Remove from topic_subscribers
         let mut topic_subs = self.topic_subscribers.lock().await;
         if let Some(subscribers) = topic_subs.get_mut(topic) {
-            removed = subscribers.remove(&connection_id);
+            removed = subscribers.remove(&connection_id).is_some();
             if subscribers.is_empty() {
                 topic_subs.remove(topic);
             }
@@ -128,7 +188,41 @@ impl SubscriptionManager {
         let topic_subs = self.topic_subscribers.lock().await;
         topic_subs
             .get(topic)
-            .map(|subs| subs.iter().copied().collect())
+            .map(|subs| subs.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get the connection IDs that should receive `data` published to a topic
+    ///
+    /// Like `get_subscribers`, but leaves out subscribers whose content
+    /// filter doesn't match `data` and queue group members.
+    pub async fn get_recipients(&self, topic: &str, data: &serde_json::Value) -> Vec<u64> {
+        let topic_subs = self.topic_subscribers.lock().await;
+        topic_subs
+            .get(topic)
+            .map(|subs| {
+                subs.iter()
+                    .filter(|(_, sub)| sub.queue_group.is_none() && sub.accepts(data))
+                    .map(|(&conn_id, _)| conn_id)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Get the queue group members whose filter matches `data` published to
+    /// a topic
+    ///
+    /// Returns `(queue_group, connection_id)` pairs.
+    pub async fn get_queue_members(&self, topic: &str, data: &serde_json::Value) -> Vec<(String, u64)> {
+        let topic_subs = self.topic_subscribers.lock().await;
+        topic_subs
+            .get(topic)
+            .map(|subs| {
+                subs.iter()
+                    .filter(|(_, sub)| sub.accepts(data))
+                    .filter_map(|(&conn_id, sub)| Some((sub.queue_group.clone()?, conn_id)))
+                    .collect()
+            })
             .unwrap_or_default()
     }
 
@@ -141,6 +235,12 @@ impl SubscriptionManager {
             .unwrap_or_default()
     }
 
+    /// Get all topics with subscribers
+    pub async fn get_all_topics(&self) -> Vec<String> {
+        let topic_subs = self.topic_subscribers.lock().await;
+        topic_subs.keys().cloned().collect()
+    }
+
     /// Remove all subscriptions for a connection (cleanup on disconnect)
     pub async fn remove_connection(&self, connection_id: u64) {
         // Get all topics this connection is subscribed to
@@ -265,6 +365,43 @@ mod tests {
         assert_eq!(manager.subscription_count().await, 3);
         assert_eq!(manager.topic_count().await, 2);
     }
-}
 
+    #[tokio::test]
+    async fn test_filtered_recipients() {
+        let manager = SubscriptionManager::new();
+
+        manager.subscribe(1, "orders").await;
+        manager
+            .subscribe_filtered(2, "orders", Some(ContentFilter::gte("/amount", 100)))
+            .await;
 
+        let mut recipients = manager.get_recipients("orders", &serde_json::json!({"amount": 250})).await;
+        recipients.sort_unstable();
+        assert_eq!(recipients, vec![1, 2]);
+        assert_eq!(manager.get_recipients("orders", &serde_json::json!({"amount": 5})).await, vec![1]);
+
+        // Subscribing again replaces the filter
+        assert!(!manager.subscribe(2, "orders").await);
+        assert_eq!(manager.get_recipients("orders", &serde_json::json!({"amount": 5})).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_queue_members() {
+        let manager = SubscriptionManager::new();
+        let data = serde_json::json!({});
+
+        manager.subscribe(1, "jobs").await;
+        manager.subscribe_queue(2, "jobs", Some("workers".to_string()), None).await;
+        manager.subscribe_queue(3, "jobs", Some("workers".to_string()), None).await;
+
+        assert_eq!(manager.get_recipients("jobs", &data).await, vec![1]);
+        let mut members = manager.get_queue_members("jobs", &data).await;
+        members.sort_unstable();
+        assert_eq!(members, vec![("workers".to_string(), 2), ("workers".to_string(), 3)]);
+        assert_eq!(manager.get_subscribers("jobs").await.len(), 3);
+
+        // Subscribing again without a group leaves the group
+        manager.subscribe(3, "jobs").await;
+        assert_eq!(manager.get_queue_members("jobs", &data).await.len(), 1);
+    }
+}
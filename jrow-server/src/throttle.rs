@@ -0,0 +1,299 @@
+//! Throttling and conflation of subscriptions
+//!
+//! High-frequency topics such as tickers can send more messages than a
+//! browser can render. A subscription can ask the server to slow down:
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "method": "rpc.subscribe", "params": {"topic": "ticker.*", "max_rate": 4, "conflate": "/symbol"}, "id": 1}
+//! ```
+//!
+//! - `max_rate` limits delivery to that many rounds per second
+//! - `conflate` is a JSON pointer to the key of each message, such as the
+//!   symbol of a price update
+//!
+//! A message is sent straight away when the previous round is at least
+//! `1 / max_rate` seconds old and the client has read every published
+//! message queued for it. Otherwise the subscription has fallen behind, and
+//! the message is held until both are true again. Only the latest held
+//! message per key is kept, and the others are conflated: dropped and
+//! counted by `ServerMetrics::record_conflated`. A round then sends the held
+//! messages in the order their keys first arrived. Without `conflate` the
+//! whole subscription is one key, so each round sends only the latest
+//! message.
+//!
+//! Throttling applies to exact and pattern subscriptions alike, after
+//! content filters. Retained messages and snapshots are sent without delay.
+//! Conflating the deltas of a topic with a snapshot provider loses changes,
+//! so only conflate topics whose messages carry full values.
+
+use crate::connection::Connection;
+use jrow_core::{Error, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite::Utf8Bytes;
+
+/// Delivery limits of one subscription
+#[derive(Debug)]
+pub(crate) struct Throttle {
+    /// Minimum time between rounds
+    interval: Duration,
+    /// JSON pointer to the conflation key
+    key: Option<String>,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    last_round: Option<Instant>,
+    /// Latest message per key, in order of first arrival
+    held: Vec<Utf8Bytes>,
+    positions: HashMap<String, usize>,
+    /// Whether a task is waiting to send the held messages
+    flushing: bool,
+    /// Set when the subscription is removed
+    closed: bool,
+}
+
+/// What happened to a message offered to a throttled subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Delivery {
+    /// Queued for the connection
+    Sent,
+    /// Held for the next round
+    Held,
+    /// Held, replacing an earlier message with the same key
+    Conflated,
+    /// The connection is closed
+    Failed,
+}
+
+impl Throttle {
+    /// Limit a subscription to `max_rate` rounds per second, keeping the
+    /// latest message per value at the `conflate` pointer
+    ///
+    /// Returns `None` if neither is set, and an error if `max_rate` isn't a
+    /// positive number or is too small for its interval to be a `Duration`.
+    pub(crate) fn new(max_rate: Option<f64>, conflate: Option<String>) -> Result<Option<Self>> {
+        let interval = match max_rate {
+            Some(rate) if rate.is_finite() && rate > 0.0 => Duration::try_from_secs_f64(1.0 / rate)
+                .map_err(|_| Error::InvalidParams(format!("'max_rate' {} is too small", rate)))?,
+            Some(_) => return Err(Error::InvalidParams("'max_rate' must be a positive number".to_string())),
+            None if conflate.is_none() => return Ok(None),
+            None => Duration::ZERO,
+        };
+        Ok(Some(Self {
+            interval,
+            key: conflate,
+            state: Mutex::default(),
+        }))
+    }
+
+    /// Send `text`, the notification for `data`, or hold it for a later round
+    pub(crate) fn deliver(self: &Arc<Self>, conn: &Connection, data: &Value, text: &Utf8Bytes) -> Delivery {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.closed {
+            return Delivery::Failed;
+        }
+
+        let now = Instant::now();
+        if !state.flushing && self.ready(&state, now) && conn.pending() == 0 {
+            state.last_round = Some(now);
+            return match conn.send_shared(text.clone()) {
+                Ok(()) => Delivery::Sent,
+                Err(_) => Delivery::Failed,
+            };
+        }
+
+        let key = match &self.key {
+            Some(pointer) => data.pointer(pointer).map_or_else(String::new, Value::to_string),
+            None => String::new(),
+        };
+        let delivery = match state.positions.get(&key) {
+            Some(&position) => {
+                state.held[position] = text.clone();
+                Delivery::Conflated
+            }
+            None => {
+                let position = state.held.len();
+                state.held.push(text.clone());
+                state.positions.insert(key, position);
+                Delivery::Held
+            }
+        };
+
+        if !state.flushing {
+            state.flushing = true;
+            tokio::spawn(Arc::clone(self).flush(conn.clone()));
+        }
+        delivery
+    }
+
+    /// Drop held messages and stop delivering
+    pub(crate) fn close(&self) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.closed = true;
+        state.held.clear();
+        state.positions.clear();
+    }
+
+    fn ready(&self, state: &State, now: Instant) -> bool {
+        state
+            .last_round
+            .is_none_or(|last| now.duration_since(last) >= self.interval)
+    }
+
+    /// Send held messages in rounds until none are left
+    async fn flush(self: Arc<Self>, conn: Connection) {
+        loop {
+            let wait = {
+                let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+                state
+                    .last_round
+                    .map_or(Duration::ZERO, |last| self.interval.saturating_sub(last.elapsed()))
+            };
+            tokio::time::sleep(wait).await;
+            if !conn.drained().await {
+                return;
+            }
+
+            let held = {
+                let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+                if state.closed || state.held.is_empty() {
+                    state.flushing = false;
+                    return;
+                }
+                state.last_round = Some(Instant::now());
+                state.positions.clear();
+                std::mem::take(&mut state.held)
+            };
+            for text in held {
+                if conn.send_shared(text).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Throttled subscriptions by connection and topic or pattern
+#[derive(Debug, Default)]
+pub(crate) struct Throttles {
+    subscriptions: dashmap::DashMap<u64, HashMap<String, Arc<Throttle>>>,
+}
+
+impl Throttles {
+    /// Set or clear the throttle of a connection's subscription
+    pub(crate) fn set(&self, conn_id: u64, subscription: &str, throttle: Option<Throttle>) {
+        let replaced = match throttle {
+            Some(throttle) => self
+                .subscriptions
+                .entry(conn_id)
+                .or_default()
+                .insert(subscription.to_string(), Arc::new(throttle)),
+            None => self.subscriptions.get_mut(&conn_id).and_then(|mut throttles| throttles.remove(subscription)),
+        };
+        if let Some(replaced) = replaced {
+            replaced.close();
+        }
+        self.subscriptions.remove_if(&conn_id, |_, throttles| throttles.is_empty());
+    }
+
+    /// Throttle of a connection's subscription, if it has one
+    pub(crate) fn get(&self, conn_id: u64, subscription: &str) -> Option<Arc<Throttle>> {
+        if self.subscriptions.is_empty() {
+            return None;
+        }
+        self.subscriptions.get(&conn_id)?.get(subscription).cloned()
+    }
+
+    /// Clear the throttles of a closed connection
+    pub(crate) fn remove_connection(&self, conn_id: u64) {
+        if let Some((_, throttles)) = self.subscriptions.remove(&conn_id) {
+            throttles.values().for_each(|throttle| throttle.close());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    fn texts(rx: &mut mpsc::UnboundedReceiver<crate::connection::Outbound>) -> Vec<String> {
+        let mut texts = Vec::new();
+        while let Ok(crate::connection::Outbound::Shared(text)) = rx.try_recv() {
+            texts.push(text.to_string());
+        }
+        texts
+    }
+
+    #[tokio::test]
+    async fn test_max_rate_keeps_latest() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let conn = Connection::new(1, tx);
+        let throttle = Arc::new(Throttle::new(Some(20.0), None).unwrap().unwrap());
+
+        let deliveries: Vec<Delivery> = (0..4)
+            .map(|i| throttle.deliver(&conn, &json!(i), &Utf8Bytes::from(i.to_string())))
+            .collect();
+        assert_eq!(deliveries, vec![Delivery::Sent, Delivery::Held, Delivery::Conflated, Delivery::Conflated]);
+        assert_eq!(texts(&mut rx), vec!["0"]);
+
+        // The held message waits for the interval and for the client
+        conn.backlog.sent();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(texts(&mut rx), vec!["3"]);
+    }
+
+    #[tokio::test]
+    async fn test_conflate_by_key_when_behind() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let conn = Connection::new(1, tx);
+        let throttle = Arc::new(Throttle::new(None, Some("/symbol".to_string())).unwrap().unwrap());
+
+        let offer = |symbol: &str, price: u64| {
+            let text = Utf8Bytes::from(format!("{}={}", symbol, price));
+            throttle.deliver(&conn, &json!({"symbol": symbol, "price": price}), &text)
+        };
+        assert_eq!(offer("btc", 1), Delivery::Sent);
+        assert_eq!(offer("btc", 2), Delivery::Held);
+        assert_eq!(offer("eth", 3), Delivery::Held);
+        assert_eq!(offer("btc", 4), Delivery::Conflated);
+
+        // Nothing more is sent until the client reads what it was sent
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(texts(&mut rx), vec!["btc=1"]);
+        conn.backlog.sent();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(texts(&mut rx), vec!["btc=4", "eth=3"]);
+    }
+
+    #[test]
+    fn test_invalid_max_rate() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY, 1e-300] {
+            let err = Throttle::new(Some(rate), None).unwrap_err();
+            assert!(matches!(err, Error::InvalidParams(_)), "{}: {}", rate, err);
+        }
+        assert!(Throttle::new(Some(1e-3), None).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_set_and_clear() {
+        let throttles = Throttles::default();
+        assert!(Throttle::new(None, None).unwrap().is_none());
+
+        throttles.set(1, "ticker.*", Throttle::new(Some(1.0), None).unwrap());
+        assert!(throttles.get(1, "ticker.*").is_some());
+        assert!(throttles.get(1, "ticker.btc").is_none());
+
+        throttles.set(1, "ticker.*", None);
+        assert!(throttles.get(1, "ticker.*").is_none());
+
+        throttles.set(2, "ticker.btc", Throttle::new(Some(1.0), None).unwrap());
+        throttles.remove_connection(2);
+        assert!(throttles.get(2, "ticker.btc").is_none());
+    }
+}
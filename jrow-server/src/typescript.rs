@@ -0,0 +1,713 @@
+//! TypeScript client bindings generator
+//!
+//! Browser clients otherwise call methods by string with untyped params,
+//! which drifts from the Rust params structs over time. This module turns
+//! the OpenRPC document (see the `openrpc` module) into a TypeScript module
+//! containing:
+//!
+//! - **Types**: an interface or type alias per schema component
+//! - **Request functions**: one typed function per method
+//! - **Topic helpers**: typed subscribe/unsubscribe functions per registered [`Topic`]
+//! - **Error codes**: an `ErrorCode` enum with the JSON-RPC and custom codes
+//!
+//! Methods registered with `from_typed_fn_with_schema`, `#[handler]` or
+//! `#[rpc]` get precise types; untyped handlers fall back to `unknown`.
+//! Built-in `rpc.*` methods are left to the client library.
+//!
+//! Function names are the camelCased method names, with `_` appended to
+//! reserved words (`delete` becomes `delete_`) and prepended to names
+//! starting with a digit. Generation fails if two methods map to the same
+//! function name, such as `user.list` and `user_list`.
+//!
+//! # Transport
+//!
+//! Generated functions take any object with `request`, `subscribe` and
+//! `unsubscribe` methods (the `JrowTransport` interface), which the
+//! playground's `JrowClient` satisfies.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use jrow_core::Topic;
+//! use jrow_server::{from_typed_fn_with_schema, openrpc_document, OpenRpcInfo, Router, TypeScriptGenerator};
+//! use schemars::JsonSchema;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, JsonSchema)]
+//! struct AddParams { a: i32, b: i32 }
+//!
+//! #[derive(Serialize, JsonSchema)]
+//! struct OrderCreated { order_id: u64 }
+//!
+//! impl Topic for OrderCreated {
+//!     const TOPIC: &'static str = "orders.created";
+//! }
+//!
+//! let mut router = Router::new();
+//! router.register("math.add", from_typed_fn_with_schema(|p: AddParams| async move {
+//!     Ok(p.a + p.b)
+//! }));
+//!
+//! let document = openrpc_document(&OpenRpcInfo::new("Math", "1.0.0"), &router, false);
+//! let bindings = TypeScriptGenerator::new(document)
+//!     .topic::<OrderCreated>()
+//!     .error_code("Unauthorized", -32001)
+//!     .generate()
+//!     .unwrap();
+//!
+//! assert!(bindings.contains("export function mathAdd("));
+//! assert!(bindings.contains("export function subscribeOrderCreated("));
+//! assert!(bindings.contains("Unauthorized = -32001,"));
+//! ```
+
+use crate::openrpc::{schema_generator, to_value};
+use jrow_core::{Error, Result, Topic};
+use schemars::JsonSchema;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Prefix of schema references in OpenRPC documents
+const COMPONENT_PREFIX: &str = "#/components/schemas/";
+
+/// Standard JSON-RPC 2.0 error codes emitted in every `ErrorCode` enum
+const STANDARD_ERROR_CODES: [(&str, i32); 5] = [
+    ("ParseError", -32700),
+    ("InvalidRequest", -32600),
+    ("MethodNotFound", -32601),
+    ("InvalidParams", -32602),
+    ("InternalError", -32603),
+];
+
+/// Words TypeScript doesn't allow as function names
+const RESERVED_WORDS: [&str; 46] = [
+    "await", "break", "case", "catch", "class", "const", "continue", "debugger", "default",
+    "delete", "do", "else", "enum", "export", "extends", "false", "finally", "for", "function",
+    "if", "implements", "import", "in", "instanceof", "interface", "let", "new", "null",
+    "package", "private", "protected", "public", "return", "static", "super", "switch", "this",
+    "throw", "true", "try", "typeof", "var", "void", "while", "with", "yield",
+];
+
+/// Transport interface emitted at the top of every generated module
+const TRANSPORT_INTERFACE: &str = r#"/** Minimal client surface used by the generated functions. */
+export interface JrowTransport {
+  request(method: string, params?: unknown): Promise<unknown>;
+  subscribe(topic: string, handler: (params: any) => void): Promise<unknown>;
+  unsubscribe(topic: string): Promise<unknown>;
+}
+
+/** JSON-RPC error object rejected by failed requests. */
+export interface JsonRpcError {
+  code: ErrorCode | number;
+  message: string;
+  data?: unknown;
+}
+"#;
+
+/// A topic binding registered with the generator
+struct TopicBinding {
+    /// TypeScript name of the payload type
+    name: String,
+    /// Topic name or NATS pattern
+    topic: &'static str,
+}
+
+/// Generator for TypeScript client bindings
+///
+/// Built from an OpenRPC document, optionally extended with topic bindings
+/// and application error codes, then rendered with [`generate`](Self::generate).
+pub struct TypeScriptGenerator {
+    /// The OpenRPC document describing the server's methods
+    document: Value,
+    /// Extra schema components contributed by topic payload types
+    schemas: Map<String, Value>,
+    /// Topic bindings in registration order
+    topics: Vec<TopicBinding>,
+    /// Application-specific error codes
+    error_codes: Vec<(String, i32)>,
+}
+
+impl TypeScriptGenerator {
+    /// Create a generator from an OpenRPC document
+    pub fn new(document: Value) -> Self {
+        let schemas = document["components"]["schemas"]
+            .as_object()
+            .cloned()
+            .unwrap_or_default();
+
+        Self {
+            document,
+            schemas,
+            topics: Vec::new(),
+            error_codes: Vec::new(),
+        }
+    }
+
+    /// Add typed subscription helpers for a topic payload type
+    pub fn topic<T: Topic + JsonSchema>(mut self) -> Self {
+        let mut generator = schema_generator();
+        let root = generator.root_schema_for::<T>();
+
+        for (name, schema) in generator.take_definitions() {
+            self.schemas.insert(name, to_value(&schema));
+        }
+
+        let name = type_name(&T::schema_name());
+        let mut schema = to_value(&root.schema);
+        if let Some(object) = schema.as_object_mut() {
+            object.remove("title");
+        }
+        self.schemas.insert(name.clone(), schema);
+        self.topics.push(TopicBinding {
+            name,
+            topic: T::TOPIC,
+        });
+        self
+    }
+
+    /// Add an application error code to the `ErrorCode` enum
+    pub fn error_code(mut self, name: impl Into<String>, code: i32) -> Self {
+        self.error_codes.push((type_name(&name.into()), code));
+        self
+    }
+
+    /// Render the TypeScript module
+    ///
+    /// Returns `Error::InvalidRequest` if two methods map to the same
+    /// function name.
+    pub fn generate(&self) -> Result<String> {
+        let mut out = String::new();
+        let title = self.document["info"]["title"].as_str().unwrap_or("jrow API");
+        let version = self.document["info"]["version"].as_str().unwrap_or("");
+
+        let _ = writeln!(out, "// Generated by jrow from the OpenRPC document of {} {}.", title, version);
+        let _ = writeln!(out, "// Do not edit by hand; regenerate when the server API changes.");
+        let _ = writeln!(out, "/* eslint-disable */");
+        out.push('\n');
+
+        self.write_error_codes(&mut out);
+        out.push_str(TRANSPORT_INTERFACE);
+        self.write_schemas(&mut out);
+        self.write_methods(&mut out)?;
+        self.write_topics(&mut out);
+
+        Ok(out)
+    }
+
+    /// Render the module and write it to a file
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.generate()?).map_err(|e| Error::Io(e.to_string()))
+    }
+
+    /// Emit the `ErrorCode` enum
+    fn write_error_codes(&self, out: &mut String) {
+        out.push_str("/** JSON-RPC error codes returned by the server. */\n");
+        out.push_str("export enum ErrorCode {\n");
+        for (name, code) in STANDARD_ERROR_CODES {
+            let _ = writeln!(out, "  {} = {},", name, code);
+        }
+        for (name, code) in &self.error_codes {
+            let _ = writeln!(out, "  {} = {},", name, code);
+        }
+        out.push_str("}\n\n");
+    }
+
+    /// Emit one interface or type alias per schema component
+    fn write_schemas(&self, out: &mut String) {
+        let mut names: Vec<&String> = self.schemas.keys().collect();
+        names.sort();
+
+        for name in names {
+            let schema = &self.schemas[name];
+            out.push('\n');
+            write_doc(out, schema, "");
+            if is_object_schema(schema) {
+                let _ = writeln!(out, "export interface {} {}", type_name(name), object_body(schema, ""));
+            } else {
+                let _ = writeln!(out, "export type {} = {};", type_name(name), ts_type(schema));
+            }
+        }
+    }
+
+    /// Emit one request function per non-builtin method
+    fn write_methods(&self, out: &mut String) -> Result<()> {
+        let Some(methods) = self.document["methods"].as_array() else {
+            return Ok(());
+        };
+
+        let mut functions: HashMap<String, &str> = HashMap::new();
+        for method in methods {
+            let Some(name) = method["name"].as_str() else {
+                continue;
+            };
+            if name.starts_with("rpc.") {
+                continue;
+            }
+
+            let function = function_name(name);
+            if let Some(other) = functions.insert(function.clone(), name) {
+                return Err(Error::InvalidRequest(format!(
+                    "Methods '{}' and '{}' both map to the TypeScript function '{}'",
+                    other, name, function
+                )));
+            }
+            let result = ts_type(&method["result"]["schema"]);
+            let descriptors = method["params"].as_array().cloned().unwrap_or_default();
+            let structure = method["paramStructure"].as_str().unwrap_or("either");
+            // Methods taking named arguments by name or position are called
+            // by name; no descriptors, or a lone `params` one describing the
+            // whole params value, mean there are no named arguments
+            let opaque = descriptors.len() <= 1 && descriptors.iter().all(|d| d["name"] == "params");
+            let by_name = structure == "by-name" || (structure == "either" && !opaque);
+
+            let (signature, params) = match structure {
+                _ if by_name => {
+                    let interface = format!("{}Params", type_name(&function));
+                    out.push('\n');
+                    let _ = writeln!(out, "/** Params of `{}`. */", name);
+                    let _ = writeln!(out, "export interface {} {{", interface);
+                    for descriptor in &descriptors {
+                        write_doc(out, &descriptor["schema"], "  ");
+                        let optional = if descriptor["required"] == true { "" } else { "?" };
+                        let _ = writeln!(
+                            out,
+                            "  {}{}: {};",
+                            property_name(descriptor["name"].as_str().unwrap_or("_")),
+                            optional,
+                            ts_type(&descriptor["schema"])
+                        );
+                    }
+                    out.push_str("}\n");
+                    (format!(", params: {}", interface), "params".to_string())
+                }
+                "by-position" => {
+                    let args: Vec<String> = descriptors
+                        .iter()
+                        .map(|d| d["name"].as_str().unwrap_or("arg").to_string())
+                        .collect();
+                    let signature = descriptors
+                        .iter()
+                        .zip(&args)
+                        .map(|(d, arg)| format!(", {}: {}", arg, ts_type(&d["schema"])))
+                        .collect();
+                    (signature, format!("[{}]", args.join(", ")))
+                }
+                _ => match descriptors.first() {
+                    Some(descriptor) => {
+                        let optional = if descriptor["required"] == true { "" } else { "?" };
+                        (
+                            format!(", params{}: {}", optional, ts_type(&descriptor["schema"])),
+                            "params".to_string(),
+                        )
+                    }
+                    None => (String::new(), "null".to_string()),
+                },
+            };
+
+            out.push('\n');
+            let _ = writeln!(out, "/** Call `{}`. */", name);
+            let _ = writeln!(
+                out,
+                "export function {}(client: JrowTransport{}): Promise<{}> {{",
+                function, signature, result
+            );
+            let _ = writeln!(
+                out,
+                "  return client.request({}, {}) as Promise<{}>;",
+                Value::from(name),
+                params,
+                result
+            );
+            out.push_str("}\n");
+        }
+        Ok(())
+    }
+
+    /// Emit the topic constants and subscription helpers
+    fn write_topics(&self, out: &mut String) {
+        if self.topics.is_empty() {
+            return;
+        }
+
+        out.push_str("\n/** Topic names and patterns bound to payload types. */\n");
+        out.push_str("export const Topics = {\n");
+        for binding in &self.topics {
+            let _ = writeln!(out, "  {}: {},", binding.name, Value::from(binding.topic));
+        }
+        out.push_str("} as const;\n");
+
+        for binding in &self.topics {
+            let name = &binding.name;
+            let pattern = binding.topic.split('.').any(|t| t == "*" || t == ">");
+
+            out.push('\n');
+            let _ = writeln!(out, "/** Subscribe to `{}`. */", binding.topic);
+            if pattern {
+                // Pattern notifications arrive as {topic, data}
+                let _ = writeln!(
+                    out,
+                    "export function subscribe{name}(client: JrowTransport, handler: (message: {name}, topic: string) => void): Promise<unknown> {{"
+                );
+                let _ = writeln!(
+                    out,
+                    "  return client.subscribe(Topics.{name}, (params: {{ topic: string; data: {name} }}) => handler(params.data, params.topic));"
+                );
+            } else {
+                let _ = writeln!(
+                    out,
+                    "export function subscribe{name}(client: JrowTransport, handler: (message: {name}) => void): Promise<unknown> {{"
+                );
+                let _ = writeln!(out, "  return client.subscribe(Topics.{name}, handler);");
+            }
+            out.push_str("}\n\n");
+
+            let _ = writeln!(out, "/** Unsubscribe from `{}`. */", binding.topic);
+            let _ = writeln!(
+                out,
+                "export function unsubscribe{name}(client: JrowTransport): Promise<unknown> {{"
+            );
+            let _ = writeln!(out, "  return client.unsubscribe(Topics.{name});");
+            out.push_str("}\n");
+        }
+    }
+}
+
+/// Check whether a schema describes an object with named properties
+fn is_object_schema(schema: &Value) -> bool {
+    schema.get("properties").is_some_and(Value::is_object)
+}
+
+/// Render the `{ ... }` body of an object schema
+fn object_body(schema: &Value, indent: &str) -> String {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let mut body = String::from("{\n");
+    if let Some(properties) = schema["properties"].as_object() {
+        for (name, property) in properties {
+            let inner = format!("{}  ", indent);
+            write_doc(&mut body, property, &inner);
+            let optional = if required.contains(&name.as_str()) { "" } else { "?" };
+            let _ = writeln!(
+                body,
+                "{}{}{}: {};",
+                inner,
+                property_name(name),
+                optional,
+                ts_type_indented(property, &inner)
+            );
+        }
+    }
+    body.push_str(indent);
+    body.push('}');
+    body
+}
+
+/// Convert a JSON Schema into a TypeScript type expression
+fn ts_type(schema: &Value) -> String {
+    ts_type_indented(schema, "")
+}
+
+/// Convert a JSON Schema into a TypeScript type, indenting nested objects
+fn ts_type_indented(schema: &Value, indent: &str) -> String {
+    let object = match schema {
+        Value::Bool(true) => return "unknown".to_string(),
+        Value::Bool(false) => return "never".to_string(),
+        Value::Object(object) => object,
+        _ => return "unknown".to_string(),
+    };
+
+    if let Some(reference) = object.get("$ref").and_then(Value::as_str) {
+        return type_name(reference.trim_start_matches(COMPONENT_PREFIX));
+    }
+    if let Some(constant) = object.get("const") {
+        return constant.to_string();
+    }
+    if let Some(values) = object.get("enum").and_then(Value::as_array) {
+        return values.iter().map(Value::to_string).collect::<Vec<_>>().join(" | ");
+    }
+    for combinator in ["anyOf", "oneOf"] {
+        if let Some(variants) = object.get(combinator).and_then(Value::as_array) {
+            return union(variants.iter().map(|v| ts_type_indented(v, indent)));
+        }
+    }
+    if let Some(parts) = object.get("allOf").and_then(Value::as_array) {
+        let parts: Vec<String> = parts.iter().map(|v| ts_type_indented(v, indent)).collect();
+        return if parts.len() == 1 {
+            parts[0].clone()
+        } else {
+            parts.join(" & ")
+        };
+    }
+
+    match object.get("type") {
+        Some(Value::String(ty)) => primitive(ty, schema, indent),
+        Some(Value::Array(types)) => union(
+            types
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|ty| primitive(ty, schema, indent)),
+        ),
+        _ if is_object_schema(schema) => object_body(schema, indent),
+        _ => "unknown".to_string(),
+    }
+}
+
+/// Convert a single JSON Schema `type` into TypeScript
+fn primitive(ty: &str, schema: &Value, indent: &str) -> String {
+    match ty {
+        "string" => "string".to_string(),
+        "integer" | "number" => "number".to_string(),
+        "boolean" => "boolean".to_string(),
+        "null" => "null".to_string(),
+        "array" => match &schema["items"] {
+            Value::Array(items) => format!(
+                "[{}]",
+                items
+                    .iter()
+                    .map(|item| ts_type_indented(item, indent))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Value::Null => "unknown[]".to_string(),
+            item => wrap_array(ts_type_indented(item, indent)),
+        },
+        "object" if is_object_schema(schema) => object_body(schema, indent),
+        "object" => match schema.get("additionalProperties") {
+            Some(Value::Bool(false)) => "Record<string, never>".to_string(),
+            Some(value @ Value::Object(_)) => {
+                format!("Record<string, {}>", ts_type_indented(value, indent))
+            }
+            _ => "Record<string, unknown>".to_string(),
+        },
+        _ => "unknown".to_string(),
+    }
+}
+
+/// Join TypeScript types into a deduplicated union
+fn union(types: impl Iterator<Item = String>) -> String {
+    let mut seen: Vec<String> = Vec::new();
+    for ty in types {
+        if !seen.contains(&ty) {
+            seen.push(ty);
+        }
+    }
+    seen.join(" | ")
+}
+
+/// Wrap an element type as an array, parenthesizing unions
+fn wrap_array(element: String) -> String {
+    if element.contains(" | ") || element.contains(" & ") {
+        format!("({})[]", element)
+    } else {
+        format!("{}[]", element)
+    }
+}
+
+/// Emit a `/** */` comment from a schema's `description`, if any
+fn write_doc(out: &mut String, schema: &Value, indent: &str) {
+    if let Some(description) = schema.get("description").and_then(Value::as_str) {
+        let _ = writeln!(out, "{}/** {} */", indent, description.replace("*/", "*\\/").replace('\n', " "));
+    }
+}
+
+/// Quote a property name when it is not a valid identifier
+fn property_name(name: &str) -> String {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+
+    if valid {
+        name.to_string()
+    } else {
+        Value::from(name).to_string()
+    }
+}
+
+/// Split a name into words on separators and non-alphanumeric characters
+fn words(name: &str) -> impl Iterator<Item = &str> {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+}
+
+/// Convert a method name such as `user.create_account` into `userCreateAccount`
+///
+/// Reserved words get a `_` suffix and names starting with a digit a `_`
+/// prefix, so every name is a valid identifier.
+fn function_name(method: &str) -> String {
+    let pascal = type_name(method);
+    let mut chars = pascal.chars();
+    let name = match chars.next() {
+        Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+        None => "call".to_string(),
+    };
+    if RESERVED_WORDS.contains(&name.as_str()) {
+        name + "_"
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+/// Convert a name such as `Option_String` or `user.create` into a PascalCase type name
+fn type_name(name: &str) -> String {
+    words(name)
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(function_name("user.create_account"), "userCreateAccount");
+        assert_eq!(function_name("ping"), "ping");
+        assert_eq!(function_name("delete"), "delete_");
+        assert_eq!(function_name("new"), "new_");
+        assert_eq!(function_name("user.delete"), "userDelete");
+        assert_eq!(function_name("2fa.verify"), "_2faVerify");
+        assert_eq!(type_name("Option_String"), "OptionString");
+        assert_eq!(property_name("user_id"), "user_id");
+        assert_eq!(property_name("content-type"), "\"content-type\"");
+    }
+
+    #[test]
+    fn test_schema_types() {
+        assert_eq!(ts_type(&json!({"type": "integer"})), "number");
+        assert_eq!(ts_type(&json!({"type": ["string", "null"]})), "string | null");
+        assert_eq!(
+            ts_type(&json!({"type": "array", "items": {"$ref": "#/components/schemas/Point"}})),
+            "Point[]"
+        );
+        assert_eq!(
+            ts_type(&json!({"anyOf": [{"$ref": "#/components/schemas/Point"}, {"type": "null"}]})),
+            "Point | null"
+        );
+        assert_eq!(ts_type(&json!({"enum": ["a", "b"]})), "\"a\" | \"b\"");
+        assert_eq!(
+            ts_type(&json!({"type": "object", "additionalProperties": {"type": "boolean"}})),
+            "Record<string, boolean>"
+        );
+        assert_eq!(ts_type(&json!({})), "unknown");
+    }
+
+    #[test]
+    fn test_generate_methods() {
+        let document = json!({
+            "openrpc": "1.2.6",
+            "info": {"title": "Test", "version": "1.0.0"},
+            "methods": [
+                {
+                    "name": "user.create",
+                    "params": [
+                        {"name": "name", "required": true, "schema": {"type": "string"}},
+                        {"name": "age", "required": false, "schema": {"type": ["integer", "null"]}}
+                    ],
+                    "paramStructure": "by-name",
+                    "result": {"name": "result", "schema": {"$ref": "#/components/schemas/User"}}
+                },
+                {
+                    "name": "ping",
+                    "params": [],
+                    "paramStructure": "either",
+                    "result": {"name": "result", "schema": {"type": "string"}}
+                },
+                {
+                    "name": "user.rename",
+                    "params": [
+                        {"name": "id", "required": true, "schema": {"type": "integer"}},
+                        {"name": "name", "required": true, "schema": {"type": "string"}}
+                    ],
+                    "paramStructure": "either",
+                    "result": {"name": "result", "schema": {"type": "boolean"}}
+                },
+                {
+                    "name": "rpc.subscribe",
+                    "params": [],
+                    "paramStructure": "by-name",
+                    "result": {"name": "result", "schema": {}}
+                }
+            ],
+            "components": {"schemas": {
+                "User": {
+                    "type": "object",
+                    "properties": {"name": {"type": "string", "description": "Display name"}},
+                    "required": ["name"]
+                }
+            }}
+        });
+
+        let output = TypeScriptGenerator::new(document).generate().unwrap();
+
+        assert!(output.contains("export interface User {\n  /** Display name */\n  name: string;\n}"));
+        assert!(output.contains("export interface UserCreateParams {\n  name: string;\n  age?: number | null;\n}"));
+        assert!(output.contains(
+            "export function userCreate(client: JrowTransport, params: UserCreateParams): Promise<User> {"
+        ));
+        assert!(output.contains("export function ping(client: JrowTransport): Promise<string> {"));
+        assert!(output.contains("export interface UserRenameParams {\n  id: number;\n  name: string;\n}"));
+        assert!(output.contains("return client.request(\"ping\", null) as Promise<string>;"));
+        assert!(!output.contains("rpcSubscribe"));
+        assert!(output.contains("MethodNotFound = -32601,"));
+    }
+
+    #[test]
+    fn test_reserved_and_colliding_names() {
+        let method = |name: &str| {
+            json!({"name": name, "params": [], "paramStructure": "either", "result": {"name": "result", "schema": {}}})
+        };
+        let document = json!({"info": {"title": "Test", "version": "1"}, "methods": [method("delete"), method("default")]});
+        let output = TypeScriptGenerator::new(document).generate().unwrap();
+        assert!(output.contains("export function delete_(client: JrowTransport): Promise<unknown> {"));
+        assert!(output.contains("return client.request(\"delete\", null) as Promise<unknown>;"));
+        assert!(output.contains("export function default_(client: JrowTransport)"));
+
+        let document = json!({"info": {"title": "Test", "version": "1"}, "methods": [method("user.list"), method("user_list")]});
+        let err = TypeScriptGenerator::new(document).generate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid request: Methods 'user.list' and 'user_list' both map to the TypeScript function 'userList'"
+        );
+    }
+
+    #[test]
+    fn test_generate_topics() {
+        #[derive(JsonSchema)]
+        #[allow(dead_code)]
+        struct UserEvent {
+            user_id: String,
+        }
+
+        impl Topic for UserEvent {
+            const TOPIC: &'static str = "users.*.events";
+        }
+
+        let document = json!({"info": {"title": "Test", "version": "1"}, "methods": []});
+        let output = TypeScriptGenerator::new(document)
+            .topic::<UserEvent>()
+            .generate()
+            .unwrap();
+
+        assert!(output.contains("export interface UserEvent {\n  user_id: string;\n}"));
+        assert!(output.contains("  UserEvent: \"users.*.events\","));
+        assert!(output.contains(
+            "export function subscribeUserEvent(client: JrowTransport, handler: (message: UserEvent, topic: string) => void): Promise<unknown> {"
+        ));
+        assert!(output.contains("export function unsubscribeUserEvent(client: JrowTransport): Promise<unknown> {"));
+    }
+}
@@ -0,0 +1,350 @@
+//! JSON Schema validation of method params
+//!
+//! Handlers normally reject bad input when deserialization fails, which
+//! produces a serde error string describing Rust types. Validating params
+//! against a JSON Schema before the handler runs gives every client, Rust or
+//! not, the same structured error.
+//!
+//! # Error Format
+//!
+//! A request whose params violate the schema is rejected with `-32602` and a
+//! `data` array listing every violation:
+//!
+//! ```json
+//! {
+//!   "code": -32602,
+//!   "message": "Invalid params",
+//!   "data": [
+//!     { "pointer": "/age", "keyword": "minimum", "message": "-1 is less than the minimum of 0" },
+//!     { "pointer": "", "keyword": "required", "message": "\"name\" is a required property" }
+//!   ]
+//! }
+//! ```
+//!
+//! `pointer` is the JSON pointer (RFC 6901) of the offending value within
+//! `params`; the empty pointer refers to `params` itself.
+//!
+//! # Attaching Schemas
+//!
+//! - `Router::register_with_schema(method, schema, handler)` validates
+//!   against a hand-written schema
+//! - `validated(handler)` validates against the schema a typed handler
+//!   already carries (what `#[handler(validate)]` generates)
+//!
+//! # Examples
+//!
+//! ```rust
+//! use jrow_server::{from_fn, Router};
+//! use serde_json::json;
+//!
+//! let mut router = Router::new();
+//! router
+//!     .register_with_schema(
+//!         "greet",
+//!         json!({
+//!             "type": "object",
+//!             "properties": { "name": { "type": "string" } },
+//!             "required": ["name"]
+//!         }),
+//!         from_fn(|params| async move { Ok(params.unwrap_or_default()) }),
+//!     )
+//!     .unwrap();
+//! ```
+
+use crate::handler::{Handler, HandlerResult};
+use crate::openrpc::MethodSchema;
+use jrow_core::{Error, JsonRpcErrorData, Result};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Compiled JSON Schema for a method's params
+///
+/// Compilation happens once, when the validator is created; validating a
+/// request only walks the compiled schema.
+#[derive(Clone)]
+pub struct ParamsValidator {
+    /// The schema as given, reported in discovery documents
+    schema: Value,
+    /// Compiled form of `schema`
+    validator: Arc<jsonschema::Validator>,
+}
+
+impl ParamsValidator {
+    /// Compile a params schema
+    ///
+    /// Returns `Error::InvalidRequest` if `schema` is not a valid JSON Schema.
+    pub fn new(schema: Value) -> Result<Self> {
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|e| Error::InvalidRequest(format!("invalid params schema: {}", e)))?;
+        Ok(Self {
+            schema,
+            validator: Arc::new(validator),
+        })
+    }
+
+    /// Compile the params schema of a typed handler
+    ///
+    /// The referenced definitions are placed where the schema's
+    /// `#/components/schemas/...` references expect them. Typed handler
+    /// schemas are generated as draft-07 (tuples use the array form of
+    /// `items`), so they are compiled as draft-07 regardless of `$schema`.
+    pub fn from_method_schema(schema: &MethodSchema) -> Result<Self> {
+        let mut root = schema.params.clone();
+        if !schema.definitions.is_empty() {
+            if let Some(object) = root.as_object_mut() {
+                object.insert(
+                    "components".to_string(),
+                    json!({ "schemas": schema.definitions }),
+                );
+            }
+        }
+
+        let validator = jsonschema::draft7::new(&root)
+            .map_err(|e| Error::InvalidRequest(format!("invalid params schema: {}", e)))?;
+        Ok(Self {
+            schema: schema.params.clone(),
+            validator: Arc::new(validator),
+        })
+    }
+
+    /// The schema this validator checks against
+    pub fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    /// Validate params, returning an invalid params error listing every violation
+    ///
+    /// Omitted params are validated as `null`.
+    pub fn validate(&self, params: Option<&Value>) -> Result<()> {
+        let params = params.unwrap_or(&Value::Null);
+
+        let violations: Vec<Value> = self
+            .validator
+            .iter_errors(params)
+            .map(|error| {
+                // The last schema path segment is the keyword that failed
+                let schema_path = error.schema_path.to_string();
+                let keyword = schema_path.rsplit('/').next().unwrap_or_default().to_string();
+                json!({
+                    "pointer": error.instance_path.to_string(),
+                    "keyword": keyword,
+                    "message": error.to_string(),
+                })
+            })
+            .collect();
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::JsonRpc(JsonRpcErrorData::with_data(
+                -32602,
+                "Invalid params",
+                Value::Array(violations),
+            )))
+        }
+    }
+}
+
+impl std::fmt::Debug for ParamsValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParamsValidator")
+            .field("schema", &self.schema)
+            .finish()
+    }
+}
+
+/// Validate params against `validator` before running `handler`
+///
+/// The wrapped handler reports `validator`'s schema as its params schema
+/// for discovery, keeping the result schema of `handler` if it has one.
+pub fn with_validation(handler: Box<dyn Handler>, validator: ParamsValidator) -> Box<dyn Handler> {
+    Box::new(ValidatingHandler {
+        inner: handler,
+        validator,
+    })
+}
+
+/// Validate params against the schema the handler already carries
+///
+/// Handlers without a schema (`from_fn`, `from_typed_fn`) are returned
+/// unchanged. This is what `#[handler(validate)]` generates.
+///
+/// Returns `Error::InvalidRequest` if the handler's schema does not compile
+/// (see `ParamsValidator::from_method_schema`).
+pub fn validated(handler: Box<dyn Handler>) -> Result<Box<dyn Handler>> {
+    match handler.schema() {
+        Some(schema) => {
+            let validator = ParamsValidator::from_method_schema(&schema)?;
+            Ok(with_validation(handler, validator))
+        }
+        None => Ok(handler),
+    }
+}
+
+/// Handler wrapper that validates params before calling another handler
+struct ValidatingHandler {
+    inner: Box<dyn Handler>,
+    validator: ParamsValidator,
+}
+
+impl Handler for ValidatingHandler {
+    fn handle(&self, params: Option<Value>) -> HandlerResult {
+        if let Err(err) = self.validator.validate(params.as_ref()) {
+            return Box::pin(async move { Err(err) });
+        }
+        self.inner.handle(params)
+    }
+
+    fn schema(&self) -> Option<MethodSchema> {
+        let mut schema = self.inner.schema().unwrap_or_else(|| MethodSchema {
+            params: json!({}),
+            result: json!({}),
+            definitions: Default::default(),
+            positional: None,
+        });
+        schema.params = self.validator.schema().clone();
+        Some(schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::{from_fn, from_typed_fn_with_schema};
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    fn violations(err: Error) -> Vec<Value> {
+        match err {
+            Error::JsonRpc(data) => {
+                assert_eq!(data.code, -32602);
+                data.data.unwrap().as_array().unwrap().clone()
+            }
+            other => panic!("expected JSON-RPC error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_invalid_schema_rejected() {
+        let err = ParamsValidator::new(json!({"type": 42})).unwrap_err();
+        assert!(matches!(err, Error::InvalidRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_reports_every_violation() {
+        let validator = ParamsValidator::new(json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "integer", "minimum": 0 }
+            },
+            "required": ["name", "age"]
+        }))
+        .unwrap();
+
+        let handler = with_validation(
+            from_fn(|params| async move { Ok(params.unwrap_or_default()) }),
+            validator,
+        );
+
+        let ok = handler
+            .handle(Some(json!({"name": "Ada", "age": 36})))
+            .await
+            .unwrap();
+        assert_eq!(ok["name"], "Ada");
+
+        let mut found = violations(handler.handle(Some(json!({"age": -1}))).await.unwrap_err());
+        found.sort_by_key(|v| v["pointer"].as_str().unwrap().to_string());
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0]["pointer"], "");
+        assert_eq!(found[0]["keyword"], "required");
+        assert_eq!(found[1]["pointer"], "/age");
+        assert_eq!(found[1]["keyword"], "minimum");
+
+        let found = violations(handler.handle(None).await.unwrap_err());
+        assert_eq!(found[0]["keyword"], "type");
+    }
+
+    #[tokio::test]
+    async fn test_validated_uses_handler_schema() {
+        #[derive(Deserialize, JsonSchema)]
+        struct Address {
+            city: String,
+        }
+
+        #[derive(Deserialize, JsonSchema)]
+        struct Params {
+            address: Address,
+        }
+
+        let handler = validated(from_typed_fn_with_schema(|p: Params| async move {
+            Ok(p.address.city)
+        }))
+        .unwrap();
+
+        let result = handler
+            .handle(Some(json!({"address": {"city": "Oslo"}})))
+            .await
+            .unwrap();
+        assert_eq!(result, json!("Oslo"));
+
+        let found = violations(
+            handler
+                .handle(Some(json!({"address": {"city": 7}})))
+                .await
+                .unwrap_err(),
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0]["pointer"], "/address/city");
+    }
+
+    #[tokio::test]
+    async fn test_validated_tuple_params() {
+        let handler = validated(from_typed_fn_with_schema(|(name, times): (String, u32)| async move {
+            Ok(name.repeat(times as usize))
+        }))
+        .unwrap();
+
+        let result = handler.handle(Some(json!(["ab", 2]))).await.unwrap();
+        assert_eq!(result, json!("abab"));
+
+        let found = violations(handler.handle(Some(json!(["ab", "x"]))).await.unwrap_err());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0]["pointer"], "/1");
+    }
+
+    #[tokio::test]
+    async fn test_validated_optional_field() {
+        #[derive(Deserialize, JsonSchema)]
+        struct Params {
+            name: String,
+            nickname: Option<String>,
+        }
+
+        let handler = validated(from_typed_fn_with_schema(|p: Params| async move {
+            Ok(p.nickname.unwrap_or(p.name))
+        }))
+        .unwrap();
+
+        let result = handler.handle(Some(json!({"name": "Ada"}))).await.unwrap();
+        assert_eq!(result, json!("Ada"));
+        let result = handler
+            .handle(Some(json!({"name": "Ada", "nickname": null})))
+            .await
+            .unwrap();
+        assert_eq!(result, json!("Ada"));
+        let result = handler
+            .handle(Some(json!({"name": "Ada", "nickname": "Lovelace"})))
+            .await
+            .unwrap();
+        assert_eq!(result, json!("Lovelace"));
+
+        let found = violations(
+            handler
+                .handle(Some(json!({"name": "Ada", "nickname": 1})))
+                .await
+                .unwrap_err(),
+        );
+        assert_eq!(found[0]["pointer"], "/nickname");
+    }
+}
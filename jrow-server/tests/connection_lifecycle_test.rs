@@ -105,3 +105,72 @@ async fn test_connection_batch_processing() {
     // Placeholder for now
 }
 
+
+#[tokio::test]
+async fn test_connection_lifecycle_hooks() {
+    let (connect_tx, mut connects) = tokio::sync::mpsc::unbounded_channel();
+    let (disconnect_tx, mut disconnects) = tokio::sync::mpsc::unbounded_channel();
+
+    let builder = JrowServer::builder().bind_str("127.0.0.1:0").unwrap();
+    let identities = builder.identities();
+    let server = builder
+        .handler("login", from_fn(|_| async { Ok(serde_json::json!(true)) }))
+        .on_connect(move |info| {
+            let connect_tx = connect_tx.clone();
+            async move {
+                connect_tx.send(info.clone()).ok();
+                if info.handshake.path.contains("deny") {
+                    return Err(jrow_core::Error::InvalidRequest("not allowed".to_string()));
+                }
+                Ok(())
+            }
+        })
+        .on_disconnect(move |info, reason| {
+            let disconnect_tx = disconnect_tx.clone();
+            async move {
+                disconnect_tx.send((info, reason)).ok();
+            }
+        })
+        .build()
+        .await
+        .unwrap();
+    let server_addr = server.local_addr().unwrap();
+    tokio::spawn(async move {
+        let _ = server.run().await;
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let client = JrowClient::connect(&format!("ws://{}/chat?room=1", server_addr))
+        .await
+        .unwrap();
+    let info = connects.recv().await.unwrap();
+    assert_eq!(info.handshake.path, "/chat?room=1");
+    assert_eq!(info.handshake.header("Host"), Some(server_addr.to_string().as_str()));
+    assert!(info.peer_addr.ip().is_loopback());
+    assert_eq!(info.identity, None);
+
+    let _: bool = client.request("login", ()).await.unwrap();
+    identities.bind(info.id, "alice");
+    client.disconnect().await;
+
+    let (closed, reason) = tokio::time::timeout(Duration::from_secs(2), disconnects.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(closed.id, info.id);
+    assert_eq!(closed.identity.as_deref(), Some("alice"));
+    assert_eq!(reason, jrow_server::DisconnectReason::ClientClosed);
+
+    // Rejected connections are closed before handling any request
+    let rejected = JrowClient::connect(&format!("ws://{}/deny", server_addr))
+        .await
+        .unwrap();
+    assert!(connects.recv().await.unwrap().handshake.path.ends_with("deny"));
+    let result: jrow_core::Result<bool> =
+        tokio::time::timeout(Duration::from_secs(2), rejected.request("login", ()))
+            .await
+            .unwrap();
+    assert!(result.is_err());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(disconnects.try_recv().is_err());
+}
@@ -0,0 +1,157 @@
+//! Integration tests for gateway mode
+
+use jrow_client::JrowClient;
+use jrow_server::{from_fn, JrowServer, Upstream};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+async fn start(server: JrowServer) -> (Arc<JrowServer>, String) {
+    let addr = server.local_addr().unwrap();
+    let server = Arc::new(server);
+    let running = Arc::clone(&server);
+    tokio::spawn(async move {
+        running.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    (server, format!("ws://{}", addr))
+}
+
+async fn upstream(name: &'static str) -> (Arc<JrowServer>, String) {
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .handler(
+            format!("{}.whoami", name),
+            from_fn(move |_| async move { Ok(json!(name)) }),
+        )
+        .handler("whoami", from_fn(move |_| async move { Ok(json!(name)) }))
+        .build()
+        .await
+        .unwrap();
+    start(server).await
+}
+
+#[tokio::test]
+async fn test_gateway_forwards_by_prefix() {
+    let (_users, users_url) = upstream("users").await;
+    let (_billing, billing_url) = upstream("billing").await;
+
+    let gateway = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .handler("local", from_fn(|_| async { Ok(json!("gateway")) }))
+        .upstream("users", Upstream::new(users_url))
+        .upstream("billing", Upstream::new(billing_url).strip_prefix())
+        .build()
+        .await
+        .unwrap();
+    let (_gateway, gateway_url) = start(gateway).await;
+
+    let client = JrowClient::connect(&gateway_url).await.unwrap();
+    let users: String = client.request("users.whoami", ()).await.unwrap();
+    assert_eq!(users, "users");
+    let billing: String = client.request("billing.whoami", ()).await.unwrap();
+    assert_eq!(billing, "billing");
+    let local: String = client.request("local", ()).await.unwrap();
+    assert_eq!(local, "gateway");
+
+    // Upstream errors are passed through unchanged
+    match client.request::<_, serde_json::Value>("users.missing", ()).await {
+        Err(jrow_core::Error::JsonRpc(error)) => assert_eq!(error.code, -32601),
+        other => panic!("expected method not found, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_gateway_relays_subscriptions() {
+    let (users, users_url) = upstream("users").await;
+
+    let gateway = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .upstream("users", Upstream::new(users_url))
+        .build()
+        .await
+        .unwrap();
+    let (_gateway, gateway_url) = start(gateway).await;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let subscriber = JrowClient::connect(&gateway_url).await.unwrap();
+    subscriber
+        .subscribe("users.events", move |data| {
+            let tx = tx.clone();
+            async move {
+                tx.send(data).ok();
+            }
+        })
+        .await
+        .unwrap();
+
+    // A second downstream connection must not receive the first one's messages
+    let (other_tx, mut other_rx) = mpsc::unbounded_channel();
+    let other = JrowClient::connect(&gateway_url).await.unwrap();
+    other
+        .on_unhandled_notification(move |notification| {
+            let tx = other_tx.clone();
+            async move {
+                tx.send(notification).ok();
+            }
+        })
+        .await;
+    let _: String = other.request("users.whoami", ()).await.unwrap();
+
+    assert_eq!(users.publish("users.events", json!({"id": 1})).await.unwrap(), 1);
+    let data = tokio::time::timeout(Duration::from_secs(2), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(data, json!({"id": 1}));
+    assert!(other_rx.try_recv().is_err());
+
+    subscriber.unsubscribe("users.events").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(users.publish("users.events", json!({"id": 2})).await.unwrap(), 0);
+
+    // Closing the downstream connection closes its upstream connections
+    subscriber.subscribe("users.events", |_| async {}).await.unwrap();
+    assert_eq!(users.publish("users.events", json!({"id": 3})).await.unwrap(), 1);
+    subscriber.disconnect().await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(users.publish("users.events", json!({"id": 4})).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_gateway_prefixes_pattern_topics() {
+    let (billing, billing_url) = upstream("billing").await;
+
+    let gateway = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .upstream("billing", Upstream::new(billing_url).strip_prefix())
+        .build()
+        .await
+        .unwrap();
+    let (_gateway, gateway_url) = start(gateway).await;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let subscriber = JrowClient::connect(&gateway_url).await.unwrap();
+    subscriber
+        .subscribe("billing.invoices.*", move |data| {
+            let tx = tx.clone();
+            async move {
+                tx.send(data).ok();
+            }
+        })
+        .await
+        .unwrap();
+
+    // The upstream publishes without the prefix the gateway stripped
+    assert_eq!(billing.publish("invoices.paid", json!({"id": 1})).await.unwrap(), 1);
+    let data = tokio::time::timeout(Duration::from_secs(2), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(data, json!({"topic": "billing.invoices.paid", "data": {"id": 1}}));
+}
@@ -0,0 +1,212 @@
+//! Integration tests for the `#[handler]` attribute macro
+
+use jrow_core::{Error, Result};
+use jrow_macros::handler;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize, JsonSchema)]
+struct AddParams {
+    a: i32,
+    b: i32,
+}
+
+#[handler]
+async fn add(params: AddParams) -> Result<i32> {
+    Ok(params.a + params.b)
+}
+
+#[handler]
+async fn ping() -> Result<String> {
+    Ok("pong".to_string())
+}
+
+#[handler]
+async fn scale(value: i32, #[default(1)] factor: i32) -> Result<i32> {
+    Ok(value * factor)
+}
+
+#[handler]
+async fn greet(name: String, greeting: Option<String>, #[default] excited: bool) -> Result<String> {
+    let greeting = greeting.unwrap_or_else(|| "Hello".to_string());
+    Ok(format!("{}, {}{}", greeting, name, if excited { "!" } else { "." }))
+}
+
+#[handler]
+async fn page(#[default(10)] size: u32) -> Result<u32> {
+    Ok(size)
+}
+
+#[handler]
+async fn tag(label: Option<String>) -> Result<String> {
+    Ok(label.unwrap_or_else(|| "none".to_string()))
+}
+
+#[handler(args)]
+async fn square(value: i64) -> Result<i64> {
+    Ok(value * value)
+}
+
+fn invalid_params(err: Error) -> String {
+    match err {
+        Error::InvalidParams(msg) => msg,
+        other => panic!("expected invalid params, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_whole_params_handlers() {
+    let result = add().handle(Some(json!({"a": 2, "b": 3}))).await.unwrap();
+    assert_eq!(result, json!(5));
+
+    let result = ping().handle(None).await.unwrap();
+    assert_eq!(result, json!("pong"));
+}
+
+#[tokio::test]
+async fn test_named_and_positional_args() {
+    let handler = scale();
+    assert_eq!(handler.handle(Some(json!({"value": 4, "factor": 3}))).await.unwrap(), json!(12));
+    assert_eq!(handler.handle(Some(json!([4, 3]))).await.unwrap(), json!(12));
+    assert_eq!(handler.handle(Some(json!([4]))).await.unwrap(), json!(4));
+    assert_eq!(handler.handle(Some(json!({"value": 4}))).await.unwrap(), json!(4));
+
+    let handler = square();
+    assert_eq!(handler.handle(Some(json!([7]))).await.unwrap(), json!(49));
+    assert_eq!(handler.handle(Some(json!({"value": 7}))).await.unwrap(), json!(49));
+}
+
+#[tokio::test]
+async fn test_optional_and_default_args() {
+    let handler = greet();
+    assert_eq!(
+        handler.handle(Some(json!({"name": "Ada"}))).await.unwrap(),
+        json!("Hello, Ada.")
+    );
+    assert_eq!(
+        handler.handle(Some(json!(["Ada", "Hi", true]))).await.unwrap(),
+        json!("Hi, Ada!")
+    );
+    assert_eq!(
+        handler.handle(Some(json!(["Ada", null, true]))).await.unwrap(),
+        json!("Hello, Ada!")
+    );
+}
+
+#[tokio::test]
+async fn test_single_omittable_arg_is_named() {
+    assert_eq!(page().handle(None).await.unwrap(), json!(10));
+    assert_eq!(page().handle(Some(json!({}))).await.unwrap(), json!(10));
+    assert_eq!(page().handle(Some(json!({"size": 5}))).await.unwrap(), json!(5));
+    assert_eq!(page().handle(Some(json!([5]))).await.unwrap(), json!(5));
+
+    assert_eq!(tag().handle(None).await.unwrap(), json!("none"));
+    assert_eq!(tag().handle(Some(json!({"label": "x"}))).await.unwrap(), json!("x"));
+}
+
+#[tokio::test]
+async fn test_invalid_params_name_the_field() {
+    let msg = invalid_params(scale().handle(Some(json!([4, "x"]))).await.unwrap_err());
+    assert!(msg.starts_with("invalid params at `factor`"), "{}", msg);
+
+    let msg = invalid_params(add().handle(Some(json!({"a": 1, "b": false}))).await.unwrap_err());
+    assert!(msg.starts_with("invalid params at `b`"), "{}", msg);
+
+    let msg = invalid_params(greet().handle(None).await.unwrap_err());
+    assert!(msg.contains("missing field `name`"), "{}", msg);
+
+    let msg = invalid_params(scale().handle(Some(json!([1, 2, 3]))).await.unwrap_err());
+    assert_eq!(msg, "expected at most 2 positional params, got 3");
+
+    let msg = invalid_params(scale().handle(Some(json!(5))).await.unwrap_err());
+    assert_eq!(msg, "params must be an object or an array");
+}
+
+#[test]
+fn test_args_schema() {
+    let schema = greet().schema().unwrap();
+    assert_eq!(schema.params["title"], json!("GreetArgs"));
+    assert_eq!(schema.params["required"], json!(["name"]));
+    assert!(schema.params["properties"]["greeting"].is_object());
+    assert!(schema.params["properties"]["excited"].is_object());
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct SignupParams {
+    #[schemars(length(min = 1))]
+    name: String,
+    #[schemars(range(min = 13))]
+    age: u32,
+}
+
+#[handler(validate)]
+async fn signup(params: SignupParams) -> Result<String> {
+    Ok(format!("{} ({})", params.name, params.age))
+}
+
+#[handler(validate)]
+async fn resize(width: u32, #[default(1)] height: u32) -> Result<u32> {
+    Ok(width * height)
+}
+
+#[handler(validate)]
+async fn label(pair: (String, u32)) -> Result<String> {
+    Ok(format!("{}#{}", pair.0, pair.1))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct RenameParams {
+    name: String,
+    nickname: Option<String>,
+}
+
+#[handler(validate)]
+async fn rename(params: RenameParams) -> Result<String> {
+    Ok(params.nickname.unwrap_or(params.name))
+}
+
+fn violation_pointers(err: Error) -> Vec<String> {
+    match err {
+        Error::JsonRpc(error) => {
+            assert_eq!(error.code, -32602);
+            let mut pointers: Vec<String> = error
+                .data
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["pointer"].as_str().unwrap().to_string())
+                .collect();
+            pointers.sort();
+            pointers
+        }
+        other => panic!("expected JSON-RPC error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_validated_handlers() {
+    let result = signup().handle(Some(json!({"name": "Ada", "age": 36}))).await.unwrap();
+    assert_eq!(result, json!("Ada (36)"));
+
+    let err = signup().handle(Some(json!({"name": "", "age": 7}))).await.unwrap_err();
+    assert_eq!(violation_pointers(err), vec!["/age", "/name"]);
+
+    assert_eq!(resize().handle(Some(json!([3, 4]))).await.unwrap(), json!(12));
+    assert_eq!(resize().handle(Some(json!({"width": 3}))).await.unwrap(), json!(3));
+
+    let err = resize().handle(Some(json!([3, "tall"]))).await.unwrap_err();
+    assert_eq!(violation_pointers(err), vec!["/height"]);
+
+    assert_eq!(label().handle(Some(json!(["v", 2]))).await.unwrap(), json!("v#2"));
+    let err = label().handle(Some(json!([2, "v"]))).await.unwrap_err();
+    assert_eq!(violation_pointers(err), vec!["/0", "/1"]);
+
+    assert_eq!(rename().handle(Some(json!({"name": "Ada"}))).await.unwrap(), json!("Ada"));
+    let err = rename()
+        .handle(Some(json!({"name": "Ada", "nickname": 1})))
+        .await
+        .unwrap_err();
+    assert_eq!(violation_pointers(err), vec!["/nickname"]);
+}
@@ -342,3 +342,162 @@ async fn test_multiple_topics() {
     assert_eq!(received_b.lock().await.len(), 3);
 }
 
+
+#[tokio::test]
+async fn test_mixed_batch() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let db_path = temp_dir.path().join("test_batch.db");
+
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .with_persistent_storage(&db_path)
+        .register_topic("orders", RetentionPolicy::unlimited())
+        .handler("echo", jrow_server::from_fn(|params| async move {
+            Ok(params.unwrap_or_default())
+        }))
+        .build()
+        .await
+        .unwrap();
+
+    let addr = server.local_addr().unwrap();
+    let server = Arc::new(server);
+    let server_clone = Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    for i in 1..=2 {
+        server
+            .publish_persistent("orders", serde_json::json!({ "value": i }))
+            .await
+            .unwrap();
+    }
+
+    let client = JrowClient::connect(&format!("ws://{}", addr))
+        .await
+        .unwrap();
+
+    // Pattern and persistent subscriptions alongside an app method
+    let mut batch = jrow_client::BatchRequest::new();
+    let pattern_id = batch.add_request("rpc.subscribe", serde_json::json!({"topic": "events.*"}));
+    let persistent_id = batch.add_request(
+        "rpc.subscribe_persistent",
+        serde_json::json!({"subscription_id": "batch_sub", "topic": "orders"}),
+    );
+    let echo_id = batch.add_request("echo", serde_json::json!({"value": 7}));
+    let responses = client.batch(batch).await.unwrap();
+
+    assert!(responses.all_success(), "{:?}", responses.errors());
+    let pattern: serde_json::Value = responses.get(&pattern_id).unwrap();
+    assert_eq!(pattern["pattern"], true);
+    let persistent: serde_json::Value = responses.get(&persistent_id).unwrap();
+    assert_eq!(persistent["undelivered_count"], 2);
+    let echo: serde_json::Value = responses.get(&echo_id).unwrap();
+    assert_eq!(echo, serde_json::json!({"value": 7}));
+
+    assert_eq!(server.publish("events.created", serde_json::json!({})).await.unwrap(), 1);
+
+    // Acknowledging in a later batch moves the subscription forward
+    let mut batch = jrow_client::BatchRequest::new();
+    batch.add_request(
+        "rpc.ack_persistent",
+        serde_json::json!({"subscription_id": "batch_sub", "sequence_id": 2}),
+    );
+    batch.add_request("rpc.unsubscribe_persistent", serde_json::json!({"subscription_id": "batch_sub"}));
+    let responses = client.batch(batch).await.unwrap();
+    assert!(responses.all_success(), "{:?}", responses.errors());
+
+    let state = server
+        .persistent_storage()
+        .unwrap()
+        .get_subscription_state("batch_sub")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(state.last_ack_seq, 2);
+}
+
+#[tokio::test]
+async fn test_client_publish_with_acl() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let db_path = temp_dir.path().join("test_client_publish.db");
+
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .with_persistent_storage(&db_path)
+        .register_topic("orders", RetentionPolicy::unlimited())
+        .publish_acl(|attempt| async move { attempt.topic.starts_with("orders") })
+        .build()
+        .await
+        .unwrap();
+
+    let addr = server.local_addr().unwrap();
+    let server = Arc::new(server);
+    let server_clone = Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let url = format!("ws://{}", addr);
+    let subscriber = JrowClient::connect(&url).await.unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_clone = Arc::clone(&received);
+    subscriber
+        .subscribe("orders", move |msg| {
+            let received = Arc::clone(&received_clone);
+            async move {
+                received.lock().await.push(msg);
+            }
+        })
+        .await
+        .unwrap();
+
+    let publisher = JrowClient::connect(&url).await.unwrap();
+    assert_eq!(publisher.publish("orders", serde_json::json!({"value": 1})).await.unwrap(), 1);
+    assert_eq!(
+        publisher.publish_persistent("orders", serde_json::json!({"value": 2})).await.unwrap(),
+        1
+    );
+    assert_eq!(
+        publisher.publish_persistent("orders", serde_json::json!({"value": 3})).await.unwrap(),
+        2
+    );
+
+    // Topics the ACL denies fail, including topic requests
+    assert!(publisher.publish("admin", serde_json::json!({})).await.is_err());
+    assert!(publisher.publish_persistent("admin", serde_json::json!({})).await.is_err());
+    assert!(publisher
+        .request_topic("admin", serde_json::json!({}), Duration::from_secs(1))
+        .await
+        .is_err());
+
+    // In a batch, only the denied messages fail
+    let mut batch = jrow_client::BatchRequest::new();
+    let publish_id = batch.add_request(
+        "rpc.publish_batch",
+        serde_json::json!([{"topic": "orders", "data": {"value": 4}}, {"topic": "admin"}]),
+    );
+    let persistent_id = batch.add_request(
+        "rpc.publish_persistent_batch",
+        serde_json::json!([{"topic": "orders", "data": {"value": 5}}]),
+    );
+    let responses = publisher.batch(batch).await.unwrap();
+    assert!(responses.all_success(), "{:?}", responses.errors());
+    let published: serde_json::Value = responses.get(&publish_id).unwrap();
+    assert_eq!(published[0]["success"], true);
+    assert_eq!(published[0]["delivered"], 1);
+    assert_eq!(published[1]["success"], false);
+    let persisted: serde_json::Value = responses.get(&persistent_id).unwrap();
+    assert_eq!(persisted[0]["sequence_id"], 3);
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let received = received.lock().await;
+    let values: Vec<u64> = received.iter().filter_map(|msg| msg["value"].as_u64()).collect();
+    assert_eq!(values, vec![1, 4]);
+}
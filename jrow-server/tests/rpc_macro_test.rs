@@ -0,0 +1,153 @@
+//! Integration tests for the `#[rpc]` trait macro
+
+use futures::StreamExt;
+use jrow_client::JrowClient;
+use jrow_core::Result;
+use jrow_macros::rpc;
+use jrow_server::{JrowServer, RouterBuilder};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+struct AddParams {
+    a: i64,
+    b: i64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct Total {
+    value: i64,
+}
+
+#[rpc(namespace = "calc")]
+trait Calculator {
+    /// Add two numbers
+    async fn add(&self, params: AddParams) -> Result<i64>;
+
+    /// Return the number of additions performed so far
+    #[method(name = "count")]
+    async fn additions(&self) -> Result<u64>;
+
+    /// Published after every addition
+    #[subscription(topic = "calc.totals")]
+    fn totals(&self) -> Total;
+
+    /// Per-user totals
+    #[subscription(topic = "calc.user.*")]
+    fn user_totals(&self) -> Total;
+}
+
+#[derive(Default)]
+struct CalculatorService {
+    additions: AtomicU64,
+}
+
+impl Calculator for CalculatorService {
+    async fn add(&self, params: AddParams) -> Result<i64> {
+        self.additions.fetch_add(1, Ordering::SeqCst);
+        Ok(params.a + params.b)
+    }
+
+    async fn additions(&self) -> Result<u64> {
+        Ok(self.additions.load(Ordering::SeqCst))
+    }
+}
+
+#[test]
+fn test_register_into_uses_wire_names() {
+    let router = CalculatorService::default()
+        .register_into(RouterBuilder::new())
+        .try_build()
+        .unwrap();
+
+    assert!(router.has_method("calc.add"));
+    assert!(router.has_method("calc.count"));
+    assert!(!router.has_method("calc.additions"));
+    assert!(router.get("calc.add").unwrap().schema().is_some());
+    assert_eq!(CalculatorTopics::TOTALS, "calc.totals");
+}
+
+#[tokio::test]
+async fn test_typed_client_roundtrip() {
+    let router = CalculatorService::default()
+        .register_into(RouterBuilder::new())
+        .try_build()
+        .unwrap();
+
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .router(router)
+        .build()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let server = Arc::new(server);
+    let server_clone = Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let client = JrowClient::connect(&format!("ws://{}", addr)).await.unwrap();
+    let calc = CalculatorClient::new(client);
+
+    assert_eq!(calc.add(AddParams { a: 2, b: 3 }).await.unwrap(), 5);
+    assert_eq!(calc.additions().await.unwrap(), 1);
+
+    let mut totals = calc.totals().await.unwrap();
+    let mut user_totals = calc.user_totals().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    CalculatorTopics::publish_totals(&server, &Total { value: 5 })
+        .await
+        .unwrap();
+    CalculatorTopics::publish_user_totals(&server, "calc.user.alice", &Total { value: 7 })
+        .await
+        .unwrap();
+
+    let total = tokio::time::timeout(Duration::from_secs(1), totals.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(total, Total { value: 5 });
+
+    let user_total = tokio::time::timeout(Duration::from_secs(1), user_totals.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(user_total, Total { value: 7 });
+}
+
+#[tokio::test]
+async fn test_pattern_publish_rejects_mismatched_topic() {
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+
+    for topic in ["calc.totals", "calc.user.alice.extra", "calc.user.*"] {
+        let result =
+            CalculatorTopics::publish_user_totals(&server, topic, &Total { value: 1 }).await;
+        assert!(
+            matches!(result, Err(jrow_core::Error::InvalidParams(_))),
+            "{} should be rejected",
+            topic
+        );
+    }
+
+    assert_eq!(
+        CalculatorTopics::publish_user_totals(&server, "calc.user.bob", &Total { value: 1 })
+            .await
+            .unwrap(),
+        0
+    );
+}
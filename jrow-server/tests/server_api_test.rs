@@ -80,3 +80,748 @@ async fn test_server_drop_cleanup() {
     tokio::time::sleep(Duration::from_millis(100)).await;
 }
 
+
+#[tokio::test]
+async fn test_server_runtime_registration() {
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let routes = server.router_handle();
+
+    let server = std::sync::Arc::new(server);
+    let server_clone = std::sync::Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Connected before the method exists
+    let client = jrow_client::JrowClient::connect(&format!("ws://{}", addr))
+        .await
+        .unwrap();
+    let missing: jrow_core::Result<String> = client.request("plugin.hello", ()).await;
+    assert!(missing.is_err());
+
+    routes.register("plugin.hello", from_fn(|_| async { Ok(serde_json::json!("hello")) }));
+    let hello: String = client.request("plugin.hello", ()).await.unwrap();
+    assert_eq!(hello, "hello");
+
+    routes.unregister("plugin.hello");
+    let removed: jrow_core::Result<String> = client.request("plugin.hello", ()).await;
+    assert!(removed.is_err());
+}
+
+/// Binds the connection to the `user` param of `login`
+struct Login(jrow_server::IdentityRegistry);
+
+impl jrow_server::SyncMiddleware for Login {
+    fn pre_handle(
+        &self,
+        ctx: &mut jrow_server::MiddlewareContext,
+    ) -> jrow_core::Result<jrow_server::MiddlewareAction> {
+        if ctx.method == "login" {
+            if let Some(user) = ctx.params.as_ref().and_then(|p| p["user"].as_str()) {
+                self.0.bind(ctx.conn_id, user);
+            }
+        }
+        Ok(jrow_server::MiddlewareAction::Continue)
+    }
+
+    fn post_handle(
+        &self,
+        _ctx: &mut jrow_server::MiddlewareContext,
+        _result: &jrow_core::Result<serde_json::Value>,
+    ) -> jrow_core::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_server_targeted_notifications() {
+    let builder = JrowServer::builder().bind_str("127.0.0.1:0").unwrap();
+    let login = Login(builder.identities());
+    let server = builder
+        .use_sync_middleware(login)
+        .handler("login", from_fn(|_| async { Ok(serde_json::json!(true)) }))
+        .build()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = std::sync::Arc::new(server);
+    let server_clone = std::sync::Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut clients = Vec::new();
+    for user in ["alice", "alice", "bob"] {
+        let client = jrow_client::JrowClient::connect(&format!("ws://{}", addr))
+            .await
+            .unwrap();
+        let tx = tx.clone();
+        client
+            .on_notification("alert", move |notification| {
+                let tx = tx.clone();
+                async move {
+                    tx.send((user, notification.params)).ok();
+                }
+            })
+            .await;
+        let _: bool = client
+            .request("login", serde_json::json!({"user": user}))
+            .await
+            .unwrap();
+        clients.push(client);
+    }
+
+    let report = server
+        .notify_identity("alice", "alert", Some(serde_json::json!("hi alice")))
+        .unwrap();
+    assert_eq!(report.delivered_count(), 2);
+    for _ in 0..2 {
+        let (user, params) = rx.recv().await.unwrap();
+        assert_eq!(user, "alice");
+        assert_eq!(params, Some(serde_json::json!("hi alice")));
+    }
+
+    let bob = server.identities().connections_of("bob")[0];
+    let report = server.notify_connection(bob, "alert", None).unwrap();
+    assert_eq!(report.delivered, vec![bob]);
+    assert_eq!(rx.recv().await.unwrap().0, "bob");
+
+    let report = server.notify_connection(9999, "alert", None).unwrap();
+    assert_eq!(report.failed, vec![9999]);
+    assert!(!report.is_complete());
+
+    let report = server.broadcast("alert", None).unwrap();
+    assert_eq!(report.delivered_count(), 3);
+    assert!(report.is_complete());
+
+    // Identities are released when the connection closes
+    clients.pop().unwrap().disconnect().await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(server.identities().connections_of("bob").is_empty());
+    let report = server.notify_identity("bob", "alert", None).unwrap();
+    assert_eq!(report, jrow_server::DeliveryReport::default());
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct User(String);
+
+/// Counts the requests of each connection in its session
+struct CountRequests;
+
+impl jrow_server::SyncMiddleware for CountRequests {
+    fn pre_handle(
+        &self,
+        ctx: &mut jrow_server::MiddlewareContext,
+    ) -> jrow_core::Result<jrow_server::MiddlewareAction> {
+        ctx.session.update(|count: &mut u32| *count += 1);
+        Ok(jrow_server::MiddlewareAction::Continue)
+    }
+
+    fn post_handle(
+        &self,
+        _ctx: &mut jrow_server::MiddlewareContext,
+        _result: &jrow_core::Result<serde_json::Value>,
+    ) -> jrow_core::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_session_outlives_requests() {
+    use jrow_server::Session;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .on_connect(|info| async move {
+            info.session.insert(0u32);
+            Ok(())
+        })
+        .on_disconnect(move |info, _| {
+            let tx = tx.clone();
+            async move {
+                tx.send((info.session.get::<User>(), info.session.get::<u32>())).ok();
+            }
+        })
+        .use_sync_middleware(CountRequests)
+        .handler(
+            "login",
+            from_fn(|params| async move {
+                let user = params.unwrap()["user"].as_str().unwrap().to_string();
+                Session::current().unwrap().insert(User(user));
+                Ok(serde_json::json!(true))
+            }),
+        )
+        .handler(
+            "whoami",
+            from_fn(|_| async move {
+                let user = Session::current().unwrap().get::<User>();
+                Ok(serde_json::json!({"user": user.map(|User(name)| name)}))
+            }),
+        )
+        .build()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    tokio::spawn(async move {
+        server.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let alice = jrow_client::JrowClient::connect(&format!("ws://{}", addr))
+        .await
+        .unwrap();
+    let anonymous = jrow_client::JrowClient::connect(&format!("ws://{}", addr))
+        .await
+        .unwrap();
+
+    let _: bool = alice
+        .request("login", serde_json::json!({"user": "alice"}))
+        .await
+        .unwrap();
+    let me: serde_json::Value = alice.request("whoami", None::<()>).await.unwrap();
+    assert_eq!(me["user"], "alice");
+
+    // Sessions aren't shared between connections
+    let me: serde_json::Value = anonymous.request("whoami", None::<()>).await.unwrap();
+    assert!(me["user"].is_null());
+
+    alice.disconnect().await;
+    let (user, requests) = rx.recv().await.unwrap();
+    assert_eq!(user, Some(User("alice".to_string())));
+    assert_eq!(requests, Some(2));
+}
+
+#[tokio::test]
+async fn test_presence_join_and_leave() {
+    let builder = JrowServer::builder().bind_str("127.0.0.1:0").unwrap();
+    let login = Login(builder.identities());
+    let server = builder
+        .use_sync_middleware(login)
+        .handler("login", from_fn(|_| async { Ok(serde_json::json!(true)) }))
+        .track_presence("rooms.*")
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = std::sync::Arc::new(server);
+    let server_clone = std::sync::Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let connect = || async {
+        jrow_client::JrowClient::connect(&format!("ws://{}", addr))
+            .await
+            .unwrap()
+    };
+    let alice = connect().await;
+    let _: bool = alice
+        .request("login", serde_json::json!({"user": "alice"}))
+        .await
+        .unwrap();
+    alice.subscribe("rooms.1", |_| async {}).await.unwrap();
+
+    // The initial listing includes members that joined earlier
+    let watcher = connect().await;
+    let mut room = watcher.subscribe_presence("rooms.1").await.unwrap();
+    assert!(room.contains_identity("alice"));
+    assert!(watcher.subscribe_presence("rooms.*").await.is_err());
+
+    let bob = connect().await;
+    bob.subscribe("rooms.1", |_| async {}).await.unwrap();
+    room.changed().await.unwrap();
+    let members = room.members();
+    assert_eq!(members.len(), 2);
+    assert_eq!(server.presence("rooms.1").await, members);
+
+    // Unsubscribing and disconnecting both count as leaving
+    bob.unsubscribe("rooms.1").await.unwrap();
+    room.changed().await.unwrap();
+    assert_eq!(room.members().len(), 1);
+
+    // A pattern subscriber joins the occupied topics it matches
+    let carol = connect().await;
+    carol.subscribe("rooms.*", |_| async {}).await.unwrap();
+    room.changed().await.unwrap();
+    assert_eq!(room.members().len(), 2);
+    assert_eq!(server.presence("rooms.1").await, room.members());
+
+    carol.unsubscribe("rooms.*").await.unwrap();
+    room.changed().await.unwrap();
+    assert_eq!(room.members().len(), 1);
+
+    alice.disconnect().await;
+    room.changed().await.unwrap();
+    assert!(room.members().is_empty());
+    assert!(server.presence("rooms.1").await.is_empty());
+
+    watcher.unsubscribe_presence("rooms.1").await.unwrap();
+    assert!(room.changed().await.is_err());
+}
+
+#[tokio::test]
+async fn test_filtered_subscriptions() {
+    use jrow_core::ContentFilter;
+
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = std::sync::Arc::new(server);
+    let server_clone = std::sync::Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let client = jrow_client::JrowClient::connect(&format!("ws://{}", addr))
+        .await
+        .unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let exact_tx = tx.clone();
+    client
+        .subscribe_filtered("orders.eu", ContentFilter::gte("/amount", 100), move |data| {
+            let tx = exact_tx.clone();
+            async move {
+                tx.send(("exact", data)).ok();
+            }
+        })
+        .await
+        .unwrap();
+    client
+        .subscribe_filtered("orders.*", ContentFilter::eq("/region", "us"), move |data| {
+            let tx = tx.clone();
+            async move {
+                tx.send(("pattern", data)).ok();
+            }
+        })
+        .await
+        .unwrap();
+
+    // Only matching subscribers count as recipients
+    let small = serde_json::json!({"region": "eu", "amount": 10});
+    assert_eq!(server.publish("orders.eu", small).await.unwrap(), 0);
+    let large = serde_json::json!({"region": "eu", "amount": 500});
+    assert_eq!(server.publish("orders.eu", large.clone()).await.unwrap(), 1);
+    let us = serde_json::json!({"region": "us", "amount": 1});
+    server.publish_batch(vec![("orders.us".to_string(), us.clone())]).await.unwrap();
+
+    let mut received = vec![rx.recv().await.unwrap(), rx.recv().await.unwrap()];
+    received.sort_by_key(|(kind, _)| *kind);
+    let wrapped = serde_json::json!({"topic": "orders.us", "data": us});
+    assert_eq!(received, vec![("exact", large), ("pattern", wrapped)]);
+
+    // Malformed filters are rejected
+    let result: jrow_core::Result<serde_json::Value> = client
+        .request(
+            "rpc.subscribe",
+            serde_json::json!({"topic": "orders.eu", "filter": {"path": "/amount", "like": 1}}),
+        )
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_queue_group_delivery() {
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = std::sync::Arc::new(server);
+    let server_clone = std::sync::Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut clients = Vec::new();
+    for (name, topic, group) in [
+        ("worker-1", "jobs.build", Some("workers")),
+        ("worker-2", "jobs.*", Some("workers")),
+        ("auditor", "jobs.build", None),
+    ] {
+        let client = jrow_client::JrowClient::connect(&format!("ws://{}", addr))
+            .await
+            .unwrap();
+        let tx = tx.clone();
+        let handler = move |_| {
+            let tx = tx.clone();
+            async move {
+                tx.send(name).ok();
+            }
+        };
+        match group {
+            Some(group) => client.subscribe_queue(topic, group, handler).await.unwrap(),
+            None => client.subscribe(topic, handler).await.unwrap(),
+        }
+        clients.push(client);
+    }
+
+    // Every message reaches the auditor and one of the workers, in turn
+    for id in 0..4 {
+        let sent = server.publish("jobs.build", serde_json::json!({"id": id})).await.unwrap();
+        assert_eq!(sent, 2);
+    }
+    let mut received = Vec::new();
+    for _ in 0..8 {
+        received.push(rx.recv().await.unwrap());
+    }
+    let count = |name| received.iter().filter(|&&n| n == name).count();
+    assert_eq!((count("worker-1"), count("worker-2"), count("auditor")), (2, 2, 4));
+
+    // Group names can't be empty
+    let result: jrow_core::Result<serde_json::Value> = clients[0]
+        .request("rpc.subscribe", serde_json::json!({"topic": "jobs.build", "queue_group": ""}))
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_retained_messages() {
+    use jrow_client::SubscribeOptions;
+    use serde_json::json;
+
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .retain_messages("prices.*", 1)
+        .unwrap()
+        .retain_messages("logs", 2)
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = std::sync::Arc::new(server);
+    let server_clone = std::sync::Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    for (topic, value) in [("prices.btc", 1), ("prices.eth", 2), ("prices.btc", 3)] {
+        server.publish(topic, json!({"price": value})).await.unwrap();
+    }
+    for line in 0..3 {
+        server.publish("logs", json!({"line": line})).await.unwrap();
+    }
+
+    let client = jrow_client::JrowClient::connect(&format!("ws://{}", addr))
+        .await
+        .unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let subscribe = |topic: &'static str, options: SubscribeOptions| {
+        let tx = tx.clone();
+        let client = &client;
+        async move {
+            client
+                .subscribe_with_options(topic, options, move |data| {
+                    let tx = tx.clone();
+                    async move {
+                        tx.send((topic, data)).ok();
+                    }
+                })
+                .await
+                .unwrap();
+        }
+    };
+
+    // Retained messages arrive before the subscribe call returns
+    subscribe("prices.btc", SubscribeOptions::new()).await;
+    assert_eq!(rx.try_recv().unwrap(), ("prices.btc", json!({"price": 3})));
+
+    subscribe("prices.*", SubscribeOptions::new()).await;
+    assert_eq!(
+        rx.try_recv().unwrap(),
+        ("prices.*", json!({"topic": "prices.eth", "data": {"price": 2}}))
+    );
+    assert_eq!(
+        rx.try_recv().unwrap(),
+        ("prices.*", json!({"topic": "prices.btc", "data": {"price": 3}}))
+    );
+
+    subscribe("logs", SubscribeOptions::new().without_retained()).await;
+    assert!(rx.try_recv().is_err());
+    server.publish("logs", json!({"line": 3})).await.unwrap();
+    assert_eq!(rx.recv().await.unwrap(), ("logs", json!({"line": 3})));
+}
+
+#[tokio::test]
+async fn test_snapshot_then_deltas() {
+    use jrow_core::snapshot::Versioned;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    let open_orders = Arc::new(Mutex::new(0));
+    let state = Arc::clone(&open_orders);
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .snapshot_provider("stats.*", move |topic| {
+            let state = Arc::clone(&state);
+            async move {
+                if topic == "stats.broken" {
+                    return Err(jrow_core::Error::Internal("no stats".to_string()));
+                }
+                Ok(json!({"open_orders": *state.lock().unwrap()}))
+            }
+        })
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = Arc::new(server);
+    let server_clone = Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let open = |server: Arc<JrowServer>, open_orders: Arc<Mutex<u64>>| async move {
+        server
+            .publish_update("stats.orders", || {
+                *open_orders.lock().unwrap() += 1;
+                json!({"opened": 1})
+            })
+            .await
+            .unwrap();
+    };
+    open(Arc::clone(&server), Arc::clone(&open_orders)).await;
+    open(Arc::clone(&server), Arc::clone(&open_orders)).await;
+
+    let client = jrow_client::JrowClient::connect(&format!("ws://{}", addr))
+        .await
+        .unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    client
+        .subscribe_versioned("stats.orders", move |message| {
+            let tx = tx.clone();
+            async move {
+                tx.send(message).ok();
+            }
+        })
+        .await
+        .unwrap();
+
+    // The snapshot arrives before the subscribe call returns
+    assert_eq!(rx.try_recv().unwrap(), Versioned::snapshot(2, json!({"open_orders": 2})));
+    open(Arc::clone(&server), Arc::clone(&open_orders)).await;
+    assert_eq!(rx.recv().await.unwrap(), Versioned::delta(3, json!({"opened": 1})));
+
+    // A failing provider fails the subscription
+    assert!(client.subscribe_versioned("stats.broken", |_| async {}).await.is_err());
+    assert!(client.subscribe_versioned("stats.*", |_| async {}).await.is_err());
+}
+
+#[tokio::test]
+async fn test_throttled_subscription() {
+    use jrow_client::SubscribeOptions;
+    use serde_json::json;
+
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = std::sync::Arc::new(server);
+    let server_clone = std::sync::Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let client = jrow_client::JrowClient::connect(&format!("ws://{}", addr))
+        .await
+        .unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    client
+        .subscribe_with_options(
+            "ticker.*",
+            SubscribeOptions::new().max_rate(4.0).conflate("/symbol"),
+            move |tick| {
+                let tx = tx.clone();
+                async move {
+                    tx.send(tick["data"].clone()).ok();
+                }
+            },
+        )
+        .await
+        .unwrap();
+
+    // The first tick goes out straight away and the rest wait a quarter of
+    // a second, keeping the latest tick of each symbol
+    let ticks = [("btc", 1), ("btc", 2), ("eth", 3), ("btc", 4), ("eth", 5)];
+    for (symbol, price) in ticks {
+        let sent = server
+            .publish(format!("ticker.{}", symbol), json!({"symbol": symbol, "price": price}))
+            .await
+            .unwrap();
+        assert_eq!(sent, 1);
+    }
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        let tick = tokio::time::timeout(Duration::from_secs(2), rx.recv()).await.unwrap().unwrap();
+        received.push(tick["price"].as_u64().unwrap());
+    }
+    assert_eq!(received, vec![1, 4, 5]);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(rx.try_recv().is_err());
+
+    // Invalid limits are rejected
+    for rate in [0.0, 1e-300] {
+        let options = SubscribeOptions::new().max_rate(rate);
+        assert!(client.subscribe_with_options("ticker.*", options, |_| async {}).await.is_err());
+    }
+    // The connection survives a rate too small for its interval
+    assert_eq!(server.publish("ticker.btc", json!({"symbol": "btc", "price": 6})).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_request_reply_over_topics() {
+    use serde_json::json;
+
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = std::sync::Arc::new(server);
+    let server_clone = std::sync::Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let url = format!("ws://{}", addr);
+    let responder = jrow_client::JrowClient::connect(&url).await.unwrap();
+    responder
+        .respond("orders.*", |request| async move {
+            match request["order_id"].as_u64() {
+                Some(id) => Ok(json!({"order_id": id, "status": "shipped"})),
+                None => Err(jrow_core::Error::InvalidParams("missing order_id".to_string())),
+            }
+        })
+        .await
+        .unwrap();
+
+    let timeout = Duration::from_secs(2);
+    let reply = server
+        .request_topic("orders.status", json!({"order_id": 1}), timeout)
+        .await
+        .unwrap();
+    assert_eq!(reply, json!({"order_id": 1, "status": "shipped"}));
+
+    // Clients send requests through the server, including to themselves
+    let requester = jrow_client::JrowClient::connect(&url).await.unwrap();
+    let reply = requester
+        .request_topic("orders.status", json!({"order_id": 2}), timeout)
+        .await
+        .unwrap();
+    assert_eq!(reply["order_id"], 2);
+    let reply = responder
+        .request_topic("orders.status", json!({"order_id": 3}), timeout)
+        .await
+        .unwrap();
+    assert_eq!(reply["order_id"], 3);
+
+    // Unanswered requests time out, and topics without subscribers fail
+    let unanswered = server
+        .request_topic("orders.status", json!({}), Duration::from_millis(200))
+        .await;
+    assert!(matches!(unanswered, Err(jrow_core::Error::Timeout)));
+    assert!(requester
+        .request_topic("users.status", json!({}), timeout)
+        .await
+        .is_err());
+
+    // Clients can only publish to reply inboxes
+    assert!(requester.reply("orders.status", json!({})).await.is_err());
+    assert_eq!(requester.reply("_INBOX.unknown", json!({})).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_batch_builtin_from_descriptor() {
+    use jrow_server::{OpenRpcInfo, ParamsValidator};
+    use serde_json::{json, Value};
+
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .with_discovery(OpenRpcInfo::new("Test", "1.0.0"))
+        .publish_acl(|_| async { true })
+        .build()
+        .await
+        .unwrap();
+    let document = server.openrpc_document().unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = std::sync::Arc::new(server);
+    let server_clone = std::sync::Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Each positional param of the batch is one message matching the descriptor
+    let method = document["methods"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|method| method["name"] == "rpc.publish_batch")
+        .unwrap();
+    assert_eq!(method["paramStructure"], "by-position");
+    let descriptor = &method["params"][0];
+    let validator = ParamsValidator::new(descriptor["schema"].clone()).unwrap();
+    let messages = vec![
+        json!({"topic": "news", "data": 1}),
+        json!({"topic": "news", "data": 2}),
+    ];
+    for message in &messages {
+        validator.validate(Some(message)).unwrap();
+    }
+
+    let client = jrow_client::JrowClient::connect(&format!("ws://{}", addr))
+        .await
+        .unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    client
+        .subscribe("news", move |data| {
+            let tx = tx.clone();
+            async move {
+                tx.send(data).ok();
+            }
+        })
+        .await
+        .unwrap();
+
+    let results: Vec<Value> = client.request("rpc.publish_batch", messages).await.unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result["success"] == true), "{:?}", results);
+    assert_eq!(rx.recv().await.unwrap(), json!(1));
+    assert_eq!(rx.recv().await.unwrap(), json!(2));
+}
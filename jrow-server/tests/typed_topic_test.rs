@@ -0,0 +1,117 @@
+//! Integration tests for typed topics (`#[derive(Topic)]`)
+
+use futures::StreamExt;
+use jrow_client::JrowClient;
+use jrow_core::Topic;
+use jrow_server::JrowServer;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, jrow_macros::Topic)]
+#[topic("orders.created")]
+struct OrderCreated {
+    order_id: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, jrow_macros::Topic)]
+#[topic("users.{user_id}.events")]
+struct UserEvent {
+    user_id: String,
+    kind: String,
+}
+
+#[test]
+fn test_derived_topics() {
+    assert_eq!(OrderCreated::TOPIC, "orders.created");
+    assert_eq!(OrderCreated { order_id: 1 }.topic(), "orders.created");
+
+    let event = UserEvent {
+        user_id: "alice".to_string(),
+        kind: "login".to_string(),
+    };
+    assert_eq!(UserEvent::TOPIC, "users.*.events");
+    assert_eq!(event.topic(), "users.alice.events");
+}
+
+#[tokio::test]
+async fn test_publish_typed_rejects_multiple_tokens() {
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+
+    for user_id in ["alice.admin", "*", ">", ""] {
+        let event = UserEvent {
+            user_id: user_id.to_string(),
+            kind: "login".to_string(),
+        };
+        let result = server.publish_typed(&event).await;
+        assert!(
+            matches!(result, Err(jrow_core::Error::InvalidParams(_))),
+            "{:?}: {:?}",
+            user_id,
+            result
+        );
+    }
+
+    let event = UserEvent {
+        user_id: "alice".to_string(),
+        kind: "login".to_string(),
+    };
+    assert_eq!(server.publish_typed(&event).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_publish_and_subscribe_typed() {
+    let server = JrowServer::builder()
+        .bind_str("127.0.0.1:0")
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let server = Arc::new(server);
+    let server_clone = Arc::clone(&server);
+    tokio::spawn(async move {
+        server_clone.run().await.ok();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let client = JrowClient::connect(&format!("ws://{}", addr)).await.unwrap();
+    let mut orders = client.subscribe_typed::<OrderCreated>().await.unwrap();
+    let mut events = client.subscribe_typed::<UserEvent>().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let delivered = server
+        .publish_typed(&OrderCreated { order_id: 42 })
+        .await
+        .unwrap();
+    assert_eq!(delivered, 1);
+
+    let event = UserEvent {
+        user_id: "alice".to_string(),
+        kind: "login".to_string(),
+    };
+    server.publish_typed(&event).await.unwrap();
+
+    let order = tokio::time::timeout(Duration::from_secs(1), orders.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(order, OrderCreated { order_id: 42 });
+
+    let received = tokio::time::timeout(Duration::from_secs(1), events.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(received, event);
+
+    client.unsubscribe_typed::<OrderCreated>().await.unwrap();
+    assert!(orders.next().await.is_none());
+}